@@ -1,7 +1,32 @@
 use std::convert::TryInto;
-use winapi::shared::dxgiformat::*;
-use winapi::shared::dxgitype::*;
-use winapi::um::d3d11::*;
+
+// the DXGI_FORMAT values of the formats that can be stored in a dds file
+// same values as in dxgiformat.h so they can be passed to D3D11 unchanged
+pub type DxgiFormat = u32;
+
+pub const DXGI_FORMAT_R8G8B8A8_UNORM: DxgiFormat = 28;
+pub const DXGI_FORMAT_R8G8B8A8_UNORM_SRGB: DxgiFormat = 29;
+pub const DXGI_FORMAT_BC1_TYPELESS: DxgiFormat = 70;
+pub const DXGI_FORMAT_BC1_UNORM: DxgiFormat = 71;
+pub const DXGI_FORMAT_BC1_UNORM_SRGB: DxgiFormat = 72;
+pub const DXGI_FORMAT_BC2_TYPELESS: DxgiFormat = 73;
+pub const DXGI_FORMAT_BC2_UNORM: DxgiFormat = 74;
+pub const DXGI_FORMAT_BC2_UNORM_SRGB: DxgiFormat = 75;
+pub const DXGI_FORMAT_BC3_TYPELESS: DxgiFormat = 76;
+pub const DXGI_FORMAT_BC3_UNORM: DxgiFormat = 77;
+pub const DXGI_FORMAT_BC3_UNORM_SRGB: DxgiFormat = 78;
+pub const DXGI_FORMAT_BC4_TYPELESS: DxgiFormat = 79;
+pub const DXGI_FORMAT_BC4_UNORM: DxgiFormat = 80;
+pub const DXGI_FORMAT_BC4_SNORM: DxgiFormat = 81;
+pub const DXGI_FORMAT_BC5_TYPELESS: DxgiFormat = 82;
+pub const DXGI_FORMAT_BC5_UNORM: DxgiFormat = 83;
+pub const DXGI_FORMAT_BC5_SNORM: DxgiFormat = 84;
+pub const DXGI_FORMAT_BC6H_TYPELESS: DxgiFormat = 94;
+pub const DXGI_FORMAT_BC6H_UF16: DxgiFormat = 95;
+pub const DXGI_FORMAT_BC6H_SF16: DxgiFormat = 96;
+pub const DXGI_FORMAT_BC7_TYPELESS: DxgiFormat = 97;
+pub const DXGI_FORMAT_BC7_UNORM: DxgiFormat = 98;
+pub const DXGI_FORMAT_BC7_UNORM_SRGB: DxgiFormat = 99;
 
 #[derive(Debug)]
 pub enum DdsParserError {
@@ -11,16 +36,33 @@ pub enum DdsParserError {
     ImageSizeNotMultipleOf4,
 }
 
-pub struct ParsedTextureData {
-    pub desc: D3D11_TEXTURE2D_DESC,
-    pub subresources_data: Vec<D3D11_SUBRESOURCE_DATA>,
+// the parts of D3D11_TEXTURE2D_DESC that are stored in the dds file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+    pub array_size: u32,
+    pub format: DxgiFormat,
+}
+
+// the data of a single mip level, points into the data of the dds file
+pub struct SubresourceData<'a> {
+    pub data: &'a [u8],
+    pub row_pitch: u32,
+    pub slice_pitch: u32,
+}
+
+pub struct ParsedTextureData<'a> {
+    pub desc: TextureDesc,
+    pub subresources_data: Vec<SubresourceData<'a>>,
 }
 
 fn is_multiple_of4(value: u32) -> bool {
     value % 4 == 0
 }
 
-pub fn parse_dds_header(src_data: &[u8]) -> Result<ParsedTextureData, DdsParserError> {
+pub fn parse_dds_header(src_data: &[u8]) -> Result<ParsedTextureData<'_>, DdsParserError> {
     // each dds file follows a high level structure
     // DWORD with value "DDS " 0x20534444
     // DDS_HEADER
@@ -246,23 +288,15 @@ pub fn parse_dds_header(src_data: &[u8]) -> Result<ParsedTextureData, DdsParserE
     };
 
     // fill the texture header with the information we parsed
-    let texture_header_ref = D3D11_TEXTURE2D_DESC {
-        Width: dds_header_dw_width,
-        Height: dds_header_dw_height,
-        MipLevels: mipmap_count,
-        ArraySize: 1, // only supported with DXT10 headers
-        Format: format,
-        SampleDesc: DXGI_SAMPLE_DESC {
-            Count: 1,
-            Quality: 0,
-        },
-        Usage: D3D11_USAGE_DEFAULT,
-        BindFlags: D3D11_BIND_SHADER_RESOURCE,
-        MiscFlags: 0,
-        CPUAccessFlags: 0,
+    let texture_header_ref = TextureDesc {
+        width: dds_header_dw_width,
+        height: dds_header_dw_height,
+        mip_levels: mipmap_count,
+        array_size: 1, // only supported with DXT10 headers
+        format,
     };
 
-    let mut subresources: Vec<D3D11_SUBRESOURCE_DATA> = Vec::new();
+    let mut subresources: Vec<SubresourceData> = Vec::new();
 
     let block_size = match format {
         DXGI_FORMAT_BC1_UNORM | DXGI_FORMAT_BC1_UNORM_SRGB | DXGI_FORMAT_BC1_TYPELESS => 8,
@@ -281,8 +315,8 @@ pub fn parse_dds_header(src_data: &[u8]) -> Result<ParsedTextureData, DdsParserE
     // calculate the subresource data for each mip map
 
     for mip_level in 0..mipmap_count {
-        let mip_level_width = texture_header_ref.Width >> mip_level;
-        let mip_level_height = texture_header_ref.Height >> mip_level;
+        let mip_level_width = texture_header_ref.width >> mip_level;
+        let mip_level_height = texture_header_ref.height >> mip_level;
 
         let (line_pitch, slice_pitch) = if block_size > 1 {
             let line_pitch = std::cmp::max(1, (mip_level_width + 3) / 4) * block_size;
@@ -296,16 +330,15 @@ pub fn parse_dds_header(src_data: &[u8]) -> Result<ParsedTextureData, DdsParserE
 
             let bits_per_pixel = 32;
             let line_pitch = (mip_level_width * bits_per_pixel + 7) / 8;
-            let slice_pitch = line_pitch * texture_header_ref.Height;
+            let slice_pitch = line_pitch * texture_header_ref.height;
 
             (line_pitch, slice_pitch)
         };
 
-        subresources.push(D3D11_SUBRESOURCE_DATA {
-            pSysMem: src_data[file_cursor..(file_cursor + (slice_pitch as usize))].as_ptr()
-                as *const winapi::ctypes::c_void, // todo, calculate this correctly
-            SysMemPitch: line_pitch,
-            SysMemSlicePitch: slice_pitch,
+        subresources.push(SubresourceData {
+            data: &src_data[file_cursor..(file_cursor + (slice_pitch as usize))], // todo, calculate this correctly
+            row_pitch: line_pitch,
+            slice_pitch,
         });
 
         file_cursor += slice_pitch as usize;
@@ -332,54 +365,28 @@ mod tests {
             include_bytes!("../tests/data/paintnet/black_4x4_mips_bc1.dds");
     }
 
-    fn validate_texture_header(
-        texture_header_ref: &D3D11_TEXTURE2D_DESC,
-        texture_header: &D3D11_TEXTURE2D_DESC,
-    ) {
-        assert_eq!(texture_header_ref.Width, texture_header.Width);
-        assert_eq!(texture_header_ref.Height, texture_header.Height);
-        assert_eq!(texture_header_ref.MipLevels, texture_header.MipLevels);
-        assert_eq!(texture_header_ref.ArraySize, texture_header.ArraySize);
-        assert_eq!(texture_header_ref.Format, texture_header.Format);
-        assert_eq!(
-            texture_header_ref.SampleDesc.Count,
-            texture_header.SampleDesc.Count
-        );
-        assert_eq!(
-            texture_header_ref.SampleDesc.Quality,
-            texture_header.SampleDesc.Quality
-        );
-        assert_eq!(texture_header_ref.Usage, texture_header.Usage);
-        assert_eq!(texture_header_ref.BindFlags, texture_header.BindFlags);
-        assert_eq!(texture_header_ref.MiscFlags, texture_header.MiscFlags);
-        assert_eq!(
-            texture_header_ref.CPUAccessFlags,
-            texture_header.CPUAccessFlags
-        );
+    fn validate_texture_header(texture_header_ref: &TextureDesc, texture_header: &TextureDesc) {
+        assert_eq!(texture_header_ref.width, texture_header.width);
+        assert_eq!(texture_header_ref.height, texture_header.height);
+        assert_eq!(texture_header_ref.mip_levels, texture_header.mip_levels);
+        assert_eq!(texture_header_ref.array_size, texture_header.array_size);
+        assert_eq!(texture_header_ref.format, texture_header.format);
     }
 
     #[test]
     fn validate_texture_header_black_4x4_bc1() {
-        let texture_header_ref = D3D11_TEXTURE2D_DESC {
-            Width: 4,
-            Height: 4,
-            MipLevels: 1,
-            ArraySize: 1,
-            Format: DXGI_FORMAT_BC1_UNORM,
-            SampleDesc: DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            Usage: D3D11_USAGE_DEFAULT,
-            BindFlags: D3D11_BIND_SHADER_RESOURCE,
-            MiscFlags: 0,
-            CPUAccessFlags: 0,
+        let texture_header_ref = TextureDesc {
+            width: 4,
+            height: 4,
+            mip_levels: 1,
+            array_size: 1,
+            format: DXGI_FORMAT_BC1_UNORM,
         };
 
-        let texture_data_desc = D3D11_SUBRESOURCE_DATA {
-            pSysMem: std::ptr::null_mut(), // can't validate this, will be pointing into the original block
-            SysMemPitch: 8,                // 4x4 texture = 1 BC1 block = 8 bytes
-            SysMemSlicePitch: 8,           // 1 block
+        let texture_data_desc = SubresourceData {
+            data: &paintnet::BLACK_4X4_BC1[128..], // the block after the header
+            row_pitch: 8,                          // 4x4 texture = 1 BC1 block = 8 bytes
+            slice_pitch: 8,                        // 1 block
         };
 
         let texture_load_result = parse_dds_header(paintnet::BLACK_4X4_BC1);
@@ -394,31 +401,27 @@ mod tests {
         assert_eq!(texture_header.subresources_data.len(), 1);
 
         assert_eq!(
-            texture_data_desc.SysMemPitch,
-            texture_header.subresources_data[0].SysMemPitch
+            texture_data_desc.data,
+            texture_header.subresources_data[0].data
+        );
+        assert_eq!(
+            texture_data_desc.row_pitch,
+            texture_header.subresources_data[0].row_pitch
         );
         assert_eq!(
-            texture_data_desc.SysMemSlicePitch,
-            texture_header.subresources_data[0].SysMemSlicePitch
+            texture_data_desc.slice_pitch,
+            texture_header.subresources_data[0].slice_pitch
         );
     }
 
     #[test]
     fn validate_texture_header_black_4x4_mips_bc1() {
-        let texture_header_ref = D3D11_TEXTURE2D_DESC {
-            Width: 4,
-            Height: 4,
-            MipLevels: 3,
-            ArraySize: 1,
-            Format: DXGI_FORMAT_BC1_UNORM,
-            SampleDesc: DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            Usage: D3D11_USAGE_DEFAULT,
-            BindFlags: D3D11_BIND_SHADER_RESOURCE,
-            MiscFlags: 0,
-            CPUAccessFlags: 0,
+        let texture_header_ref = TextureDesc {
+            width: 4,
+            height: 4,
+            mip_levels: 3,
+            array_size: 1,
+            format: DXGI_FORMAT_BC1_UNORM,
         };
 
         let texture_load_result = parse_dds_header(paintnet::BLACK_4X4_MIPS_BC1);
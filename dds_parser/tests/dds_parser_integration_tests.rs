@@ -1,3 +1,6 @@
+// the textures are created on a D3D11 device, which only exists on windows
+#![cfg(windows)]
+
 use graphics_device::GraphicsDeviceLayer;
 
 mod paintnet {
@@ -80,6 +83,7 @@ fn test_texture_load_and_creation(data: &[u8]) {
         &graphics_layer.device,
         texture_load_result.desc,
         texture_load_result.subresources_data,
+        "Test Texture",
     )
     .unwrap();
 }
@@ -0,0 +1,271 @@
+use crate::glyph_rasterizer::GlyphBitmap;
+
+// empty pixels between glyphs, stops linear filtering from bleeding neighbouring glyphs into each other
+const GLYPH_SPACING: usize = 1;
+
+pub struct BakedGlyph {
+    pub character: char,
+    pub bitmap: GlyphBitmap,
+    pub advance: i32,
+
+    // position in the atlas, filled by pack_glyphs
+    pub x: usize,
+    pub y: usize,
+}
+
+pub struct FontAtlas {
+    pub face_name: String,
+    pub size: u32,
+    pub line_height: i32,
+    pub base: i32, // distance from the top of a line to the baseline
+    pub width: usize,
+    pub height: usize,
+    pub glyphs: Vec<BakedGlyph>,
+    pub kerning: Vec<(char, char, i32)>,
+}
+
+// tries to place the glyphs onto shelves of an atlas with the given width, returns the used height
+fn pack_into_shelves(glyphs: &mut [BakedGlyph], order: &[usize], width: usize) -> Option<usize> {
+    let mut shelf_y = GLYPH_SPACING;
+    let mut shelf_height = 0;
+    let mut cursor_x = GLYPH_SPACING;
+
+    for &index in order {
+        let glyph = &mut glyphs[index];
+
+        if glyph.bitmap.width + GLYPH_SPACING * 2 > width {
+            return None;
+        }
+
+        if cursor_x + glyph.bitmap.width + GLYPH_SPACING > width {
+            shelf_y += shelf_height + GLYPH_SPACING;
+            shelf_height = 0;
+            cursor_x = GLYPH_SPACING;
+        }
+
+        glyph.x = cursor_x;
+        glyph.y = shelf_y;
+
+        cursor_x += glyph.bitmap.width + GLYPH_SPACING;
+        shelf_height = shelf_height.max(glyph.bitmap.height);
+    }
+
+    Some(shelf_y + shelf_height + GLYPH_SPACING)
+}
+
+// assigns atlas positions to all glyphs and returns the smallest power of two atlas size that fits them
+// glyphs are placed tallest first onto shelves, this keeps the wasted space of each shelf small
+pub fn pack_glyphs(glyphs: &mut [BakedGlyph]) -> (usize, usize) {
+    let mut order: Vec<usize> = (0..glyphs.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse((glyphs[i].bitmap.height, glyphs[i].bitmap.width)));
+
+    let mut width = 64;
+
+    loop {
+        if let Some(used_height) = pack_into_shelves(glyphs, &order, width) {
+            let height = used_height.next_power_of_two();
+
+            // prefer square atlases over tall ones
+            if height <= width {
+                return (width, height);
+            }
+        }
+
+        width *= 2;
+    }
+}
+
+// white texels with the glyph coverage in alpha, so the color of the text is applied by the sprite color
+pub fn atlas_texels(atlas: &FontAtlas) -> Vec<u8> {
+    let mut texels = vec![255u8; atlas.width * atlas.height * 4];
+
+    for texel in texels.chunks_exact_mut(4) {
+        texel[3] = 0;
+    }
+
+    for glyph in atlas.glyphs.iter() {
+        for y in 0..glyph.bitmap.height {
+            for x in 0..glyph.bitmap.width {
+                let texel = ((glyph.y + y) * atlas.width + glyph.x + x) * 4;
+                texels[texel + 3] = glyph.bitmap.coverage[y * glyph.bitmap.width + x];
+            }
+        }
+    }
+
+    texels
+}
+
+// uncompressed RGBA8 texture with a single mip level, uses the DX10 header extension to store the DXGI format
+pub fn write_dds(width: usize, height: usize, texels: &[u8]) -> Vec<u8> {
+    const DDSD_CAPS: u32 = 0x1;
+    const DDSD_HEIGHT: u32 = 0x2;
+    const DDSD_WIDTH: u32 = 0x4;
+    const DDSD_PITCH: u32 = 0x8;
+    const DDSD_PIXELFORMAT: u32 = 0x1000;
+    const DDPF_FOURCC: u32 = 0x4;
+    const DDSCAPS_TEXTURE: u32 = 0x1000;
+    const DXGI_FORMAT_R8G8B8A8_UNORM: u32 = 28;
+    const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+
+    assert_eq!(texels.len(), width * height * 4);
+
+    let mut header: Vec<u32> = vec![
+        0x2053_4444, // "DDS "
+        124,
+        DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT,
+        height as u32,
+        width as u32,
+        width as u32 * 4,
+        0, // depth
+        0, // mip map count, only used with DDSD_MIPMAPCOUNT
+    ];
+
+    header.extend_from_slice(&[0; 11]); // reserved
+
+    // pixel format
+    header.extend_from_slice(&[32, DDPF_FOURCC, 0x3031_5844, 0, 0, 0, 0, 0]); // "DX10"
+
+    header.extend_from_slice(&[DDSCAPS_TEXTURE, 0, 0, 0, 0]);
+
+    // DDS_HEADER_DXT10
+    header.extend_from_slice(&[
+        DXGI_FORMAT_R8G8B8A8_UNORM,
+        D3D10_RESOURCE_DIMENSION_TEXTURE2D,
+        0,
+        1, // array size
+        0,
+    ]);
+
+    let mut file: Vec<u8> = header
+        .iter()
+        .flat_map(|x| x.to_le_bytes().to_vec())
+        .collect();
+    file.extend_from_slice(texels);
+
+    file
+}
+
+// AngelCode BMFont text format, see http://www.angelcode.com/products/bmfont/doc/file_format.html
+pub fn write_bmfont(atlas: &FontAtlas, texture_file_name: &str) -> String {
+    let mut text = format!(
+        "info face=\"{}\" size={} bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 padding=0,0,0,0 spacing={},{}\n",
+        atlas.face_name, atlas.size, GLYPH_SPACING, GLYPH_SPACING
+    );
+
+    text += &format!(
+        "common lineHeight={} base={} scaleW={} scaleH={} pages=1 packed=0\n",
+        atlas.line_height, atlas.base, atlas.width, atlas.height
+    );
+
+    text += &format!("page id=0 file=\"{}\"\n", texture_file_name);
+    text += &format!("chars count={}\n", atlas.glyphs.len());
+
+    for glyph in atlas.glyphs.iter() {
+        text += &format!(
+            "char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page=0 chnl=15\n",
+            glyph.character as u32,
+            glyph.x,
+            glyph.y,
+            glyph.bitmap.width,
+            glyph.bitmap.height,
+            glyph.bitmap.left,
+            atlas.base - glyph.bitmap.top,
+            glyph.advance
+        );
+    }
+
+    text += &format!("kernings count={}\n", atlas.kerning.len());
+
+    for (first, second, amount) in atlas.kerning.iter() {
+        text += &format!(
+            "kerning first={} second={} amount={}\n",
+            *first as u32, *second as u32, amount
+        );
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(character: char, width: usize, height: usize) -> BakedGlyph {
+        BakedGlyph {
+            character,
+            bitmap: GlyphBitmap {
+                width,
+                height,
+                coverage: vec![255; width * height],
+                left: 0,
+                top: height as i32,
+            },
+            advance: width as i32,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    #[test]
+    fn packed_glyphs_do_not_overlap() {
+        let mut glyphs: Vec<BakedGlyph> = (0..40)
+            .map(|i| glyph((b'A' + i as u8) as char, 5 + i % 7, 10 + i % 5))
+            .collect();
+
+        let (width, height) = pack_glyphs(&mut glyphs);
+
+        assert!(width.is_power_of_two() && height.is_power_of_two());
+
+        for (i, a) in glyphs.iter().enumerate() {
+            assert!(a.x >= GLYPH_SPACING && a.x + a.bitmap.width + GLYPH_SPACING <= width);
+            assert!(a.y >= GLYPH_SPACING && a.y + a.bitmap.height + GLYPH_SPACING <= height);
+
+            for b in glyphs.iter().skip(i + 1) {
+                let separated_x = a.x + a.bitmap.width + GLYPH_SPACING <= b.x
+                    || b.x + b.bitmap.width + GLYPH_SPACING <= a.x;
+                let separated_y = a.y + a.bitmap.height + GLYPH_SPACING <= b.y
+                    || b.y + b.bitmap.height + GLYPH_SPACING <= a.y;
+
+                assert!(separated_x || separated_y);
+            }
+        }
+    }
+
+    #[test]
+    fn bmfont_offsets_are_relative_to_the_top_of_the_line() {
+        let mut a = glyph('A', 4, 6);
+        a.bitmap.left = 1;
+        a.x = 1;
+        a.y = 1;
+
+        let atlas = FontAtlas {
+            face_name: String::from("Test"),
+            size: 10,
+            line_height: 12,
+            base: 9,
+            width: 64,
+            height: 64,
+            glyphs: vec![a],
+            kerning: vec![('A', 'V', -2)],
+        };
+
+        let text = write_bmfont(&atlas, "test.dds");
+
+        assert!(text.contains("common lineHeight=12 base=9 scaleW=64 scaleH=64 pages=1"));
+        assert!(text.contains(
+            "char id=65 x=1 y=1 width=4 height=6 xoffset=1 yoffset=3 xadvance=4 page=0 chnl=15\n"
+        ));
+        assert!(text.contains("kerning first=65 second=86 amount=-2\n"));
+    }
+
+    #[test]
+    fn dds_header_describes_an_rgba8_texture() {
+        let file = write_dds(2, 1, &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert_eq!(file.len(), 4 + 124 + 20 + 8);
+        assert_eq!(&file[0..4], b"DDS ");
+        assert_eq!(&file[84..88], b"DX10");
+        assert_eq!(file[128], 28);
+        assert_eq!(&file[148..], &[1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::truetype::{Contour, OutlinePoint};
+
+// number of sub-scanlines per pixel row, horizontal coverage inside a sub-scanline is exact
+const SUBSAMPLES_Y: usize = 16;
+
+// anti-aliased coverage of a glyph, rows are stored top to bottom
+pub struct GlyphBitmap {
+    pub width: usize,
+    pub height: usize,
+    pub coverage: Vec<u8>,
+
+    // position of the top-left pixel relative to the pen position on the baseline, y going up
+    pub left: i32,
+    pub top: i32,
+}
+
+// converts a contour into a closed polyline
+// TrueType contours contain quadratic curves, two off curve points in a row have an implied on curve point between them
+fn flatten_contour(contour: &[OutlinePoint], scale: f32) -> Vec<(f32, f32)> {
+    let n = contour.len();
+
+    if n < 2 {
+        return Vec::new();
+    }
+
+    let mut points = Vec::with_capacity(n * 2);
+
+    for i in 0..n {
+        let current = contour[i];
+        let next = contour[(i + 1) % n];
+
+        points.push(current);
+
+        if !current.on_curve && !next.on_curve {
+            points.push(OutlinePoint {
+                x: (current.x + next.x) * 0.5,
+                y: (current.y + next.y) * 0.5,
+                on_curve: true,
+            });
+        }
+    }
+
+    // start on a point that is on the curve
+    let start = points.iter().position(|p| p.on_curve).unwrap();
+    points.rotate_left(start);
+
+    let to_pixels = |p: &OutlinePoint| (p.x * scale, p.y * scale);
+
+    let mut polyline = vec![to_pixels(&points[0])];
+
+    let mut i = 1;
+
+    while i <= points.len() {
+        let point = &points[i % points.len()];
+
+        if point.on_curve {
+            polyline.push(to_pixels(point));
+            i += 1;
+            continue;
+        }
+
+        let p0 = *polyline.last().unwrap();
+        let p1 = to_pixels(point);
+        let p2 = to_pixels(&points[(i + 1) % points.len()]);
+
+        // about one segment every 2 pixels of curve length is enough at the sizes glyphs are baked at
+        let length = ((p1.0 - p0.0).hypot(p1.1 - p0.1) + (p2.0 - p1.0).hypot(p2.1 - p1.1)).max(0.0);
+        let segments = ((length / 2.0).ceil() as usize).clamp(1, 64);
+
+        for segment in 1..=segments {
+            let t = segment as f32 / segments as f32;
+            let u = 1.0 - t;
+
+            polyline.push((
+                u * u * p0.0 + 2.0 * u * t * p1.0 + t * t * p2.0,
+                u * u * p0.1 + 2.0 * u * t * p1.1 + t * t * p2.1,
+            ));
+        }
+
+        i += 2;
+    }
+
+    polyline
+}
+
+// adds the coverage of the span [x0, x1) to a row, partially covered pixels get the covered fraction
+fn add_span(row: &mut [f32], x0: f32, x1: f32) {
+    let x0 = x0.max(0.0);
+    let x1 = x1.min(row.len() as f32);
+
+    if x0 >= x1 {
+        return;
+    }
+
+    let first = x0 as usize;
+    let last = x1 as usize;
+
+    if first == last {
+        row[first] += x1 - x0;
+        return;
+    }
+
+    row[first] += (first + 1) as f32 - x0;
+
+    for x in row.iter_mut().take(last).skip(first + 1) {
+        *x += 1.0;
+    }
+
+    if last < row.len() {
+        row[last] += x1 - last as f32;
+    }
+}
+
+// rasterizes the outline with the nonzero winding rule, scale converts from font units to pixels
+pub fn rasterize_glyph(contours: &[Contour], scale: f32) -> GlyphBitmap {
+    let polylines: Vec<Vec<(f32, f32)>> = contours
+        .iter()
+        .map(|contour| flatten_contour(contour, scale))
+        .filter(|polyline| polyline.len() > 2)
+        .collect();
+
+    let points = polylines.iter().flatten();
+
+    let min_x = points.clone().map(|p| p.0).fold(f32::MAX, f32::min);
+    let max_x = points.clone().map(|p| p.0).fold(f32::MIN, f32::max);
+    let min_y = points.clone().map(|p| p.1).fold(f32::MAX, f32::min);
+    let max_y = points.map(|p| p.1).fold(f32::MIN, f32::max);
+
+    if polylines.is_empty() || min_x >= max_x || min_y >= max_y {
+        return GlyphBitmap {
+            width: 0,
+            height: 0,
+            coverage: Vec::new(),
+            left: 0,
+            top: 0,
+        };
+    }
+
+    let left = min_x.floor() as i32;
+    let top = max_y.ceil() as i32;
+
+    let width = (max_x.ceil() as i32 - left) as usize;
+    let height = (top - min_y.floor() as i32) as usize;
+
+    // edges in bitmap space, y going down, with the winding direction of the edge
+    let mut edges = Vec::new();
+
+    for polyline in polylines.iter() {
+        for segment in polyline.windows(2) {
+            let (x0, y0) = (segment[0].0 - left as f32, top as f32 - segment[0].1);
+            let (x1, y1) = (segment[1].0 - left as f32, top as f32 - segment[1].1);
+
+            if y0 != y1 {
+                edges.push((x0, y0, x1, y1));
+            }
+        }
+    }
+
+    let mut accumulated = vec![0.0f32; width * height];
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+
+    for y in 0..height {
+        let row = &mut accumulated[(y * width)..((y + 1) * width)];
+
+        for sample in 0..SUBSAMPLES_Y {
+            let sample_y = y as f32 + (sample as f32 + 0.5) / SUBSAMPLES_Y as f32;
+
+            crossings.clear();
+
+            for (x0, y0, x1, y1) in edges.iter() {
+                let (upper, lower) = if y0 < y1 { (*y0, *y1) } else { (*y1, *y0) };
+
+                if sample_y < upper || sample_y >= lower {
+                    continue;
+                }
+
+                let x = x0 + (sample_y - y0) * (x1 - x0) / (y1 - y0);
+                let winding = if y1 > y0 { 1 } else { -1 };
+
+                crossings.push((x, winding));
+            }
+
+            crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            let mut winding = 0;
+            let mut span_start = 0.0;
+
+            for (x, direction) in crossings.iter() {
+                if winding == 0 {
+                    span_start = *x;
+                }
+
+                winding += direction;
+
+                if winding == 0 {
+                    add_span(row, span_start, *x);
+                }
+            }
+        }
+    }
+
+    let coverage = accumulated
+        .iter()
+        .map(|x| ((x / SUBSAMPLES_Y as f32) * 255.0).round().min(255.0) as u8)
+        .collect();
+
+    GlyphBitmap {
+        width,
+        height,
+        coverage,
+        left,
+        top,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(x0: f32, y0: f32, x1: f32, y1: f32, clockwise: bool) -> Contour {
+        let mut points = vec![(x0, y0), (x0, y1), (x1, y1), (x1, y0)];
+
+        if !clockwise {
+            points.reverse();
+        }
+
+        points
+            .into_iter()
+            .map(|(x, y)| OutlinePoint {
+                x,
+                y,
+                on_curve: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rectangle_with_partial_coverage() {
+        // 2.5 by 2 pixels starting half a pixel below the baseline
+        let bitmap = rasterize_glyph(&[rectangle(0.0, -1.0, 5.0, 3.0, true)], 0.5);
+
+        assert_eq!((bitmap.width, bitmap.height), (3, 3));
+        assert_eq!((bitmap.left, bitmap.top), (0, 2));
+
+        // the top and bottom rows are half covered, the right column is half covered
+        assert_eq!(
+            bitmap.coverage,
+            vec![128, 128, 64, 255, 255, 128, 128, 128, 64]
+        );
+    }
+
+    #[test]
+    fn holes_use_the_nonzero_winding_rule() {
+        let outer = rectangle(0.0, 0.0, 3.0, 3.0, true);
+
+        // an inner contour in the opposite direction cuts out the center pixel
+        let bitmap = rasterize_glyph(&[outer.clone(), rectangle(1.0, 1.0, 2.0, 2.0, false)], 1.0);
+        assert_eq!(bitmap.coverage[4], 0);
+        assert_eq!(bitmap.coverage[3], 255);
+
+        // with the same direction the contours overlap and the center stays filled
+        let bitmap = rasterize_glyph(&[outer, rectangle(1.0, 1.0, 2.0, 2.0, true)], 1.0);
+        assert_eq!(bitmap.coverage[4], 255);
+    }
+
+    #[test]
+    fn implied_on_curve_points_between_control_points() {
+        // a circle made only of off curve points at the corners of a square
+        let circle: Contour = [(-4.0, -4.0), (-4.0, 4.0), (4.0, 4.0), (4.0, -4.0)]
+            .iter()
+            .map(|&(x, y)| OutlinePoint {
+                x,
+                y,
+                on_curve: false,
+            })
+            .collect();
+
+        let bitmap = rasterize_glyph(&[circle], 1.0);
+
+        // the curves pass through the edge midpoints, so the shape spans 8 pixels but leaves the corners empty
+        assert_eq!((bitmap.width, bitmap.height), (8, 8));
+        assert_eq!(bitmap.coverage[0], 0);
+        assert_eq!(bitmap.coverage[3 * 8 + 3], 255);
+    }
+}
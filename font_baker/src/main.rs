@@ -0,0 +1,183 @@
+// offline tool that bakes a TrueType font into a glyph atlas for the game
+// writes <output>.fnt in the BMFont text format and <output>.dds with the glyph coverage in alpha
+// has no dependencies so it can be built and run on any platform, e.g.
+// cargo run --release --manifest-path font_baker/Cargo.toml -- font.ttf 32 out/font
+
+mod font_atlas;
+mod glyph_rasterizer;
+mod truetype;
+
+use font_atlas::*;
+use glyph_rasterizer::*;
+use truetype::*;
+
+struct CommandLineArgs {
+    font_path: String,
+    pixel_size: u32,
+
+    // path of the output files without extension
+    output_path: String,
+
+    // name written into the info line of the fnt file, the file name of the font by default
+    face_name: String,
+}
+
+fn parse_cmdline() -> Option<CommandLineArgs> {
+    let mut positional: Vec<String> = Vec::new();
+    let mut face_name = None;
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(argument) = args.next() {
+        // make sure we always compare against the lowercase version so that casing doesn't matter
+        let mut arg = argument.clone();
+        arg.make_ascii_lowercase();
+
+        // -name <face name>
+        if arg == "-name" {
+            face_name = args.next();
+            continue;
+        }
+
+        positional.push(argument);
+    }
+
+    if positional.len() != 3 {
+        return None;
+    }
+
+    let pixel_size = positional[1].parse::<u32>().ok().filter(|x| *x > 0)?;
+
+    let face_name = face_name.unwrap_or_else(|| {
+        std::path::Path::new(&positional[0])
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+
+    Some(CommandLineArgs {
+        font_path: positional[0].clone(),
+        pixel_size,
+        output_path: positional[2].clone(),
+        face_name,
+    })
+}
+
+// printable ASCII and the Latin-1 supplement, characters missing from the font are skipped
+fn baked_characters() -> impl Iterator<Item = char> {
+    (32u8..=126).chain(160u8..=255).map(char::from)
+}
+
+fn bake_font(font: &Font, face_name: &str, pixel_size: u32) -> Result<FontAtlas, TrueTypeError> {
+    // the pixel size is the size of the em square, the same as the size of fonts in most tools
+    let scale = pixel_size as f32 / f32::from(font.units_per_em);
+
+    let ascender = (f32::from(font.ascender) * scale).round() as i32;
+    let descender = (f32::from(font.descender) * scale).round() as i32;
+    let line_gap = (f32::from(font.line_gap) * scale).round() as i32;
+
+    let mut glyphs = Vec::new();
+    let mut glyph_indices = Vec::new();
+
+    for character in baked_characters() {
+        let glyph_index = match font.glyph_index(character)? {
+            Some(x) => x,
+            None => continue,
+        };
+
+        let outline = font.glyph_outline(glyph_index)?;
+
+        glyphs.push(BakedGlyph {
+            character,
+            bitmap: rasterize_glyph(&outline, scale),
+            advance: (f32::from(font.advance_width(glyph_index)?) * scale).round() as i32,
+            x: 0,
+            y: 0,
+        });
+
+        glyph_indices.push((glyph_index, character));
+    }
+
+    // only keep pairs that are between baked characters and still move the glyph after rounding to pixels
+    let mut kerning: Vec<(char, char, i32)> = font
+        .kerning_pairs()
+        .filter_map(|(&(left, right), &amount)| {
+            let first = glyph_indices.iter().find(|x| x.0 == left)?.1;
+            let second = glyph_indices.iter().find(|x| x.0 == right)?.1;
+
+            let amount = (f32::from(amount) * scale).round() as i32;
+
+            if amount == 0 {
+                None
+            } else {
+                Some((first, second, amount))
+            }
+        })
+        .collect();
+
+    // the kern table is sorted by glyph index, sort by character to make the output stable
+    kerning.sort();
+
+    let (width, height) = pack_glyphs(&mut glyphs);
+
+    Ok(FontAtlas {
+        face_name: face_name.to_string(),
+        size: pixel_size,
+        line_height: ascender - descender + line_gap,
+        base: ascender,
+        width,
+        height,
+        glyphs,
+        kerning,
+    })
+}
+
+fn main() {
+    let args = match parse_cmdline() {
+        Some(x) => x,
+        None => {
+            println!("usage: font_baker <font.ttf> <pixel size> <output path without extension> [-name <face name>]");
+            std::process::exit(1);
+        }
+    };
+
+    let font_data = std::fs::read(&args.font_path).unwrap_or_else(|error| {
+        println!("failed to read {}: {}", args.font_path, error);
+        std::process::exit(1);
+    });
+
+    let atlas = Font::parse(&font_data)
+        .and_then(|font| bake_font(&font, &args.face_name, args.pixel_size))
+        .unwrap_or_else(|error| {
+            println!("failed to bake {}: {}", args.font_path, error);
+            std::process::exit(1);
+        });
+
+    let dds_path = format!("{}.dds", args.output_path);
+    let fnt_path = format!("{}.fnt", args.output_path);
+
+    // the fnt file references the texture relative to its own location
+    let dds_file_name = std::path::Path::new(&dds_path)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+
+    let dds = write_dds(atlas.width, atlas.height, &atlas_texels(&atlas));
+    let fnt = write_bmfont(&atlas, &dds_file_name);
+
+    for (path, data) in [(&dds_path, dds), (&fnt_path, fnt.into_bytes())].iter() {
+        if let Err(error) = std::fs::write(path, data) {
+            println!("failed to write {}: {}", path, error);
+            std::process::exit(1);
+        }
+    }
+
+    println!(
+        "baked {} glyphs and {} kerning pairs into a {}x{} atlas",
+        atlas.glyphs.len(),
+        atlas.kerning.len(),
+        atlas.width,
+        atlas.height
+    );
+}
@@ -0,0 +1,635 @@
+// minimal TrueType parser, only reads the tables that are needed to bake a glyph atlas
+// supports fonts with glyf outlines, cmap formats 4 and 12 and horizontal kerning from a format 0 kern table
+// kerning that is only stored in the GPOS table is not supported
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+#[derive(Debug, PartialEq)]
+pub enum TrueTypeError {
+    UnexpectedEndOfFile,
+    MissingTable(&'static str),
+    NoUnicodeCmap,
+}
+
+impl std::fmt::Display for TrueTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TrueTypeError::UnexpectedEndOfFile => write!(f, "unexpected end of file"),
+            TrueTypeError::MissingTable(tag) => write!(f, "the font has no {} table", tag),
+            TrueTypeError::NoUnicodeCmap => write!(f, "the font has no unicode character map"),
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, TrueTypeError> {
+    data.get(offset..(offset + 2))
+        .map(|x| u16::from_be_bytes(x.try_into().unwrap()))
+        .ok_or(TrueTypeError::UnexpectedEndOfFile)
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Result<i16, TrueTypeError> {
+    read_u16(data, offset).map(|x| x as i16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, TrueTypeError> {
+    data.get(offset..(offset + 4))
+        .map(|x| u32::from_be_bytes(x.try_into().unwrap()))
+        .ok_or(TrueTypeError::UnexpectedEndOfFile)
+}
+
+// 2.14 fixed point number used by the transforms of composite glyphs
+fn read_f2dot14(data: &[u8], offset: usize) -> Result<f32, TrueTypeError> {
+    read_i16(data, offset).map(|x| f32::from(x) / 16384.0)
+}
+
+// a point of a glyph outline in font units, y going up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutlinePoint {
+    pub x: f32,
+    pub y: f32,
+    pub on_curve: bool, // off curve points are the control points of quadratic curves
+}
+
+pub type Contour = Vec<OutlinePoint>;
+
+#[derive(Clone, Copy, Debug)]
+enum CmapFormat {
+    SegmentMapping,    // format 4, only covers the basic multilingual plane
+    SegmentedCoverage, // format 12
+}
+
+pub struct Font<'a> {
+    data: &'a [u8],
+
+    pub units_per_em: u16,
+
+    // distances from the baseline in font units, descender is negative
+    pub ascender: i16,
+    pub descender: i16,
+    pub line_gap: i16,
+
+    index_to_loc_format: i16,
+    num_glyphs: u16,
+    number_of_h_metrics: u16,
+
+    cmap_offset: usize,
+    cmap_format: CmapFormat,
+
+    loca_offset: usize,
+    glyf_offset: usize,
+    hmtx_offset: usize,
+
+    // kerning adjustment in font units for pairs of glyph indices
+    kerning: HashMap<(u16, u16), i16>,
+}
+
+fn find_table(data: &[u8], tag: &'static str) -> Result<Option<usize>, TrueTypeError> {
+    let num_tables = read_u16(data, 4)?;
+
+    for i in 0..usize::from(num_tables) {
+        let record = 12 + i * 16;
+
+        if data.get(record..(record + 4)) == Some(tag.as_bytes()) {
+            return Ok(Some(read_u32(data, record + 8)? as usize));
+        }
+    }
+
+    Ok(None)
+}
+
+fn require_table(data: &[u8], tag: &'static str) -> Result<usize, TrueTypeError> {
+    find_table(data, tag)?.ok_or(TrueTypeError::MissingTable(tag))
+}
+
+// prefers the full unicode subtable and falls back to the basic multilingual plane
+fn find_unicode_cmap(data: &[u8], cmap: usize) -> Result<(usize, CmapFormat), TrueTypeError> {
+    let num_subtables = read_u16(data, cmap + 2)?;
+
+    let mut result = None;
+
+    for i in 0..usize::from(num_subtables) {
+        let record = cmap + 4 + i * 8;
+
+        let platform_id = read_u16(data, record)?;
+        let encoding_id = read_u16(data, record + 2)?;
+        let subtable = cmap + read_u32(data, record + 4)? as usize;
+
+        let is_unicode =
+            platform_id == 0 || (platform_id == 3 && (encoding_id == 1 || encoding_id == 10));
+
+        if !is_unicode {
+            continue;
+        }
+
+        match read_u16(data, subtable)? {
+            12 => return Ok((subtable, CmapFormat::SegmentedCoverage)),
+            4 => result = Some((subtable, CmapFormat::SegmentMapping)),
+            _ => {}
+        }
+    }
+
+    result.ok_or(TrueTypeError::NoUnicodeCmap)
+}
+
+fn parse_kern_table(data: &[u8], kern: usize) -> Result<HashMap<(u16, u16), i16>, TrueTypeError> {
+    let mut kerning = HashMap::new();
+
+    let num_subtables = read_u16(data, kern + 2)?;
+    let mut subtable = kern + 4;
+
+    for _ in 0..num_subtables {
+        let length = usize::from(read_u16(data, subtable + 2)?);
+        let coverage = read_u16(data, subtable + 4)?;
+
+        let format = coverage >> 8;
+        let is_horizontal = coverage & 0x1 != 0;
+        let is_minimum = coverage & 0x2 != 0;
+        let is_cross_stream = coverage & 0x4 != 0;
+
+        if format == 0 && is_horizontal && !is_minimum && !is_cross_stream {
+            let num_pairs = read_u16(data, subtable + 6)?;
+
+            for i in 0..usize::from(num_pairs) {
+                let pair = subtable + 14 + i * 6;
+
+                kerning.insert(
+                    (read_u16(data, pair)?, read_u16(data, pair + 2)?),
+                    read_i16(data, pair + 4)?,
+                );
+            }
+        }
+
+        subtable += length;
+    }
+
+    Ok(kerning)
+}
+
+impl<'a> Font<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Font<'a>, TrueTypeError> {
+        let head = require_table(data, "head")?;
+        let hhea = require_table(data, "hhea")?;
+        let maxp = require_table(data, "maxp")?;
+        let cmap = require_table(data, "cmap")?;
+
+        let (cmap_offset, cmap_format) = find_unicode_cmap(data, cmap)?;
+
+        let kerning = match find_table(data, "kern")? {
+            Some(kern) => parse_kern_table(data, kern)?,
+            None => HashMap::new(),
+        };
+
+        Ok(Font {
+            data,
+            units_per_em: read_u16(data, head + 18)?,
+            ascender: read_i16(data, hhea + 4)?,
+            descender: read_i16(data, hhea + 6)?,
+            line_gap: read_i16(data, hhea + 8)?,
+            index_to_loc_format: read_i16(data, head + 50)?,
+            num_glyphs: read_u16(data, maxp + 4)?,
+            number_of_h_metrics: read_u16(data, hhea + 34)?,
+            cmap_offset,
+            cmap_format,
+            loca_offset: require_table(data, "loca")?,
+            glyf_offset: require_table(data, "glyf")?,
+            hmtx_offset: require_table(data, "hmtx")?,
+            kerning,
+        })
+    }
+
+    // returns None if the font doesn't contain a glyph for the character
+    pub fn glyph_index(&self, character: char) -> Result<Option<u16>, TrueTypeError> {
+        let codepoint = character as u32;
+        let data = self.data;
+        let cmap = self.cmap_offset;
+
+        let glyph = match self.cmap_format {
+            CmapFormat::SegmentMapping => {
+                if codepoint > 0xffff {
+                    return Ok(None);
+                }
+
+                let seg_count = usize::from(read_u16(data, cmap + 6)? / 2);
+
+                let end_codes = cmap + 14;
+                let start_codes = end_codes + seg_count * 2 + 2;
+                let id_deltas = start_codes + seg_count * 2;
+                let id_range_offsets = id_deltas + seg_count * 2;
+
+                let mut glyph = 0;
+
+                for i in 0..seg_count {
+                    let end_code = u32::from(read_u16(data, end_codes + i * 2)?);
+
+                    if end_code < codepoint {
+                        continue;
+                    }
+
+                    let start_code = u32::from(read_u16(data, start_codes + i * 2)?);
+
+                    if start_code > codepoint {
+                        break;
+                    }
+
+                    let id_delta = u32::from(read_u16(data, id_deltas + i * 2)?);
+                    let id_range_offset = usize::from(read_u16(data, id_range_offsets + i * 2)?);
+
+                    glyph = if id_range_offset == 0 {
+                        (codepoint + id_delta) & 0xffff
+                    } else {
+                        // the offset is relative to the location of the offset itself
+                        let glyph_id_offset = id_range_offsets
+                            + i * 2
+                            + id_range_offset
+                            + (codepoint - start_code) as usize * 2;
+
+                        match u32::from(read_u16(data, glyph_id_offset)?) {
+                            0 => 0,
+                            x => (x + id_delta) & 0xffff,
+                        }
+                    };
+
+                    break;
+                }
+
+                glyph
+            }
+
+            CmapFormat::SegmentedCoverage => {
+                let num_groups = read_u32(data, cmap + 12)? as usize;
+
+                let mut glyph = 0;
+
+                for i in 0..num_groups {
+                    let group = cmap + 16 + i * 12;
+
+                    let start_code = read_u32(data, group)?;
+                    let end_code = read_u32(data, group + 4)?;
+
+                    if (start_code..=end_code).contains(&codepoint) {
+                        glyph = read_u32(data, group + 8)? + (codepoint - start_code);
+                        break;
+                    }
+                }
+
+                glyph
+            }
+        };
+
+        // glyph 0 is the missing character glyph
+        if glyph == 0 || glyph >= u32::from(self.num_glyphs) {
+            Ok(None)
+        } else {
+            Ok(Some(glyph as u16))
+        }
+    }
+
+    pub fn advance_width(&self, glyph: u16) -> Result<u16, TrueTypeError> {
+        // glyphs after the last metric share the advance of the last metric, this is used by monospaced fonts
+        let metric = glyph.min(self.number_of_h_metrics - 1);
+
+        read_u16(self.data, self.hmtx_offset + usize::from(metric) * 4)
+    }
+
+    pub fn kerning_pairs(&self) -> impl Iterator<Item = (&(u16, u16), &i16)> {
+        self.kerning.iter()
+    }
+
+    // returns the byte range of the glyph in the glyf table, empty for glyphs without an outline
+    fn glyph_data(&self, glyph: u16) -> Result<&'a [u8], TrueTypeError> {
+        let index = usize::from(glyph);
+
+        let (start, end) = if self.index_to_loc_format == 0 {
+            (
+                usize::from(read_u16(self.data, self.loca_offset + index * 2)?) * 2,
+                usize::from(read_u16(self.data, self.loca_offset + index * 2 + 2)?) * 2,
+            )
+        } else {
+            (
+                read_u32(self.data, self.loca_offset + index * 4)? as usize,
+                read_u32(self.data, self.loca_offset + index * 4 + 4)? as usize,
+            )
+        };
+
+        if end <= start {
+            return Ok(&[]);
+        }
+
+        self.data
+            .get((self.glyf_offset + start)..(self.glyf_offset + end))
+            .ok_or(TrueTypeError::UnexpectedEndOfFile)
+    }
+
+    pub fn glyph_outline(&self, glyph: u16) -> Result<Vec<Contour>, TrueTypeError> {
+        let mut contours = Vec::new();
+
+        self.append_glyph_outline(glyph, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], 0, &mut contours)?;
+
+        Ok(contours)
+    }
+
+    // transform is [xx, xy, yx, yy, dx, dy], composite glyphs reference other glyphs with a transform
+    fn append_glyph_outline(
+        &self,
+        glyph: u16,
+        transform: [f32; 6],
+        depth: u32,
+        contours: &mut Vec<Contour>,
+    ) -> Result<(), TrueTypeError> {
+        // guards against fonts with cyclic composite glyphs
+        if depth > 8 {
+            return Ok(());
+        }
+
+        let data = self.glyph_data(glyph)?;
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let number_of_contours = read_i16(data, 0)?;
+
+        if number_of_contours >= 0 {
+            for contour in parse_simple_glyph(data, number_of_contours as usize)? {
+                contours.push(
+                    contour
+                        .iter()
+                        .map(|p| OutlinePoint {
+                            x: transform[0] * p.x + transform[2] * p.y + transform[4],
+                            y: transform[1] * p.x + transform[3] * p.y + transform[5],
+                            on_curve: p.on_curve,
+                        })
+                        .collect(),
+                );
+            }
+
+            return Ok(());
+        }
+
+        const ARG_1_AND_2_ARE_WORDS: u16 = 0x1;
+        const ARGS_ARE_XY_VALUES: u16 = 0x2;
+        const WE_HAVE_A_SCALE: u16 = 0x8;
+        const MORE_COMPONENTS: u16 = 0x20;
+        const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x40;
+        const WE_HAVE_A_TWO_BY_TWO: u16 = 0x80;
+
+        let mut cursor = 10;
+
+        loop {
+            let flags = read_u16(data, cursor)?;
+            let component_glyph = read_u16(data, cursor + 2)?;
+            cursor += 4;
+
+            let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                cursor += 4;
+                (
+                    f32::from(read_i16(data, cursor - 4)?),
+                    f32::from(read_i16(data, cursor - 2)?),
+                )
+            } else {
+                cursor += 2;
+                (
+                    f32::from(data[cursor - 2] as i8),
+                    f32::from(data[cursor - 1] as i8),
+                )
+            };
+
+            // matching points of the parent and the component is not supported, the component is not moved
+            let (dx, dy) = if flags & ARGS_ARE_XY_VALUES != 0 {
+                (arg1, arg2)
+            } else {
+                (0.0, 0.0)
+            };
+
+            let [mut xx, mut xy, mut yx, mut yy] = [1.0, 0.0, 0.0, 1.0];
+
+            if flags & WE_HAVE_A_SCALE != 0 {
+                xx = read_f2dot14(data, cursor)?;
+                yy = xx;
+                cursor += 2;
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                xx = read_f2dot14(data, cursor)?;
+                yy = read_f2dot14(data, cursor + 2)?;
+                cursor += 4;
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                xx = read_f2dot14(data, cursor)?;
+                xy = read_f2dot14(data, cursor + 2)?;
+                yx = read_f2dot14(data, cursor + 4)?;
+                yy = read_f2dot14(data, cursor + 6)?;
+                cursor += 8;
+            }
+
+            // apply the component transform first and the parent transform afterwards
+            let combined = [
+                transform[0] * xx + transform[2] * xy,
+                transform[1] * xx + transform[3] * xy,
+                transform[0] * yx + transform[2] * yy,
+                transform[1] * yx + transform[3] * yy,
+                transform[0] * dx + transform[2] * dy + transform[4],
+                transform[1] * dx + transform[3] * dy + transform[5],
+            ];
+
+            self.append_glyph_outline(component_glyph, combined, depth + 1, contours)?;
+
+            if flags & MORE_COMPONENTS == 0 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_simple_glyph(
+    data: &[u8],
+    number_of_contours: usize,
+) -> Result<Vec<Contour>, TrueTypeError> {
+    const ON_CURVE_POINT: u8 = 0x1;
+    const X_SHORT_VECTOR: u8 = 0x2;
+    const Y_SHORT_VECTOR: u8 = 0x4;
+    const REPEAT_FLAG: u8 = 0x8;
+    const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+
+    if number_of_contours == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut end_points = Vec::with_capacity(number_of_contours);
+
+    for i in 0..number_of_contours {
+        end_points.push(usize::from(read_u16(data, 10 + i * 2)?));
+    }
+
+    let num_points = end_points[number_of_contours - 1] + 1;
+
+    // skip the hinting instructions
+    let instruction_length = usize::from(read_u16(data, 10 + number_of_contours * 2)?);
+    let mut cursor = 12 + number_of_contours * 2 + instruction_length;
+
+    let read_u8 = |offset: usize| -> Result<u8, TrueTypeError> {
+        data.get(offset)
+            .copied()
+            .ok_or(TrueTypeError::UnexpectedEndOfFile)
+    };
+
+    let mut flags = Vec::with_capacity(num_points);
+
+    while flags.len() < num_points {
+        let flag = read_u8(cursor)?;
+        cursor += 1;
+
+        flags.push(flag);
+
+        if flag & REPEAT_FLAG != 0 {
+            let repeat_count = read_u8(cursor)?;
+            cursor += 1;
+
+            for _ in 0..repeat_count {
+                flags.push(flag);
+            }
+        }
+    }
+
+    flags.truncate(num_points);
+
+    // coordinates are stored as deltas to the previous point
+    let mut read_coordinates = |short_flag: u8, same_or_positive_flag: u8| {
+        let mut values = Vec::with_capacity(num_points);
+        let mut value: i32 = 0;
+
+        for flag in flags.iter() {
+            if flag & short_flag != 0 {
+                let delta = i32::from(read_u8(cursor)?);
+                cursor += 1;
+
+                value += if flag & same_or_positive_flag != 0 {
+                    delta
+                } else {
+                    -delta
+                };
+            } else if flag & same_or_positive_flag == 0 {
+                value += i32::from(read_i16(data, cursor)?);
+                cursor += 2;
+            }
+
+            values.push(value as f32);
+        }
+
+        Ok(values)
+    };
+
+    let xs = read_coordinates(X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE)?;
+    let ys = read_coordinates(Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE)?;
+
+    let mut contours = Vec::with_capacity(number_of_contours);
+    let mut start = 0;
+
+    for end in end_points {
+        if end < start || end >= num_points {
+            return Err(TrueTypeError::UnexpectedEndOfFile);
+        }
+
+        contours.push(
+            (start..=end)
+                .map(|i| OutlinePoint {
+                    x: xs[i],
+                    y: ys[i],
+                    on_curve: flags[i] & ON_CURVE_POINT != 0,
+                })
+                .collect(),
+        );
+
+        start = end + 1;
+    }
+
+    Ok(contours)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_u16(data: &mut Vec<u8>, value: u16) {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    #[test]
+    fn cmap_format_4_lookup() {
+        // 'A' to 'C' map to glyphs 10 to 12 through the delta, the last segment is the required 0xffff end segment
+        let mut cmap = Vec::new();
+
+        for x in [4, 0, 0, 4, 0, 0, 0].iter() {
+            push_u16(&mut cmap, *x);
+        }
+
+        push_u16(&mut cmap, 0x43); // end codes
+        push_u16(&mut cmap, 0xffff);
+        push_u16(&mut cmap, 0); // reserved
+        push_u16(&mut cmap, 0x41); // start codes
+        push_u16(&mut cmap, 0xffff);
+        push_u16(&mut cmap, (10u16).wrapping_sub(0x41)); // deltas
+        push_u16(&mut cmap, 1);
+        push_u16(&mut cmap, 0); // range offsets
+        push_u16(&mut cmap, 0);
+
+        let font = Font {
+            data: &cmap,
+            units_per_em: 2048,
+            ascender: 0,
+            descender: 0,
+            line_gap: 0,
+            index_to_loc_format: 0,
+            num_glyphs: 20,
+            number_of_h_metrics: 1,
+            cmap_offset: 0,
+            cmap_format: CmapFormat::SegmentMapping,
+            loca_offset: 0,
+            glyf_offset: 0,
+            hmtx_offset: 0,
+            kerning: HashMap::new(),
+        };
+
+        assert_eq!(font.glyph_index('A'), Ok(Some(10)));
+        assert_eq!(font.glyph_index('C'), Ok(Some(12)));
+        assert_eq!(font.glyph_index('D'), Ok(None));
+        assert_eq!(font.glyph_index('@'), Ok(None));
+    }
+
+    #[test]
+    fn simple_glyph_with_repeated_flags_and_short_coordinates() {
+        // outline (0, 0), (100, 0), (200, 0), (100, 80), the middle points are control points of curves
+        let mut glyph = Vec::new();
+
+        for x in [1, 0, 0, 200, 80].iter() {
+            push_u16(&mut glyph, *x); // contour count and bounding box
+        }
+
+        push_u16(&mut glyph, 3); // end point of the contour
+        push_u16(&mut glyph, 0); // no instructions
+
+        glyph.push(0x1 | 0x10 | 0x20); // x and y are the same as the origin
+        glyph.push(0x2 | 0x8 | 0x10 | 0x20); // short positive x vector, repeated for the next point
+        glyph.push(1);
+        glyph.push(0x1 | 0x2 | 0x4 | 0x20); // short negative x vector and short positive y vector
+
+        glyph.extend_from_slice(&[100, 100, 100]); // x deltas
+        glyph.push(80); // y delta
+
+        let contours = parse_simple_glyph(&glyph, 1).unwrap();
+
+        let points: Vec<(f32, f32, bool)> =
+            contours[0].iter().map(|p| (p.x, p.y, p.on_curve)).collect();
+
+        assert_eq!(
+            points,
+            vec![
+                (0.0, 0.0, true),
+                (100.0, 0.0, false),
+                (200.0, 0.0, false),
+                (100.0, 80.0, true)
+            ]
+        );
+    }
+}
@@ -0,0 +1,509 @@
+// computes the memory layout of cbuffer and struct declarations in HLSL source code
+// used to validate that the rust structs uploaded as constants match the layout the shaders expect
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstantBufferField {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstantBufferLayout {
+    pub name: String,
+    pub fields: Vec<ConstantBufferField>,
+
+    // size without the padding to the next 16 byte register
+    pub size: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConstantBufferLayoutError {
+    UnexpectedEndOfFile,
+    UnexpectedToken(String),
+    UnknownType(String),
+
+    // the rust and HLSL declarations don't match
+    FieldMismatch {
+        hlsl: Option<ConstantBufferField>,
+        rust: Option<ConstantBufferField>,
+    },
+    SizeMismatch {
+        hlsl_size: usize,
+        rust_size: usize,
+    },
+}
+
+// implemented by constant_buffer_struct!, returns the fields in declaration order
+pub trait ConstantBufferData {
+    fn constant_buffer_fields() -> Vec<ConstantBufferField>;
+}
+
+// declares a #[repr(C)] struct and implements ConstantBufferData for it
+// this makes sure the fields that are validated are always the fields of the struct
+#[macro_export]
+macro_rules! constant_buffer_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : $field_type:ty),* $(,)?
+        }
+    ) => {
+        #[repr(C)]
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $field_type),*
+        }
+
+        impl $crate::cbuffer_layout::ConstantBufferData for $name {
+            fn constant_buffer_fields() -> Vec<$crate::cbuffer_layout::ConstantBufferField> {
+                vec![$($crate::cbuffer_layout::ConstantBufferField {
+                    name: String::from(stringify!($field)),
+                    offset: std::mem::offset_of!($name, $field),
+                    size: std::mem::size_of::<$field_type>(),
+                }),*]
+            }
+        }
+    };
+}
+
+fn round_up_to_register(offset: usize) -> usize {
+    offset.div_ceil(16) * 16
+}
+
+#[derive(Clone, Copy)]
+struct HlslType {
+    size: usize,
+
+    // structs and matrices always start on a new register, vectors only if they would cross a register boundary
+    starts_register: bool,
+}
+
+fn remove_comments(source: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while !rest.is_empty() {
+        if let Some(x) = rest.strip_prefix("//") {
+            rest = x.find('\n').map_or("", |end| &x[end..]);
+        } else if let Some(x) = rest.strip_prefix("/*") {
+            rest = x.find("*/").map_or("", |end| &x[end + 2..]);
+            result.push(' ');
+        } else {
+            let c = rest.chars().next().unwrap();
+            result.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    result
+}
+
+fn tokenize(source: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' {
+            let mut token = c.to_string();
+
+            while let Some(x) = chars.peek().copied() {
+                if !x.is_alphanumeric() && x != '_' {
+                    break;
+                }
+
+                token.push(x);
+                chars.next();
+            }
+
+            tokens.push(token);
+        } else {
+            tokens.push(c.to_string());
+        }
+    }
+
+    tokens
+}
+
+// modifiers that don't change the layout
+const TYPE_MODIFIERS: [&str; 9] = [
+    "nointerpolation",
+    "linear",
+    "centroid",
+    "noperspective",
+    "sample",
+    "precise",
+    "column_major",
+    "const",
+    "uniform",
+];
+
+fn parse_builtin_type(name: &str) -> Option<HlslType> {
+    let scalar_types = ["float", "int", "uint", "bool", "half", "dword"];
+
+    let base = scalar_types.iter().find(|x| name.starts_with(*x))?;
+    let dimensions = &name[base.len()..];
+
+    let parse_dimension = |x: &str| -> Option<usize> {
+        match x.parse::<usize>() {
+            Ok(x) if (1..=4).contains(&x) => Some(x),
+            _ => None,
+        }
+    };
+
+    if dimensions.is_empty() {
+        return Some(HlslType {
+            size: 4,
+            starts_register: false,
+        });
+    }
+
+    match dimensions.split_once('x') {
+        // column major matrix, each column uses one register
+        Some((rows, columns)) => {
+            let rows = parse_dimension(rows)?;
+            let columns = parse_dimension(columns)?;
+
+            Some(HlslType {
+                size: (columns - 1) * 16 + rows * 4,
+                starts_register: true,
+            })
+        }
+
+        None => Some(HlslType {
+            size: parse_dimension(dimensions)? * 4,
+            starts_register: false,
+        }),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+    structs: Vec<ConstantBufferLayout>,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Result<&'a str, ConstantBufferLayoutError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or(ConstantBufferLayoutError::UnexpectedEndOfFile)?;
+
+        self.position += 1;
+
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ConstantBufferLayoutError> {
+        match self.next()? {
+            x if x == expected => Ok(()),
+            x => Err(ConstantBufferLayoutError::UnexpectedToken(x.to_string())),
+        }
+    }
+
+    fn find_type(&self, name: &str) -> Result<HlslType, ConstantBufferLayoutError> {
+        if let Some(x) = self.structs.iter().find(|x| x.name == name) {
+            return Ok(HlslType {
+                size: x.size,
+                starts_register: true,
+            });
+        }
+
+        parse_builtin_type(name)
+            .ok_or_else(|| ConstantBufferLayoutError::UnknownType(name.to_string()))
+    }
+
+    // parses the members between { and }
+    fn parse_members(
+        &mut self,
+        name: &str,
+    ) -> Result<ConstantBufferLayout, ConstantBufferLayoutError> {
+        self.expect("{")?;
+
+        let mut layout = ConstantBufferLayout {
+            name: name.to_string(),
+            fields: Vec::new(),
+            size: 0,
+        };
+
+        loop {
+            let mut token = self.next()?.to_string();
+
+            if token == "}" {
+                return Ok(layout);
+            }
+
+            while TYPE_MODIFIERS.contains(&token.as_str()) {
+                token = self.next()?.to_string();
+            }
+
+            let member_type = self.find_type(&token)?;
+            let member_name = self.next()?.to_string();
+
+            let mut token = self.next()?.to_string();
+
+            let mut array_size: Option<usize> = None;
+
+            if token == "[" {
+                let size = self.next()?.to_string();
+                array_size = Some(
+                    size.parse()
+                        .map_err(|_| ConstantBufferLayoutError::UnexpectedToken(size))?,
+                );
+
+                self.expect("]")?;
+                token = self.next()?.to_string();
+            }
+
+            // semantics are ignored, explicit packing is not supported
+            if token == ":" {
+                let semantic = self.next()?.to_string();
+
+                if semantic == "packoffset" {
+                    return Err(ConstantBufferLayoutError::UnexpectedToken(semantic));
+                }
+
+                token = self.next()?.to_string();
+            }
+
+            if token != ";" {
+                return Err(ConstantBufferLayoutError::UnexpectedToken(token));
+            }
+
+            let (offset, size) = match array_size {
+                // every array element starts on a new register
+                Some(count) => (
+                    round_up_to_register(layout.size),
+                    round_up_to_register(member_type.size) * (count.max(1) - 1) + member_type.size,
+                ),
+
+                None => {
+                    let crosses_register = layout.size % 16 + member_type.size > 16;
+
+                    if member_type.starts_register || crosses_register {
+                        (round_up_to_register(layout.size), member_type.size)
+                    } else {
+                        (layout.size, member_type.size)
+                    }
+                }
+            };
+
+            layout.fields.push(ConstantBufferField {
+                name: member_name,
+                offset,
+                size,
+            });
+
+            layout.size = offset + size;
+        }
+    }
+}
+
+// returns the layouts of all cbuffer and struct declarations in the order they are declared
+pub fn parse_constant_buffer_layouts(
+    source: &str,
+) -> Result<Vec<ConstantBufferLayout>, ConstantBufferLayoutError> {
+    let tokens = tokenize(&remove_comments(source));
+
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        structs: Vec::new(),
+    };
+
+    let mut layouts = Vec::new();
+
+    while parser.position < tokens.len() {
+        match parser.next()? {
+            "struct" => {
+                let name = parser.next()?.to_string();
+                let layout = parser.parse_members(&name)?;
+                parser.expect(";")?;
+
+                // structs can be used as types of later members
+                parser.structs.push(layout.clone());
+                layouts.push(layout);
+            }
+
+            "cbuffer" => {
+                let name = parser.next()?.to_string();
+
+                // skip the register binding
+                while parser.tokens.get(parser.position).map(|x| x.as_str()) != Some("{") {
+                    parser.next()?;
+                }
+
+                layouts.push(parser.parse_members(&name)?);
+
+                if parser.tokens.get(parser.position).map(|x| x.as_str()) == Some(";") {
+                    parser.position += 1;
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(layouts)
+}
+
+// checks that T has the same fields in the same order at the same offsets as the HLSL declaration
+// both need to use the same number of 16 byte registers
+pub fn validate_constant_buffer_data<T: ConstantBufferData>(
+    layout: &ConstantBufferLayout,
+) -> Result<(), ConstantBufferLayoutError> {
+    let rust_fields = T::constant_buffer_fields();
+    let field_count = rust_fields.len().max(layout.fields.len());
+
+    for i in 0..field_count {
+        let hlsl = layout.fields.get(i);
+        let rust = rust_fields.get(i);
+
+        if hlsl != rust {
+            return Err(ConstantBufferLayoutError::FieldMismatch {
+                hlsl: hlsl.cloned(),
+                rust: rust.cloned(),
+            });
+        }
+    }
+
+    let rust_size = std::mem::size_of::<T>();
+
+    if round_up_to_register(rust_size) != round_up_to_register(layout.size) {
+        return Err(ConstantBufferLayoutError::SizeMismatch {
+            hlsl_size: layout.size,
+            rust_size,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, offset: usize, size: usize) -> ConstantBufferField {
+        ConstantBufferField {
+            name: name.to_string(),
+            offset,
+            size,
+        }
+    }
+
+    #[test]
+    fn members_are_packed_into_registers() {
+        let source = "
+            // comments are ignored
+            struct Item
+            {
+                float3 position;
+                float scale;
+            };
+
+            cbuffer Constants : register(b0)
+            {
+                float a;      /* packed with b */
+                float2 b;
+                float2 c;     // would cross the register boundary
+                float d[2];   // array elements start on a new register
+                float e;      // can be packed behind the last element
+                Item item;
+                float4x4 transform;
+            };
+        ";
+
+        let layouts = parse_constant_buffer_layouts(source).unwrap();
+
+        assert_eq!(layouts.len(), 2);
+        assert_eq!(layouts[0].size, 16);
+
+        assert_eq!(
+            layouts[1].fields,
+            vec![
+                field("a", 0, 4),
+                field("b", 4, 8),
+                field("c", 16, 8),
+                field("d", 32, 20),
+                field("e", 52, 4),
+                field("item", 64, 16),
+                field("transform", 80, 64),
+            ]
+        );
+
+        assert_eq!(layouts[1].size, 144);
+    }
+
+    constant_buffer_struct! {
+        struct QuadData {
+            color: [f32; 4],
+            size: [i32; 2],
+            position: [i32; 2],
+        }
+    }
+
+    constant_buffer_struct! {
+        struct SwappedQuadData {
+            color: [f32; 4],
+            position: [i32; 2],
+            size: [i32; 2],
+        }
+    }
+
+    constant_buffer_struct! {
+        struct PaddedQuadData {
+            color: [f32; 3],
+            size: [i32; 2],
+            position: [i32; 2],
+        }
+    }
+
+    #[test]
+    fn rust_structs_are_validated_against_the_hlsl_layout() {
+        let source = "
+            cbuffer QuadData : register(b0)
+            {
+                float4 color;
+                int2 size;
+                int2 position;
+            };
+        ";
+
+        let layouts = parse_constant_buffer_layouts(source).unwrap();
+
+        assert_eq!(
+            validate_constant_buffer_data::<QuadData>(&layouts[0]),
+            Ok(())
+        );
+
+        // field order
+        assert!(matches!(
+            validate_constant_buffer_data::<SwappedQuadData>(&layouts[0]),
+            Err(ConstantBufferLayoutError::FieldMismatch { .. })
+        ));
+
+        // the HLSL int2 doesn't fit into the first register anymore
+        assert_eq!(
+            validate_constant_buffer_data::<PaddedQuadData>(&layouts[0]),
+            Err(ConstantBufferLayoutError::FieldMismatch {
+                hlsl: Some(field("color", 0, 16)),
+                rust: Some(field("color", 0, 12)),
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_types_are_reported() {
+        assert_eq!(
+            parse_constant_buffer_layouts("cbuffer A { float5 x; };"),
+            Err(ConstantBufferLayoutError::UnknownType(String::from(
+                "float5"
+            )))
+        );
+    }
+}
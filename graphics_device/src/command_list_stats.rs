@@ -0,0 +1,10 @@
+// counters for the commands that have been recorded into a command list, every backend keeps them
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CommandListStats {
+    pub render_passes: u32,
+    pub draw_calls: u32,
+    pub pso_binds: u32,
+    pub texture_binds: u32,
+    pub constant_binds: u32,
+}
@@ -0,0 +1,304 @@
+// command list backend that doesn't execute any commands
+// instead it records what has been requested, this allows tests to validate what would be drawn
+
+use crate::command_list_stats::CommandListStats;
+use crate::software_rasterizer::{
+    decode_quad_constants, decode_quad_constants_array, BlendMode, ClipRect, ClipStack,
+    QuadConstants, QuadShader, SamplerDesc, QUAD_BATCH_STRIDE,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedConstants {
+    Quad(QuadConstants),
+
+    // batched shaders, one entry per instance. contains the padding of the allocation too
+    QuadArray(Vec<QuadConstants>),
+
+    // the bound shader is unknown or the allocation is too small, only the raw bytes are available
+    Raw(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedCommand {
+    BeginRenderPass {
+        render_target: String,
+    },
+    ClearRenderTarget {
+        color: [f32; 4],
+    },
+    BindPipelineState {
+        shader_name: String,
+        blend_mode: BlendMode,
+        sampler: SamplerDesc,
+    },
+    BindTexture {
+        bind_slot: u32,
+        texture_name: String,
+    },
+    BindConstants {
+        bind_slot: u32,
+        first_constant: u32,
+        num_constants: u32,
+        constants: RecordedConstants,
+    },
+    PushClipRect {
+        rect: ClipRect,
+    },
+    PopClipRect,
+    Draw {
+        vertex_count: u32,
+        instance_count: u32,
+    },
+}
+
+// the state that was bound when a draw call was issued
+#[derive(Clone, Debug)]
+pub struct RecordedDraw {
+    pub render_target: String,
+    pub shader_name: String,
+    pub blend_mode: BlendMode,
+    pub sampler: SamplerDesc,
+    pub textures: Vec<Option<String>>,
+    pub constants: Vec<Option<RecordedConstants>>,
+
+    // the intersection of all clip rects that were pushed, None if the draw isn't clipped
+    pub clip_rect: Option<ClipRect>,
+    pub vertex_count: u32,
+    pub instance_count: u32,
+}
+
+impl RecordedDraw {
+    pub fn texture(&self, bind_slot: u32) -> Option<&str> {
+        self.textures
+            .get(bind_slot as usize)
+            .and_then(|x| x.as_deref())
+    }
+
+    // returns the decoded quad constants bound to the slot
+    pub fn quad_constants(&self, bind_slot: u32) -> Option<&QuadConstants> {
+        match self.constants.get(bind_slot as usize) {
+            Some(Some(RecordedConstants::Quad(x))) => Some(x),
+            Some(Some(RecordedConstants::QuadArray(x))) => x.first(),
+            _ => None,
+        }
+    }
+
+    // returns the quad constants used by each instance of the draw
+    pub fn quad_instances(&self, bind_slot: u32) -> Vec<QuadConstants> {
+        match self.constants.get(bind_slot as usize) {
+            Some(Some(RecordedConstants::Quad(x))) => vec![*x; self.instance_count as usize],
+            Some(Some(RecordedConstants::QuadArray(x))) => x
+                .iter()
+                .take(self.instance_count as usize)
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+pub struct CommandRecorder {
+    pub commands: Vec<RecordedCommand>,
+
+    // needed to decode the constants
+    bound_shader: Option<QuadShader>,
+
+    // the clip rects that have been pushed and not popped yet
+    clip_stack: ClipStack,
+
+    pub stats: CommandListStats,
+}
+
+impl CommandRecorder {
+    pub fn new() -> CommandRecorder {
+        CommandRecorder {
+            commands: Vec::new(),
+            bound_shader: None,
+            clip_stack: ClipStack::new(),
+            stats: CommandListStats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.bound_shader = None;
+        self.clip_stack = ClipStack::new();
+    }
+
+    pub fn begin_render_pass(&mut self, render_target: &str) {
+        self.commands.push(RecordedCommand::BeginRenderPass {
+            render_target: render_target.to_string(),
+        });
+    }
+
+    pub fn clear_render_target(&mut self, color: [f32; 4]) {
+        self.commands
+            .push(RecordedCommand::ClearRenderTarget { color });
+    }
+
+    pub fn bind_pipeline_state(
+        &mut self,
+        shader_name: &str,
+        shader: Option<QuadShader>,
+        blend_mode: BlendMode,
+        sampler: SamplerDesc,
+    ) {
+        self.bound_shader = shader;
+
+        self.commands.push(RecordedCommand::BindPipelineState {
+            shader_name: shader_name.to_string(),
+            blend_mode,
+            sampler,
+        });
+    }
+
+    pub fn bind_texture(&mut self, bind_slot: u32, texture_name: &str) {
+        self.commands.push(RecordedCommand::BindTexture {
+            bind_slot,
+            texture_name: texture_name.to_string(),
+        });
+    }
+
+    pub fn bind_constants(
+        &mut self,
+        bind_slot: u32,
+        first_constant: u32,
+        num_constants: u32,
+        data: &[u8],
+    ) {
+        let constants = match self.bound_shader {
+            Some(QuadShader::GameSpaceQuadBatch) if data.len() >= QUAD_BATCH_STRIDE => {
+                let instance_count = (data.len() / QUAD_BATCH_STRIDE) as u32;
+
+                RecordedConstants::QuadArray(decode_quad_constants_array(
+                    QuadShader::GameSpaceQuadBatch,
+                    data,
+                    instance_count,
+                ))
+            }
+
+            shader => match shader.and_then(|shader| decode_quad_constants(shader, data)) {
+                Some(x) => RecordedConstants::Quad(x),
+                None => RecordedConstants::Raw(data.to_vec()),
+            },
+        };
+
+        self.commands.push(RecordedCommand::BindConstants {
+            bind_slot,
+            first_constant,
+            num_constants,
+            constants,
+        });
+    }
+
+    pub fn push_clip_rect(&mut self, rect: ClipRect) {
+        self.clip_stack.push(rect);
+        self.commands.push(RecordedCommand::PushClipRect { rect });
+    }
+
+    // None if there is no rect to pop, nothing is recorded in that case
+    pub fn pop_clip_rect(&mut self) -> Option<ClipRect> {
+        let rect = self.clip_stack.pop()?;
+        self.commands.push(RecordedCommand::PopClipRect);
+
+        Some(rect)
+    }
+
+    pub fn draw(&mut self, vertex_count: u32) {
+        self.draw_instanced(vertex_count, 1);
+    }
+
+    pub fn draw_instanced(&mut self, vertex_count: u32, instance_count: u32) {
+        self.commands.push(RecordedCommand::Draw {
+            vertex_count,
+            instance_count,
+        });
+    }
+
+    // replays the recorded commands and returns the state for each draw call
+    pub fn draws(&self) -> Vec<RecordedDraw> {
+        let mut draws = Vec::new();
+        let mut clip_stack = ClipStack::new();
+
+        let mut state = RecordedDraw {
+            render_target: String::new(),
+            shader_name: String::new(),
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+            textures: Vec::new(),
+            constants: Vec::new(),
+            clip_rect: None,
+            vertex_count: 0,
+            instance_count: 0,
+        };
+
+        for command in self.commands.iter() {
+            match command {
+                RecordedCommand::BeginRenderPass { render_target } => {
+                    state.render_target = render_target.clone();
+                }
+
+                RecordedCommand::ClearRenderTarget { .. } => {}
+
+                RecordedCommand::BindPipelineState {
+                    shader_name,
+                    blend_mode,
+                    sampler,
+                } => {
+                    state.shader_name = shader_name.clone();
+                    state.blend_mode = *blend_mode;
+                    state.sampler = *sampler;
+                }
+
+                RecordedCommand::BindTexture {
+                    bind_slot,
+                    texture_name,
+                } => {
+                    let slot = *bind_slot as usize;
+                    if state.textures.len() <= slot {
+                        state.textures.resize(slot + 1, None);
+                    }
+                    state.textures[slot] = Some(texture_name.clone());
+                }
+
+                RecordedCommand::BindConstants {
+                    bind_slot,
+                    constants,
+                    ..
+                } => {
+                    let slot = *bind_slot as usize;
+                    if state.constants.len() <= slot {
+                        state.constants.resize(slot + 1, None);
+                    }
+                    state.constants[slot] = Some(constants.clone());
+                }
+
+                RecordedCommand::PushClipRect { rect } => {
+                    clip_stack.push(*rect);
+                }
+
+                RecordedCommand::PopClipRect => {
+                    clip_stack.pop();
+                }
+
+                RecordedCommand::Draw {
+                    vertex_count,
+                    instance_count,
+                } => {
+                    state.clip_rect = clip_stack.current();
+                    state.vertex_count = *vertex_count;
+                    state.instance_count = *instance_count;
+                    draws.push(state.clone());
+                }
+            }
+        }
+
+        draws
+    }
+}
+
+impl Default for CommandRecorder {
+    fn default() -> Self {
+        CommandRecorder::new()
+    }
+}
@@ -0,0 +1,137 @@
+// detects modified files by polling the modification time of all files in a directory
+// used during development to reload shaders and textures without restarting the game
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+pub struct FileWatcher {
+    root: String,
+    extensions: Vec<String>,
+
+    // normalized path -> last seen modification time
+    modified_times: HashMap<String, SystemTime>,
+}
+
+// paths are compared with '/' as separator so that they match the paths passed to the load functions
+pub fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+fn collect_files(
+    directory: &std::path::Path,
+    extensions: &[String],
+    files: &mut HashMap<String, SystemTime>,
+) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(x) => x,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_files(&path, extensions, files);
+            continue;
+        }
+
+        let has_extension = match path.extension().and_then(|x| x.to_str()) {
+            Some(extension) => extensions.iter().any(|x| x == extension),
+            None => false,
+        };
+
+        if !has_extension {
+            continue;
+        }
+
+        // files might be deleted or still be written while we are iterating
+        if let Ok(modified) = entry.metadata().and_then(|x| x.modified()) {
+            files.insert(normalize_path(&path.to_string_lossy()), modified);
+        }
+    }
+}
+
+impl FileWatcher {
+    // watches all files in root and its subdirectories that have one of the extensions (without the leading dot)
+    pub fn new(root: &str, extensions: &[&str]) -> FileWatcher {
+        let mut watcher = FileWatcher {
+            root: root.to_string(),
+            extensions: extensions.iter().map(|x| x.to_string()).collect(),
+            modified_times: HashMap::new(),
+        };
+
+        collect_files(
+            std::path::Path::new(&watcher.root),
+            &watcher.extensions,
+            &mut watcher.modified_times,
+        );
+
+        watcher
+    }
+
+    // returns all files that have been modified or created since the last call
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut files = HashMap::new();
+
+        collect_files(
+            std::path::Path::new(&self.root),
+            &self.extensions,
+            &mut files,
+        );
+
+        let mut changed_files: Vec<String> = files
+            .iter()
+            .filter(|(path, modified)| self.modified_times.get(*path) != Some(*modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        changed_files.sort();
+
+        self.modified_times = files;
+
+        changed_files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_modified_and_new_files() {
+        let root = std::env::temp_dir().join(format!("file_watcher_test_{}", std::process::id()));
+        let shaders = root.join("shaders");
+        std::fs::create_dir_all(&shaders).unwrap();
+
+        std::fs::write(shaders.join("quad.vsb"), b"a").unwrap();
+        std::fs::write(shaders.join("notes.txt"), b"a").unwrap();
+
+        let root_name = root.to_string_lossy().to_string();
+        let mut watcher = FileWatcher::new(&root_name, &["vsb", "psb"]);
+
+        assert!(watcher.poll().is_empty());
+
+        // make sure the modification time is different, some file systems only have a low resolution
+        let modified = SystemTime::now() + std::time::Duration::from_secs(10);
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(shaders.join("quad.vsb"))
+            .unwrap();
+        file.set_modified(modified).unwrap();
+
+        std::fs::write(shaders.join("quad.psb"), b"b").unwrap();
+        std::fs::write(shaders.join("notes.txt"), b"b").unwrap();
+
+        let changed = watcher.poll();
+
+        let expected: Vec<String> = ["quad.psb", "quad.vsb"]
+            .iter()
+            .map(|x| normalize_path(&shaders.join(x).to_string_lossy()))
+            .collect();
+
+        assert_eq!(changed, expected);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,26 +1,73 @@
-use winapi::shared::dxgi::*;
-use winapi::shared::dxgi1_2::*;
-use winapi::shared::dxgiformat::*;
-use winapi::shared::dxgitype::*;
-use winapi::shared::minwindef::{UINT, ULONG};
-use winapi::shared::ntdef::HRESULT;
-use winapi::shared::windef::HWND;
-use winapi::shared::winerror::S_OK;
-use winapi::um::d3d11::*;
-use winapi::um::d3d11_1::*;
-use winapi::um::d3d11sdklayers::*;
-use winapi::um::d3dcommon::*;
+// the native D3D11 backend is only available on windows
+// the software rasterizer and the command recorder work on every platform
+#[cfg(windows)]
+use winapi::shared::{
+    dxgi::*,
+    dxgi1_2::*,
+    dxgiformat::*,
+    dxgitype::*,
+    minwindef::{UINT, ULONG},
+    ntdef::HRESULT,
+    windef::HWND,
+    winerror::{S_FALSE, S_OK},
+};
+#[cfg(windows)]
+use winapi::um::{d3d11::*, d3d11_1::*, d3d11sdklayers::*, d3dcommon::*};
+#[cfg(windows)]
 use winapi::Interface;
 
+pub mod cbuffer_layout;
+pub mod command_list_stats;
+pub mod command_recorder;
+pub mod file_watcher;
+pub mod graphics_error;
+pub mod png_writer;
+pub mod resource_cache;
+pub mod resource_handles;
+pub mod resource_tracker;
+pub mod software_post_process;
+pub mod software_rasterizer;
+
+pub use command_list_stats::CommandListStats;
+use command_recorder::*;
+use dds_parser::{SubresourceData, TextureDesc};
+use dds_parser::{DXGI_FORMAT_BC1_UNORM, DXGI_FORMAT_BC1_UNORM_SRGB};
+use dds_parser::{DXGI_FORMAT_BC2_UNORM, DXGI_FORMAT_BC2_UNORM_SRGB};
+use dds_parser::{DXGI_FORMAT_BC3_UNORM, DXGI_FORMAT_BC3_UNORM_SRGB};
+use dds_parser::{DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM_SRGB};
+use file_watcher::normalize_path;
+#[cfg(windows)]
+use graphics_error::check_hresult;
+use graphics_error::read_file;
+pub use graphics_error::GraphicsError;
+pub use resource_cache::ResourceCacheStats;
+use resource_cache::*;
+use resource_handles::*;
+pub use resource_handles::{BufferHandle, PsoHandle, TextureHandle};
+#[cfg(windows)]
+use resource_tracker::texture_memory_bytes;
+pub use resource_tracker::{ResourceTracker, ResourceUsage, TrackedResource, TrackedResourceKind};
+use software_rasterizer::*;
+pub use software_rasterizer::{
+    BlendMode, ClipRect, SamplerDesc, SamplerFilter, TextureAddressMode,
+};
+use std::cell::UnsafeCell;
+use std::panic::Location;
+
+// the type of the native fields on platforms without D3D11, the fields are always None
+#[cfg(not(windows))]
+pub enum NoNativeObject {}
+
+#[cfg(windows)]
 pub fn leak_check_release(
     object_to_release: &winapi::um::unknwnbase::IUnknown,
     expected_ref_count: ULONG,
     debug_device: Option<&ID3D11Debug>,
-) {
+) -> Result<(), GraphicsError> {
     let prev_refcount: ULONG = unsafe { object_to_release.Release() };
 
     if prev_refcount == expected_ref_count {
-        return;
+        return Ok(());
     }
 
     // if we are runnign with the debug device, log the outstanding references
@@ -30,14 +77,65 @@ pub fn leak_check_release(
         }
     }
 
-    assert!(
-        prev_refcount == expected_ref_count,
-        "object was not released, still has {} outstanding references, expected {} ",
-        prev_refcount,
-        expected_ref_count
-    );
+    Err(GraphicsError::OutstandingReferences {
+        ref_count: prev_refcount,
+        expected_ref_count,
+    })
 }
 
+// for drop and error paths that have no way to return the error, a leaked object is a bug so it's fatal
+#[cfg(windows)]
+fn assert_leak_check_release(
+    object_to_release: &winapi::um::unknwnbase::IUnknown,
+    expected_ref_count: ULONG,
+    debug_device: Option<&ID3D11Debug>,
+) {
+    if let Err(error) = leak_check_release(object_to_release, expected_ref_count, debug_device) {
+        panic!("{}", error);
+    }
+}
+
+// releases the native objects that have been added to it when it goes out of scope
+#[cfg(windows)]
+struct NativeObjectReleaser {
+    objects: Vec<*mut winapi::um::unknwnbase::IUnknown>,
+}
+
+#[cfg(windows)]
+impl NativeObjectReleaser {
+    fn new() -> NativeObjectReleaser {
+        NativeObjectReleaser {
+            objects: Vec::new(),
+        }
+    }
+
+    // null objects are ignored, so objects can be added before checking if they have been created
+    fn add<T>(&mut self, object: *mut T) {
+        if !object.is_null() {
+            self.objects
+                .push(object as *mut winapi::um::unknwnbase::IUnknown);
+        }
+    }
+
+    // the objects are owned by someone else now and are not released
+    fn keep_all(&mut self) {
+        self.objects.clear();
+    }
+}
+
+#[cfg(windows)]
+impl Drop for NativeObjectReleaser {
+    fn drop(&mut self) {
+        // in reverse order, objects that have been created from other objects are released first
+        for object in self.objects.iter().rev() {
+            unsafe {
+                object.as_ref().unwrap().Release();
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
 fn set_debug_name(device_child: &ID3D11DeviceChild, name: &str) {
     unsafe {
         device_child.SetPrivateData(
@@ -48,280 +146,1501 @@ fn set_debug_name(device_child: &ID3D11DeviceChild, name: &str) {
     }
 }
 
-pub struct MappedGpuData<'a> {
-    data: &'a [u8],        // reference to slice of cpu accessible gpu memory
-    buffer: &'a GpuBuffer, // reference to the d3d11 buffer the data comes from
+// a range of mapped memory inside one of the pages of the LinearAllocator
+struct LinearAllocation {
+    buffer: std::rc::Rc<GpuBuffer>,
+    data: *mut u8,
+    offset_in_bytes: usize,
 }
 
-pub fn map_gpu_buffer<'a>(
-    buffer: &'a GpuBuffer,
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinearAllocatorStats {
+    // usage of the current frame
+    pub frame_used_bytes: usize,
+    pub frame_pages: usize,
+
+    // highest usage of any frame so far
+    pub high_water_mark_bytes: usize,
+    pub high_water_mark_pages: usize,
+
+    // number of pages that have been created and their combined size
+    pub page_count: usize,
+    pub capacity_bytes: usize,
+}
+
+// allocates per-frame constant data from a ring of constant buffer pages
+// new pages are created when a frame doesn't fit into the existing pages
+// the pages used by a frame can't be written again until the GPU is done with the frame, see retire_frame
+pub struct LinearAllocator {
+    #[cfg(windows)]
+    native_device: *mut ID3D11Device, // null when allocating for the software rasterizer
+    #[cfg(windows)]
+    immediate_context: *mut ID3D11DeviceContext,
+
+    // pages are recorded as created where the allocator has been created
+    tracker: ResourceTracker,
+    creation_site: &'static Location<'static>,
+
+    page_size: usize,
+    pages: Vec<std::rc::Rc<GpuBuffer>>,
+
+    // pages that are not used by any frame the GPU might still be processing, reused oldest first
+    free_pages: std::collections::VecDeque<usize>,
+
+    // frame number and pages of frames that have been submitted but not retired
+    submitted_frames: std::collections::VecDeque<(u64, Vec<usize>)>,
+
+    frame_number: u64,
+    frame_pages: Vec<usize>,
+
+    // the page allocations are made from, stays mapped until it's full or the frame ends
+    current_page: Option<usize>,
+    current_page_data: *mut u8,
+    current_page_used_bytes: usize,
+
+    pub stats: LinearAllocatorStats,
+}
+
+impl LinearAllocator {
+    // a single allocation can't be larger than a page
+    // constant buffer bindings are limited to 64 KB, so larger pages only reduce the number of pages
+    #[track_caller]
+    pub fn new(device: &GraphicsDevice, page_size_in_bytes: usize) -> LinearAllocator {
+        #[cfg(windows)]
+        let (native_device, immediate_context) = match &device.native {
+            Some(native_device) => unsafe {
+                let mut immediate_context: *mut ID3D11DeviceContext = std::ptr::null_mut();
+                native_device.GetImmediateContext(&mut immediate_context);
+
+                // keep the device alive for as long as pages can be created
+                native_device.AddRef();
+
+                (
+                    &**native_device as *const ID3D11Device as *mut ID3D11Device,
+                    immediate_context,
+                )
+            },
+            None => (std::ptr::null_mut(), std::ptr::null_mut()),
+        };
+
+        LinearAllocator {
+            #[cfg(windows)]
+            native_device,
+            #[cfg(windows)]
+            immediate_context,
+            tracker: device.tracker.clone(),
+            creation_site: Location::caller(),
+            page_size: round_up_to_multiple(page_size_in_bytes, 256),
+            pages: Vec::new(),
+            free_pages: std::collections::VecDeque::new(),
+            submitted_frames: std::collections::VecDeque::new(),
+            frame_number: 0,
+            frame_pages: Vec::new(),
+            current_page: None,
+            current_page_data: std::ptr::null_mut(),
+            current_page_used_bytes: 0,
+            stats: LinearAllocatorStats::default(),
+        }
+    }
+
+    pub fn begin_frame(&mut self, frame_number: u64) {
+        self.frame_number = frame_number;
+        self.stats.frame_used_bytes = 0;
+        self.stats.frame_pages = 0;
+    }
+
+    // unmaps all pages, no further allocations can be made for this frame
+    // needs to be called before the command list is executed
+    pub fn end_frame(&mut self) {
+        self.unmap_current_page();
+
+        let frame_pages = std::mem::take(&mut self.frame_pages);
+        self.submitted_frames
+            .push_back((self.frame_number, frame_pages));
+
+        self.stats.high_water_mark_bytes = self
+            .stats
+            .high_water_mark_bytes
+            .max(self.stats.frame_used_bytes);
+        self.stats.high_water_mark_pages =
+            self.stats.high_water_mark_pages.max(self.stats.frame_pages);
+    }
+
+    // the GPU has finished all frames up to and including frame_number, their pages can be reused
+    pub fn retire_frame(&mut self, frame_number: u64) {
+        while let Some((submitted_frame_number, _)) = self.submitted_frames.front() {
+            if *submitted_frame_number > frame_number {
+                break;
+            }
+
+            let (_, pages) = self.submitted_frames.pop_front().unwrap();
+            self.free_pages.extend(pages);
+        }
+    }
+
+    fn allocate(&mut self, size_in_bytes: usize) -> Result<LinearAllocation, GraphicsError> {
+        if size_in_bytes > self.page_size {
+            return Err(GraphicsError::InvalidArgument {
+                resource_name: String::from("Linear Allocator"),
+                reason: "the allocation is larger than the page size",
+            });
+        }
+
+        let page_index = match self.current_page {
+            Some(x) if self.current_page_used_bytes + size_in_bytes <= self.page_size => x,
+            _ => {
+                self.unmap_current_page();
+                self.map_next_page()?
+            }
+        };
+
+        let offset_in_bytes = self.current_page_used_bytes;
+
+        self.current_page_used_bytes += size_in_bytes;
+        self.stats.frame_used_bytes += size_in_bytes;
+
+        Ok(LinearAllocation {
+            buffer: self.pages[page_index].clone(),
+            data: unsafe { self.current_page_data.add(offset_in_bytes) },
+            offset_in_bytes,
+        })
+    }
+
+    fn map_next_page(&mut self) -> Result<usize, GraphicsError> {
+        let page_index = match self.free_pages.pop_front() {
+            Some(x) => x,
+            None => {
+                let debug_name = format!("Linear Allocator Page {}", self.pages.len());
+
+                let buffer = self.create_page(&debug_name)?;
+
+                self.pages.push(std::rc::Rc::new(buffer));
+                self.stats.page_count = self.pages.len();
+                self.stats.capacity_bytes = self.pages.len() * self.page_size;
+
+                self.pages.len() - 1
+            }
+        };
+
+        self.current_page_data = match self.map_page(&self.pages[page_index]) {
+            Ok(x) => x,
+            Err(error) => {
+                // the page can still be used by the next frame
+                self.free_pages.push_front(page_index);
+                return Err(error);
+            }
+        };
+
+        self.frame_pages.push(page_index);
+        self.stats.frame_pages += 1;
+
+        self.current_page = Some(page_index);
+        self.current_page_used_bytes = 0;
+
+        Ok(page_index)
+    }
+
+    fn create_page(&self, debug_name: &str) -> Result<GpuBuffer, GraphicsError> {
+        #[cfg(windows)]
+        if let Some(native_device) = unsafe { self.native_device.as_ref() } {
+            return create_native_constant_buffer(
+                native_device,
+                &self.tracker,
+                self.page_size as u32,
+                debug_name,
+                self.creation_site,
+            );
+        }
+
+        Ok(create_software_constant_buffer(
+            &self.tracker,
+            self.page_size as u32,
+            debug_name,
+            self.creation_site,
+        ))
+    }
+
+    // returns the address the allocations of the page are written to
+    fn map_page(&self, buffer: &GpuBuffer) -> Result<*mut u8, GraphicsError> {
+        #[cfg(windows)]
+        if !buffer.is_software() {
+            let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE {
+                pData: std::ptr::null_mut(),
+                RowPitch: 0,
+                DepthPitch: 0,
+            };
+
+            // the page isn't used by any frame in flight, so it's safe to overwrite it
+            let result: HRESULT = unsafe {
+                self.immediate_context.as_ref().unwrap().Map(
+                    buffer.native_buffer as *mut winapi::um::d3d11::ID3D11Resource,
+                    0,
+                    D3D11_MAP_WRITE_NO_OVERWRITE,
+                    0,
+                    &mut mapped_resource,
+                )
+            };
+
+            check_hresult(result, "Map", "Linear Allocator Page")?;
+
+            return Ok(mapped_resource.pData as *mut u8);
+        }
+
+        // software buffers are always cpu accessible, no need to map them
+        Ok(unsafe { (*buffer.software_memory.get()).as_mut_ptr() })
+    }
+
+    fn unmap_current_page(&mut self) {
+        self.current_page_data = std::ptr::null_mut();
+
+        #[cfg(windows)]
+        if let Some(page_index) = self.current_page {
+            let buffer = &self.pages[page_index];
+
+            if !buffer.is_software() {
+                unsafe {
+                    self.immediate_context.as_ref().unwrap().Unmap(
+                        buffer.native_buffer as *mut winapi::um::d3d11::ID3D11Resource,
+                        0,
+                    );
+                }
+            }
+        }
+
+        self.current_page = None;
+    }
+}
+
+impl Drop for LinearAllocator {
+    fn drop(&mut self) {
+        self.unmap_current_page();
+
+        // pages still referenced by a HeapAlloc are released once the allocation is dropped
+        self.pages.clear();
+
+        #[cfg(windows)]
+        unsafe {
+            if let Some(immediate_context) = self.immediate_context.as_ref() {
+                immediate_context.Release();
+            }
+
+            if let Some(native_device) = self.native_device.as_ref() {
+                native_device.Release();
+            }
+        }
+    }
+}
+
+// tracks which of the submitted frames the GPU has finished
+// the end of every frame is marked with a D3D11_QUERY_EVENT that is signaled once the GPU has executed all commands before it
+// frames of the software rasterizer and the command recorder are finished as soon as they are signaled
+pub struct FrameFences {
+    #[cfg(windows)]
+    native_device: *mut ID3D11Device, // null without a native device
+    #[cfg(windows)]
+    immediate_context: *mut ID3D11DeviceContext,
+
+    // frame number and query of frames the GPU might still be processing, oldest first
+    #[cfg(windows)]
+    pending_frames: std::collections::VecDeque<(u64, *mut ID3D11Query)>,
+
+    // queries of finished frames, reused for the next frames
+    #[cfg(windows)]
+    free_queries: Vec<*mut ID3D11Query>,
+
+    // the last frame the GPU has finished, None until the first frame is finished
+    pub completed_frame: Option<u64>,
+}
+
+impl FrameFences {
+    pub fn new(device: &GraphicsDevice) -> FrameFences {
+        #[cfg(windows)]
+        let (native_device, immediate_context) = match &device.native {
+            Some(native_device) => unsafe {
+                let mut immediate_context: *mut ID3D11DeviceContext = std::ptr::null_mut();
+                native_device.GetImmediateContext(&mut immediate_context);
+
+                // keep the device alive for as long as queries can be created
+                native_device.AddRef();
+
+                (
+                    &**native_device as *const ID3D11Device as *mut ID3D11Device,
+                    immediate_context,
+                )
+            },
+            None => (std::ptr::null_mut(), std::ptr::null_mut()),
+        };
+
+        #[cfg(not(windows))]
+        let _ = device;
+
+        FrameFences {
+            #[cfg(windows)]
+            native_device,
+            #[cfg(windows)]
+            immediate_context,
+            #[cfg(windows)]
+            pending_frames: std::collections::VecDeque::new(),
+            #[cfg(windows)]
+            free_queries: Vec::new(),
+            completed_frame: None,
+        }
+    }
+
+    // needs to be called after the command list of the frame has been executed
+    pub fn signal_frame(&mut self, frame_number: u64) -> Result<(), GraphicsError> {
+        #[cfg(windows)]
+        if let Some(immediate_context) = unsafe { self.immediate_context.as_ref() } {
+            let query = match self.free_queries.pop() {
+                Some(x) => x,
+                None => self.create_query()?,
+            };
+
+            unsafe {
+                immediate_context.End(query as *mut ID3D11Asynchronous);
+            }
+
+            self.pending_frames.push_back((frame_number, query));
+
+            return Ok(());
+        }
+
+        // the software rasterizer executes the commands immediately, the recorder doesn't execute them at all
+        self.completed_frame = Some(frame_number);
+
+        Ok(())
+    }
+
+    // checks which frames have been finished without waiting for the GPU, returns the last finished frame
+    pub fn poll_completed_frame(&mut self) -> Result<Option<u64>, GraphicsError> {
+        #[cfg(windows)]
+        while let Some(&(frame_number, query)) = self.pending_frames.front() {
+            if !self.is_query_signaled(query, D3D11_ASYNC_GETDATA_DONOTFLUSH)? {
+                break;
+            }
+
+            self.finish_oldest_frame(frame_number, query);
+        }
+
+        Ok(self.completed_frame)
+    }
+
+    // blocks until the GPU has finished all signaled frames up to and including frame_number
+    #[cfg_attr(not(windows), allow(unused_variables))]
+    pub fn wait_for_frame(&mut self, frame_number: u64) -> Result<(), GraphicsError> {
+        #[cfg(windows)]
+        while let Some(&(pending_frame_number, query)) = self.pending_frames.front() {
+            if pending_frame_number > frame_number {
+                break;
+            }
+
+            // without DONOTFLUSH the commands are submitted to the GPU, so the query will be signaled eventually
+            while !self.is_query_signaled(query, 0)? {
+                std::thread::yield_now();
+            }
+
+            self.finish_oldest_frame(pending_frame_number, query);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn finish_oldest_frame(&mut self, frame_number: u64, query: *mut ID3D11Query) {
+        self.pending_frames.pop_front();
+        self.free_queries.push(query);
+        self.completed_frame = Some(frame_number);
+    }
+
+    #[cfg(windows)]
+    fn create_query(&self) -> Result<*mut ID3D11Query, GraphicsError> {
+        let query_desc = D3D11_QUERY_DESC {
+            Query: D3D11_QUERY_EVENT,
+            MiscFlags: 0,
+        };
+
+        let mut query: *mut ID3D11Query = std::ptr::null_mut();
+
+        let result: HRESULT = unsafe {
+            self.native_device
+                .as_ref()
+                .unwrap()
+                .CreateQuery(&query_desc, &mut query)
+        };
+
+        check_hresult(result, "CreateQuery", "Frame Fence")?;
+
+        set_debug_name(unsafe { query.as_ref().unwrap() }, "Frame Fence");
+
+        Ok(query)
+    }
+
+    #[cfg(windows)]
+    fn is_query_signaled(
+        &self,
+        query: *mut ID3D11Query,
+        flags: UINT,
+    ) -> Result<bool, GraphicsError> {
+        let mut signaled: winapi::shared::minwindef::BOOL = 0;
+
+        let result: HRESULT = unsafe {
+            self.immediate_context.as_ref().unwrap().GetData(
+                query as *mut ID3D11Asynchronous,
+                &mut signaled as *mut winapi::shared::minwindef::BOOL
+                    as *mut winapi::ctypes::c_void,
+                std::mem::size_of::<winapi::shared::minwindef::BOOL>() as UINT,
+                flags,
+            )
+        };
+
+        // S_FALSE while the GPU hasn't reached the query yet
+        if result == S_FALSE {
+            return Ok(false);
+        }
+
+        check_hresult(result, "GetData", "Frame Fence")?;
+
+        Ok(signaled != 0)
+    }
+}
+
+#[cfg(windows)]
+impl Drop for FrameFences {
+    fn drop(&mut self) {
+        let pending_queries = self.pending_frames.drain(..).map(|(_, query)| query);
+
+        for query in pending_queries.chain(self.free_queries.drain(..)) {
+            unsafe {
+                assert_leak_check_release(query.as_ref().unwrap(), 0, None);
+            }
+        }
+
+        unsafe {
+            if let Some(immediate_context) = self.immediate_context.as_ref() {
+                immediate_context.Release();
+            }
+
+            if let Some(native_device) = self.native_device.as_ref() {
+                native_device.Release();
+            }
+        }
+    }
+}
+
+pub struct HeapAlloc {
+    gpu_buffer_src: std::rc::Rc<GpuBuffer>,
+    pub first_constant_offset: u32,
+    pub num_constants: u32,
+}
+
+pub fn round_up_to_multiple(number: usize, multiple: usize) -> usize {
+    ((number + multiple - 1) / multiple) * multiple
+}
+
+impl HeapAlloc {
+    pub fn new<T>(x: T, allocator: &mut LinearAllocator) -> Result<HeapAlloc, GraphicsError> {
+        let allocation_size: usize = round_up_to_multiple(std::mem::size_of::<T>(), 256);
+
+        let allocation = allocator.allocate(allocation_size)?;
+
+        unsafe {
+            // write data into target destination
+            // software pages are byte vectors and don't guarantee the alignment of T
+            std::ptr::write_unaligned(allocation.data as *mut T, x);
+        }
+
+        Ok(HeapAlloc {
+            gpu_buffer_src: allocation.buffer,
+            first_constant_offset: (allocation.offset_in_bytes / 16) as u32,
+            num_constants: (allocation_size / 16) as u32,
+        })
+    }
+
+    // copies all elements into a single allocation, used for per-instance data
+    pub fn new_array<T: Copy>(
+        x: &[T],
+        allocator: &mut LinearAllocator,
+    ) -> Result<HeapAlloc, GraphicsError> {
+        let size_in_bytes = std::mem::size_of_val(x);
+        let allocation_size: usize = round_up_to_multiple(size_in_bytes, 256);
+
+        let allocation = allocator.allocate(allocation_size)?;
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(x.as_ptr() as *const u8, allocation.data, size_in_bytes);
+        }
+
+        Ok(HeapAlloc {
+            gpu_buffer_src: allocation.buffer,
+            first_constant_offset: (allocation.offset_in_bytes / 16) as u32,
+            num_constants: (allocation_size / 16) as u32,
+        })
+    }
+}
+
+pub struct GpuBuffer {
+    #[cfg(windows)]
+    pub native_buffer: *mut ID3D11Buffer, // null when the buffer has been created for the software rasterizer
+
+    // the linear allocator writes into the memory while the buffer is shared with the allocations of earlier frames
+    software_memory: UnsafeCell<Box<[u8]>>,
+    pub tracking: TrackedResource,
+}
+
+impl GpuBuffer {
+    // software buffers are plain memory that can be accessed on the CPU
+    fn is_software(&self) -> bool {
+        #[cfg(windows)]
+        if !self.native_buffer.is_null() {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(windows)]
+impl Drop for GpuBuffer {
+    fn drop(&mut self) {
+        if let Some(native_buffer) = unsafe { self.native_buffer.as_ref() } {
+            assert_leak_check_release(native_buffer, 0, None);
+        }
+    }
+}
+
+#[track_caller]
+pub fn create_constant_buffer(
     device_layer: &GraphicsDeviceLayer,
-) -> MappedGpuData<'a> {
-    let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE {
-        pData: std::ptr::null_mut(),
-        RowPitch: 0,
-        DepthPitch: 0,
+    size_in_bytes: u32,
+    debug_name: &str,
+) -> Result<GpuBuffer, GraphicsError> {
+    let device = &device_layer.device;
+
+    #[cfg(windows)]
+    if let Some(native_device) = &device.native {
+        return create_native_constant_buffer(
+            native_device,
+            &device.tracker,
+            size_in_bytes,
+            debug_name,
+            Location::caller(),
+        );
+    }
+
+    Ok(create_software_constant_buffer(
+        &device.tracker,
+        size_in_bytes,
+        debug_name,
+        Location::caller(),
+    ))
+}
+
+fn track_constant_buffer(
+    tracker: &ResourceTracker,
+    size_in_bytes: u32,
+    debug_name: &str,
+    creation_site: &'static Location<'static>,
+) -> TrackedResource {
+    tracker.track(
+        TrackedResourceKind::Buffer,
+        debug_name,
+        creation_site,
+        size_in_bytes as usize,
+    )
+}
+
+fn create_software_constant_buffer(
+    tracker: &ResourceTracker,
+    size_in_bytes: u32,
+    debug_name: &str,
+    creation_site: &'static Location<'static>,
+) -> GpuBuffer {
+    GpuBuffer {
+        #[cfg(windows)]
+        native_buffer: std::ptr::null_mut(),
+        software_memory: UnsafeCell::new(vec![0; size_in_bytes as usize].into_boxed_slice()),
+        tracking: track_constant_buffer(tracker, size_in_bytes, debug_name, creation_site),
+    }
+}
+
+#[cfg(windows)]
+fn create_native_constant_buffer(
+    native_device: &ID3D11Device,
+    tracker: &ResourceTracker,
+    size_in_bytes: u32,
+    debug_name: &str,
+    creation_site: &'static Location<'static>,
+) -> Result<GpuBuffer, GraphicsError> {
+    let mut constant_buffer: *mut ID3D11Buffer = std::ptr::null_mut();
+
+    let buffer_desc = D3D11_BUFFER_DESC {
+        ByteWidth: size_in_bytes,
+        Usage: D3D11_USAGE_DYNAMIC,
+        BindFlags: D3D11_BIND_CONSTANT_BUFFER,
+        CPUAccessFlags: D3D11_CPU_ACCESS_WRITE,
+        MiscFlags: 0,
+        StructureByteStride: 0,
     };
 
-    let native_buffer: &mut ID3D11Buffer = unsafe { buffer.native_buffer.as_mut().unwrap() };
+    let error =
+        unsafe { native_device.CreateBuffer(&buffer_desc, std::ptr::null(), &mut constant_buffer) };
 
-    // map the buffer
-    let result: HRESULT = unsafe {
-        device_layer.immediate_context.as_ref().unwrap().Map(
-            native_buffer as *mut ID3D11Buffer as *mut winapi::um::d3d11::ID3D11Resource,
-            0,
-            D3D11_MAP_WRITE_NO_OVERWRITE,
-            0,
-            &mut mapped_resource,
-        )
+    check_hresult(error, "CreateBuffer", debug_name)?;
+
+    unsafe {
+        set_debug_name(
+            constant_buffer.as_ref().unwrap(),
+            format!("Constant Buffer - {}", debug_name).as_str(),
+        );
+    }
+
+    Ok(GpuBuffer {
+        native_buffer: constant_buffer,
+        software_memory: UnsafeCell::new(Box::default()),
+        tracking: track_constant_buffer(tracker, size_in_bytes, debug_name, creation_site),
+    })
+}
+
+pub enum GraphicsCommandList {
+    #[cfg(windows)]
+    Native {
+        command_context: *mut ID3D11DeviceContext1, // released when the command list is dropped
+
+        // the scissor rect is derived from the clip stack, without a clip rect it covers the bound render target
+        clip_stack: ClipStack,
+        render_target_rect: ClipRect,
+
+        stats: CommandListStats,
+    },
+
+    // commands are executed immediately on the CPU
+    Software(SoftwareCommandList),
+
+    // commands are not executed, only recorded so that tests can inspect them
+    Recording(CommandRecorder),
+}
+
+#[cfg(windows)]
+impl Drop for GraphicsCommandList {
+    fn drop(&mut self) {
+        if let GraphicsCommandList::Native {
+            command_context, ..
+        } = self
+        {
+            unsafe {
+                assert_leak_check_release(command_context.as_ref().unwrap(), 0, None);
+            }
+        }
+    }
+}
+
+// resources created for the software rasterizer don't have a native object
+// they instead share the CPU side texture with the command list
+
+pub struct RenderTargetView<'a> {
+    #[cfg(windows)]
+    pub native_view: Option<&'a mut winapi::um::d3d11::ID3D11RenderTargetView>,
+    #[cfg(not(windows))]
+    pub native_view: Option<&'a mut NoNativeObject>,
+    pub software_target: Option<SoftwareTexture>,
+    pub width: i32,
+    pub height: i32,
+
+    // None for the backbuffer, it's owned by the device layer
+    pub tracking: Option<TrackedResource>,
+}
+
+#[cfg(windows)]
+impl Drop for RenderTargetView<'_> {
+    fn drop(&mut self) {
+        if let Some(native_view) = &self.native_view {
+            assert_leak_check_release(native_view, 0, None);
+        }
+    }
+}
+
+pub struct ShaderResourceView<'a> {
+    #[cfg(windows)]
+    pub native_view: Option<&'a mut winapi::um::d3d11::ID3D11ShaderResourceView>,
+    #[cfg(not(windows))]
+    pub native_view: Option<&'a mut NoNativeObject>,
+    pub software_texture: Option<SoftwareTexture>,
+    pub tracking: TrackedResource,
+}
+
+#[cfg(windows)]
+impl Drop for ShaderResourceView<'_> {
+    fn drop(&mut self) {
+        if let Some(native_view) = &self.native_view {
+            assert_leak_check_release(native_view, 0, None);
+        }
+    }
+}
+
+pub struct Texture<'a> {
+    #[cfg(windows)]
+    pub native_texture: Option<&'a mut winapi::um::d3d11::ID3D11Texture2D>,
+    #[cfg(not(windows))]
+    pub native_texture: Option<&'a mut NoNativeObject>,
+    pub srv: ShaderResourceView<'a>,
+
+    // size of the first mip level in texels
+    pub width: u32,
+    pub height: u32,
+
+    // the file the texture has been loaded from, reload_changed_files reloads it when the file changes
+    // None for textures that have been created from memory
+    pub source_file: Option<String>,
+
+    pub tracking: TrackedResource,
+}
+
+#[cfg(windows)]
+impl Drop for Texture<'_> {
+    fn drop(&mut self) {
+        if let Some(native_texture) = &self.native_texture {
+            assert_leak_check_release(native_texture, 0, None);
+        }
+    }
+}
+
+fn create_software_texture(
+    texture_desc: &TextureDesc,
+    subresources_data: &[SubresourceData],
+    resource_name: &str,
+) -> Result<CpuTexture, GraphicsError> {
+    let (format, srgb) = match texture_desc.format {
+        DXGI_FORMAT_R8G8B8A8_UNORM => (CpuTextureFormat::Rgba8, false),
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => (CpuTextureFormat::Rgba8, true),
+        DXGI_FORMAT_BC1_UNORM => (CpuTextureFormat::Bc1, false),
+        DXGI_FORMAT_BC1_UNORM_SRGB => (CpuTextureFormat::Bc1, true),
+        DXGI_FORMAT_BC2_UNORM => (CpuTextureFormat::Bc2, false),
+        DXGI_FORMAT_BC2_UNORM_SRGB => (CpuTextureFormat::Bc2, true),
+        DXGI_FORMAT_BC3_UNORM => (CpuTextureFormat::Bc3, false),
+        DXGI_FORMAT_BC3_UNORM_SRGB => (CpuTextureFormat::Bc3, true),
+        format => {
+            return Err(GraphicsError::UnsupportedFormat {
+                resource_name: resource_name.to_string(),
+                format,
+            });
+        }
     };
 
-    assert!(result == winapi::shared::winerror::S_OK);
+    // the software rasterizer only samples from the first mip level
+    let mip0 = subresources_data
+        .first()
+        .ok_or_else(|| GraphicsError::InvalidArgument {
+            resource_name: resource_name.to_string(),
+            reason: "texture has no subresource data",
+        })?;
+
+    let mip0_data = &mip0.data[..(mip0.slice_pitch as usize).min(mip0.data.len())];
+
+    decode_texture(
+        format,
+        srgb,
+        texture_desc.width,
+        texture_desc.height,
+        mip0.row_pitch,
+        mip0_data,
+    )
+    .map(|mut texture| {
+        texture.name = resource_name.to_string();
+        texture
+    })
+    .map_err(|error| GraphicsError::InvalidTextureData {
+        resource_name: resource_name.to_string(),
+        error,
+    })
+}
 
-    MappedGpuData {
-        data: unsafe {
-            std::slice::from_raw_parts_mut(
-                mapped_resource.pData as *mut u8,
-                mapped_resource.RowPitch as usize,
-            )
+// estimate of the memory used by all mip levels and array slices
+#[cfg(windows)]
+fn native_texture_memory_bytes(texture_desc: &TextureDesc) -> usize {
+    let (block_size, bytes_per_block) = match texture_desc.format {
+        DXGI_FORMAT_BC1_UNORM | DXGI_FORMAT_BC1_UNORM_SRGB => (4, 8),
+        DXGI_FORMAT_BC2_UNORM | DXGI_FORMAT_BC2_UNORM_SRGB => (4, 16),
+        DXGI_FORMAT_BC3_UNORM | DXGI_FORMAT_BC3_UNORM_SRGB => (4, 16),
+        _ => (1, 4),
+    };
+
+    texture_memory_bytes(
+        texture_desc.width,
+        texture_desc.height,
+        texture_desc.mip_levels,
+        block_size,
+        bytes_per_block,
+    ) * texture_desc.array_size.max(1) as usize
+}
+
+#[track_caller]
+pub fn create_texture<'a>(
+    device: &GraphicsDevice,
+    texture_desc: TextureDesc,
+    subresources_data: Vec<SubresourceData>,
+    debug_name: &str,
+) -> Result<Texture<'a>, GraphicsError> {
+    let creation_site = Location::caller();
+
+    #[cfg(windows)]
+    if let Some(native_device) = &device.native {
+        return create_native_texture(
+            device,
+            native_device,
+            &texture_desc,
+            &subresources_data,
+            debug_name,
+            creation_site,
+        );
+    }
+
+    let track = |kind, memory_bytes| {
+        device
+            .tracker
+            .track(kind, debug_name, creation_site, memory_bytes)
+    };
+
+    let software_texture = create_software_texture(&texture_desc, &subresources_data, debug_name)?;
+
+    // the software rasterizer only keeps the decoded first mip level
+    let memory_bytes = software_texture.pixels.len();
+
+    Ok(Texture {
+        native_texture: None,
+        srv: ShaderResourceView {
+            native_view: None,
+            software_texture: Some(std::rc::Rc::new(std::cell::RefCell::new(software_texture))),
+            tracking: track(TrackedResourceKind::ShaderResourceView, 0),
+        },
+        width: texture_desc.width,
+        height: texture_desc.height,
+        source_file: None,
+        tracking: track(TrackedResourceKind::Texture, memory_bytes),
+    })
+}
+
+#[cfg(windows)]
+fn create_native_texture<'a>(
+    device: &GraphicsDevice,
+    native_device: &ID3D11Device,
+    texture_desc: &TextureDesc,
+    subresources_data: &[SubresourceData],
+    debug_name: &str,
+    creation_site: &'static Location<'static>,
+) -> Result<Texture<'a>, GraphicsError> {
+    let track = |kind, memory_bytes| {
+        device
+            .tracker
+            .track(kind, debug_name, creation_site, memory_bytes)
+    };
+
+    let native_texture_desc = D3D11_TEXTURE2D_DESC {
+        Width: texture_desc.width,
+        Height: texture_desc.height,
+        MipLevels: texture_desc.mip_levels,
+        ArraySize: texture_desc.array_size,
+        Format: texture_desc.format,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Usage: D3D11_USAGE_DEFAULT,
+        BindFlags: D3D11_BIND_SHADER_RESOURCE,
+        CPUAccessFlags: 0,
+        MiscFlags: 0,
+    };
+
+    let native_subresources_data: Vec<D3D11_SUBRESOURCE_DATA> = subresources_data
+        .iter()
+        .map(|x| D3D11_SUBRESOURCE_DATA {
+            pSysMem: x.data.as_ptr() as *const winapi::ctypes::c_void,
+            SysMemPitch: x.row_pitch,
+            SysMemSlicePitch: x.slice_pitch,
+        })
+        .collect();
+
+    let mut texture: *mut winapi::um::d3d11::ID3D11Texture2D = std::ptr::null_mut();
+    let mut texture_view: *mut winapi::um::d3d11::ID3D11ShaderResourceView = std::ptr::null_mut();
+
+    unsafe {
+        let hr = native_device.CreateTexture2D(
+            &native_texture_desc,
+            native_subresources_data.as_ptr(),
+            &mut texture,
+        );
+
+        check_hresult(hr, "CreateTexture2D", debug_name)?;
+
+        set_debug_name(
+            texture.as_ref().unwrap(),
+            format!("Texture - {}", debug_name).as_str(),
+        );
+
+        // create a resource view
+        let hr = native_device.CreateShaderResourceView(
+            texture as *mut winapi::um::d3d11::ID3D11Resource,
+            std::ptr::null_mut(),
+            &mut texture_view,
+        );
+
+        if hr != S_OK {
+            assert_leak_check_release(texture.as_ref().unwrap(), 0, device.debug_device);
+        }
+
+        check_hresult(hr, "CreateShaderResourceView", debug_name)?;
+
+        set_debug_name(
+            texture_view.as_ref().unwrap(),
+            format!("Texture SRV - {}", debug_name).as_str(),
+        );
+    }
+
+    Ok(Texture {
+        native_texture: unsafe { texture.as_mut() },
+        srv: ShaderResourceView {
+            native_view: unsafe { texture_view.as_mut() },
+            software_texture: None,
+            tracking: track(TrackedResourceKind::ShaderResourceView, 0),
+        },
+        width: texture_desc.width,
+        height: texture_desc.height,
+        source_file: None,
+        tracking: track(
+            TrackedResourceKind::Texture,
+            native_texture_memory_bytes(texture_desc),
+        ),
+    })
+}
+
+// a texture that can be rendered into and afterwards be sampled from
+pub struct RenderTarget<'a> {
+    #[cfg(windows)]
+    pub native_texture: Option<&'a mut winapi::um::d3d11::ID3D11Texture2D>,
+    #[cfg(not(windows))]
+    pub native_texture: Option<&'a mut NoNativeObject>,
+    pub rtv: RenderTargetView<'a>,
+    pub srv: ShaderResourceView<'a>,
+    pub tracking: TrackedResource,
+}
+
+#[cfg(windows)]
+impl Drop for RenderTarget<'_> {
+    fn drop(&mut self) {
+        if let Some(native_texture) = &self.native_texture {
+            assert_leak_check_release(native_texture, 0, None);
+        }
+    }
+}
+
+#[track_caller]
+pub fn create_render_target<'a>(
+    device: &GraphicsDevice,
+    width: i32,
+    height: i32,
+    debug_name: &str,
+) -> Result<RenderTarget<'a>, GraphicsError> {
+    if width <= 0 || height <= 0 {
+        return Err(GraphicsError::InvalidArgument {
+            resource_name: debug_name.to_string(),
+            reason: "render target size must be positive",
+        });
+    }
+
+    let creation_site = Location::caller();
+
+    #[cfg(windows)]
+    if let Some(native_device) = &device.native {
+        return create_native_render_target(
+            device,
+            native_device,
+            width,
+            height,
+            debug_name,
+            creation_site,
+        );
+    }
+
+    let track = |kind, memory_bytes| {
+        device
+            .tracker
+            .track(kind, debug_name, creation_site, memory_bytes)
+    };
+
+    // the render target and shader resource view share the same CPU texture
+    let mut software_texture = CpuTexture::new(width as u32, height as u32);
+    software_texture.name = debug_name.to_string();
+
+    let memory_bytes = software_texture.pixels.len();
+    let software_texture = std::rc::Rc::new(std::cell::RefCell::new(software_texture));
+
+    Ok(RenderTarget {
+        native_texture: None,
+        rtv: RenderTargetView {
+            native_view: None,
+            software_target: Some(software_texture.clone()),
+            width,
+            height,
+            tracking: Some(track(TrackedResourceKind::RenderTargetView, 0)),
+        },
+        srv: ShaderResourceView {
+            native_view: None,
+            software_texture: Some(software_texture),
+            tracking: track(TrackedResourceKind::ShaderResourceView, 0),
         },
-        buffer,
+        tracking: track(TrackedResourceKind::RenderTarget, memory_bytes),
+    })
+}
+
+#[cfg(windows)]
+fn create_native_render_target<'a>(
+    device: &GraphicsDevice,
+    native_device: &ID3D11Device,
+    width: i32,
+    height: i32,
+    debug_name: &str,
+    creation_site: &'static Location<'static>,
+) -> Result<RenderTarget<'a>, GraphicsError> {
+    let track = |kind, memory_bytes| {
+        device
+            .tracker
+            .track(kind, debug_name, creation_site, memory_bytes)
+    };
+
+    // same format as the swapchain so that render targets can be used in place of the backbuffer
+    let texture_desc = D3D11_TEXTURE2D_DESC {
+        Width: width as u32,
+        Height: height as u32,
+        MipLevels: 1,
+        ArraySize: 1,
+        Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Usage: D3D11_USAGE_DEFAULT,
+        BindFlags: D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET,
+        MiscFlags: 0,
+        CPUAccessFlags: 0,
+    };
+
+    let mut texture: *mut ID3D11Texture2D = std::ptr::null_mut();
+    let mut texture_rtv: *mut ID3D11RenderTargetView = std::ptr::null_mut();
+    let mut texture_srv: *mut ID3D11ShaderResourceView = std::ptr::null_mut();
+
+    unsafe {
+        let hr = native_device.CreateTexture2D(&texture_desc, std::ptr::null(), &mut texture);
+
+        check_hresult(hr, "CreateTexture2D", debug_name)?;
+
+        set_debug_name(
+            texture.as_ref().unwrap(),
+            format!("Render Target - {}", debug_name).as_str(),
+        );
+
+        let hr = native_device.CreateRenderTargetView(
+            texture as *mut winapi::um::d3d11::ID3D11Resource,
+            std::ptr::null(),
+            &mut texture_rtv,
+        );
+
+        if hr != S_OK {
+            assert_leak_check_release(texture.as_ref().unwrap(), 0, device.debug_device);
+        }
+
+        check_hresult(hr, "CreateRenderTargetView", debug_name)?;
+
+        set_debug_name(
+            texture_rtv.as_ref().unwrap(),
+            format!("Render Target RTV - {}", debug_name).as_str(),
+        );
+
+        let hr = native_device.CreateShaderResourceView(
+            texture as *mut winapi::um::d3d11::ID3D11Resource,
+            std::ptr::null(),
+            &mut texture_srv,
+        );
+
+        if hr != S_OK {
+            assert_leak_check_release(texture_rtv.as_ref().unwrap(), 0, device.debug_device);
+            assert_leak_check_release(texture.as_ref().unwrap(), 0, device.debug_device);
+        }
+
+        check_hresult(hr, "CreateShaderResourceView", debug_name)?;
+
+        set_debug_name(
+            texture_srv.as_ref().unwrap(),
+            format!("Render Target SRV - {}", debug_name).as_str(),
+        );
     }
+
+    Ok(RenderTarget {
+        native_texture: unsafe { texture.as_mut() },
+        rtv: RenderTargetView {
+            native_view: unsafe { texture_rtv.as_mut() },
+            software_target: None,
+            width,
+            height,
+            tracking: Some(track(TrackedResourceKind::RenderTargetView, 0)),
+        },
+        srv: ShaderResourceView {
+            native_view: unsafe { texture_srv.as_mut() },
+            software_texture: None,
+            tracking: track(TrackedResourceKind::ShaderResourceView, 0),
+        },
+        tracking: track(
+            TrackedResourceKind::RenderTarget,
+            width as usize * height as usize * 4,
+        ),
+    })
 }
 
-pub fn unmap_gpu_buffer(mapped_data: MappedGpuData, device_layer: &GraphicsDeviceLayer) {
-    unsafe {
-        device_layer.immediate_context.as_ref().unwrap().Unmap(
-            mapped_data.buffer.native_buffer as *mut ID3D11Buffer
-                as *mut winapi::um::d3d11::ID3D11Resource,
-            0,
-        );
-    }
-}
+#[track_caller]
+pub fn load_dds_from_file<'a>(
+    filename: &str,
+    device: &GraphicsDevice,
+) -> Result<Texture<'a>, GraphicsError> {
+    // load the texture data
+    let data = read_file(filename)?;
+
+    // parse the header
+    let texture_load_result =
+        dds_parser::parse_dds_header(&data).map_err(|error| GraphicsError::InvalidDds {
+            path: filename.to_string(),
+            error,
+        })?;
+
+    let mut texture = create_texture(
+        device,
+        texture_load_result.desc,
+        texture_load_result.subresources_data,
+        filename,
+    )?;
+
+    texture.source_file = Some(filename.to_string());
 
-pub struct LinearAllocatorState {
-    pub used_bytes: usize,
+    Ok(texture)
 }
 
-pub struct LinearAllocator<'a> {
-    pub gpu_data: MappedGpuData<'a>,
+pub struct GraphicsDevice<'a> {
+    #[cfg(windows)]
+    pub native: Option<&'a mut ID3D11Device>, // None when running on the software rasterizer
+    #[cfg(windows)]
+    pub debug_device: Option<&'a ID3D11Debug>,
+    #[cfg(not(windows))]
+    pub native: Option<&'a mut NoNativeObject>,
+    #[cfg(not(windows))]
+    pub debug_device: Option<&'a NoNativeObject>,
 
-    pub state: LinearAllocatorState,
+    // every buffer, texture, view and pso created with this device
+    pub tracker: ResourceTracker,
 }
 
-pub struct HeapAlloc<'a> {
-    gpu_buffer_src: &'a GpuBuffer,
-    pub first_constant_offset: u32,
-    pub num_constants: u32,
-}
+#[cfg(windows)]
+impl Drop for GraphicsDevice<'_> {
+    fn drop(&mut self) {
+        let native = match &self.native {
+            Some(x) => x,
+            None => return,
+        };
 
-pub fn round_up_to_multiple(number: usize, multiple: usize) -> usize {
-    ((number + multiple - 1) / multiple) * multiple
-}
+        let expected_device_ref_count = if self.debug_device.is_some() { 1 } else { 0 };
 
-impl<'a> HeapAlloc<'a> {
-    pub fn new<T>(
-        x: T,
-        gpu_data: &'a MappedGpuData,
-        state: &mut LinearAllocatorState,
-    ) -> HeapAlloc<'a> {
-        let allocation_size: usize = round_up_to_multiple(std::mem::size_of::<T>(), 256);
+        assert_leak_check_release(native, expected_device_ref_count, self.debug_device);
 
-        let data_slice = gpu_data.data;
-        let start_offset_in_bytes = state.used_bytes;
-        // let end_offset_in_byes    = allocator.used_bytes + allocation_size;
+        if let Some(x) = self.debug_device {
+            assert_leak_check_release(&x, 0, None);
+        }
+    }
+}
 
-        let data_ptr =
-            data_slice[state.used_bytes..(state.used_bytes + allocation_size)].as_ptr() as *mut T;
+// the result of ResourceRegistry::reload_changed_files
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    // the texture files and shader names of the resources that have been replaced
+    pub reloaded: Vec<String>,
 
-        state.used_bytes += allocation_size;
+    // resources that failed to reload keep their previous version
+    pub errors: Vec<GraphicsError>,
+}
 
-        unsafe {
-            // write data into target destination
-            std::ptr::write(data_ptr, x);
+impl std::fmt::Display for ReloadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for x in self.reloaded.iter() {
+            writeln!(f, "reloaded {}", x)?;
+        }
 
-            HeapAlloc {
-                gpu_buffer_src: gpu_data.buffer,
-                first_constant_offset: (start_offset_in_bytes / 16) as u32,
-                num_constants: (allocation_size / 16) as u32,
-            }
+        for x in self.errors.iter() {
+            writeln!(f, "failed to reload, keeping the previous version: {}", x)?;
         }
+
+        Ok(())
     }
 }
 
-pub struct GpuBuffer {
-    pub native_buffer: *mut ID3D11Buffer,
+// owns resources and hands out copyable handles to them
+// handles are validated on every access, handles to destroyed resources return GraphicsError::InvalidHandle
+pub struct ResourceRegistry<'a> {
+    textures: ResourcePool<Texture<'a>>,
+    psos: ResourcePool<PipelineStateObject<'a>>,
+    buffers: ResourcePool<GpuBuffer>,
+
+    // resources created with load_texture / load_pso are shared between all users
+    texture_cache: ResourceCache<String, TextureHandle>,
+    pso_cache: ResourceCache<PipelineStateObjectKey, PsoHandle>,
 }
 
-impl Drop for GpuBuffer {
-    fn drop(&mut self) {
-        leak_check_release(unsafe { self.native_buffer.as_ref().unwrap() }, 0, None);
+impl<'a> ResourceRegistry<'a> {
+    pub fn new() -> ResourceRegistry<'a> {
+        ResourceRegistry {
+            textures: ResourcePool::new(),
+            psos: ResourcePool::new(),
+            buffers: ResourcePool::new(),
+            texture_cache: ResourceCache::new(),
+            pso_cache: ResourceCache::new(),
+        }
     }
-}
 
-pub fn create_constant_buffer(
-    device_layer: &GraphicsDeviceLayer,
-    size_in_bytes: u32,
-    debug_name: &str,
-) -> GpuBuffer {
-    let mut constant_buffer: *mut ID3D11Buffer = std::ptr::null_mut();
+    // loads the texture or returns the already loaded texture for the same file
+    // every call needs to be matched with a call to release_texture
+    #[track_caller]
+    pub fn load_texture(
+        &mut self,
+        device: &GraphicsDevice,
+        filename: &str,
+    ) -> Result<TextureHandle, GraphicsError> {
+        let key = filename.to_string();
+
+        if let Some(handle) = self.texture_cache.acquire(&key) {
+            return Ok(handle);
+        }
 
-    let buffer_desc = D3D11_BUFFER_DESC {
-        ByteWidth: size_in_bytes,
-        Usage: D3D11_USAGE_DYNAMIC,
-        BindFlags: D3D11_BIND_CONSTANT_BUFFER,
-        CPUAccessFlags: D3D11_CPU_ACCESS_WRITE,
-        MiscFlags: 0,
-        StructureByteStride: 0,
-    };
+        let texture = load_dds_from_file(filename, device)?;
+        let handle = self.add_texture(texture);
 
-    let error = unsafe {
-        device_layer.device.native.CreateBuffer(
-            &buffer_desc,
-            std::ptr::null(),
-            &mut constant_buffer,
-        )
-    };
+        self.texture_cache.insert(key, handle);
 
-    assert!(error == winapi::shared::winerror::S_OK);
+        Ok(handle)
+    }
 
-    unsafe {
-        set_debug_name(
-            constant_buffer.as_ref().unwrap(),
-            format!("Constant Buffer - {}", debug_name).as_str(),
-        );
+    // creates the pso or returns the already created pso for the same desc
+    // every call needs to be matched with a call to release_pso
+    #[track_caller]
+    pub fn load_pso(
+        &mut self,
+        device: &GraphicsDevice,
+        desc: PipelineStateObjectDesc,
+    ) -> Result<PsoHandle, GraphicsError> {
+        let key = PipelineStateObjectKey::new(&desc);
+
+        if let Some(handle) = self.pso_cache.acquire(&key) {
+            return Ok(handle);
+        }
+
+        let pso = create_pso(device, desc)?;
+        let handle = self.add_pso(pso);
+
+        self.pso_cache.insert(key, handle);
+
+        Ok(handle)
     }
 
-    GpuBuffer {
-        native_buffer: constant_buffer,
+    // the texture is destroyed once the last reference has been released
+    pub fn release_texture(&mut self, handle: TextureHandle) -> Result<(), GraphicsError> {
+        let is_last_reference = self
+            .texture_cache
+            .release(handle)
+            .ok_or(GraphicsError::InvalidHandle)?;
+
+        if is_last_reference {
+            self.textures
+                .remove(handle.0)
+                .ok_or(GraphicsError::InvalidHandle)?;
+        }
+
+        Ok(())
     }
-}
 
-pub struct GraphicsCommandList<'a> {
-    pub command_context: *mut ID3D11DeviceContext1,
-    phantom: std::marker::PhantomData<&'a mut ID3D11DeviceContext1>, // a marker to indicate that we are holding a reference to ID3D11DeviceContext1 evenso we store a pointer. This is required for lifetime tracking
-}
+    pub fn release_pso(&mut self, handle: PsoHandle) -> Result<(), GraphicsError> {
+        let is_last_reference = self
+            .pso_cache
+            .release(handle)
+            .ok_or(GraphicsError::InvalidHandle)?;
 
-impl Drop for GraphicsCommandList<'_> {
-    fn drop(&mut self) {
-        unsafe {
-            leak_check_release(self.command_context.as_ref().unwrap(), 0, None);
+        if is_last_reference {
+            self.psos
+                .remove(handle.0)
+                .ok_or(GraphicsError::InvalidHandle)?;
         }
+
+        Ok(())
     }
-}
 
-pub struct RenderTargetView<'a> {
-    pub native_view: &'a mut winapi::um::d3d11::ID3D11RenderTargetView,
-    width: i32,
-    height: i32,
-}
+    // recreates all textures and psos that have been created from one of the files
+    // this includes resources that have been added with add_texture / add_pso, textures created from memory are never reloaded
+    // if loading fails the previous version of the resource is kept and the error is part of the report
+    // must not be called while resources are bound to a command list
+    pub fn reload_changed_files(
+        &mut self,
+        device: &GraphicsDevice,
+        changed_files: &[String],
+    ) -> ReloadReport {
+        let changed_files: Vec<String> = changed_files.iter().map(|x| normalize_path(x)).collect();
+        let is_changed = |filename: &str| changed_files.contains(&normalize_path(filename));
+
+        let mut report = ReloadReport::default();
+
+        let textures: Vec<(ResourceId, String)> = self
+            .textures
+            .iter()
+            .filter_map(|(id, texture)| Some((id, texture.source_file.clone()?)))
+            .filter(|(_, filename)| is_changed(filename))
+            .collect();
+
+        for (id, filename) in textures {
+            match load_dds_from_file(&filename, device) {
+                Ok(texture) => {
+                    self.textures.replace(id, texture);
+                    report.reloaded.push(filename);
+                }
+                Err(error) => report.errors.push(error),
+            }
+        }
 
-impl Drop for RenderTargetView<'_> {
-    fn drop(&mut self) {
-        leak_check_release(self.native_view, 0, None);
+        // the vertex and pixel shader might both have changed, only create the pso once
+        let psos: Vec<(ResourceId, PipelineStateObjectKey)> = self
+            .psos
+            .iter()
+            .filter(|(_, pso)| {
+                is_changed(&format!("{}.vsb", pso.shader_name))
+                    || is_changed(&format!("{}.psb", pso.shader_name))
+            })
+            .map(|(id, pso)| {
+                let key = PipelineStateObjectKey {
+                    shader_name: pso.shader_name.clone(),
+                    blend_mode: pso.blend_mode,
+                    sampler: pso.sampler,
+                };
+
+                (id, key)
+            })
+            .collect();
+
+        for (id, key) in psos {
+            match create_pso(device, key.desc()) {
+                Ok(pso) => {
+                    self.psos.replace(id, pso);
+                    report.reloaded.push(key.shader_name);
+                }
+                Err(error) => report.errors.push(error),
+            }
+        }
+
+        report
     }
-}
 
-pub struct ShaderResourceView<'a> {
-    pub native_view: &'a mut winapi::um::d3d11::ID3D11ShaderResourceView,
-}
+    pub fn texture_cache_stats(&self) -> ResourceCacheStats {
+        self.texture_cache.stats
+    }
 
-impl Drop for ShaderResourceView<'_> {
-    fn drop(&mut self) {
-        leak_check_release(self.native_view, 0, None);
+    pub fn pso_cache_stats(&self) -> ResourceCacheStats {
+        self.pso_cache.stats
     }
-}
 
-pub struct Texture<'a> {
-    pub native_texture: &'a mut winapi::um::d3d11::ID3D11Texture2D,
-    pub srv: ShaderResourceView<'a>,
-}
+    pub fn add_texture(&mut self, texture: Texture<'a>) -> TextureHandle {
+        TextureHandle(self.textures.insert(texture))
+    }
 
-impl Drop for Texture<'_> {
-    fn drop(&mut self) {
-        leak_check_release(self.native_texture, 0, None);
+    pub fn add_pso(&mut self, pso: PipelineStateObject<'a>) -> PsoHandle {
+        PsoHandle(self.psos.insert(pso))
     }
-}
 
-pub fn create_texture<'a>(
-    device: &GraphicsDevice,
-    texture_desc: D3D11_TEXTURE2D_DESC,
-    subresources_data: Vec<D3D11_SUBRESOURCE_DATA>,
-) -> Result<Texture<'a>, ()> {
-    let mut texture: *mut winapi::um::d3d11::ID3D11Texture2D = std::ptr::null_mut();
-    let mut texture_view: *mut winapi::um::d3d11::ID3D11ShaderResourceView = std::ptr::null_mut();
+    pub fn add_buffer(&mut self, buffer: GpuBuffer) -> BufferHandle {
+        BufferHandle(self.buffers.insert(buffer))
+    }
 
-    unsafe {
-        let hr =
-            device
-                .native
-                .CreateTexture2D(&texture_desc, subresources_data.as_ptr(), &mut texture);
+    pub fn texture(&self, handle: TextureHandle) -> Result<&Texture<'a>, GraphicsError> {
+        self.textures
+            .get(handle.0)
+            .ok_or(GraphicsError::InvalidHandle)
+    }
 
-        if hr != S_OK {
-            return Err(());
-        }
+    pub fn pso(&self, handle: PsoHandle) -> Result<&PipelineStateObject<'a>, GraphicsError> {
+        self.psos.get(handle.0).ok_or(GraphicsError::InvalidHandle)
+    }
 
-        // create a resource view
-        let hr = device.native.CreateShaderResourceView(
-            texture as *mut winapi::um::d3d11::ID3D11Resource,
-            std::ptr::null_mut(),
-            &mut texture_view,
-        );
+    pub fn buffer(&self, handle: BufferHandle) -> Result<&GpuBuffer, GraphicsError> {
+        self.buffers
+            .get(handle.0)
+            .ok_or(GraphicsError::InvalidHandle)
+    }
 
-        if hr != S_OK {
-            return Err(());
+    // releases the resource, fails if the handle has already been destroyed
+    // cached resources can be shared and need to be released with release_texture / release_pso instead
+    pub fn destroy_texture(&mut self, handle: TextureHandle) -> Result<(), GraphicsError> {
+        if let Some(filename) = self.texture_cache.key(handle) {
+            return Err(GraphicsError::InvalidArgument {
+                resource_name: filename.clone(),
+                reason: "shared textures need to be released with release_texture",
+            });
         }
+
+        self.textures
+            .remove(handle.0)
+            .map(|_| ())
+            .ok_or(GraphicsError::InvalidHandle)
     }
 
-    Ok(Texture {
-        native_texture: unsafe { texture.as_mut().unwrap() },
-        srv: ShaderResourceView {
-            native_view: unsafe { texture_view.as_mut().unwrap() },
-        },
-    })
-}
+    pub fn destroy_pso(&mut self, handle: PsoHandle) -> Result<(), GraphicsError> {
+        if let Some(key) = self.pso_cache.key(handle) {
+            return Err(GraphicsError::InvalidArgument {
+                resource_name: key.shader_name.clone(),
+                reason: "shared psos need to be released with release_pso",
+            });
+        }
 
-pub fn load_dds_from_file<'a>(
-    filename: &str,
-    device: &'a GraphicsDevice,
-) -> Result<Texture<'a>, ()> {
-    // load the texture data
-    let data = std::fs::read(filename).unwrap();
+        self.psos
+            .remove(handle.0)
+            .map(|_| ())
+            .ok_or(GraphicsError::InvalidHandle)
+    }
 
-    // parse the header
-    let texture_load_result = dds_parser::parse_dds_header(&data).unwrap();
+    pub fn destroy_buffer(&mut self, handle: BufferHandle) -> Result<(), GraphicsError> {
+        self.buffers
+            .remove(handle.0)
+            .map(|_| ())
+            .ok_or(GraphicsError::InvalidHandle)
+    }
 
-    create_texture(
-        device,
-        texture_load_result.desc,
-        texture_load_result.subresources_data,
-    )
-}
+    // releases all resources, called when the device is destroyed
+    pub fn clear(&mut self) {
+        self.texture_cache.clear();
+        self.pso_cache.clear();
 
-pub struct GraphicsDevice<'a> {
-    pub native: &'a mut ID3D11Device,
-    pub debug_device: Option<&'a ID3D11Debug>,
+        self.textures.clear();
+        self.psos.clear();
+        self.buffers.clear();
+    }
 }
 
-impl Drop for GraphicsDevice<'_> {
-    fn drop(&mut self) {
-        let expected_device_ref_count = if self.debug_device.is_some() { 1 } else { 0 };
-
-        leak_check_release(self.native, expected_device_ref_count, self.debug_device);
-
-        if let Some(x) = self.debug_device {
-            leak_check_release(&x, 0, None);
-        }
+impl Default for ResourceRegistry<'_> {
+    fn default() -> Self {
+        ResourceRegistry::new()
     }
 }
 
 pub struct GraphicsDeviceLayer<'a> {
+    #[cfg(windows)]
     pub immediate_context: *mut ID3D11DeviceContext,
+    #[cfg(windows)]
     pub swapchain: *mut IDXGISwapChain1,
+    #[cfg(windows)]
     pub backbuffer_texture: *mut ID3D11Texture2D,
 
     pub backbuffer_rtv: RenderTargetView<'a>,
-    pub graphics_command_list: GraphicsCommandList<'a>,
+    pub graphics_command_list: GraphicsCommandList,
+
+    // resources that are referenced by handles, released when the device layer is dropped
+    pub resources: ResourceRegistry<'a>,
 
     // this needs to be the last parameter to make sure that all items that depend on ID3D11Device have been dropped before the device is dropped
     pub device: GraphicsDevice<'a>,
@@ -329,29 +1648,48 @@ pub struct GraphicsDeviceLayer<'a> {
 
 impl Drop for GraphicsDeviceLayer<'_> {
     fn drop(&mut self) {
-        unsafe {
-            leak_check_release(
-                self.backbuffer_texture.as_ref().unwrap(),
-                0,
-                self.device.debug_device,
-            );
-            leak_check_release(
-                self.immediate_context.as_ref().unwrap(),
-                0,
-                self.device.debug_device,
-            );
+        // resources that are still alive at this point have not been released by their owners
+        if let Some(leak_report) = self.device.tracker.leak_report() {
+            println!("{}", leak_report);
+        }
 
-            // in headless mode a swapchain might not exist
-            if let Some(swapchain) = self.swapchain.as_ref() {
-                leak_check_release(swapchain, 0, self.device.debug_device);
+        // all resources need to be released before the device
+        self.resources.clear();
+
+        // the software rasterizer doesn't create any native objects
+        #[cfg(windows)]
+        if self.device.native.is_some() {
+            unsafe {
+                // can be null if resizing the swapchain failed
+                if let Some(backbuffer_texture) = self.backbuffer_texture.as_ref() {
+                    assert_leak_check_release(backbuffer_texture, 0, self.device.debug_device);
+                }
+
+                assert_leak_check_release(
+                    self.immediate_context.as_ref().unwrap(),
+                    0,
+                    self.device.debug_device,
+                );
+
+                // in headless mode a swapchain might not exist
+                if let Some(swapchain) = self.swapchain.as_ref() {
+                    assert_leak_check_release(swapchain, 0, self.device.debug_device);
+                }
             }
         }
     }
 }
 
+#[cfg(windows)]
 pub fn create_device_graphics_layer_headless<'a>(
     enable_debug_device: bool,
-) -> Result<GraphicsDeviceLayer<'a>, ()> {
+) -> Result<GraphicsDeviceLayer<'a>, GraphicsError> {
+    // everything that has been created is released again if creating the device fails part way through
+    let mut created_objects = NativeObjectReleaser::new();
+
+    // objects that are only needed during creation, always released
+    let mut temporary_objects = NativeObjectReleaser::new();
+
     unsafe {
         // use default adapter
         let adapter: *mut IDXGIAdapter = std::ptr::null_mut();
@@ -381,10 +1719,10 @@ pub fn create_device_graphics_layer_headless<'a>(
             &mut d3d11_immediate_context,
         );
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "d3d11 device creation failed"
-        );
+        created_objects.add(d3d11_device);
+        created_objects.add(d3d11_immediate_context);
+
+        check_hresult(result, "D3D11CreateDevice", "Device")?;
 
         set_debug_name(
             d3d11_immediate_context.as_ref().unwrap(),
@@ -399,6 +1737,8 @@ pub fn create_device_graphics_layer_headless<'a>(
                 &ID3D11Debug::uuidof(),
                 &mut debug_device as *mut *mut ID3D11Debug as *mut *mut winapi::ctypes::c_void,
             );
+
+            created_objects.add(debug_device);
         }
 
         let mut dxgi_device: *mut IDXGIDevice = std::ptr::null_mut();
@@ -409,18 +1749,16 @@ pub fn create_device_graphics_layer_headless<'a>(
             &mut dxgi_device as *mut *mut IDXGIDevice as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "QueryInterface failed"
-        );
+        temporary_objects.add(dxgi_device);
+
+        check_hresult(result, "QueryInterface(IDXGIDevice)", "Device")?;
 
         let mut dxgi_adapter: *mut IDXGIAdapter = std::ptr::null_mut();
         let result = dxgi_device.as_ref().unwrap().GetAdapter(&mut dxgi_adapter);
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "GetAdapter failed"
-        );
+        temporary_objects.add(dxgi_adapter);
+
+        check_hresult(result, "GetAdapter", "Device")?;
 
         let mut dxgi_factory: *mut IDXGIFactory1 = std::ptr::null_mut();
 
@@ -429,7 +1767,9 @@ pub fn create_device_graphics_layer_headless<'a>(
             &mut dxgi_factory as *mut *mut IDXGIFactory1 as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(result == winapi::shared::winerror::S_OK, "GetParent failed");
+        temporary_objects.add(dxgi_factory);
+
+        check_hresult(result, "GetParent(IDXGIFactory1)", "Device")?;
 
         let mut dxgi_factory_2: *mut IDXGIFactory2 = std::ptr::null_mut();
 
@@ -438,10 +1778,9 @@ pub fn create_device_graphics_layer_headless<'a>(
             &mut dxgi_factory_2 as *mut *mut IDXGIFactory2 as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "dxgi_factory QueryInterface failed"
-        );
+        temporary_objects.add(dxgi_factory_2);
+
+        check_hresult(result, "QueryInterface(IDXGIFactory2)", "Device")?;
 
         let texture_desc = D3D11_TEXTURE2D_DESC {
             Width: 512,
@@ -468,72 +1807,278 @@ pub fn create_device_graphics_layer_headless<'a>(
             &mut backbuffer_texture,
         );
 
-        if hr != S_OK {
-            return Err(());
-        }
+        created_objects.add(backbuffer_texture);
+
+        check_hresult(hr, "CreateTexture2D", "Backbuffer Texture")?;
 
         let mut backbuffer_rtv: *mut ID3D11RenderTargetView = std::ptr::null_mut();
 
         // now create a render target view onto the texture
-        d3d11_device.as_ref().unwrap().CreateRenderTargetView(
+        let result = d3d11_device.as_ref().unwrap().CreateRenderTargetView(
             backbuffer_texture as *mut winapi::um::d3d11::ID3D11Resource,
             std::ptr::null_mut(),
             &mut backbuffer_rtv,
         );
 
+        created_objects.add(backbuffer_rtv);
+
+        check_hresult(result, "CreateRenderTargetView", "Backbuffer")?;
+
         set_debug_name(backbuffer_rtv.as_ref().unwrap(), "Backbuffer RTV");
 
         let mut command_context: *mut ID3D11DeviceContext = std::ptr::null_mut();
         let mut command_context1: *mut ID3D11DeviceContext1 = std::ptr::null_mut();
 
-        let error = d3d11_device
+        let result = d3d11_device
             .as_ref()
             .unwrap()
             .CreateDeferredContext(0, &mut command_context);
 
-        assert!(error == winapi::shared::winerror::S_OK);
+        // command_context1 is another view onto the same object, so only one of them is added
+        created_objects.add(command_context);
 
-        command_context.as_ref().unwrap().QueryInterface(
+        check_hresult(result, "CreateDeferredContext", "Deferred Context")?;
+
+        let result = command_context.as_ref().unwrap().QueryInterface(
             &ID3D11DeviceContext1::uuidof(),
             &mut command_context1 as *mut *mut ID3D11DeviceContext1
                 as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(error == winapi::shared::winerror::S_OK);
+        check_hresult(
+            result,
+            "QueryInterface(ID3D11DeviceContext1)",
+            "Deferred Context",
+        )?;
 
         // should keep a ref-count of 1 because they are alternative views onto objects that have another view that is still active
-        leak_check_release(command_context.as_ref().unwrap(), 1, debug_device.as_ref());
-        dxgi_device.as_ref().unwrap().Release();
+        leak_check_release(command_context.as_ref().unwrap(), 1, debug_device.as_ref())?;
 
         set_debug_name(command_context.as_ref().unwrap(), "Deferred Context");
 
         let swapchain: *mut IDXGISwapChain1 = std::ptr::null_mut();
 
+        // the objects are owned by the device layer from here on
+        created_objects.keep_all();
+
         Ok(GraphicsDeviceLayer {
+            resources: ResourceRegistry::new(),
             device: GraphicsDevice {
-                native: d3d11_device.as_mut().unwrap(),
+                native: d3d11_device.as_mut(),
                 debug_device: debug_device.as_ref(),
+                tracker: ResourceTracker::new(),
             },
             immediate_context: d3d11_immediate_context,
             swapchain,
             backbuffer_texture,
             backbuffer_rtv: RenderTargetView {
-                native_view: backbuffer_rtv.as_mut().unwrap(),
+                native_view: backbuffer_rtv.as_mut(),
+                software_target: None,
                 width: 512,
                 height: 512,
+                tracking: None,
             },
-            graphics_command_list: GraphicsCommandList {
+            graphics_command_list: GraphicsCommandList::Native {
                 command_context: command_context1,
-                phantom: std::marker::PhantomData,
+                clip_stack: ClipStack::new(),
+                render_target_rect: ClipRect {
+                    x: 0,
+                    y: 0,
+                    width: 0,
+                    height: 0,
+                },
+                stats: CommandListStats::default(),
             },
         })
     }
 }
 
+// creates a device that renders on the CPU into a RGBA8 backbuffer
+// doesn't require a GPU, the rendered image can be accessed through backbuffer_rtv.software_target
+pub fn create_device_graphics_layer_software<'a>(
+    width: i32,
+    height: i32,
+) -> Result<GraphicsDeviceLayer<'a>, GraphicsError> {
+    create_cpu_device_graphics_layer(
+        width,
+        height,
+        GraphicsCommandList::Software(SoftwareCommandList::new()),
+    )
+}
+
+// creates a device that only records the commands issued to graphics_command_list
+// resources are created the same way as for the software rasterizer
+pub fn create_device_graphics_layer_recording<'a>(
+    width: i32,
+    height: i32,
+) -> Result<GraphicsDeviceLayer<'a>, GraphicsError> {
+    create_cpu_device_graphics_layer(
+        width,
+        height,
+        GraphicsCommandList::Recording(CommandRecorder::new()),
+    )
+}
+
+fn create_cpu_device_graphics_layer<'a>(
+    width: i32,
+    height: i32,
+    graphics_command_list: GraphicsCommandList,
+) -> Result<GraphicsDeviceLayer<'a>, GraphicsError> {
+    if width <= 0 || height <= 0 {
+        return Err(GraphicsError::InvalidArgument {
+            resource_name: String::from("Backbuffer"),
+            reason: "the backbuffer size needs to be positive",
+        });
+    }
+
+    Ok(GraphicsDeviceLayer {
+        resources: ResourceRegistry::new(),
+        device: GraphicsDevice {
+            native: None,
+            debug_device: None,
+            tracker: ResourceTracker::new(),
+        },
+        #[cfg(windows)]
+        immediate_context: std::ptr::null_mut(),
+        #[cfg(windows)]
+        swapchain: std::ptr::null_mut(),
+        #[cfg(windows)]
+        backbuffer_texture: std::ptr::null_mut(),
+        backbuffer_rtv: create_cpu_backbuffer_rtv(width, height),
+        graphics_command_list,
+    })
+}
+
+fn create_cpu_backbuffer_rtv<'a>(width: i32, height: i32) -> RenderTargetView<'a> {
+    let mut backbuffer = CpuTexture::new(width as u32, height as u32);
+    backbuffer.name = String::from("Backbuffer");
+
+    RenderTargetView {
+        native_view: None,
+        software_target: Some(std::rc::Rc::new(std::cell::RefCell::new(backbuffer))),
+        width,
+        height,
+        tracking: None,
+    }
+}
+
+// resizes the backbuffer, needs to be called when the client area of the window changes size
+// the content of the backbuffer is undefined afterwards
+pub fn resize_swapchain(
+    graphics_layer: &mut GraphicsDeviceLayer,
+    width: i32,
+    height: i32,
+) -> Result<(), GraphicsError> {
+    if width <= 0 || height <= 0 {
+        return Err(GraphicsError::InvalidArgument {
+            resource_name: String::from("Backbuffer"),
+            reason: "the backbuffer size needs to be positive",
+        });
+    }
+
+    #[cfg(windows)]
+    if let Some(swapchain) = unsafe { graphics_layer.swapchain.as_ref() } {
+        return resize_native_swapchain(graphics_layer, swapchain, width, height);
+    }
+
+    if graphics_layer.device.native.is_some() {
+        return Err(GraphicsError::InvalidArgument {
+            resource_name: String::from("Backbuffer"),
+            reason: "headless devices don't have a swapchain that can be resized",
+        });
+    }
+
+    // software and recording devices get a new backbuffer
+    graphics_layer.backbuffer_rtv = create_cpu_backbuffer_rtv(width, height);
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn resize_native_swapchain(
+    graphics_layer: &mut GraphicsDeviceLayer,
+    swapchain: &IDXGISwapChain1,
+    width: i32,
+    height: i32,
+) -> Result<(), GraphicsError> {
+    let native_device = graphics_layer.device.native.as_ref().unwrap();
+
+    unsafe {
+        // all references to the buffers of the swapchain need to be released before they can be resized
+        let immediate_context = graphics_layer.immediate_context.as_ref().unwrap();
+        immediate_context.ClearState();
+        immediate_context.Flush();
+
+        graphics_layer.backbuffer_rtv = RenderTargetView {
+            native_view: None,
+            software_target: None,
+            width: 0,
+            height: 0,
+            tracking: None,
+        };
+
+        let release_result = leak_check_release(
+            graphics_layer.backbuffer_texture.as_ref().unwrap(),
+            0,
+            graphics_layer.device.debug_device,
+        );
+        graphics_layer.backbuffer_texture = std::ptr::null_mut();
+        release_result?;
+
+        let result =
+            swapchain.ResizeBuffers(0, width as u32, height as u32, DXGI_FORMAT_UNKNOWN, 0);
+
+        check_hresult(result, "ResizeBuffers", "Swapchain")?;
+
+        let mut backbuffer_texture: *mut ID3D11Texture2D = std::ptr::null_mut();
+        let result = swapchain.GetBuffer(
+            0,
+            &ID3D11Texture2D::uuidof(),
+            &mut backbuffer_texture as *mut *mut ID3D11Texture2D
+                as *mut *mut winapi::ctypes::c_void,
+        );
+
+        check_hresult(result, "GetBuffer", "Swapchain")?;
+
+        set_debug_name(backbuffer_texture.as_ref().unwrap(), "Backbuffer Texture");
+
+        let mut backbuffer_rtv: *mut ID3D11RenderTargetView = std::ptr::null_mut();
+
+        let result = native_device.CreateRenderTargetView(
+            backbuffer_texture as *mut winapi::um::d3d11::ID3D11Resource,
+            std::ptr::null_mut(),
+            &mut backbuffer_rtv,
+        );
+
+        graphics_layer.backbuffer_texture = backbuffer_texture;
+
+        check_hresult(result, "CreateRenderTargetView", "Backbuffer")?;
+
+        set_debug_name(backbuffer_rtv.as_ref().unwrap(), "Backbuffer RTV");
+
+        graphics_layer.backbuffer_rtv = RenderTargetView {
+            native_view: backbuffer_rtv.as_mut(),
+            software_target: None,
+            width,
+            height,
+            tracking: None,
+        };
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
 pub fn create_device_graphics_layer<'a>(
     hwnd: HWND,
     enable_debug_device: bool,
-) -> Result<GraphicsDeviceLayer<'a>, ()> {
+) -> Result<GraphicsDeviceLayer<'a>, GraphicsError> {
+    // everything that has been created is released again if creating the device fails part way through
+    let mut created_objects = NativeObjectReleaser::new();
+
+    // objects that are only needed during creation, always released
+    let mut temporary_objects = NativeObjectReleaser::new();
+
     unsafe {
         // use default adapter
         let adapter: *mut IDXGIAdapter = std::ptr::null_mut();
@@ -563,10 +2108,10 @@ pub fn create_device_graphics_layer<'a>(
             &mut d3d11_immediate_context,
         );
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "d3d11 device creation failed"
-        );
+        created_objects.add(d3d11_device);
+        created_objects.add(d3d11_immediate_context);
+
+        check_hresult(result, "D3D11CreateDevice", "Device")?;
 
         set_debug_name(
             d3d11_immediate_context.as_ref().unwrap(),
@@ -581,6 +2126,8 @@ pub fn create_device_graphics_layer<'a>(
                 &ID3D11Debug::uuidof(),
                 &mut debug_device as *mut *mut ID3D11Debug as *mut *mut winapi::ctypes::c_void,
             );
+
+            created_objects.add(debug_device);
         }
 
         let mut dxgi_device: *mut IDXGIDevice = std::ptr::null_mut();
@@ -591,18 +2138,16 @@ pub fn create_device_graphics_layer<'a>(
             &mut dxgi_device as *mut *mut IDXGIDevice as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "QueryInterface failed"
-        );
+        temporary_objects.add(dxgi_device);
+
+        check_hresult(result, "QueryInterface(IDXGIDevice)", "Device")?;
 
         let mut dxgi_adapter: *mut IDXGIAdapter = std::ptr::null_mut();
         let result = dxgi_device.as_ref().unwrap().GetAdapter(&mut dxgi_adapter);
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "GetAdapter failed"
-        );
+        temporary_objects.add(dxgi_adapter);
+
+        check_hresult(result, "GetAdapter", "Device")?;
 
         let mut dxgi_factory: *mut IDXGIFactory1 = std::ptr::null_mut();
 
@@ -611,7 +2156,9 @@ pub fn create_device_graphics_layer<'a>(
             &mut dxgi_factory as *mut *mut IDXGIFactory1 as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(result == winapi::shared::winerror::S_OK, "GetParent failed");
+        temporary_objects.add(dxgi_factory);
+
+        check_hresult(result, "GetParent(IDXGIFactory1)", "Device")?;
 
         let mut dxgi_factory_2: *mut IDXGIFactory2 = std::ptr::null_mut();
 
@@ -620,10 +2167,9 @@ pub fn create_device_graphics_layer<'a>(
             &mut dxgi_factory_2 as *mut *mut IDXGIFactory2 as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "dxgi_factory QueryInterface failed"
-        );
+        temporary_objects.add(dxgi_factory_2);
+
+        check_hresult(result, "QueryInterface(IDXGIFactory2)", "Device")?;
 
         let sd = DXGI_SWAP_CHAIN_DESC1 {
             Width: 0,
@@ -653,53 +2199,66 @@ pub fn create_device_graphics_layer<'a>(
             &mut swapchain,
         );
 
-        assert!(
-            result == winapi::shared::winerror::S_OK,
-            "CreateSwapChainForHwnd failed"
-        );
+        created_objects.add(swapchain);
+
+        check_hresult(result, "CreateSwapChainForHwnd", "Swapchain")?;
 
         let mut backbuffer_texture: *mut ID3D11Texture2D = std::ptr::null_mut();
-        swapchain.as_ref().unwrap().GetBuffer(
+        let result = swapchain.as_ref().unwrap().GetBuffer(
             0,
             &ID3D11Texture2D::uuidof(),
             &mut backbuffer_texture as *mut *mut ID3D11Texture2D
                 as *mut *mut winapi::ctypes::c_void,
         );
 
+        created_objects.add(backbuffer_texture);
+
+        check_hresult(result, "GetBuffer", "Swapchain")?;
+
         set_debug_name(backbuffer_texture.as_ref().unwrap(), "Backbuffer Texture");
 
         let mut backbuffer_rtv: *mut ID3D11RenderTargetView = std::ptr::null_mut();
 
         // now create a render target view onto the texture
-        d3d11_device.as_ref().unwrap().CreateRenderTargetView(
+        let result = d3d11_device.as_ref().unwrap().CreateRenderTargetView(
             backbuffer_texture as *mut winapi::um::d3d11::ID3D11Resource,
             std::ptr::null_mut(),
             &mut backbuffer_rtv,
         );
 
+        created_objects.add(backbuffer_rtv);
+
+        check_hresult(result, "CreateRenderTargetView", "Backbuffer")?;
+
         set_debug_name(backbuffer_rtv.as_ref().unwrap(), "Backbuffer RTV");
 
         let mut command_context: *mut ID3D11DeviceContext = std::ptr::null_mut();
         let mut command_context1: *mut ID3D11DeviceContext1 = std::ptr::null_mut();
 
-        let error = d3d11_device
+        let result = d3d11_device
             .as_ref()
             .unwrap()
             .CreateDeferredContext(0, &mut command_context);
 
-        assert!(error == winapi::shared::winerror::S_OK);
+        // command_context1 is another view onto the same object, so only one of them is added
+        created_objects.add(command_context);
 
-        command_context.as_ref().unwrap().QueryInterface(
+        check_hresult(result, "CreateDeferredContext", "Deferred Context")?;
+
+        let result = command_context.as_ref().unwrap().QueryInterface(
             &ID3D11DeviceContext1::uuidof(),
             &mut command_context1 as *mut *mut ID3D11DeviceContext1
                 as *mut *mut winapi::ctypes::c_void,
         );
 
-        assert!(error == winapi::shared::winerror::S_OK);
+        check_hresult(
+            result,
+            "QueryInterface(ID3D11DeviceContext1)",
+            "Deferred Context",
+        )?;
 
         // should keep a ref-count of 1 because they are alternative views onto objects that have another view that is still active
-        leak_check_release(command_context.as_ref().unwrap(), 1, debug_device.as_ref());
-        dxgi_device.as_ref().unwrap().Release();
+        leak_check_release(command_context.as_ref().unwrap(), 1, debug_device.as_ref())?;
 
         set_debug_name(command_context.as_ref().unwrap(), "Deferred Context");
 
@@ -712,57 +2271,163 @@ pub fn create_device_graphics_layer<'a>(
 
         winapi::um::winuser::GetClientRect(hwnd, &mut rect);
 
+        // the objects are owned by the device layer from here on
+        created_objects.keep_all();
+
         Ok(GraphicsDeviceLayer {
+            resources: ResourceRegistry::new(),
             device: GraphicsDevice {
-                native: d3d11_device.as_mut().unwrap(),
+                native: d3d11_device.as_mut(),
                 debug_device: debug_device.as_ref(),
+                tracker: ResourceTracker::new(),
             },
             immediate_context: d3d11_immediate_context,
             swapchain,
             backbuffer_texture,
             backbuffer_rtv: RenderTargetView {
-                native_view: backbuffer_rtv.as_mut().unwrap(),
+                native_view: backbuffer_rtv.as_mut(),
+                software_target: None,
                 width: rect.right,
                 height: rect.bottom,
+                tracking: None,
             },
-            graphics_command_list: GraphicsCommandList {
+            graphics_command_list: GraphicsCommandList::Native {
                 command_context: command_context1,
-                phantom: std::marker::PhantomData,
+                clip_stack: ClipStack::new(),
+                render_target_rect: ClipRect {
+                    x: 0,
+                    y: 0,
+                    width: 0,
+                    height: 0,
+                },
+                stats: CommandListStats::default(),
             },
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct PipelineStateObjectDesc<'a> {
     pub shader_name: &'a str,
-    pub premultiplied_alpha: bool,
+    pub blend_mode: BlendMode,
+
+    // used for all textures the pixel shader samples from
+    pub sampler: SamplerDesc,
 }
 
-pub struct PipelineStateObject<'a> {
+// owned version of PipelineStateObjectDesc, used to find existing psos in the resource cache
+// every field of the desc is part of the key, psos are only shared if they are identical
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PipelineStateObjectKey {
+    shader_name: String,
+    blend_mode: BlendMode,
+    sampler: SamplerDesc,
+}
+
+impl PipelineStateObjectKey {
+    fn new(desc: &PipelineStateObjectDesc) -> PipelineStateObjectKey {
+        PipelineStateObjectKey {
+            shader_name: desc.shader_name.to_string(),
+            blend_mode: desc.blend_mode,
+            sampler: desc.sampler,
+        }
+    }
+
+    fn desc(&self) -> PipelineStateObjectDesc<'_> {
+        PipelineStateObjectDesc {
+            shader_name: &self.shader_name,
+            blend_mode: self.blend_mode,
+            sampler: self.sampler,
+        }
+    }
+}
+
+#[cfg(windows)]
+pub struct NativePipelineStateObject<'a> {
     pub vertex_shader: &'a ID3D11VertexShader,
     pub pixel_shader: &'a ID3D11PixelShader,
     pub blend_state: &'a ID3D11BlendState,
+    pub rasterizer_state: &'a ID3D11RasterizerState,
     pub static_samplers: &'a winapi::um::d3d11::ID3D11SamplerState,
 }
 
-impl Drop for PipelineStateObject<'_> {
+pub struct PipelineStateObject<'a> {
+    pub shader_name: String,
+    pub blend_mode: BlendMode,
+    pub sampler: SamplerDesc,
+    #[cfg(windows)]
+    pub native: Option<NativePipelineStateObject<'a>>, // None when created for the software rasterizer
+    #[cfg(not(windows))]
+    pub native: Option<&'a NoNativeObject>,
+
+    // how the software rasterizer emulates the shaders, None if the shaders are unknown to the software rasterizer
+    pub software: Option<SoftwarePipelineState>,
+
+    pub tracking: TrackedResource,
+}
+
+#[cfg(windows)]
+impl Drop for NativePipelineStateObject<'_> {
     fn drop(&mut self) {
-        leak_check_release(self.vertex_shader, 0, None);
-        leak_check_release(self.pixel_shader, 0, None);
+        assert_leak_check_release(self.vertex_shader, 0, None);
+        assert_leak_check_release(self.pixel_shader, 0, None);
 
         // not leak_check release because when we are creating the same sampler twice the runtime will deduliate it and increment the refcount on the same object instea
         unsafe {
             self.static_samplers.Release();
             self.blend_state.Release();
+            self.rasterizer_state.Release();
         }
     }
 }
 
+#[track_caller]
 pub fn create_pso<'a>(
     device: &GraphicsDevice,
     desc: PipelineStateObjectDesc,
-) -> PipelineStateObject<'a> {
+) -> Result<PipelineStateObject<'a>, GraphicsError> {
+    let creation_site = Location::caller();
+
+    let software = find_quad_shader(desc.shader_name).map(|shader| SoftwarePipelineState {
+        shader,
+        blend_mode: desc.blend_mode,
+        sampler: desc.sampler,
+    });
+
+    #[cfg(windows)]
+    if let Some(native_device) = &device.native {
+        return create_native_pso(device, native_device, desc, software, creation_site);
+    }
+
+    if software.is_none() {
+        return Err(GraphicsError::UnsupportedShader {
+            shader_name: desc.shader_name.to_string(),
+        });
+    }
+
+    Ok(PipelineStateObject {
+        shader_name: desc.shader_name.to_string(),
+        blend_mode: desc.blend_mode,
+        sampler: desc.sampler,
+        native: None,
+        software,
+        tracking: device.tracker.track(
+            TrackedResourceKind::PipelineState,
+            desc.shader_name,
+            creation_site,
+            0,
+        ),
+    })
+}
+
+#[cfg(windows)]
+fn create_native_pso<'a>(
+    device: &GraphicsDevice,
+    native_device: &ID3D11Device,
+    desc: PipelineStateObjectDesc,
+    software: Option<SoftwarePipelineState>,
+    creation_site: &'static Location<'static>,
+) -> Result<PipelineStateObject<'a>, GraphicsError> {
     // build the name of the vertex and pixel shader to load
     let vertex_shader_name = format!("{0}.vsb", desc.shader_name);
     let pixel_shader_name = format!("{0}.psb", desc.shader_name);
@@ -772,11 +2437,11 @@ pub fn create_pso<'a>(
     let mut blend_state: *mut ID3D11BlendState = std::ptr::null_mut();
 
     // load a shader
-    let vertex_shader_memory = std::fs::read(&vertex_shader_name).unwrap();
-    let pixel_shader_memory = std::fs::read(&pixel_shader_name).unwrap();
+    let vertex_shader_memory = read_file(&vertex_shader_name)?;
+    let pixel_shader_memory = read_file(&pixel_shader_name)?;
 
     let error: HRESULT = unsafe {
-        device.native.CreateVertexShader(
+        native_device.CreateVertexShader(
             vertex_shader_memory.as_ptr() as *const winapi::ctypes::c_void,
             vertex_shader_memory.len(),
             std::ptr::null_mut(),
@@ -784,7 +2449,7 @@ pub fn create_pso<'a>(
         )
     };
 
-    assert!(error == winapi::shared::winerror::S_OK);
+    check_hresult(error, "CreateVertexShader", &vertex_shader_name)?;
 
     unsafe {
         set_debug_name(
@@ -794,7 +2459,7 @@ pub fn create_pso<'a>(
     }
 
     let error: HRESULT = unsafe {
-        device.native.CreatePixelShader(
+        native_device.CreatePixelShader(
             pixel_shader_memory.as_ptr() as *const winapi::ctypes::c_void,
             pixel_shader_memory.len(),
             std::ptr::null_mut(),
@@ -802,7 +2467,13 @@ pub fn create_pso<'a>(
         )
     };
 
-    assert!(error == winapi::shared::winerror::S_OK);
+    if error != winapi::shared::winerror::S_OK {
+        unsafe {
+            assert_leak_check_release(vertex_shader.as_ref().unwrap(), 0, None);
+        }
+    }
+
+    check_hresult(error, "CreatePixelShader", &pixel_shader_name)?;
 
     unsafe {
         set_debug_name(
@@ -811,13 +2482,52 @@ pub fn create_pso<'a>(
         );
     }
 
+    // the software rasterizer emulates these in software_rasterizer::blend
+    // (src color, dst color, src alpha, dst alpha)
+    let (src_blend, dest_blend, src_blend_alpha, dest_blend_alpha) = match desc.blend_mode {
+        BlendMode::Opaque => (
+            D3D11_BLEND_ONE,
+            D3D11_BLEND_ZERO,
+            D3D11_BLEND_ONE,
+            D3D11_BLEND_ZERO,
+        ),
+        BlendMode::Alpha => (
+            D3D11_BLEND_SRC_ALPHA,
+            D3D11_BLEND_INV_SRC_ALPHA,
+            D3D11_BLEND_INV_DEST_ALPHA,
+            D3D11_BLEND_ONE,
+        ),
+        BlendMode::Premultiplied => (
+            D3D11_BLEND_ONE,
+            D3D11_BLEND_INV_SRC_ALPHA,
+            D3D11_BLEND_ONE,
+            D3D11_BLEND_INV_SRC_ALPHA,
+        ),
+        BlendMode::Additive => (
+            D3D11_BLEND_SRC_ALPHA,
+            D3D11_BLEND_ONE,
+            D3D11_BLEND_ZERO,
+            D3D11_BLEND_ONE,
+        ),
+        BlendMode::Multiply => (
+            D3D11_BLEND_DEST_COLOR,
+            D3D11_BLEND_ZERO,
+            D3D11_BLEND_ZERO,
+            D3D11_BLEND_ONE,
+        ),
+    };
+
     let rt0_blend_desc = D3D11_RENDER_TARGET_BLEND_DESC {
-        BlendEnable: if desc.premultiplied_alpha { 1 } else { 0 },
-        SrcBlend: D3D11_BLEND_SRC_ALPHA,
-        DestBlend: D3D11_BLEND_INV_SRC_ALPHA,
+        BlendEnable: if desc.blend_mode == BlendMode::Opaque {
+            0
+        } else {
+            1
+        },
+        SrcBlend: src_blend,
+        DestBlend: dest_blend,
         BlendOp: D3D11_BLEND_OP_ADD,
-        SrcBlendAlpha: D3D11_BLEND_INV_DEST_ALPHA,
-        DestBlendAlpha: D3D11_BLEND_ONE,
+        SrcBlendAlpha: src_blend_alpha,
+        DestBlendAlpha: dest_blend_alpha,
         BlendOpAlpha: D3D11_BLEND_OP_ADD,
         RenderTargetWriteMask: D3D11_COLOR_WRITE_ENABLE_ALL as u8,
     };
@@ -829,17 +2539,59 @@ pub fn create_pso<'a>(
         RenderTarget: [rt0_blend_desc; 8],
     };
 
-    let error: HRESULT = unsafe {
-        device
-            .native
-            .CreateBlendState(&blend_desc, &mut blend_state)
+    let error: HRESULT = unsafe { native_device.CreateBlendState(&blend_desc, &mut blend_state) };
+
+    if error != winapi::shared::winerror::S_OK {
+        unsafe {
+            assert_leak_check_release(vertex_shader.as_ref().unwrap(), 0, None);
+            assert_leak_check_release(pixel_shader.as_ref().unwrap(), 0, None);
+        }
+    }
+
+    check_hresult(error, "CreateBlendState", desc.shader_name)?;
+
+    // the default rasterizer state with the scissor test enabled, used to implement clip rects
+    let rasterizer_desc = D3D11_RASTERIZER_DESC {
+        FillMode: D3D11_FILL_SOLID,
+        CullMode: D3D11_CULL_BACK,
+        FrontCounterClockwise: 0,
+        DepthBias: 0,
+        DepthBiasClamp: 0.0,
+        SlopeScaledDepthBias: 0.0,
+        DepthClipEnable: 1,
+        ScissorEnable: 1,
+        MultisampleEnable: 0,
+        AntialiasedLineEnable: 0,
+    };
+
+    let mut rasterizer_state: *mut ID3D11RasterizerState = std::ptr::null_mut();
+
+    let error: HRESULT =
+        unsafe { native_device.CreateRasterizerState(&rasterizer_desc, &mut rasterizer_state) };
+
+    if error != winapi::shared::winerror::S_OK {
+        unsafe {
+            assert_leak_check_release(vertex_shader.as_ref().unwrap(), 0, None);
+            assert_leak_check_release(pixel_shader.as_ref().unwrap(), 0, None);
+            blend_state.as_ref().unwrap().Release();
+        }
+    }
+
+    check_hresult(error, "CreateRasterizerState", desc.shader_name)?;
+
+    let native_address_mode = |address_mode: TextureAddressMode| match address_mode {
+        TextureAddressMode::Clamp => winapi::um::d3d11::D3D11_TEXTURE_ADDRESS_CLAMP,
+        TextureAddressMode::Wrap => winapi::um::d3d11::D3D11_TEXTURE_ADDRESS_WRAP,
+        TextureAddressMode::Mirror => winapi::um::d3d11::D3D11_TEXTURE_ADDRESS_MIRROR,
     };
 
-    assert!(error == winapi::shared::winerror::S_OK);
     let sampler_desc = winapi::um::d3d11::D3D11_SAMPLER_DESC {
-        Filter: winapi::um::d3d11::D3D11_FILTER_MIN_MAG_MIP_LINEAR,
-        AddressU: winapi::um::d3d11::D3D11_TEXTURE_ADDRESS_CLAMP,
-        AddressV: winapi::um::d3d11::D3D11_TEXTURE_ADDRESS_CLAMP,
+        Filter: match desc.sampler.filter {
+            SamplerFilter::Point => winapi::um::d3d11::D3D11_FILTER_MIN_MAG_MIP_POINT,
+            SamplerFilter::Linear => winapi::um::d3d11::D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+        },
+        AddressU: native_address_mode(desc.sampler.address_u),
+        AddressV: native_address_mode(desc.sampler.address_v),
         AddressW: winapi::um::d3d11::D3D11_TEXTURE_ADDRESS_CLAMP,
         MinLOD: 0.0,
         MaxLOD: 32.0,
@@ -853,138 +2605,483 @@ pub fn create_pso<'a>(
 
     let error: HRESULT = unsafe {
         // create a sampler
-        device
-            .native
-            .CreateSamplerState(&sampler_desc, &mut native_sampler)
+        native_device.CreateSamplerState(&sampler_desc, &mut native_sampler)
     };
 
-    assert!(error == winapi::shared::winerror::S_OK);
-
-    PipelineStateObject {
-        vertex_shader: unsafe { vertex_shader.as_mut().unwrap() },
-        pixel_shader: unsafe { pixel_shader.as_mut().unwrap() },
-        blend_state: unsafe { blend_state.as_mut().unwrap() },
-        static_samplers: unsafe { native_sampler.as_mut().unwrap() },
+    if error != winapi::shared::winerror::S_OK {
+        unsafe {
+            assert_leak_check_release(vertex_shader.as_ref().unwrap(), 0, None);
+            assert_leak_check_release(pixel_shader.as_ref().unwrap(), 0, None);
+            blend_state.as_ref().unwrap().Release();
+            rasterizer_state.as_ref().unwrap().Release();
+        }
     }
+
+    check_hresult(error, "CreateSamplerState", desc.shader_name)?;
+
+    Ok(PipelineStateObject {
+        shader_name: desc.shader_name.to_string(),
+        blend_mode: desc.blend_mode,
+        sampler: desc.sampler,
+        native: Some(NativePipelineStateObject {
+            vertex_shader: unsafe { vertex_shader.as_mut().unwrap() },
+            pixel_shader: unsafe { pixel_shader.as_mut().unwrap() },
+            blend_state: unsafe { blend_state.as_mut().unwrap() },
+            rasterizer_state: unsafe { rasterizer_state.as_mut().unwrap() },
+            static_samplers: unsafe { native_sampler.as_mut().unwrap() },
+        }),
+        software,
+
+        // the driver copies of the shaders are about the size of the bytecode
+        tracking: device.tracker.track(
+            TrackedResourceKind::PipelineState,
+            desc.shader_name,
+            creation_site,
+            vertex_shader_memory.len() + pixel_shader_memory.len(),
+        ),
+    })
 }
 
 fn clear_render_target(
     command_list: &mut GraphicsCommandList,
     clear_color: [f32; 4],
     rtv: &RenderTargetView,
+) -> Result<(), GraphicsError> {
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context, ..
+        } => unsafe {
+            let command_context = command_context.as_ref().unwrap();
+
+            let rtv_mut: *mut ID3D11RenderTargetView = native_rtv(rtv)?;
+
+            command_context.ClearRenderTargetView(rtv_mut, &clear_color);
+        },
+
+        GraphicsCommandList::Software(_) => {
+            clear_texture(&mut software_rtv(rtv)?.borrow_mut(), clear_color);
+        }
+
+        GraphicsCommandList::Recording(recorder) => {
+            recorder.clear_render_target(clear_color);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn native_rtv(rtv: &RenderTargetView) -> Result<*mut ID3D11RenderTargetView, GraphicsError> {
+    let native_view: &ID3D11RenderTargetView =
+        rtv.native_view
+            .as_ref()
+            .ok_or_else(|| GraphicsError::InvalidArgument {
+                resource_name: render_target_name(rtv),
+                reason: "software render target used with a native command list",
+            })?;
+
+    Ok(native_view as *const ID3D11RenderTargetView as u64 as *mut ID3D11RenderTargetView)
+}
+
+fn software_rtv(rtv: &RenderTargetView) -> Result<SoftwareTexture, GraphicsError> {
+    rtv.software_target
+        .clone()
+        .ok_or_else(|| GraphicsError::InvalidArgument {
+            resource_name: render_target_name(rtv),
+            reason: "native render target used with the software rasterizer",
+        })
+}
+
+// names used to identify resources in recorded commands
+fn render_target_name(rtv: &RenderTargetView) -> String {
+    match &rtv.software_target {
+        Some(x) => x.borrow().name.clone(),
+        None => String::from("Native Render Target"),
+    }
+}
+
+fn texture_name(srv: &ShaderResourceView) -> String {
+    match &srv.software_texture {
+        Some(x) => x.borrow().name.clone(),
+        None => String::from("Native Texture"),
+    }
+}
+
+// the scissor test is always enabled, without a clip rect the scissor rect covers the whole render target
+#[cfg(windows)]
+fn set_native_scissor_rect(
+    command_context: &ID3D11DeviceContext1,
+    clip_stack: &ClipStack,
+    render_target_rect: &ClipRect,
 ) {
+    let [left, top, right, bottom] = clip_stack
+        .current()
+        .unwrap_or(*render_target_rect)
+        .scissor_rect(render_target_rect.height);
+
+    let rect = winapi::shared::windef::RECT {
+        left,
+        top,
+        right,
+        bottom,
+    };
+
     unsafe {
-        let command_context = command_list.command_context.as_ref().unwrap();
+        command_context.RSSetScissorRects(1, &rect);
+    }
+}
+
+fn command_list_stats_mut(command_list: &mut GraphicsCommandList) -> &mut CommandListStats {
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native { stats, .. } => stats,
+        GraphicsCommandList::Software(software_command_list) => &mut software_command_list.stats,
+        GraphicsCommandList::Recording(recorder) => &mut recorder.stats,
+    }
+}
+
+// the counters since the last call, they are reset afterwards
+pub fn take_command_list_stats(command_list: &mut GraphicsCommandList) -> CommandListStats {
+    std::mem::take(command_list_stats_mut(command_list))
+}
+
+pub fn begin_render_pass(
+    command_list: &mut GraphicsCommandList,
+    rtv: &RenderTargetView,
+) -> Result<(), GraphicsError> {
+    command_list_stats_mut(command_list).render_passes += 1;
+
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context,
+            clip_stack,
+            render_target_rect,
+            ..
+        } => unsafe {
+            let command_context = command_context.as_ref().unwrap();
+
+            let rtv_mut: *mut ID3D11RenderTargetView = native_rtv(rtv)?;
+
+            let viewport: D3D11_VIEWPORT = D3D11_VIEWPORT {
+                Height: rtv.height as f32,
+                Width: rtv.width as f32,
+                MinDepth: 0.0,
+                MaxDepth: 1.0,
+                TopLeftX: 0.0,
+                TopLeftY: 0.0,
+            };
+
+            // set viewport for the output window
+            command_context.RSSetViewports(1, &viewport);
+
+            // bind backbuffer as render target
+            let rtvs: [*mut winapi::um::d3d11::ID3D11RenderTargetView; 1] = [rtv_mut];
+            command_context.OMSetRenderTargets(1, rtvs.as_ptr(), std::ptr::null_mut());
+
+            *render_target_rect = ClipRect {
+                x: 0,
+                y: 0,
+                width: rtv.width,
+                height: rtv.height,
+            };
+            set_native_scissor_rect(command_context, clip_stack, render_target_rect);
+        },
 
-        let rtv_mut: *mut ID3D11RenderTargetView =
-            rtv.native_view as *const ID3D11RenderTargetView as u64 as *mut ID3D11RenderTargetView;
+        GraphicsCommandList::Software(software_command_list) => {
+            // the viewport always covers the full render target
+            software_command_list.render_target = Some(software_rtv(rtv)?);
+        }
 
-        command_context.ClearRenderTargetView(rtv_mut, &clear_color);
+        GraphicsCommandList::Recording(recorder) => {
+            recorder.begin_render_pass(&render_target_name(rtv));
+        }
     }
+
+    Ok(())
 }
 
-pub fn begin_render_pass(command_list: &mut GraphicsCommandList, rtv: &RenderTargetView) {
-    unsafe {
-        let command_context = command_list.command_context.as_ref().unwrap();
-
-        let viewport: D3D11_VIEWPORT = D3D11_VIEWPORT {
-            Height: rtv.height as f32,
-            Width: rtv.width as f32,
-            MinDepth: 0.0,
-            MaxDepth: 1.0,
-            TopLeftX: 0.0,
-            TopLeftY: 0.0,
-        };
+// restricts all following draws to the rect, nested rects are intersected with the current clip rect
+// the rect is in pixels of the render target with x,y as the bottom-left corner, the same as GameSpaceQuadData
+pub fn push_clip_rect(
+    command_list: &mut GraphicsCommandList,
+    rect: ClipRect,
+) -> Result<(), GraphicsError> {
+    if rect.width < 0 || rect.height < 0 {
+        return Err(GraphicsError::InvalidArgument {
+            resource_name: String::from("Clip Rect"),
+            reason: "clip rect size must not be negative",
+        });
+    }
 
-        // set viewport for the output window
-        command_context.RSSetViewports(1, &viewport);
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context,
+            clip_stack,
+            render_target_rect,
+            ..
+        } => {
+            clip_stack.push(rect);
+
+            let command_context = unsafe { command_context.as_ref().unwrap() };
+            set_native_scissor_rect(command_context, clip_stack, render_target_rect);
+        }
 
-        let rtv_mut: *mut ID3D11RenderTargetView =
-            rtv.native_view as *const ID3D11RenderTargetView as u64 as *mut ID3D11RenderTargetView;
+        GraphicsCommandList::Software(software_command_list) => {
+            software_command_list.clip_stack.push(rect);
+        }
 
-        // bind backbuffer as render target
-        let rtvs: [*mut winapi::um::d3d11::ID3D11RenderTargetView; 1] = [rtv_mut];
-        command_context.OMSetRenderTargets(1, rtvs.as_ptr(), std::ptr::null_mut());
+        GraphicsCommandList::Recording(recorder) => {
+            recorder.push_clip_rect(rect);
+        }
     }
+
+    Ok(())
+}
+
+// restores the clip rect that was active before the matching push_clip_rect
+pub fn pop_clip_rect(command_list: &mut GraphicsCommandList) -> Result<(), GraphicsError> {
+    let popped_rect = match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context,
+            clip_stack,
+            render_target_rect,
+            ..
+        } => {
+            let popped_rect = clip_stack.pop();
+
+            let command_context = unsafe { command_context.as_ref().unwrap() };
+            set_native_scissor_rect(command_context, clip_stack, render_target_rect);
+
+            popped_rect
+        }
+
+        GraphicsCommandList::Software(software_command_list) => {
+            software_command_list.clip_stack.pop()
+        }
+
+        GraphicsCommandList::Recording(recorder) => recorder.pop_clip_rect(),
+    };
+
+    popped_rect
+        .map(|_| ())
+        .ok_or_else(|| GraphicsError::InvalidArgument {
+            resource_name: String::from("Clip Rect"),
+            reason: "pop_clip_rect called without a matching push_clip_rect",
+        })
 }
 
 pub fn begin_render_pass_and_clear(
     command_list: &mut GraphicsCommandList,
     clear_color: [f32; 4],
     rtv: &RenderTargetView,
-) {
-    begin_render_pass(command_list, rtv);
-    clear_render_target(command_list, clear_color, rtv);
+) -> Result<(), GraphicsError> {
+    begin_render_pass(command_list, rtv)?;
+    clear_render_target(command_list, clear_color, rtv)
 }
 
-pub fn bind_pso(command_list: &mut GraphicsCommandList, pso: &PipelineStateObject) {
-    unsafe {
-        let command_context = command_list.command_context.as_ref().unwrap();
-
-        // hack around the fact that VSSetShader takes a mutable pointer
-        // the function never modifies the vertex or pixel shader
-        // don't want the interface to have to expose mutable PipelineStateObject references because of it
-        // instead take the poiner value, read the absolute u64 value of the adress and cast that to a mutable pointer
-        // sorry borrow checker :)
-        let vertex_shader_mut: *mut ID3D11VertexShader =
-            (pso.vertex_shader as *const ID3D11VertexShader as u64) as *mut ID3D11VertexShader;
-        let pixel_shader_mut: *mut ID3D11PixelShader =
-            (pso.pixel_shader as *const ID3D11PixelShader as u64) as *mut ID3D11PixelShader;
-        let blend_state_mut: *mut ID3D11BlendState =
-            (pso.blend_state as *const ID3D11BlendState as u64) as *mut ID3D11BlendState;
-
-        // bind the shaders
-        command_context.VSSetShader(vertex_shader_mut, std::ptr::null_mut(), 0);
-        command_context.PSSetShader(pixel_shader_mut, std::ptr::null_mut(), 0);
-
-        // fow now assume all PSO will be using this state
-        command_context.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
-
-        // and set the correct blending states
-        command_context.OMSetBlendState(blend_state_mut, &[0.0; 4], 0xffff_ffff);
-
-        // bind all samplers
-        let sampler_mut: *mut ID3D11SamplerState =
-            pso.static_samplers as *const ID3D11SamplerState as u64 as *mut ID3D11SamplerState;
-
-        let samplers: [*mut winapi::um::d3d11::ID3D11SamplerState; 1] = [sampler_mut];
-
-        command_list
-            .command_context
-            .as_ref()
-            .unwrap()
-            .PSSetSamplers(0, 1, samplers.as_ptr());
+pub fn bind_pso(
+    command_list: &mut GraphicsCommandList,
+    pso: &PipelineStateObject,
+) -> Result<(), GraphicsError> {
+    command_list_stats_mut(command_list).pso_binds += 1;
+
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context, ..
+        } => unsafe {
+            let command_context = command_context.as_ref().unwrap();
+
+            let pso = pso
+                .native
+                .as_ref()
+                .ok_or_else(|| GraphicsError::InvalidArgument {
+                    resource_name: pso.shader_name.clone(),
+                    reason: "software PSO used with a native command list",
+                })?;
+
+            // hack around the fact that VSSetShader takes a mutable pointer
+            // the function never modifies the vertex or pixel shader
+            // don't want the interface to have to expose mutable PipelineStateObject references because of it
+            // instead take the poiner value, read the absolute u64 value of the adress and cast that to a mutable pointer
+            // sorry borrow checker :)
+            let vertex_shader_mut: *mut ID3D11VertexShader =
+                (pso.vertex_shader as *const ID3D11VertexShader as u64) as *mut ID3D11VertexShader;
+            let pixel_shader_mut: *mut ID3D11PixelShader =
+                (pso.pixel_shader as *const ID3D11PixelShader as u64) as *mut ID3D11PixelShader;
+            let blend_state_mut: *mut ID3D11BlendState =
+                (pso.blend_state as *const ID3D11BlendState as u64) as *mut ID3D11BlendState;
+
+            // bind the shaders
+            command_context.VSSetShader(vertex_shader_mut, std::ptr::null_mut(), 0);
+            command_context.PSSetShader(pixel_shader_mut, std::ptr::null_mut(), 0);
+
+            // fow now assume all PSO will be using this state
+            command_context.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+
+            // and set the correct blending states
+            command_context.OMSetBlendState(blend_state_mut, &[0.0; 4], 0xffff_ffff);
+
+            let rasterizer_state_mut: *mut ID3D11RasterizerState =
+                pso.rasterizer_state as *const ID3D11RasterizerState as u64
+                    as *mut ID3D11RasterizerState;
+
+            command_context.RSSetState(rasterizer_state_mut);
+
+            // bind all samplers
+            let sampler_mut: *mut ID3D11SamplerState =
+                pso.static_samplers as *const ID3D11SamplerState as u64 as *mut ID3D11SamplerState;
+
+            let samplers: [*mut winapi::um::d3d11::ID3D11SamplerState; 1] = [sampler_mut];
+
+            command_context.PSSetSamplers(0, 1, samplers.as_ptr());
+        },
+
+        GraphicsCommandList::Software(software_command_list) => {
+            software_command_list.pipeline_state =
+                Some(
+                    pso.software
+                        .ok_or_else(|| GraphicsError::UnsupportedShader {
+                            shader_name: pso.shader_name.clone(),
+                        })?,
+                );
+        }
+
+        GraphicsCommandList::Recording(recorder) => {
+            recorder.bind_pipeline_state(
+                &pso.shader_name,
+                pso.software.map(|x| x.shader),
+                pso.blend_mode,
+                pso.sampler,
+            );
+        }
     }
+
+    Ok(())
 }
 
 pub fn bind_texture(
     command_list: &mut GraphicsCommandList,
     bind_slot: u32,
     srv: &ShaderResourceView,
-) {
-    unsafe {
-        let srv_mut: *mut ID3D11ShaderResourceView =
-            (srv.native_view as *const ID3D11ShaderResourceView as u64)
-                as *mut ID3D11ShaderResourceView;
+) -> Result<(), GraphicsError> {
+    command_list_stats_mut(command_list).texture_binds += 1;
+
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context, ..
+        } => unsafe {
+            let native_view: &ID3D11ShaderResourceView =
+                srv.native_view
+                    .as_ref()
+                    .ok_or_else(|| GraphicsError::InvalidArgument {
+                        resource_name: texture_name(srv),
+                        reason: "software texture used with a native command list",
+                    })?;
+
+            let srv_mut: *mut ID3D11ShaderResourceView =
+                (native_view as *const ID3D11ShaderResourceView as u64)
+                    as *mut ID3D11ShaderResourceView;
+
+            command_context
+                .as_ref()
+                .unwrap()
+                .PSSetShaderResources(bind_slot, 1, &srv_mut);
+        },
 
-        command_list
-            .command_context
-            .as_ref()
-            .unwrap()
-            .PSSetShaderResources(bind_slot, 1, &srv_mut);
+        GraphicsCommandList::Software(software_command_list) => {
+            let software_texture =
+                srv.software_texture
+                    .clone()
+                    .ok_or_else(|| GraphicsError::InvalidArgument {
+                        resource_name: texture_name(srv),
+                        reason: "native texture used with the software rasterizer",
+                    })?;
+
+            software_command_list.bind_texture(bind_slot, software_texture);
+        }
+
+        GraphicsCommandList::Recording(recorder) => {
+            recorder.bind_texture(bind_slot, &texture_name(srv));
+        }
     }
+
+    Ok(())
 }
 
 pub fn bind_constant(
     command_list: &mut GraphicsCommandList,
     bind_slot: u32,
     constant_alloc: &HeapAlloc,
-) {
-    let command_context = unsafe { command_list.command_context.as_ref().unwrap() };
+) -> Result<(), GraphicsError> {
+    command_list_stats_mut(command_list).constant_binds += 1;
 
     let first_constant: u32 = constant_alloc.first_constant_offset;
     let num_constants: u32 = constant_alloc.num_constants;
 
+    // software constants are read directly from the buffer memory
+    // the allocation can't change anymore but the command list outlives the mapping so the data is copied
+    let software_constants = || -> Result<&[u8], GraphicsError> {
+        let start = (first_constant * 16) as usize;
+        let end = ((first_constant + num_constants) * 16) as usize;
+
+        let memory: &[u8] = unsafe { &*constant_alloc.gpu_buffer_src.software_memory.get() };
+
+        // native buffers don't have any software memory
+        memory
+            .get(start..end)
+            .ok_or_else(|| GraphicsError::InvalidArgument {
+                resource_name: String::from("Constant Buffer"),
+                reason: "native constant buffer used with the software rasterizer",
+            })
+    };
+
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context, ..
+        } => unsafe {
+            bind_native_constant(
+                command_context.as_ref().unwrap(),
+                bind_slot,
+                constant_alloc,
+                first_constant,
+                num_constants,
+            );
+        },
+
+        GraphicsCommandList::Software(software_command_list) => {
+            software_command_list.bind_constant(bind_slot, software_constants()?);
+        }
+
+        GraphicsCommandList::Recording(recorder) => {
+            // constants allocated from a native buffer can't be read back
+            let data: &[u8] = if constant_alloc.gpu_buffer_src.is_software() {
+                software_constants()?
+            } else {
+                &[]
+            };
+
+            recorder.bind_constants(bind_slot, first_constant, num_constants, data);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn bind_native_constant(
+    command_context: &ID3D11DeviceContext1,
+    bind_slot: u32,
+    constant_alloc: &HeapAlloc,
+    first_constant: u32,
+    num_constants: u32,
+) {
     let null_buffers: [*mut ID3D11Buffer; 1] = [std::ptr::null_mut()];
     let buffers: [*mut ID3D11Buffer; 1] = [constant_alloc.gpu_buffer_src.native_buffer];
 
@@ -1019,25 +3116,94 @@ pub fn bind_constant(
     }
 }
 
-pub fn draw_vertices(command_list: &mut GraphicsCommandList, vertex_count: u32) {
-    unsafe {
-        let command_context = command_list.command_context.as_ref().unwrap();
-        command_context.Draw(vertex_count, 0);
+// the software rasterizer validates the bound state and fails if it can't draw with it
+pub fn draw_vertices(
+    command_list: &mut GraphicsCommandList,
+    vertex_count: u32,
+) -> Result<(), GraphicsError> {
+    command_list_stats_mut(command_list).draw_calls += 1;
+
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context, ..
+        } => unsafe {
+            let command_context = command_context.as_ref().unwrap();
+            command_context.Draw(vertex_count, 0);
+        },
+
+        GraphicsCommandList::Software(software_command_list) => {
+            software_command_list.draw(vertex_count)?;
+        }
+
+        GraphicsCommandList::Recording(recorder) => {
+            recorder.draw(vertex_count);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn draw_vertices_instanced(
+    command_list: &mut GraphicsCommandList,
+    vertex_count: u32,
+    instance_count: u32,
+) -> Result<(), GraphicsError> {
+    command_list_stats_mut(command_list).draw_calls += 1;
+
+    match command_list {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context, ..
+        } => unsafe {
+            let command_context = command_context.as_ref().unwrap();
+            command_context.DrawInstanced(vertex_count, instance_count, 0, 0);
+        },
+
+        GraphicsCommandList::Software(software_command_list) => {
+            software_command_list.draw_instanced(vertex_count, instance_count)?;
+        }
+
+        GraphicsCommandList::Recording(recorder) => {
+            recorder.draw_instanced(vertex_count, instance_count);
+        }
     }
+
+    Ok(())
 }
 
+#[cfg_attr(not(windows), allow(unused_variables))]
 pub fn execute_command_list(
     graphics_layer: &GraphicsDeviceLayer,
     command_list_in: &GraphicsCommandList,
-) {
-    unsafe {
-        let command_context = command_list_in.command_context.as_ref().unwrap();
+) -> Result<(), GraphicsError> {
+    match command_list_in {
+        #[cfg(windows)]
+        GraphicsCommandList::Native {
+            command_context, ..
+        } => unsafe {
+            execute_native_command_list(graphics_layer, command_context.as_ref().unwrap())
+        },
+
+        // the software rasterizer executes all commands immediately
+        GraphicsCommandList::Software(_) => Ok(()),
+
+        // recorded commands are kept until the recorder is cleared
+        GraphicsCommandList::Recording(_) => Ok(()),
+    }
+}
 
+#[cfg(windows)]
+fn execute_native_command_list(
+    graphics_layer: &GraphicsDeviceLayer,
+    command_context: &ID3D11DeviceContext1,
+) -> Result<(), GraphicsError> {
+    unsafe {
         let mut command_list: *mut ID3D11CommandList = std::ptr::null_mut();
 
         let result = command_context.FinishCommandList(0, &mut command_list);
 
-        assert!(result == winapi::shared::winerror::S_OK);
+        check_hresult(result, "FinishCommandList", "Deferred Context")?;
 
         graphics_layer
             .immediate_context
@@ -1048,10 +3214,188 @@ pub fn execute_command_list(
         // once the command list is executed, we can release it
         command_list.as_ref().unwrap().Release();
     }
+
+    Ok(())
+}
+
+#[cfg_attr(not(windows), allow(unused_variables))]
+pub fn present_swapchain(graphics_layer: &GraphicsDeviceLayer) -> Result<(), GraphicsError> {
+    // headless and software devices don't have a swapchain to present
+    #[cfg(windows)]
+    if let Some(swapchain) = unsafe { graphics_layer.swapchain.as_ref() } {
+        let result = unsafe { swapchain.Present(1, 0) };
+
+        // Present can return success codes other than S_OK, e.g. when the window is occluded
+        if winapi::shared::winerror::FAILED(result) {
+            return Err(GraphicsError::Native {
+                function: "Present",
+                hresult: result,
+                resource_name: String::from("Swapchain"),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// copies the content of a render target into CPU memory
+// for native render targets this needs to be called after execute_command_list and before present_swapchain
+#[cfg_attr(not(windows), allow(unused_variables))]
+pub fn read_back_render_target(
+    graphics_layer: &GraphicsDeviceLayer,
+    rtv: &RenderTargetView,
+) -> Result<CpuTexture, GraphicsError> {
+    let resource_name = render_target_name(rtv);
+
+    #[cfg(windows)]
+    if let Some(native_view) = &rtv.native_view {
+        return read_back_native_render_target(graphics_layer, native_view, resource_name);
+    }
+
+    let software_target = rtv
+        .software_target
+        .as_ref()
+        .ok_or_else(|| GraphicsError::InvalidArgument {
+            resource_name: resource_name.clone(),
+            reason: "render target view has neither a native nor a software target",
+        })?
+        .borrow();
+
+    let mut result = CpuTexture::new(software_target.width, software_target.height);
+    result.name = software_target.name.clone();
+    result.pixels.copy_from_slice(&software_target.pixels);
+
+    Ok(result)
 }
 
-pub fn present_swapchain(graphics_layer: &GraphicsDeviceLayer) {
+#[cfg(windows)]
+fn read_back_native_render_target(
+    graphics_layer: &GraphicsDeviceLayer,
+    native_view: &ID3D11RenderTargetView,
+    resource_name: String,
+) -> Result<CpuTexture, GraphicsError> {
+    let native_device =
+        graphics_layer
+            .device
+            .native
+            .as_ref()
+            .ok_or_else(|| GraphicsError::InvalidArgument {
+                resource_name: resource_name.clone(),
+                reason: "native render targets can't be read back with a software device",
+            })?;
+
     unsafe {
-        graphics_layer.swapchain.as_ref().unwrap().Present(1, 0);
+        let mut resource: *mut ID3D11Resource = std::ptr::null_mut();
+        native_view.GetResource(&mut resource);
+
+        let mut texture: *mut ID3D11Texture2D = std::ptr::null_mut();
+        let hr = resource.as_ref().unwrap().QueryInterface(
+            &ID3D11Texture2D::uuidof(),
+            &mut texture as *mut *mut ID3D11Texture2D as *mut *mut winapi::ctypes::c_void,
+        );
+
+        // GetResource and QueryInterface both add a reference that we need to release again
+        resource.as_ref().unwrap().Release();
+
+        check_hresult(hr, "QueryInterface", &resource_name)?;
+
+        let mut texture_desc: D3D11_TEXTURE2D_DESC = std::mem::zeroed();
+        texture.as_ref().unwrap().GetDesc(&mut texture_desc);
+
+        if texture_desc.Format != DXGI_FORMAT_R8G8B8A8_UNORM
+            && texture_desc.Format != DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+        {
+            texture.as_ref().unwrap().Release();
+
+            return Err(GraphicsError::UnsupportedFormat {
+                resource_name,
+                format: texture_desc.Format,
+            });
+        }
+
+        // render targets can't be mapped, copy into a staging texture that we can read from
+        texture_desc.Usage = D3D11_USAGE_STAGING;
+        texture_desc.BindFlags = 0;
+        texture_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+        texture_desc.MiscFlags = 0;
+
+        let mut staging_texture: *mut ID3D11Texture2D = std::ptr::null_mut();
+        let hr =
+            native_device.CreateTexture2D(&texture_desc, std::ptr::null(), &mut staging_texture);
+
+        if hr != S_OK {
+            texture.as_ref().unwrap().Release();
+        }
+
+        check_hresult(hr, "CreateTexture2D", "Read Back Staging Texture")?;
+
+        set_debug_name(
+            staging_texture.as_ref().unwrap(),
+            "Read Back Staging Texture",
+        );
+
+        let immediate_context = graphics_layer.immediate_context.as_ref().unwrap();
+
+        immediate_context.CopyResource(
+            staging_texture as *mut ID3D11Resource,
+            texture as *mut ID3D11Resource,
+        );
+
+        texture.as_ref().unwrap().Release();
+
+        let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE {
+            pData: std::ptr::null_mut(),
+            RowPitch: 0,
+            DepthPitch: 0,
+        };
+
+        // waits for the GPU to finish the copy
+        let hr = immediate_context.Map(
+            staging_texture as *mut ID3D11Resource,
+            0,
+            D3D11_MAP_READ,
+            0,
+            &mut mapped_resource,
+        );
+
+        if hr != S_OK {
+            assert_leak_check_release(staging_texture.as_ref().unwrap(), 0, None);
+        }
+
+        check_hresult(hr, "Map", "Read Back Staging Texture")?;
+
+        let mut result = CpuTexture::new(texture_desc.Width, texture_desc.Height);
+        let row_size = (texture_desc.Width * 4) as usize;
+
+        for y in 0..(texture_desc.Height as usize) {
+            let src_row = std::slice::from_raw_parts(
+                (mapped_resource.pData as *const u8).add(y * mapped_resource.RowPitch as usize),
+                row_size,
+            );
+
+            result.pixels[(y * row_size)..((y + 1) * row_size)].copy_from_slice(src_row);
+        }
+
+        immediate_context.Unmap(staging_texture as *mut ID3D11Resource, 0);
+
+        leak_check_release(staging_texture.as_ref().unwrap(), 0, None)?;
+
+        Ok(result)
     }
 }
+
+// reads back the render target and saves it as PNG file
+pub fn save_render_target_to_file(
+    graphics_layer: &GraphicsDeviceLayer,
+    rtv: &RenderTargetView,
+    filename: &str,
+) -> Result<(), GraphicsError> {
+    let image = read_back_render_target(graphics_layer, rtv)?;
+
+    png_writer::write_png_file(filename, image.width, image.height, &image.pixels).map_err(
+        |error| GraphicsError::FileWrite {
+            path: filename.to_string(),
+            error: error.kind(),
+        },
+    )
+}
@@ -0,0 +1,172 @@
+use crate::software_rasterizer::SoftwareRasterizerError;
+use dds_parser::{DdsParserError, DxgiFormat};
+#[cfg(windows)]
+use winapi::shared::ntdef::HRESULT;
+#[cfg(windows)]
+use winapi::shared::winerror::S_OK;
+
+// error returned from the graphics_device functions
+// contains the information required to find the resource or file that caused the error
+#[derive(Debug)]
+pub enum GraphicsError {
+    // a file could not be read from disk
+    FileRead {
+        path: String,
+        error: std::io::ErrorKind,
+    },
+
+    // a file could not be written to disk
+    FileWrite {
+        path: String,
+        error: std::io::ErrorKind,
+    },
+
+    // the file was read but doesn't contain a valid dds texture
+    InvalidDds {
+        path: String,
+        error: DdsParserError,
+    },
+
+    // a D3D11 or DXGI function failed
+    Native {
+        function: &'static str,
+        hresult: i32, // HRESULT
+        resource_name: String,
+    },
+
+    // the format is not supported by the software rasterizer or the render target read back
+    UnsupportedFormat {
+        resource_name: String,
+        format: DxgiFormat,
+    },
+
+    // the texture data could not be decoded for the software rasterizer
+    InvalidTextureData {
+        resource_name: String,
+        error: SoftwareRasterizerError,
+    },
+
+    // the software rasterizer doesn't know how to emulate the shader
+    UnsupportedShader {
+        shader_name: String,
+    },
+
+    InvalidArgument {
+        resource_name: String,
+        reason: &'static str,
+    },
+
+    // the handle references a resource that has already been destroyed
+    InvalidHandle,
+
+    // a native object was still referenced after it should have been released for the last time
+    OutstandingReferences {
+        ref_count: u32,
+        expected_ref_count: u32,
+    },
+}
+
+impl std::fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GraphicsError::FileRead { path, error } => {
+                write!(f, "failed to read \"{}\" ({:?})", path, error)?;
+
+                // the compiled shaders are generated from src_data and not every build step creates them
+                if *error == std::io::ErrorKind::NotFound
+                    && (path.ends_with(".vsb") || path.ends_with(".psb"))
+                {
+                    write!(f, ", make sure the shaders have been compiled")?;
+                }
+
+                Ok(())
+            }
+
+            GraphicsError::FileWrite { path, error } => {
+                write!(f, "failed to write \"{}\" ({:?})", path, error)
+            }
+
+            GraphicsError::InvalidDds { path, error } => {
+                write!(f, "\"{}\" is not a supported dds file ({:?})", path, error)
+            }
+
+            GraphicsError::Native {
+                function,
+                hresult,
+                resource_name,
+            } => write!(
+                f,
+                "{} failed for \"{}\" with HRESULT 0x{:08x}",
+                function, resource_name, *hresult as u32
+            ),
+
+            GraphicsError::UnsupportedFormat {
+                resource_name,
+                format,
+            } => write!(
+                f,
+                "\"{}\" uses the unsupported format {}",
+                resource_name, format
+            ),
+
+            GraphicsError::InvalidTextureData {
+                resource_name,
+                error,
+            } => write!(
+                f,
+                "failed to decode the texture data of \"{}\" ({:?})",
+                resource_name, error
+            ),
+
+            GraphicsError::UnsupportedShader { shader_name } => write!(
+                f,
+                "shader \"{}\" is not supported by the software rasterizer",
+                shader_name
+            ),
+
+            GraphicsError::InvalidArgument {
+                resource_name,
+                reason,
+            } => write!(f, "invalid argument for \"{}\": {}", resource_name, reason),
+
+            GraphicsError::InvalidHandle => {
+                write!(f, "the handle references a destroyed resource")
+            }
+
+            GraphicsError::OutstandingReferences {
+                ref_count,
+                expected_ref_count,
+            } => write!(
+                f,
+                "object was not released, still has {} outstanding references, expected {}",
+                ref_count, expected_ref_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GraphicsError {}
+
+#[cfg(windows)]
+pub fn check_hresult(
+    hresult: HRESULT,
+    function: &'static str,
+    resource_name: &str,
+) -> Result<(), GraphicsError> {
+    if hresult == S_OK {
+        return Ok(());
+    }
+
+    Err(GraphicsError::Native {
+        function,
+        hresult,
+        resource_name: resource_name.to_string(),
+    })
+}
+
+pub fn read_file(path: &str) -> Result<Vec<u8>, GraphicsError> {
+    std::fs::read(path).map_err(|error| GraphicsError::FileRead {
+        path: path.to_string(),
+        error: error.kind(),
+    })
+}
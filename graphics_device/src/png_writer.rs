@@ -0,0 +1,159 @@
+// minimal PNG encoder used to save frame captures
+// image data is stored in uncompressed deflate blocks, this keeps the encoder small and doesn't need any external crates
+
+fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+
+    table
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let table = crc32_table();
+
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc = table[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8);
+    }
+
+    crc ^ 0xffff_ffff
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+// zlib stream with "stored" deflate blocks, each block can contain at most 65535 bytes
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() + data.len() / 65535 * 5 + 11);
+
+    // CMF: deflate with 32k window, FLG: no preset dictionary, fastest compression, header is a multiple of 31
+    result.push(0x78);
+    result.push(0x01);
+
+    let mut blocks = data.chunks(65535).peekable();
+
+    // an empty stream still needs a single final block
+    if blocks.peek().is_none() {
+        result.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let is_final_block = blocks.peek().is_none();
+        let length = block.len() as u16;
+
+        result.push(if is_final_block { 1 } else { 0 });
+        result.extend_from_slice(&length.to_le_bytes());
+        result.extend_from_slice(&(!length).to_le_bytes());
+        result.extend_from_slice(block);
+    }
+
+    result.extend_from_slice(&adler32(data).to_be_bytes());
+
+    result
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let crc_start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[crc_start..]);
+
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// encodes RGBA8 pixels (rows from top to bottom) into an RGB PNG
+// alpha is dropped, render targets don't store meaningful alpha values for the final image
+pub fn encode_png_rgb(width: u32, height: u32, rgba_pixels: &[u8]) -> Vec<u8> {
+    assert!(rgba_pixels.len() == (width * height * 4) as usize);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.push(8); // bit depth
+    header.push(2); // color type: RGB
+    header.push(0); // compression method: deflate
+    header.push(0); // filter method: adaptive
+    header.push(0); // interlace method: none
+
+    write_chunk(&mut png, b"IHDR", &header);
+
+    // every row starts with the filter type, we don't use any filtering
+    let mut image_data = Vec::with_capacity(((width * 3 + 1) * height) as usize);
+
+    for row in rgba_pixels.chunks_exact((width * 4) as usize) {
+        image_data.push(0);
+
+        for pixel in row.chunks_exact(4) {
+            image_data.extend_from_slice(&pixel[0..3]);
+        }
+    }
+
+    write_chunk(&mut png, b"IDAT", &zlib_store(&image_data));
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+pub fn write_png_file(
+    filename: &str,
+    width: u32,
+    height: u32,
+    rgba_pixels: &[u8],
+) -> std::io::Result<()> {
+    std::fs::write(filename, encode_png_rgb(width, height, rgba_pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_reference() {
+        // CRC of the IEND chunk type, every PNG file ends with these bytes
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn adler32_matches_reference() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn large_images_are_split_into_multiple_blocks() {
+        let data = vec![7u8; 65535 * 2 + 10];
+
+        let stream = zlib_store(&data);
+
+        // header + 3 block headers + data + adler32
+        assert_eq!(stream.len(), 2 + 3 * 5 + data.len() + 4);
+
+        // only the last block is marked as final
+        assert_eq!(stream[2], 0);
+        assert_eq!(stream[2 + 5 + 65535], 0);
+        assert_eq!(stream[2 + 2 * (5 + 65535)], 1);
+    }
+}
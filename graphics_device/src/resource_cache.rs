@@ -0,0 +1,147 @@
+// reference counted lookup from a resource key (e.g. the file path) to the handle of the loaded resource
+// the cache only tracks handles, the resources themselves are owned by the ResourceRegistry
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceCacheStats {
+    pub hits: u32,
+    pub misses: u32,
+    pub evictions: u32,
+}
+
+struct ResourceCacheEntry<K> {
+    key: K,
+    ref_count: u32,
+}
+
+pub struct ResourceCache<K, H> {
+    handles: HashMap<K, H>,
+    entries: HashMap<H, ResourceCacheEntry<K>>,
+
+    pub stats: ResourceCacheStats,
+}
+
+impl<K: Clone + Eq + Hash, H: Copy + Eq + Hash> ResourceCache<K, H> {
+    pub fn new() -> ResourceCache<K, H> {
+        ResourceCache {
+            handles: HashMap::new(),
+            entries: HashMap::new(),
+            stats: ResourceCacheStats::default(),
+        }
+    }
+
+    // returns the handle and adds a reference if the resource has been loaded already
+    pub fn acquire(&mut self, key: &K) -> Option<H> {
+        let handle = match self.handles.get(key) {
+            Some(x) => *x,
+            None => {
+                self.stats.misses += 1;
+                return None;
+            }
+        };
+
+        self.entries.get_mut(&handle).unwrap().ref_count += 1;
+        self.stats.hits += 1;
+
+        Some(handle)
+    }
+
+    // adds a newly loaded resource with a single reference
+    pub fn insert(&mut self, key: K, handle: H) {
+        self.handles.insert(key.clone(), handle);
+        self.entries
+            .insert(handle, ResourceCacheEntry { key, ref_count: 1 });
+    }
+
+    // removes a reference, returns true if this was the last reference and the resource needs to be destroyed
+    // returns None if the handle is not in the cache
+    pub fn release(&mut self, handle: H) -> Option<bool> {
+        let entry = self.entries.get_mut(&handle)?;
+
+        entry.ref_count -= 1;
+
+        if entry.ref_count > 0 {
+            return Some(false);
+        }
+
+        let entry = self.entries.remove(&handle).unwrap();
+        self.handles.remove(&entry.key);
+        self.stats.evictions += 1;
+
+        Some(true)
+    }
+
+    pub fn ref_count(&self, handle: H) -> u32 {
+        self.entries.get(&handle).map_or(0, |x| x.ref_count)
+    }
+
+    pub fn contains(&self, handle: H) -> bool {
+        self.entries.contains_key(&handle)
+    }
+
+    // the key the resource has been loaded with
+    pub fn key(&self, handle: H) -> Option<&K> {
+        self.entries.get(&handle).map(|x| &x.key)
+    }
+
+    // all cached keys and the handles of the resources
+    pub fn iter(&self) -> impl Iterator<Item = (&K, H)> {
+        self.handles.iter().map(|(key, handle)| (key, *handle))
+    }
+
+    // number of cached resources
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.handles.clear();
+        self.entries.clear();
+    }
+}
+
+impl<K: Clone + Eq + Hash, H: Copy + Eq + Hash> Default for ResourceCache<K, H> {
+    fn default() -> Self {
+        ResourceCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_release_evicts_the_entry() {
+        let mut cache: ResourceCache<String, u32> = ResourceCache::new();
+
+        let key = "white.dds".to_string();
+
+        assert_eq!(cache.acquire(&key), None);
+        cache.insert(key.clone(), 7);
+
+        assert_eq!(cache.acquire(&key), Some(7));
+        assert_eq!(cache.ref_count(7), 2);
+
+        assert_eq!(cache.release(7), Some(false));
+        assert_eq!(cache.release(7), Some(true));
+        assert_eq!(cache.release(7), None);
+
+        // evicted entries need to be loaded again
+        assert_eq!(cache.acquire(&key), None);
+
+        assert_eq!(
+            cache.stats,
+            ResourceCacheStats {
+                hits: 1,
+                misses: 2,
+                evictions: 1,
+            }
+        );
+    }
+}
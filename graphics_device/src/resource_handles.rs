@@ -0,0 +1,176 @@
+// copyable handles to resources that are owned by the ResourceRegistry
+// a handle stores the slot index and the generation of the slot when the resource was added
+// once a resource is destroyed the generation of the slot is increased, this invalidates all existing handles to it
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceId {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextureHandle(pub ResourceId);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PsoHandle(pub ResourceId);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BufferHandle(pub ResourceId);
+
+struct ResourceSlot<T> {
+    generation: u32,
+    resource: Option<T>,
+}
+
+pub struct ResourcePool<T> {
+    slots: Vec<ResourceSlot<T>>,
+
+    // indices of slots that don't contain a resource
+    free_slots: Vec<u32>,
+}
+
+impl<T> ResourcePool<T> {
+    pub fn new() -> ResourcePool<T> {
+        ResourcePool {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, resource: T) -> ResourceId {
+        match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.resource = Some(resource);
+
+                ResourceId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+
+            None => {
+                self.slots.push(ResourceSlot {
+                    generation: 0,
+                    resource: Some(resource),
+                });
+
+                ResourceId {
+                    index: (self.slots.len() - 1) as u32,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    // returns None if the resource has been removed
+    pub fn get(&self, id: ResourceId) -> Option<&T> {
+        match self.slots.get(id.index as usize) {
+            Some(slot) if slot.generation == id.generation => slot.resource.as_ref(),
+            _ => None,
+        }
+    }
+
+    // replaces the resource in place so that existing handles stay valid, returns the previous resource
+    pub fn replace(&mut self, id: ResourceId, resource: T) -> Option<T> {
+        match self.slots.get_mut(id.index as usize) {
+            Some(slot) if slot.generation == id.generation && slot.resource.is_some() => {
+                slot.resource.replace(resource)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn remove(&mut self, id: ResourceId) -> Option<T> {
+        let slot = match self.slots.get_mut(id.index as usize) {
+            Some(slot) if slot.generation == id.generation => slot,
+            _ => return None,
+        };
+
+        let resource = slot.resource.take()?;
+
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(id.index);
+
+        Some(resource)
+    }
+
+    // all stored resources and their ids, in slot order
+    pub fn iter(&self) -> impl Iterator<Item = (ResourceId, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let id = ResourceId {
+                index: index as u32,
+                generation: slot.generation,
+            };
+
+            slot.resource.as_ref().map(|resource| (id, resource))
+        })
+    }
+
+    // number of resources that are currently stored
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // removes all resources, existing handles become invalid
+    pub fn clear(&mut self) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.resource.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_slots.push(index as u32);
+            }
+        }
+    }
+}
+
+impl<T> Default for ResourcePool<T> {
+    fn default() -> Self {
+        ResourcePool::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_resources_invalidate_handles() {
+        let mut pool: ResourcePool<&str> = ResourcePool::new();
+
+        let a = pool.insert("a");
+        assert_eq!(pool.get(a), Some(&"a"));
+
+        assert_eq!(pool.remove(a), Some("a"));
+        assert_eq!(pool.get(a), None);
+        assert_eq!(pool.remove(a), None);
+
+        // the slot is reused, the old handle still doesn't resolve
+        let b = pool.insert("b");
+        assert_eq!(pool.get(b), Some(&"b"));
+        assert_eq!(pool.get(a), None);
+        assert_ne!(a, b);
+
+        let c = pool.insert("c");
+        pool.remove(b);
+
+        let stored: Vec<(ResourceId, &&str)> = pool.iter().collect();
+        assert_eq!(stored, vec![(c, &"c")]);
+    }
+
+    #[test]
+    fn clear_invalidates_all_handles() {
+        let mut pool: ResourcePool<i32> = ResourcePool::new();
+
+        let handles: Vec<ResourceId> = (0..4).map(|x| pool.insert(x)).collect();
+        assert_eq!(pool.len(), 4);
+
+        pool.clear();
+
+        assert!(pool.is_empty());
+        assert!(handles.iter().all(|x| pool.get(*x).is_none()));
+    }
+}
@@ -0,0 +1,298 @@
+// records every resource the device creates together with its debug name, where it has been created and an estimate of its memory usage
+// resources remove their record when they are dropped, records that are left when the device is destroyed belong to leaked resources
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::panic::Location;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackedResourceKind {
+    Buffer,
+    Texture,
+    RenderTarget,
+    ShaderResourceView,
+    RenderTargetView,
+    PipelineState,
+}
+
+pub const TRACKED_RESOURCE_KINDS: [TrackedResourceKind; 6] = [
+    TrackedResourceKind::Buffer,
+    TrackedResourceKind::Texture,
+    TrackedResourceKind::RenderTarget,
+    TrackedResourceKind::ShaderResourceView,
+    TrackedResourceKind::RenderTargetView,
+    TrackedResourceKind::PipelineState,
+];
+
+#[derive(Clone, Debug)]
+pub struct TrackedResourceInfo {
+    pub kind: TrackedResourceKind,
+    pub name: String,
+
+    // the code outside of graphics_device that requested the resource
+    pub creation_site: &'static Location<'static>,
+
+    // views share the memory of their resource and are counted with 0 bytes
+    pub memory_bytes: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceUsage {
+    pub count: usize,
+    pub memory_bytes: usize,
+}
+
+#[derive(Default)]
+struct ResourceTrackerState {
+    next_id: u64,
+
+    // ordered by id, so in the order the resources have been created
+    live_resources: BTreeMap<u64, TrackedResourceInfo>,
+}
+
+// clones share the same records, every resource keeps a clone to remove its record when it's dropped
+#[derive(Clone, Default)]
+pub struct ResourceTracker {
+    state: Rc<RefCell<ResourceTrackerState>>,
+}
+
+impl ResourceTracker {
+    pub fn new() -> ResourceTracker {
+        ResourceTracker::default()
+    }
+
+    // the record stays alive until the returned TrackedResource is dropped
+    pub fn track(
+        &self,
+        kind: TrackedResourceKind,
+        name: &str,
+        creation_site: &'static Location<'static>,
+        memory_bytes: usize,
+    ) -> TrackedResource {
+        let mut state = self.state.borrow_mut();
+
+        let id = state.next_id;
+        state.next_id += 1;
+
+        state.live_resources.insert(
+            id,
+            TrackedResourceInfo {
+                kind,
+                name: name.to_string(),
+                creation_site,
+                memory_bytes,
+            },
+        );
+
+        TrackedResource {
+            tracker: self.clone(),
+            id,
+        }
+    }
+
+    pub fn live_resources(&self) -> Vec<TrackedResourceInfo> {
+        self.state
+            .borrow()
+            .live_resources
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub fn usage(&self, kind: TrackedResourceKind) -> ResourceUsage {
+        let state = self.state.borrow();
+
+        state
+            .live_resources
+            .values()
+            .filter(|x| x.kind == kind)
+            .fold(ResourceUsage::default(), |usage, x| ResourceUsage {
+                count: usage.count + 1,
+                memory_bytes: usage.memory_bytes + x.memory_bytes,
+            })
+    }
+
+    pub fn total_usage(&self) -> ResourceUsage {
+        let state = self.state.borrow();
+
+        ResourceUsage {
+            count: state.live_resources.len(),
+            memory_bytes: state.live_resources.values().map(|x| x.memory_bytes).sum(),
+        }
+    }
+
+    // a table with the number of live resources and their memory for every kind of resource
+    pub fn report(&self) -> String {
+        let mut report = format!("{:<20}{:>8}{:>14}\n", "resource", "count", "memory");
+
+        let mut add_line = |label: &str, usage: ResourceUsage| {
+            report.push_str(&format!(
+                "{:<20}{:>8}{:>11} KB\n",
+                label,
+                usage.count,
+                usage.memory_bytes.div_ceil(1024)
+            ));
+        };
+
+        for kind in TRACKED_RESOURCE_KINDS.iter() {
+            add_line(&format!("{:?}", kind), self.usage(*kind));
+        }
+
+        add_line("Total", self.total_usage());
+
+        report
+    }
+
+    // lists all live resources, None if everything has been released
+    pub fn leak_report(&self) -> Option<String> {
+        let live_resources = self.live_resources();
+
+        if live_resources.is_empty() {
+            return None;
+        }
+
+        let mut report = format!(
+            "{} resources have not been released:\n",
+            live_resources.len()
+        );
+
+        for x in live_resources.iter() {
+            report.push_str(&format!(
+                "    {:?} \"{}\" ({} bytes) created at {}\n",
+                x.kind, x.name, x.memory_bytes, x.creation_site
+            ));
+        }
+
+        Some(report)
+    }
+}
+
+// removes the record of the resource from the tracker when dropped
+pub struct TrackedResource {
+    tracker: ResourceTracker,
+    id: u64,
+}
+
+impl Drop for TrackedResource {
+    fn drop(&mut self) {
+        self.tracker
+            .state
+            .borrow_mut()
+            .live_resources
+            .remove(&self.id);
+    }
+}
+
+// memory of all mip levels of a texture that is stored in blocks of block_size x block_size texels
+// uncompressed formats use a block size of 1
+pub fn texture_memory_bytes(
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+    block_size: u32,
+    bytes_per_block: u32,
+) -> usize {
+    (0..mip_levels.max(1))
+        .map(|mip| {
+            let mip_width = (width >> mip).max(1);
+            let mip_height = (height >> mip).max(1);
+
+            let blocks_x = mip_width.div_ceil(block_size);
+            let blocks_y = mip_height.div_ceil(block_size);
+
+            blocks_x as usize * blocks_y as usize * bytes_per_block as usize
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_removed_when_resources_are_dropped() {
+        let tracker = ResourceTracker::new();
+
+        let texture = tracker.track(
+            TrackedResourceKind::Texture,
+            "Texture",
+            Location::caller(),
+            4096,
+        );
+        let buffer = tracker.track(
+            TrackedResourceKind::Buffer,
+            "Buffer",
+            Location::caller(),
+            256,
+        );
+        let _view = tracker.track(
+            TrackedResourceKind::ShaderResourceView,
+            "Texture SRV",
+            Location::caller(),
+            0,
+        );
+
+        assert_eq!(
+            tracker.usage(TrackedResourceKind::Texture),
+            ResourceUsage {
+                count: 1,
+                memory_bytes: 4096
+            }
+        );
+        assert_eq!(
+            tracker.total_usage(),
+            ResourceUsage {
+                count: 3,
+                memory_bytes: 4352
+            }
+        );
+
+        drop(texture);
+        drop(buffer);
+
+        let live_resources = tracker.live_resources();
+        assert_eq!(live_resources.len(), 1);
+        assert_eq!(live_resources[0].name, "Texture SRV");
+        assert_eq!(live_resources[0].creation_site.file(), file!());
+
+        assert_eq!(tracker.usage(TrackedResourceKind::Texture).count, 0);
+
+        let leak_report = tracker.leak_report().unwrap();
+        assert!(leak_report.starts_with("1 resources have not been released"));
+        assert!(leak_report.contains("ShaderResourceView \"Texture SRV\" (0 bytes)"));
+    }
+
+    #[test]
+    fn nothing_is_reported_after_everything_has_been_released() {
+        let tracker = ResourceTracker::new();
+
+        {
+            let _buffer = tracker.track(
+                TrackedResourceKind::Buffer,
+                "Buffer",
+                Location::caller(),
+                2048,
+            );
+
+            assert!(tracker
+                .report()
+                .contains("Buffer                     1          2 KB"));
+        }
+
+        assert!(tracker.leak_report().is_none());
+        assert!(tracker
+            .report()
+            .contains("Total                      0          0 KB"));
+    }
+
+    #[test]
+    fn texture_memory_includes_all_mip_levels() {
+        assert_eq!(texture_memory_bytes(4, 4, 1, 1, 4), 64);
+        assert_eq!(texture_memory_bytes(4, 4, 3, 1, 4), 64 + 16 + 4);
+
+        // block compressed mips never get smaller than a single block
+        assert_eq!(texture_memory_bytes(8, 8, 4, 4, 8), 32 + 8 + 8 + 8);
+        assert_eq!(texture_memory_bytes(6, 2, 1, 4, 16), 32);
+    }
+}
@@ -0,0 +1,289 @@
+// CPU reference implementations of the full-screen post-process shaders in target_data/shaders
+// the software rasterizer runs these instead of rasterizing a quad, post-process passes always cover the whole target
+
+use crate::software_rasterizer::{CpuTexture, QuadConstants};
+
+// rec. 709 luma weights, the same as in post_process_color.hlsl
+const LUMINANCE_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+// samples beyond 3 sigma contribute less than 0.3% and are skipped
+pub fn blur_radius(sigma: f32) -> i32 {
+    (sigma * 3.0).ceil() as i32
+}
+
+// weights for the offsets 0..=radius, the same weights are used for the negative offsets
+// normalized so that all 2 * radius + 1 weights add up to 1
+pub fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    let radius = blur_radius(sigma);
+
+    let weights: Vec<f32> = (0..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+
+    weights.iter().map(|x| x / total).collect()
+}
+
+fn store_pixel(target: &mut CpuTexture, x: u32, y: u32, color: [f32; 4]) {
+    let mut result = [0u8; 4];
+
+    for (i, channel) in color.iter().enumerate() {
+        result[i] = (channel.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+    }
+
+    let offset = ((y * target.width + x) * 4) as usize;
+    target.pixels[offset..(offset + 4)].copy_from_slice(&result);
+}
+
+// one direction of the separable blur, the texel step is (1 / width, 0) or (0, 1 / height)
+// texels outside of the source are clamped to the edge, the same as the clamp sampler of the pso
+pub fn blur_pass(target: &mut CpuTexture, source: &CpuTexture, texel_step: [f32; 2], sigma: f32) {
+    assert!(
+        target.width == source.width && target.height == source.height,
+        "post-process passes need the source and target to have the same size"
+    );
+
+    let step_x = (texel_step[0] * source.width as f32).round() as i64;
+    let step_y = (texel_step[1] * source.height as f32).round() as i64;
+
+    // a sigma of 0 doesn't blur, the weight of the center texel would be 0 / 0
+    let weights = if sigma > 0.0 {
+        gaussian_weights(sigma)
+    } else {
+        vec![1.0]
+    };
+
+    let max_x = i64::from(source.width) - 1;
+    let max_y = i64::from(source.height) - 1;
+
+    for y in 0..target.height {
+        for x in 0..target.width {
+            let mut result = [0.0f32; 4];
+
+            for (i, weight) in weights.iter().enumerate() {
+                let offsets: &[i64] = if i == 0 {
+                    &[0]
+                } else {
+                    &[-(i as i64), i as i64]
+                };
+
+                for offset in offsets {
+                    let sample_x = (i64::from(x) + offset * step_x).clamp(0, max_x) as u32;
+                    let sample_y = (i64::from(y) + offset * step_y).clamp(0, max_y) as u32;
+
+                    let texel = source.texel_as_float(sample_x, sample_y);
+
+                    for channel in 0..4 {
+                        result[channel] += texel[channel] * weight;
+                    }
+                }
+            }
+
+            store_pixel(target, x, y, result);
+        }
+    }
+}
+
+// the parameters of post_process_color.hlsl, the default values leave the image unchanged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorPassParams {
+    pub tint: [f32; 4],
+    pub desaturation: f32, // 0 keeps the colors, 1 is grayscale
+    pub contrast: f32,     // scales the distance of each channel from 0.5
+    pub brightness: f32,   // added to each channel
+    pub vignette_intensity: f32,
+
+    // distance from the center where the vignette starts and how far it takes to reach full intensity
+    // 1 is the middle of the edges of the target, the corners are at about 1.41
+    pub vignette_radius: f32,
+    pub vignette_softness: f32,
+}
+
+impl Default for ColorPassParams {
+    fn default() -> Self {
+        ColorPassParams {
+            tint: [1.0, 1.0, 1.0, 1.0],
+            desaturation: 0.0,
+            contrast: 1.0,
+            brightness: 0.0,
+            vignette_intensity: 0.0,
+            vignette_radius: 1.0,
+            vignette_softness: 0.5,
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// the color of a single pixel, uv is the center of the pixel
+pub fn color_grade(color: [f32; 4], uv: [f32; 2], params: &ColorPassParams) -> [f32; 4] {
+    let luminance: f32 = (0..3).map(|i| color[i] * LUMINANCE_WEIGHTS[i]).sum();
+
+    let dx = (uv[0] - 0.5) * 2.0;
+    let dy = (uv[1] - 0.5) * 2.0;
+    let vignette = smoothstep(
+        params.vignette_radius,
+        params.vignette_radius + params.vignette_softness,
+        (dx * dx + dy * dy).sqrt(),
+    );
+
+    let darken = 1.0 - vignette * params.vignette_intensity;
+
+    let mut result = [0.0f32; 4];
+
+    for i in 0..3 {
+        let desaturated = color[i] + (luminance - color[i]) * params.desaturation;
+        let graded = (desaturated - 0.5) * params.contrast + 0.5 + params.brightness;
+
+        result[i] = (graded * params.tint[i] * darken).clamp(0.0, 1.0);
+    }
+
+    result[3] = (color[3] * params.tint[3]).clamp(0.0, 1.0);
+
+    result
+}
+
+pub fn color_pass(target: &mut CpuTexture, source: &CpuTexture, params: &ColorPassParams) {
+    assert!(
+        target.width == source.width && target.height == source.height,
+        "post-process passes need the source and target to have the same size"
+    );
+
+    for y in 0..target.height {
+        for x in 0..target.width {
+            let uv = [
+                (x as f32 + 0.5) / target.width as f32,
+                (y as f32 + 0.5) / target.height as f32,
+            ];
+
+            let color = color_grade(source.texel_as_float(x, y), uv, params);
+
+            store_pixel(target, x, y, color);
+        }
+    }
+}
+
+// runs the post-process shader the constants belong to
+pub fn apply_post_process(target: &mut CpuTexture, source: &CpuTexture, constants: &QuadConstants) {
+    match constants {
+        QuadConstants::PostProcessBlur { texel_step, sigma } => {
+            blur_pass(target, source, *texel_step, *sigma);
+        }
+
+        QuadConstants::PostProcessColor { params } => {
+            color_pass(target, source, params);
+        }
+
+        _ => panic!(
+            "{:?} are not the constants of a post-process shader",
+            constants
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::software_rasterizer::clear_texture;
+
+    #[test]
+    fn gaussian_weights_add_up_to_one() {
+        let weights = gaussian_weights(2.0);
+
+        assert_eq!(weights.len(), 7);
+
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+        assert!((total - 1.0).abs() < 0.0001);
+
+        // falls off away from the center
+        for pair in weights.windows(2) {
+            assert!(pair[0] > pair[1]);
+        }
+    }
+
+    #[test]
+    fn blur_spreads_a_pixel_along_one_axis() {
+        let mut source = CpuTexture::new(16, 16);
+        clear_texture(&mut source, [0.0, 0.0, 0.0, 1.0]);
+        store_pixel(&mut source, 8, 8, [1.0, 1.0, 1.0, 1.0]);
+
+        let mut target = CpuTexture::new(16, 16);
+        blur_pass(&mut target, &source, [1.0 / 16.0, 0.0], 1.0);
+
+        let weights = gaussian_weights(1.0);
+        let expected = |weight: f32| (weight * 255.0 + 0.5) as u8;
+
+        assert_eq!(target.pixel(8, 8)[0], expected(weights[0]));
+        assert_eq!(target.pixel(7, 8)[0], expected(weights[1]));
+        assert_eq!(target.pixel(9, 8)[0], expected(weights[1]));
+        assert_eq!(target.pixel(11, 8)[0], expected(weights[3]));
+
+        // horizontal only, the rows above and below are unchanged
+        assert_eq!(target.pixel(8, 7), [0, 0, 0, 255]);
+        assert_eq!(target.pixel(8, 9), [0, 0, 0, 255]);
+
+        // a uniform image stays the same, also at the clamped edges
+        clear_texture(&mut source, [0.2, 0.4, 0.6, 1.0]);
+        blur_pass(&mut target, &source, [0.0, 1.0 / 16.0], 3.0);
+
+        assert_eq!(target.pixel(0, 0), source.pixel(0, 0));
+        assert_eq!(target.pixel(15, 15), source.pixel(15, 15));
+    }
+
+    #[test]
+    fn color_pass_desaturates_grades_and_vignettes() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+
+        // the default parameters leave the color unchanged
+        assert_eq!(
+            color_grade(red, [0.5, 0.5], &ColorPassParams::default()),
+            red
+        );
+
+        let gray = color_grade(
+            red,
+            [0.5, 0.5],
+            &ColorPassParams {
+                desaturation: 1.0,
+                ..ColorPassParams::default()
+            },
+        );
+        for channel in gray.iter().take(3) {
+            assert!((channel - 0.2126).abs() < 0.0001);
+        }
+
+        let graded = color_grade(
+            [0.75, 0.5, 0.25, 1.0],
+            [0.5, 0.5],
+            &ColorPassParams {
+                tint: [1.0, 0.5, 1.0, 1.0],
+                contrast: 2.0,
+                brightness: 0.1,
+                ..ColorPassParams::default()
+            },
+        );
+        assert_eq!(graded, [1.0, 0.3, 0.1, 1.0]);
+
+        // the vignette only darkens the edges
+        let vignette = ColorPassParams {
+            vignette_intensity: 0.5,
+            vignette_radius: 0.5,
+            vignette_softness: 0.5,
+            ..ColorPassParams::default()
+        };
+
+        let mut source = CpuTexture::new(8, 8);
+        clear_texture(&mut source, [1.0, 1.0, 1.0, 1.0]);
+
+        let mut target = CpuTexture::new(8, 8);
+        color_pass(&mut target, &source, &vignette);
+
+        assert_eq!(target.pixel(4, 4), [255, 255, 255, 255]);
+        assert_eq!(target.pixel(0, 0), [128, 128, 128, 255]);
+        assert!(target.pixel(0, 4)[0] > 128 && target.pixel(0, 4)[0] < 255);
+    }
+}
@@ -0,0 +1,1496 @@
+// CPU implementation of the draw model used by the D3D11 backend
+// every draw is a 4 vertex triangle strip quad, the vertex positions are derived from the bound constants
+// this allows us to render frames on machines without a GPU
+
+use crate::command_list_stats::CommandListStats;
+use crate::graphics_error::GraphicsError;
+use crate::software_post_process::{apply_post_process, ColorPassParams};
+use std::convert::TryInto;
+
+// all textures are stored as RGBA8, 4 bytes per pixel, rows ordered from top to bottom
+pub struct CpuTexture {
+    pub name: String, // file the texture was loaded from, used to identify textures in tests
+    pub width: u32,
+    pub height: u32,
+    pub srgb: bool, // texels are stored in sRGB space and will be converted to linear space when sampled
+    pub pixels: Vec<u8>,
+}
+
+// textures are shared between the resource views and the command list that is rendering with them
+pub type SoftwareTexture = std::rc::Rc<std::cell::RefCell<CpuTexture>>;
+
+impl CpuTexture {
+    pub fn new(width: u32, height: u32) -> CpuTexture {
+        CpuTexture {
+            name: String::new(),
+            width,
+            height,
+            srgb: false,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * self.width + x) * 4) as usize;
+        self.pixels[offset..(offset + 4)].try_into().unwrap()
+    }
+
+    pub(crate) fn texel_as_float(&self, x: u32, y: u32) -> [f32; 4] {
+        let texel = self.pixel(x, y);
+
+        let mut result = [0.0; 4];
+
+        for (i, channel) in texel.iter().enumerate() {
+            result[i] = f32::from(*channel) / 255.0;
+        }
+
+        // alpha is always stored in linear space
+        if self.srgb {
+            for channel in result.iter_mut().take(3) {
+                *channel = srgb_to_linear(*channel);
+            }
+        }
+
+        result
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CpuTextureFormat {
+    Rgba8,
+    Bc1,
+    Bc2,
+    Bc3,
+}
+
+#[derive(Debug)]
+pub enum SoftwareRasterizerError {
+    NotEnoughData,
+    EmptyImage,
+    ConstantsTooSmall,
+}
+
+fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..(offset + 2)].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..(offset + 4)].try_into().unwrap())
+}
+
+fn read_f32(data: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(data[offset..(offset + 4)].try_into().unwrap())
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(data[offset..(offset + 4)].try_into().unwrap())
+}
+
+fn expand_rgb565(color: u16) -> [u32; 3] {
+    let r = u32::from((color >> 11) & 0x1f);
+    let g = u32::from((color >> 5) & 0x3f);
+    let b = u32::from(color & 0x1f);
+
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+// decodes the color part of a BC1, BC2 or BC3 block into 16 RGBA values
+// BC2 and BC3 always use the 4 color mode, BC1 switches to 3 colors + transparent black based on the endpoint order
+fn decode_color_block(block: &[u8], allow_transparent: bool) -> [[u8; 4]; 16] {
+    let color0 = read_u16(block, 0);
+    let color1 = read_u16(block, 2);
+    let indices = read_u32(block, 4);
+
+    let c0 = expand_rgb565(color0);
+    let c1 = expand_rgb565(color1);
+
+    let mut palette = [[0u8; 4]; 4];
+
+    for i in 0..3 {
+        palette[0][i] = c0[i] as u8;
+        palette[1][i] = c1[i] as u8;
+
+        if color0 > color1 || !allow_transparent {
+            palette[2][i] = ((2 * c0[i] + c1[i]) / 3) as u8;
+            palette[3][i] = ((c0[i] + 2 * c1[i]) / 3) as u8;
+        } else {
+            palette[2][i] = ((c0[i] + c1[i]) / 2) as u8;
+            palette[3][i] = 0;
+        }
+    }
+
+    palette[0][3] = 255;
+    palette[1][3] = 255;
+    palette[2][3] = 255;
+    palette[3][3] = if color0 > color1 || !allow_transparent {
+        255
+    } else {
+        0
+    };
+
+    let mut result = [[0u8; 4]; 16];
+
+    for (i, texel) in result.iter_mut().enumerate() {
+        *texel = palette[((indices >> (2 * i)) & 0x3) as usize];
+    }
+
+    result
+}
+
+fn decode_bc3_alpha_block(block: &[u8]) -> [u8; 16] {
+    let alpha0 = u32::from(block[0]);
+    let alpha1 = u32::from(block[1]);
+
+    let mut palette = [0u8; 8];
+    palette[0] = alpha0 as u8;
+    palette[1] = alpha1 as u8;
+
+    if alpha0 > alpha1 {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i as u32) * alpha0 + i as u32 * alpha1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i as u32) * alpha0 + i as u32 * alpha1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    // 16 indices with 3 bits each are stored in the following 6 bytes
+    let mut indices: u64 = 0;
+    for i in 0..6 {
+        indices |= u64::from(block[2 + i]) << (8 * i);
+    }
+
+    let mut result = [0u8; 16];
+
+    for (i, alpha) in result.iter_mut().enumerate() {
+        *alpha = palette[((indices >> (3 * i)) & 0x7) as usize];
+    }
+
+    result
+}
+
+// converts the first mip level of a texture into RGBA8
+// row_pitch is the number of bytes between rows of pixels (or rows of blocks for compressed formats)
+pub fn decode_texture(
+    format: CpuTextureFormat,
+    srgb: bool,
+    width: u32,
+    height: u32,
+    row_pitch: u32,
+    data: &[u8],
+) -> Result<CpuTexture, SoftwareRasterizerError> {
+    if width == 0 || height == 0 {
+        return Err(SoftwareRasterizerError::EmptyImage);
+    }
+
+    let mut texture = CpuTexture::new(width, height);
+    texture.srgb = srgb;
+
+    if format == CpuTextureFormat::Rgba8 {
+        if data.len() < (row_pitch * (height - 1) + width * 4) as usize {
+            return Err(SoftwareRasterizerError::NotEnoughData);
+        }
+
+        for y in 0..height {
+            let src_offset = (y * row_pitch) as usize;
+            let dst_offset = (y * width * 4) as usize;
+            let row_size = (width * 4) as usize;
+
+            texture.pixels[dst_offset..(dst_offset + row_size)]
+                .copy_from_slice(&data[src_offset..(src_offset + row_size)]);
+        }
+
+        return Ok(texture);
+    }
+
+    let block_size: u32 = if format == CpuTextureFormat::Bc1 { 8 } else { 16 };
+
+    // like D3D the size doesn't need to be a multiple of 4, the last blocks are only partially used
+    let blocks_x = width.div_ceil(4);
+    let blocks_y = height.div_ceil(4);
+
+    if data.len() < (row_pitch * (blocks_y - 1) + blocks_x * block_size) as usize {
+        return Err(SoftwareRasterizerError::NotEnoughData);
+    }
+
+    for block_y in 0..blocks_y {
+        for block_x in 0..blocks_x {
+            let block_offset = (block_y * row_pitch + block_x * block_size) as usize;
+            let block = &data[block_offset..(block_offset + block_size as usize)];
+
+            let texels = match format {
+                CpuTextureFormat::Bc1 => decode_color_block(block, true),
+                CpuTextureFormat::Bc2 => {
+                    // 4 bits of explicit alpha per texel, followed by a BC1 color block
+                    let mut texels = decode_color_block(&block[8..], false);
+                    for (i, texel) in texels.iter_mut().enumerate() {
+                        let alpha = (block[i / 2] >> (4 * (i % 2))) & 0xf;
+                        texel[3] = alpha * 17;
+                    }
+                    texels
+                }
+                CpuTextureFormat::Bc3 => {
+                    let mut texels = decode_color_block(&block[8..], false);
+                    let alpha = decode_bc3_alpha_block(block);
+                    for (i, texel) in texels.iter_mut().enumerate() {
+                        texel[3] = alpha[i];
+                    }
+                    texels
+                }
+                CpuTextureFormat::Rgba8 => unreachable!(),
+            };
+
+            for (i, texel) in texels.iter().enumerate() {
+                let x = block_x * 4 + (i as u32 % 4);
+                let y = block_y * 4 + (i as u32 / 4);
+
+                if x >= width || y >= height {
+                    continue;
+                }
+
+                let offset = ((y * width + x) * 4) as usize;
+
+                texture.pixels[offset..(offset + 4)].copy_from_slice(texel);
+            }
+        }
+    }
+
+    Ok(texture)
+}
+
+// the vertex shaders we know how to emulate
+// matched against the shader names that are passed to create_pso
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuadShader {
+    GameSpaceQuad,      // target_data/shaders/game_space_quad
+    GameSpaceQuadBatch, // target_data/shaders/game_space_quad_batch, one GameSpaceSpriteData per instance
+    ScreenSpaceQuad,    // target_data/shaders/screen_space_quad
+
+    // full-screen post-process shaders, executed by the CPU reference implementations in software_post_process
+    PostProcessBlur,  // target_data/shaders/post_process_blur
+    PostProcessColor, // target_data/shaders/post_process_color
+}
+
+pub fn find_quad_shader(shader_name: &str) -> Option<QuadShader> {
+    if shader_name.ends_with("game_space_quad_batch") {
+        return Some(QuadShader::GameSpaceQuadBatch);
+    }
+
+    if shader_name.ends_with("game_space_quad") {
+        return Some(QuadShader::GameSpaceQuad);
+    }
+
+    if shader_name.ends_with("screen_space_quad") {
+        return Some(QuadShader::ScreenSpaceQuad);
+    }
+
+    if shader_name.ends_with("post_process_blur") {
+        return Some(QuadShader::PostProcessBlur);
+    }
+
+    if shader_name.ends_with("post_process_color") {
+        return Some(QuadShader::PostProcessColor);
+    }
+
+    None
+}
+
+// how the pixel shader output is combined with the render target
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Opaque,        // src
+    Alpha,         // src * src_alpha + dst * (1 - src_alpha), for textures with straight alpha
+    Premultiplied, // src + dst * (1 - src_alpha), the color is already multiplied with alpha
+    Additive,      // src * src_alpha + dst
+    Multiply,      // src * dst
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SamplerFilter {
+    Point,
+    Linear,
+}
+
+// how texture coordinates outside of [0, 1] are resolved
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureAddressMode {
+    Clamp,
+    Wrap,
+    Mirror,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerDesc {
+    pub filter: SamplerFilter,
+    pub address_u: TextureAddressMode,
+    pub address_v: TextureAddressMode,
+}
+
+// linear filtering with clamp addressing
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        SamplerDesc {
+            filter: SamplerFilter::Linear,
+            address_u: TextureAddressMode::Clamp,
+            address_v: TextureAddressMode::Clamp,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SoftwarePipelineState {
+    pub shader: QuadShader,
+    pub blend_mode: BlendMode,
+    pub sampler: SamplerDesc,
+}
+
+// a rectangle in pixels of the render target, x,y is the bottom-left corner with y going up
+// this matches the coordinates of GameSpaceQuadData
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClipRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl ClipRect {
+    // rects that don't overlap result in an empty rect, nothing is drawn inside of it
+    pub fn intersect(&self, other: &ClipRect) -> ClipRect {
+        let left = self.x.max(other.x);
+        let bottom = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let top = (self.y + self.height).min(other.y + other.height);
+
+        ClipRect {
+            x: left,
+            y: bottom,
+            width: (right - left).max(0),
+            height: (top - bottom).max(0),
+        }
+    }
+
+    // left, top, right, bottom in pixels with y going down, this is the layout of a D3D11_RECT
+    pub fn scissor_rect(&self, target_height: i32) -> [i32; 4] {
+        [
+            self.x,
+            target_height - (self.y + self.height),
+            self.x + self.width,
+            target_height - self.y,
+        ]
+    }
+}
+
+// nested clip rects, every pushed rect is intersected with the rect below it
+// so content can never draw outside of any of its parents
+#[derive(Clone, Debug, Default)]
+pub struct ClipStack {
+    rects: Vec<ClipRect>,
+}
+
+impl ClipStack {
+    pub fn new() -> ClipStack {
+        ClipStack { rects: Vec::new() }
+    }
+
+    pub fn push(&mut self, rect: ClipRect) {
+        let clipped = match self.rects.last() {
+            Some(parent) => parent.intersect(&rect),
+            None => rect,
+        };
+
+        self.rects.push(clipped);
+    }
+
+    // None if there is no rect to pop
+    pub fn pop(&mut self) -> Option<ClipRect> {
+        self.rects.pop()
+    }
+
+    // None when nothing is clipped
+    pub fn current(&self) -> Option<ClipRect> {
+        self.rects.last().copied()
+    }
+}
+
+pub struct SoftwareCommandList {
+    pub render_target: Option<SoftwareTexture>,
+    pub pipeline_state: Option<SoftwarePipelineState>,
+    pub textures: Vec<Option<SoftwareTexture>>,
+    pub constants: Vec<Vec<u8>>,
+    pub clip_stack: ClipStack,
+    pub stats: CommandListStats,
+}
+
+impl SoftwareCommandList {
+    pub fn new() -> SoftwareCommandList {
+        SoftwareCommandList {
+            render_target: None,
+            pipeline_state: None,
+            textures: Vec::new(),
+            constants: Vec::new(),
+            clip_stack: ClipStack::new(),
+            stats: CommandListStats::default(),
+        }
+    }
+
+    pub fn bind_texture(&mut self, bind_slot: u32, texture: SoftwareTexture) {
+        let slot = bind_slot as usize;
+
+        if self.textures.len() <= slot {
+            self.textures.resize(slot + 1, None);
+        }
+
+        self.textures[slot] = Some(texture);
+    }
+
+    pub fn bind_constant(&mut self, bind_slot: u32, data: &[u8]) {
+        let slot = bind_slot as usize;
+
+        if self.constants.len() <= slot {
+            self.constants.resize(slot + 1, Vec::new());
+        }
+
+        self.constants[slot] = data.to_vec();
+    }
+
+    pub fn draw(&mut self, vertex_count: u32) -> Result<(), GraphicsError> {
+        self.draw_instanced(vertex_count, 1)
+    }
+
+    // fails if the bound state can't be drawn, nothing is drawn in that case
+    pub fn draw_instanced(
+        &mut self,
+        vertex_count: u32,
+        instance_count: u32,
+    ) -> Result<(), GraphicsError> {
+        let invalid_draw = |reason| GraphicsError::InvalidArgument {
+            resource_name: String::from("Software Command List"),
+            reason,
+        };
+
+        if vertex_count != 4 {
+            return Err(invalid_draw(
+                "the software rasterizer only supports drawing 4 vertex quads",
+            ));
+        }
+
+        let pipeline_state = self
+            .pipeline_state
+            .ok_or_else(|| invalid_draw("draw called without a bound pipeline state"))?;
+
+        let render_target = self
+            .render_target
+            .as_ref()
+            .ok_or_else(|| invalid_draw("draw called outside of a render pass"))?;
+
+        let texture = self
+            .textures
+            .first()
+            .and_then(|x| x.as_ref())
+            .ok_or_else(|| invalid_draw("draw called without a texture in slot 0"))?;
+
+        let constants = self
+            .constants
+            .first()
+            .ok_or_else(|| invalid_draw("draw called without constants in slot 0"))?;
+
+        // sampling from the render target we are rendering to is not supported by D3D11 either
+        if std::rc::Rc::ptr_eq(render_target, texture) {
+            return Err(GraphicsError::InvalidArgument {
+                resource_name: texture.borrow().name.clone(),
+                reason: "texture is bound as render target and shader resource at the same time",
+            });
+        }
+
+        let mut target = render_target.borrow_mut();
+        let texture = texture.borrow();
+
+        // post-process passes are not rasterized, the whole target is computed on the CPU
+        if let QuadShader::PostProcessBlur | QuadShader::PostProcessColor = pipeline_state.shader {
+            let constants = decode_quad_constants(pipeline_state.shader, constants)
+                .ok_or_else(|| invalid_draw("bound constants are too small for the shader"))?;
+
+            apply_post_process(&mut target, &texture, &constants);
+            return Ok(());
+        }
+
+        let instances =
+            decode_quad_constants_array(pipeline_state.shader, constants, instance_count);
+
+        if instances.len() != instance_count as usize {
+            return Err(invalid_draw(
+                "bound constants are too small for the number of instances",
+            ));
+        }
+
+        let scissor = match self.clip_stack.current() {
+            Some(clip_rect) => clip_rect.scissor_rect(target.height as i32),
+            None => full_scissor_rect(&target),
+        };
+
+        for instance in instances.iter() {
+            draw_decoded_quad(&mut target, &pipeline_state, &texture, instance, scissor);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SoftwareCommandList {
+    fn default() -> Self {
+        SoftwareCommandList::new()
+    }
+}
+
+pub fn clear_texture(target: &mut CpuTexture, color: [f32; 4]) {
+    let value = float4_to_unorm(color);
+
+    for pixel in target.pixels.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&value);
+    }
+}
+
+fn float4_to_unorm(color: [f32; 4]) -> [u8; 4] {
+    let mut result = [0u8; 4];
+
+    for (i, channel) in color.iter().enumerate() {
+        result[i] = (channel.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+    }
+
+    result
+}
+
+// constant buffer contents of the shaders in target_data/shaders, decoded from the bytes of a constant allocation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuadConstants {
+    GameSpaceQuad {
+        color: [f32; 4],
+        size_pixels: [i32; 2],
+        position_bottom_left: [i32; 2],
+    },
+
+    // a sprite in game space, rotated around the pivot
+    GameSpaceSprite {
+        color: [f32; 4],
+        uv_rect: [f32; 4], // left, top, right, bottom
+        position: [f32; 2],
+        size: [f32; 2],
+        pivot: [f32; 2], // relative to the size, 0,0 is bottom-left and 1,1 is top-right
+        rotation: [f32; 2], // cos and sin of the counter-clockwise rotation
+    },
+
+    ScreenSpaceQuad {
+        color: [f32; 4],
+        scale: [f32; 2],
+        position: [f32; 2],
+        uv_rect: [f32; 4], // left, top, right, bottom
+    },
+
+    // one direction of the separable gaussian blur
+    PostProcessBlur {
+        texel_step: [f32; 2], // the distance between two samples in texture coordinates
+        sigma: f32,           // in texels
+    },
+
+    PostProcessColor {
+        params: ColorPassParams,
+    },
+}
+
+impl QuadConstants {
+    pub fn color(&self) -> [f32; 4] {
+        match self {
+            QuadConstants::GameSpaceQuad { color, .. } => *color,
+            QuadConstants::GameSpaceSprite { color, .. } => *color,
+            QuadConstants::ScreenSpaceQuad { color, .. } => *color,
+            QuadConstants::PostProcessBlur { .. } => [1.0, 1.0, 1.0, 1.0],
+            QuadConstants::PostProcessColor { params } => params.tint,
+        }
+    }
+}
+
+// returns None if there are not enough bytes for the constants of the shader
+pub fn decode_quad_constants(shader: QuadShader, constants: &[u8]) -> Option<QuadConstants> {
+    let required_size = match shader {
+        QuadShader::GameSpaceQuad => 32,
+        QuadShader::GameSpaceQuadBatch => QUAD_BATCH_STRIDE,
+        QuadShader::ScreenSpaceQuad => 48,
+        QuadShader::PostProcessBlur => 12,
+        QuadShader::PostProcessColor => 40,
+    };
+
+    if constants.len() < required_size {
+        return None;
+    }
+
+    let read_float4 = |offset: usize| {
+        [
+            read_f32(constants, offset),
+            read_f32(constants, offset + 4),
+            read_f32(constants, offset + 8),
+            read_f32(constants, offset + 12),
+        ]
+    };
+
+    match shader {
+        // GameSpaceQuadData { float4 color; int2 size_pixels; int2 position_bottom_left; }
+        QuadShader::GameSpaceQuad => Some(QuadConstants::GameSpaceQuad {
+            color: read_float4(0),
+            size_pixels: [read_i32(constants, 16), read_i32(constants, 20)],
+            position_bottom_left: [read_i32(constants, 24), read_i32(constants, 28)],
+        }),
+
+        // GameSpaceSpriteData { float4 color; float4 uv_rect; float2 position; float2 size; float2 pivot; float2 rotation; }
+        // for batches this decodes the first instance
+        QuadShader::GameSpaceQuadBatch => Some(QuadConstants::GameSpaceSprite {
+            color: read_float4(0),
+            uv_rect: read_float4(16),
+            position: [read_f32(constants, 32), read_f32(constants, 36)],
+            size: [read_f32(constants, 40), read_f32(constants, 44)],
+            pivot: [read_f32(constants, 48), read_f32(constants, 52)],
+            rotation: [read_f32(constants, 56), read_f32(constants, 60)],
+        }),
+
+        // ScreenSpaceQuadData { float4 color; float2 scale; float2 position; float4 uv_rect; }
+        QuadShader::ScreenSpaceQuad => Some(QuadConstants::ScreenSpaceQuad {
+            color: read_float4(0),
+            scale: [read_f32(constants, 16), read_f32(constants, 20)],
+            position: [read_f32(constants, 24), read_f32(constants, 28)],
+            uv_rect: read_float4(32),
+        }),
+
+        // PostProcessBlurData { float2 texel_step; float sigma; }
+        QuadShader::PostProcessBlur => Some(QuadConstants::PostProcessBlur {
+            texel_step: [read_f32(constants, 0), read_f32(constants, 4)],
+            sigma: read_f32(constants, 8),
+        }),
+
+        // PostProcessColorData { float4 tint; float desaturation; float contrast; float brightness;
+        //                        float vignette_intensity; float vignette_radius; float vignette_softness; }
+        QuadShader::PostProcessColor => Some(QuadConstants::PostProcessColor {
+            params: ColorPassParams {
+                tint: read_float4(0),
+                desaturation: read_f32(constants, 16),
+                contrast: read_f32(constants, 20),
+                brightness: read_f32(constants, 24),
+                vignette_intensity: read_f32(constants, 28),
+                vignette_radius: read_f32(constants, 32),
+                vignette_softness: read_f32(constants, 36),
+            },
+        }),
+    }
+}
+
+// size of a single GameSpaceSpriteData entry in game_space_quad_batch
+pub const QUAD_BATCH_STRIDE: usize = 64;
+
+// decodes the constants for every instance of a draw
+// shaders that are not batched use the same constants for all instances
+pub fn decode_quad_constants_array(
+    shader: QuadShader,
+    constants: &[u8],
+    instance_count: u32,
+) -> Vec<QuadConstants> {
+    match shader {
+        QuadShader::GameSpaceQuadBatch => constants
+            .chunks_exact(QUAD_BATCH_STRIDE)
+            .take(instance_count as usize)
+            .filter_map(|x| decode_quad_constants(shader, x))
+            .collect(),
+
+        _ => match decode_quad_constants(shader, constants) {
+            Some(x) => vec![x; instance_count as usize],
+            None => Vec::new(),
+        },
+    }
+}
+
+#[derive(Clone, Copy)]
+struct QuadVertex {
+    position_clip: [f32; 2],
+    uv: [f32; 2],
+}
+
+// returns the vertices in triangle strip order
+// top-left, top-right, bottom-left, bottom-right
+// these match VS_main in the shaders
+fn quad_vertices(constants: &QuadConstants) -> [QuadVertex; 4] {
+    let mut uvs = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
+
+    let positions: [[f32; 2]; 4] = match *constants {
+        QuadConstants::GameSpaceQuad {
+            size_pixels,
+            position_bottom_left,
+            ..
+        } => {
+            let [size_x, size_y] = size_pixels;
+            let [left, bottom] = position_bottom_left;
+
+            let to_clip = |x: i32, y: i32| game_space_to_clip(x as f32, y as f32);
+
+            [
+                to_clip(left, bottom + size_y),
+                to_clip(left + size_x, bottom + size_y),
+                to_clip(left, bottom),
+                to_clip(left + size_x, bottom),
+            ]
+        }
+
+        QuadConstants::GameSpaceSprite {
+            uv_rect,
+            position,
+            size,
+            pivot,
+            rotation,
+            ..
+        } => {
+            let [left, top, right, bottom] = uv_rect;
+            uvs = [[left, top], [right, top], [left, bottom], [right, bottom]];
+
+            let [cos, sin] = rotation;
+
+            // corners relative to the sprite, 0,0 is bottom-left
+            let corners = [[0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]];
+
+            let mut positions = [[0.0; 2]; 4];
+            for (i, corner) in corners.iter().enumerate() {
+                let x = (corner[0] - pivot[0]) * size[0];
+                let y = (corner[1] - pivot[1]) * size[1];
+
+                positions[i] = game_space_to_clip(
+                    position[0] + x * cos - y * sin,
+                    position[1] + x * sin + y * cos,
+                );
+            }
+            positions
+        }
+
+        QuadConstants::ScreenSpaceQuad {
+            scale,
+            position,
+            uv_rect,
+            ..
+        } => {
+            let [left, top, right, bottom] = uv_rect;
+            uvs = [[left, top], [right, top], [left, bottom], [right, bottom]];
+
+            let corners = [[-1.0, 1.0], [1.0, 1.0], [-1.0, -1.0], [1.0, -1.0]];
+
+            let mut positions = [[0.0; 2]; 4];
+            for (i, corner) in corners.iter().enumerate() {
+                positions[i] = [
+                    corner[0] * scale[0] + position[0],
+                    corner[1] * scale[1] + position[1],
+                ];
+            }
+            positions
+        }
+
+        // always the whole target
+        QuadConstants::PostProcessBlur { .. } | QuadConstants::PostProcessColor { .. } => {
+            [[-1.0, 1.0], [1.0, 1.0], [-1.0, -1.0], [1.0, -1.0]]
+        }
+    };
+
+    let mut vertices = [QuadVertex {
+        position_clip: [0.0; 2],
+        uv: [0.0; 2],
+    }; 4];
+
+    for i in 0..4 {
+        vertices[i] = QuadVertex {
+            position_clip: positions[i],
+            uv: uvs[i],
+        };
+    }
+
+    vertices
+}
+
+// game space is 540x960 pixels with y going up, matches TransformWorldToScreen in the shaders
+fn game_space_to_clip(x: f32, y: f32) -> [f32; 2] {
+    [(x / 540.0) * 2.0 - 1.0, (y / 960.0) * 2.0 - 1.0]
+}
+
+// maps a texel coordinate outside of the texture back into it
+fn resolve_texel_address(x: i64, size: u32, address_mode: TextureAddressMode) -> u32 {
+    let size = i64::from(size);
+
+    let x = match address_mode {
+        TextureAddressMode::Clamp => x.max(0).min(size - 1),
+        TextureAddressMode::Wrap => x.rem_euclid(size),
+        TextureAddressMode::Mirror => {
+            let x = x.rem_euclid(size * 2);
+
+            if x >= size {
+                size * 2 - 1 - x
+            } else {
+                x
+            }
+        }
+    };
+
+    x as u32
+}
+
+// matches the D3D11_FILTER_MIN_MAG_MIP_POINT and D3D11_FILTER_MIN_MAG_MIP_LINEAR samplers created by create_pso
+// only the first mip level is available, the game renders all textures at their native resolution
+fn sample(texture: &CpuTexture, uv: [f32; 2], sampler: &SamplerDesc) -> [f32; 4] {
+    let address_x = |v: f32| resolve_texel_address(v as i64, texture.width, sampler.address_u);
+    let address_y = |v: f32| resolve_texel_address(v as i64, texture.height, sampler.address_v);
+
+    if sampler.filter == SamplerFilter::Point {
+        let x = (uv[0] * texture.width as f32).floor();
+        let y = (uv[1] * texture.height as f32).floor();
+
+        return texture.texel_as_float(address_x(x), address_y(y));
+    }
+
+    let x = uv[0] * texture.width as f32 - 0.5;
+    let y = uv[1] * texture.height as f32 - 0.5;
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let t00 = texture.texel_as_float(address_x(x0), address_y(y0));
+    let t10 = texture.texel_as_float(address_x(x0 + 1.0), address_y(y0));
+    let t01 = texture.texel_as_float(address_x(x0), address_y(y0 + 1.0));
+    let t11 = texture.texel_as_float(address_x(x0 + 1.0), address_y(y0 + 1.0));
+
+    let mut result = [0.0; 4];
+
+    for i in 0..4 {
+        let top = t00[i] + (t10[i] - t00[i]) * fx;
+        let bottom = t01[i] + (t11[i] - t01[i]) * fx;
+        result[i] = top + (bottom - top) * fy;
+    }
+
+    result
+}
+
+// matches the blend states created by create_pso
+fn blend(blend_mode: BlendMode, src: [f32; 4], dst: [u8; 4]) -> [f32; 4] {
+    let dst: [f32; 4] = dst.map(|x| f32::from(x) / 255.0);
+
+    let mut result = [0.0f32; 4];
+
+    for i in 0..3 {
+        result[i] = match blend_mode {
+            BlendMode::Opaque => src[i],
+            BlendMode::Alpha => src[i] * src[3] + dst[i] * (1.0 - src[3]),
+            BlendMode::Premultiplied => src[i] + dst[i] * (1.0 - src[3]),
+            BlendMode::Additive => src[i] * src[3] + dst[i],
+            BlendMode::Multiply => src[i] * dst[i],
+        };
+    }
+
+    result[3] = match blend_mode {
+        BlendMode::Opaque => src[3],
+        BlendMode::Alpha => src[3] * (1.0 - dst[3]) + dst[3],
+        BlendMode::Premultiplied => src[3] + dst[3] * (1.0 - src[3]),
+        BlendMode::Additive | BlendMode::Multiply => dst[3],
+    };
+
+    result
+}
+
+// positions are snapped to 8 bits of sub-pixel precision like the D3D11 rasterizer does
+// this makes the edge functions exact, shared edges between triangles will never have gaps or double coverage
+const SUBPIXEL_PRECISION: f32 = 256.0;
+
+fn edge_function(a: [i64; 2], b: [i64; 2], p: [i64; 2]) -> i64 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+// D3D top-left rule, pixels that are exactly on an edge are only drawn for top and left edges
+// with clockwise winding in pixel space (y down) a top edge goes to the right, a left edge goes up
+fn is_top_left_edge(a: [i64; 2], b: [i64; 2]) -> bool {
+    let is_top = a[1] == b[1] && b[0] > a[0];
+    let is_left = b[1] < a[1];
+
+    is_top || is_left
+}
+
+fn full_scissor_rect(target: &CpuTexture) -> [i32; 4] {
+    [0, 0, target.width as i32, target.height as i32]
+}
+
+// scissor is left, top, right, bottom in pixels, pixels outside of it are never written
+fn rasterize_triangle(
+    target: &mut CpuTexture,
+    pipeline_state: &SoftwarePipelineState,
+    texture: &CpuTexture,
+    color: [f32; 4],
+    vertices: [QuadVertex; 3],
+    scissor: [i32; 4],
+) {
+    // viewport transform, the viewport always covers the whole render target
+    let width = target.width as f32;
+    let height = target.height as f32;
+
+    let mut positions = [[0i64; 2]; 3];
+    for (i, vertex) in vertices.iter().enumerate() {
+        let x = (vertex.position_clip[0] + 1.0) * 0.5 * width;
+        let y = (1.0 - vertex.position_clip[1]) * 0.5 * height;
+
+        positions[i] = [
+            (x * SUBPIXEL_PRECISION).round() as i64,
+            (y * SUBPIXEL_PRECISION).round() as i64,
+        ];
+    }
+
+    let area = edge_function(positions[0], positions[1], positions[2]);
+
+    // the default rasterizer state culls back faces
+    // front faces are clockwise on screen, this is a positive area in pixel space
+    if area <= 0 {
+        return;
+    }
+
+    let subpixels = SUBPIXEL_PRECISION as i64;
+
+    let min_x = positions.iter().map(|p| p[0]).min().unwrap();
+    let max_x = positions.iter().map(|p| p[0]).max().unwrap();
+    let min_y = positions.iter().map(|p| p[1]).min().unwrap();
+    let max_y = positions.iter().map(|p| p[1]).max().unwrap();
+
+    // the scissor test, like D3D11 the scissor is also clamped to the render target
+    let scissor_left = i64::from(scissor[0]).max(0);
+    let scissor_top = i64::from(scissor[1]).max(0);
+    let scissor_right = i64::from(scissor[2]).min(i64::from(target.width));
+    let scissor_bottom = i64::from(scissor[3]).min(i64::from(target.height));
+
+    let start_x = (min_x / subpixels).max(scissor_left);
+    let end_x = ((max_x + subpixels - 1) / subpixels).min(scissor_right);
+    let start_y = (min_y / subpixels).max(scissor_top);
+    let end_y = ((max_y + subpixels - 1) / subpixels).min(scissor_bottom);
+
+    if start_x >= end_x || start_y >= end_y {
+        return;
+    }
+
+    let (start_x, end_x) = (start_x as u32, end_x as u32);
+    let (start_y, end_y) = (start_y as u32, end_y as u32);
+
+    let edges = [(1, 2), (2, 0), (0, 1)];
+
+    for y in start_y..end_y {
+        for x in start_x..end_x {
+            // sample at the pixel center
+            let p = [
+                i64::from(x) * subpixels + subpixels / 2,
+                i64::from(y) * subpixels + subpixels / 2,
+            ];
+
+            let mut weights = [0.0f32; 3];
+            let mut inside = true;
+
+            for (i, (a, b)) in edges.iter().enumerate() {
+                let w = edge_function(positions[*a], positions[*b], p);
+
+                if w < 0 || (w == 0 && !is_top_left_edge(positions[*a], positions[*b])) {
+                    inside = false;
+                    break;
+                }
+
+                weights[i] = w as f32 / area as f32;
+            }
+
+            if !inside {
+                continue;
+            }
+
+            let mut uv = [0.0f32; 2];
+            for (i, vertex) in vertices.iter().enumerate() {
+                uv[0] += vertex.uv[0] * weights[i];
+                uv[1] += vertex.uv[1] * weights[i];
+            }
+
+            // PS_main: Texture.Sample(Sampler, input.uv) * color
+            let texel = sample(texture, uv, &pipeline_state.sampler);
+
+            let mut src = [0.0f32; 4];
+            for i in 0..4 {
+                src[i] = texel[i] * color[i];
+            }
+
+            let result = match pipeline_state.blend_mode {
+                BlendMode::Opaque => src,
+                blend_mode => blend(blend_mode, src, target.pixel(x, y)),
+            };
+
+            let offset = ((y * target.width + x) * 4) as usize;
+            target.pixels[offset..(offset + 4)].copy_from_slice(&float4_to_unorm(result));
+        }
+    }
+}
+
+pub fn draw_quad(
+    target: &mut CpuTexture,
+    pipeline_state: &SoftwarePipelineState,
+    texture: &CpuTexture,
+    constants: &[u8],
+) -> Result<(), SoftwareRasterizerError> {
+    let constants = decode_quad_constants(pipeline_state.shader, constants)
+        .ok_or(SoftwareRasterizerError::ConstantsTooSmall)?;
+
+    let scissor = full_scissor_rect(target);
+
+    draw_decoded_quad(target, pipeline_state, texture, &constants, scissor);
+
+    Ok(())
+}
+
+fn draw_decoded_quad(
+    target: &mut CpuTexture,
+    pipeline_state: &SoftwarePipelineState,
+    texture: &CpuTexture,
+    constants: &QuadConstants,
+    scissor: [i32; 4],
+) {
+    let color = constants.color();
+    let vertices = quad_vertices(constants);
+
+    // triangle strip, the second triangle has its winding order flipped by the input assembler
+    rasterize_triangle(
+        target,
+        pipeline_state,
+        texture,
+        color,
+        [vertices[0], vertices[1], vertices[2]],
+        scissor,
+    );
+
+    rasterize_triangle(
+        target,
+        pipeline_state,
+        texture,
+        color,
+        [vertices[1], vertices[3], vertices[2]],
+        scissor,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn white_texture() -> CpuTexture {
+        let mut texture = CpuTexture::new(4, 4);
+        clear_texture(&mut texture, [1.0, 1.0, 1.0, 1.0]);
+        texture
+    }
+
+    fn game_space_constants(color: [f32; 4], size: [i32; 2], position: [i32; 2]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for x in color.iter() {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        for x in size.iter().chain(position.iter()) {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        data
+    }
+
+    fn sprite_constants(
+        color: [f32; 4],
+        uv_rect: [f32; 4],
+        position: [f32; 2],
+        size: [f32; 2],
+        pivot: [f32; 2],
+        rotation: f32,
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+        let rotation = [rotation.cos(), rotation.sin()];
+        let values = color
+            .iter()
+            .chain(uv_rect.iter())
+            .chain(position.iter())
+            .chain(size.iter())
+            .chain(pivot.iter())
+            .chain(rotation.iter());
+        for x in values {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn decode_bc1_solid_block() {
+        // color0 = pure red in 565, all indices select color0
+        let block = [0x00, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+        let texture = decode_texture(CpuTextureFormat::Bc1, false, 4, 4, 8, &block).unwrap();
+
+        assert_eq!(texture.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(texture.pixel(3, 3), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decode_bc1_transparent_texel() {
+        // color0 <= color1 enables 3 color mode, index 3 is transparent black
+        let block = [0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+        let texture = decode_texture(CpuTextureFormat::Bc1, false, 4, 4, 8, &block).unwrap();
+
+        assert_eq!(texture.pixel(1, 2), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn decode_rejects_truncated_data() {
+        let block = [0u8; 4];
+
+        assert!(decode_texture(CpuTextureFormat::Bc1, false, 4, 4, 8, &block).is_err());
+    }
+
+    #[test]
+    fn decode_pads_partial_blocks() {
+        // a 5x2 texture uses two blocks, only the first column of the second block is visible
+        let mut blocks = [0x00, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00].repeat(2);
+        blocks[8..10].copy_from_slice(&[0x1f, 0x00]);
+
+        let texture = decode_texture(CpuTextureFormat::Bc1, false, 5, 2, 16, &blocks).unwrap();
+
+        assert_eq!(texture.pixel(3, 1), [255, 0, 0, 255]);
+        assert_eq!(texture.pixel(4, 1), [0, 0, 255, 255]);
+
+        assert!(matches!(
+            decode_texture(CpuTextureFormat::Bc1, false, 4, 0, 8, &blocks),
+            Err(SoftwareRasterizerError::EmptyImage)
+        ));
+        assert!(matches!(
+            decode_texture(CpuTextureFormat::Rgba8, false, 0, 4, 0, &blocks),
+            Err(SoftwareRasterizerError::EmptyImage)
+        ));
+    }
+
+    #[test]
+    fn game_space_quad_covers_exact_pixels() {
+        let mut target = CpuTexture::new(540, 960);
+        clear_texture(&mut target, [0.0, 0.0, 0.0, 1.0]);
+
+        let pso = SoftwarePipelineState {
+            shader: QuadShader::GameSpaceQuad,
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+        };
+
+        let constants = game_space_constants([0.0, 1.0, 0.0, 1.0], [90, 90], [45, 45]);
+
+        assert!(matches!(
+            draw_quad(&mut target, &pso, &white_texture(), &constants[0..16]),
+            Err(SoftwareRasterizerError::ConstantsTooSmall)
+        ));
+
+        draw_quad(&mut target, &pso, &white_texture(), &constants).unwrap();
+
+        // game space has y going up, pixel rows go down
+        assert_eq!(target.pixel(45, 960 - 45 - 1), [0, 255, 0, 255]);
+        assert_eq!(target.pixel(134, 960 - 134), [0, 255, 0, 255]);
+        assert_eq!(target.pixel(44, 960 - 45 - 1), [0, 0, 0, 255]);
+        assert_eq!(target.pixel(135, 960 - 134), [0, 0, 0, 255]);
+        assert_eq!(target.pixel(45, 960 - 45), [0, 0, 0, 255]);
+        assert_eq!(target.pixel(45, 960 - 135 - 1), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn batched_quads_use_constants_per_instance() {
+        let mut command_list = SoftwareCommandList::new();
+
+        let target = Rc::new(RefCell::new(CpuTexture::new(540, 960)));
+        clear_texture(&mut target.borrow_mut(), [0.0, 0.0, 0.0, 1.0]);
+
+        let full_uv = [0.0, 0.0, 1.0, 1.0];
+
+        let mut constants = sprite_constants(
+            [1.0, 0.0, 0.0, 1.0],
+            full_uv,
+            [0.0, 0.0],
+            [10.0, 10.0],
+            [0.0, 0.0],
+            0.0,
+        );
+        constants.extend(sprite_constants(
+            [0.0, 0.0, 1.0, 1.0],
+            full_uv,
+            [100.0, 0.0],
+            [10.0, 10.0],
+            [0.0, 0.0],
+            0.0,
+        ));
+
+        command_list.render_target = Some(target.clone());
+        command_list.pipeline_state = Some(SoftwarePipelineState {
+            shader: QuadShader::GameSpaceQuadBatch,
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+        });
+        command_list.bind_texture(0, Rc::new(RefCell::new(white_texture())));
+        command_list.bind_constant(0, &constants);
+        command_list.draw_instanced(4, 2).unwrap();
+
+        assert_eq!(target.borrow().pixel(5, 955), [255, 0, 0, 255]);
+        assert_eq!(target.borrow().pixel(105, 955), [0, 0, 255, 255]);
+        assert_eq!(target.borrow().pixel(55, 955), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn nested_clip_rects_intersect() {
+        let mut clip_stack = ClipStack::new();
+        assert_eq!(clip_stack.current(), None);
+
+        clip_stack.push(ClipRect {
+            x: 10,
+            y: 10,
+            width: 100,
+            height: 100,
+        });
+        clip_stack.push(ClipRect {
+            x: 50,
+            y: 0,
+            width: 100,
+            height: 20,
+        });
+
+        assert_eq!(
+            clip_stack.current(),
+            Some(ClipRect {
+                x: 50,
+                y: 10,
+                width: 60,
+                height: 10,
+            })
+        );
+
+        // game space has y going up, the scissor rect has y going down
+        assert_eq!(
+            clip_stack.current().unwrap().scissor_rect(960),
+            [50, 940, 110, 950]
+        );
+
+        clip_stack.pop();
+        assert_eq!(clip_stack.current().unwrap().x, 10);
+
+        clip_stack.pop();
+        assert_eq!(clip_stack.current(), None);
+
+        // unbalanced pops are reported instead of ignored
+        assert_eq!(clip_stack.pop(), None);
+    }
+
+    #[test]
+    fn draws_without_the_required_state_fail() {
+        let mut command_list = SoftwareCommandList::new();
+
+        let target = Rc::new(RefCell::new(CpuTexture::new(540, 960)));
+        clear_texture(&mut target.borrow_mut(), [0.0, 0.0, 0.0, 1.0]);
+
+        command_list.render_target = Some(target.clone());
+        assert!(command_list.draw(4).is_err());
+
+        command_list.pipeline_state = Some(SoftwarePipelineState {
+            shader: QuadShader::GameSpaceQuad,
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+        });
+        command_list.bind_texture(0, target.clone());
+        command_list.bind_constant(
+            0,
+            &game_space_constants([0.0, 1.0, 0.0, 1.0], [100, 100], [0, 0]),
+        );
+
+        // the render target is also bound as texture
+        assert!(command_list.draw(4).is_err());
+
+        command_list.bind_texture(0, Rc::new(RefCell::new(white_texture())));
+        assert!(command_list.draw(3).is_err());
+
+        // nothing has been drawn by the failed draws
+        assert_eq!(target.borrow().pixel(0, 959), [0, 0, 0, 255]);
+
+        command_list.draw(4).unwrap();
+        assert_eq!(target.borrow().pixel(0, 959), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn draws_are_clipped_to_the_clip_rect() {
+        let mut command_list = SoftwareCommandList::new();
+
+        let target = Rc::new(RefCell::new(CpuTexture::new(540, 960)));
+        clear_texture(&mut target.borrow_mut(), [0.0, 0.0, 0.0, 1.0]);
+
+        command_list.render_target = Some(target.clone());
+        command_list.pipeline_state = Some(SoftwarePipelineState {
+            shader: QuadShader::GameSpaceQuad,
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+        });
+        command_list.bind_texture(0, Rc::new(RefCell::new(white_texture())));
+        command_list.bind_constant(
+            0,
+            &game_space_constants([0.0, 1.0, 0.0, 1.0], [100, 100], [0, 0]),
+        );
+
+        command_list.clip_stack.push(ClipRect {
+            x: 20,
+            y: 30,
+            width: 40,
+            height: 50,
+        });
+        command_list.draw(4).unwrap();
+
+        {
+            let target = target.borrow();
+
+            // covers game space x 20..60 and y 30..80
+            assert_eq!(target.pixel(20, 960 - 30 - 1), [0, 255, 0, 255]);
+            assert_eq!(target.pixel(59, 960 - 80), [0, 255, 0, 255]);
+            assert_eq!(target.pixel(19, 960 - 30 - 1), [0, 0, 0, 255]);
+            assert_eq!(target.pixel(60, 960 - 30 - 1), [0, 0, 0, 255]);
+            assert_eq!(target.pixel(20, 960 - 30), [0, 0, 0, 255]);
+            assert_eq!(target.pixel(20, 960 - 80 - 1), [0, 0, 0, 255]);
+        }
+
+        // without a clip rect the whole quad is drawn again
+        command_list.clip_stack.pop();
+        command_list.draw(4).unwrap();
+
+        assert_eq!(target.borrow().pixel(0, 959), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn alpha_blending_matches_blend_state() {
+        let mut target = CpuTexture::new(4, 4);
+        clear_texture(&mut target, [1.0, 1.0, 1.0, 1.0]);
+
+        let pso = SoftwarePipelineState {
+            shader: QuadShader::ScreenSpaceQuad,
+            blend_mode: BlendMode::Alpha,
+            sampler: SamplerDesc::default(),
+        };
+
+        // black with 50% alpha over the whole screen
+        let mut constants = Vec::new();
+        for x in [0.0f32, 0.0, 0.0, 0.5, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0].iter() {
+            constants.extend_from_slice(&x.to_le_bytes());
+        }
+
+        draw_quad(&mut target, &pso, &white_texture(), &constants).unwrap();
+
+        assert_eq!(target.pixel(2, 2), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn blend_modes_match_blend_states() {
+        let src = [0.5, 0.25, 1.0, 0.5];
+        let dst = [255, 128, 0, 255];
+
+        let expected = [
+            (BlendMode::Opaque, [128, 64, 255, 128]),
+            (BlendMode::Alpha, [191, 96, 128, 255]),
+            (BlendMode::Premultiplied, [255, 128, 255, 255]),
+            (BlendMode::Additive, [255, 160, 128, 255]),
+            (BlendMode::Multiply, [128, 32, 0, 255]),
+        ];
+
+        for (blend_mode, result) in expected.iter() {
+            assert_eq!(float4_to_unorm(blend(*blend_mode, src, dst)), *result);
+        }
+    }
+
+    #[test]
+    fn point_sampling_with_wrap_and_mirror_addressing() {
+        // 2x1 texture, black on the left and white on the right
+        let mut texture = CpuTexture::new(2, 1);
+        texture.pixels[4..8].copy_from_slice(&[255, 255, 255, 255]);
+
+        let sampler = |address_u| SamplerDesc {
+            filter: SamplerFilter::Point,
+            address_u,
+            address_v: TextureAddressMode::Clamp,
+        };
+
+        let clamp = sampler(TextureAddressMode::Clamp);
+        let wrap = sampler(TextureAddressMode::Wrap);
+        let mirror = sampler(TextureAddressMode::Mirror);
+
+        // point sampling never mixes neighbouring texels
+        assert_eq!(sample(&texture, [0.49, 0.5], &clamp)[0], 0.0);
+        assert_eq!(sample(&texture, [0.51, 0.5], &clamp)[0], 1.0);
+
+        assert_eq!(sample(&texture, [1.25, 0.5], &clamp)[0], 1.0);
+        assert_eq!(sample(&texture, [1.25, 0.5], &wrap)[0], 0.0);
+        assert_eq!(sample(&texture, [1.25, 0.5], &mirror)[0], 1.0);
+        assert_eq!(sample(&texture, [-0.25, 0.5], &wrap)[0], 1.0);
+        assert_eq!(sample(&texture, [-0.25, 0.5], &mirror)[0], 0.0);
+
+        // linear filtering blends between the texel centers
+        let linear = SamplerDesc::default();
+        assert_eq!(sample(&texture, [0.5, 0.5], &linear)[0], 0.5);
+    }
+
+    #[test]
+    fn sprites_rotate_around_the_pivot_and_flip_uvs() {
+        // 2x1 texture, red on the left and blue on the right
+        let mut texture = CpuTexture::new(2, 1);
+        texture.pixels[0..4].copy_from_slice(&[255, 0, 0, 255]);
+        texture.pixels[4..8].copy_from_slice(&[0, 0, 255, 255]);
+
+        let pso = SoftwarePipelineState {
+            shader: QuadShader::GameSpaceQuadBatch,
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc {
+                filter: SamplerFilter::Point,
+                address_u: TextureAddressMode::Clamp,
+                address_v: TextureAddressMode::Clamp,
+            },
+        };
+
+        let draw_sprite = |uv_rect: [f32; 4]| -> CpuTexture {
+            let mut target = CpuTexture::new(540, 960);
+            clear_texture(&mut target, [0.0, 0.0, 0.0, 1.0]);
+
+            // 100x20 sprite rotated by 90 degrees around its center, the left side ends up at the bottom
+            let constants = sprite_constants(
+                [1.0, 1.0, 1.0, 1.0],
+                uv_rect,
+                [270.0, 480.0],
+                [100.0, 20.0],
+                [0.5, 0.5],
+                std::f32::consts::FRAC_PI_2,
+            );
+
+            draw_quad(&mut target, &pso, &texture, &constants).unwrap();
+            target
+        };
+
+        // pixel rows go down, game space y goes up
+        let target = draw_sprite([0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(target.pixel(270, 960 - 450), [255, 0, 0, 255]);
+        assert_eq!(target.pixel(270, 960 - 510), [0, 0, 255, 255]);
+        assert_eq!(target.pixel(300, 960 - 480), [0, 0, 0, 255]);
+        assert_eq!(target.pixel(270, 960 - 540), [0, 0, 0, 255]);
+
+        // a horizontal flip swaps the left and right uv
+        let target = draw_sprite([1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(target.pixel(270, 960 - 450), [0, 0, 255, 255]);
+        assert_eq!(target.pixel(270, 960 - 510), [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,730 @@
+use graphics_device::*;
+
+static WHITE_4X4_RGBA: &[u8; 192] =
+    include_bytes!("../../dds_parser/tests/data/paintnet/white_4x4_rgba8.dds");
+
+#[repr(C)]
+struct GameSpaceQuadData {
+    color: [f32; 4],
+    size_pixels: [i32; 2],
+    position_bottom_left: [i32; 2],
+}
+
+#[test]
+fn software_rasterizer_draws_game_space_quad() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let pso = create_pso(
+        &graphics_layer.device,
+        PipelineStateObjectDesc {
+            shader_name: "target_data/shaders/game_space_quad",
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+        },
+    )
+    .unwrap();
+
+    let texture_load_result = dds_parser::parse_dds_header(WHITE_4X4_RGBA).unwrap();
+
+    let texture = create_texture(
+        &graphics_layer.device,
+        texture_load_result.desc,
+        texture_load_result.subresources_data,
+        "White 4x4",
+    )
+    .unwrap();
+
+    let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 1024);
+    gpu_heap.begin_frame(0);
+
+    let command_list = &mut graphics_layer.graphics_command_list;
+
+    begin_render_pass_and_clear(
+        command_list,
+        [0.0, 0.0, 1.0, 1.0],
+        &graphics_layer.backbuffer_rtv,
+    )
+    .unwrap();
+
+    bind_pso(command_list, &pso).unwrap();
+    bind_texture(command_list, 0, &texture.srv).unwrap();
+
+    let obj_alloc = HeapAlloc::new(
+        GameSpaceQuadData {
+            color: [1.0, 0.0, 0.0, 1.0],
+            size_pixels: [90, 90],
+            position_bottom_left: [0, 0],
+        },
+        &mut gpu_heap,
+    )
+    .unwrap();
+
+    bind_constant(command_list, 0, &obj_alloc).unwrap();
+    draw_vertices(command_list, 4).unwrap();
+
+    gpu_heap.end_frame();
+
+    let backbuffer = graphics_layer
+        .backbuffer_rtv
+        .software_target
+        .as_ref()
+        .unwrap()
+        .borrow();
+
+    // game space origin is the bottom left corner of the screen
+    assert_eq!(backbuffer.pixel(0, 959), [255, 0, 0, 255]);
+    assert_eq!(backbuffer.pixel(89, 870), [255, 0, 0, 255]);
+    assert_eq!(backbuffer.pixel(90, 959), [0, 0, 255, 255]);
+    assert_eq!(backbuffer.pixel(0, 0), [0, 0, 255, 255]);
+}
+
+#[repr(C)]
+struct ScreenSpaceQuadData {
+    color: [f32; 4],
+    scale: [f32; 2],
+    position: [f32; 2],
+    uv_rect: [f32; 4],
+}
+
+#[test]
+fn software_rasterizer_samples_from_render_target() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(64, 64).unwrap();
+
+    let render_target =
+        create_render_target(&graphics_layer.device, 64, 64, "Offscreen Target").unwrap();
+
+    let pso = create_pso(
+        &graphics_layer.device,
+        PipelineStateObjectDesc {
+            shader_name: "target_data/shaders/screen_space_quad",
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+        },
+    )
+    .unwrap();
+
+    let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 1024);
+    gpu_heap.begin_frame(0);
+
+    let command_list = &mut graphics_layer.graphics_command_list;
+
+    // fill the offscreen target with a solid color
+    begin_render_pass_and_clear(command_list, [0.0, 1.0, 0.0, 1.0], &render_target.rtv).unwrap();
+
+    // and copy it to the backbuffer at half intensity
+    begin_render_pass_and_clear(
+        command_list,
+        [0.0, 0.0, 0.0, 1.0],
+        &graphics_layer.backbuffer_rtv,
+    )
+    .unwrap();
+
+    bind_pso(command_list, &pso).unwrap();
+    bind_texture(command_list, 0, &render_target.srv).unwrap();
+
+    let obj_alloc = HeapAlloc::new(
+        ScreenSpaceQuadData {
+            color: [1.0, 0.5, 1.0, 1.0],
+            scale: [1.0, 1.0],
+            position: [0.0, 0.0],
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+        },
+        &mut gpu_heap,
+    )
+    .unwrap();
+
+    bind_constant(command_list, 0, &obj_alloc).unwrap();
+    draw_vertices(command_list, 4).unwrap();
+
+    gpu_heap.end_frame();
+
+    let backbuffer = graphics_layer
+        .backbuffer_rtv
+        .software_target
+        .as_ref()
+        .unwrap()
+        .borrow();
+
+    assert_eq!(backbuffer.pixel(0, 0), [0, 128, 0, 255]);
+    assert_eq!(backbuffer.pixel(63, 63), [0, 128, 0, 255]);
+}
+
+#[test]
+fn linear_allocator_chains_and_reuses_pages() {
+    let graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    // each allocation is rounded up to 256 bytes, so a page fits 4 allocations
+    let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 1024);
+
+    for frame_number in 0..4u64 {
+        if let Some(retired_frame_number) = frame_number.checked_sub(2) {
+            gpu_heap.retire_frame(retired_frame_number);
+        }
+
+        gpu_heap.begin_frame(frame_number);
+
+        let allocations: Vec<HeapAlloc> = (0..5)
+            .map(|_| HeapAlloc::new([1.0f32; 4], &mut gpu_heap).unwrap())
+            .collect();
+
+        // the fifth allocation doesn't fit and starts at the beginning of the next page
+        assert_eq!(allocations[3].first_constant_offset, 48);
+        assert_eq!(allocations[4].first_constant_offset, 0);
+
+        gpu_heap.end_frame();
+
+        assert_eq!(gpu_heap.stats.frame_used_bytes, 5 * 256);
+        assert_eq!(gpu_heap.stats.frame_pages, 2);
+    }
+
+    // two frames are in flight at any time, the pages of older frames are reused
+    assert_eq!(gpu_heap.stats.page_count, 4);
+    assert_eq!(gpu_heap.stats.high_water_mark_bytes, 5 * 256);
+    assert_eq!(gpu_heap.stats.high_water_mark_pages, 2);
+}
+
+#[test]
+fn software_frames_are_finished_when_they_are_signaled() {
+    let graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let mut frame_fences = FrameFences::new(&graphics_layer.device);
+    assert_eq!(frame_fences.poll_completed_frame().unwrap(), None);
+
+    // the software rasterizer has already executed the frame, nothing to wait for
+    frame_fences.signal_frame(0).unwrap();
+    frame_fences.wait_for_frame(0).unwrap();
+    assert_eq!(frame_fences.poll_completed_frame().unwrap(), Some(0));
+
+    frame_fences.signal_frame(1).unwrap();
+    assert_eq!(frame_fences.completed_frame, Some(1));
+}
+
+#[test]
+fn linear_allocator_rejects_allocations_larger_than_a_page() {
+    let graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 1024);
+    gpu_heap.begin_frame(0);
+
+    let quads = [[0.0f32; 4]; 65];
+
+    assert!(matches!(
+        HeapAlloc::new_array(&quads, &mut gpu_heap),
+        Err(GraphicsError::InvalidArgument { .. })
+    ));
+
+    // the failed allocation doesn't use any memory
+    assert!(HeapAlloc::new_array(&quads[0..64], &mut gpu_heap).is_ok());
+    assert_eq!(gpu_heap.stats.frame_used_bytes, 1024);
+
+    gpu_heap.end_frame();
+}
+
+#[test]
+fn software_backbuffer_can_be_read_back() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(8, 4).unwrap();
+
+    begin_render_pass_and_clear(
+        &mut graphics_layer.graphics_command_list,
+        [1.0, 0.0, 1.0, 1.0],
+        &graphics_layer.backbuffer_rtv,
+    )
+    .unwrap();
+
+    let image = read_back_render_target(&graphics_layer, &graphics_layer.backbuffer_rtv).unwrap();
+
+    assert_eq!(image.width, 8);
+    assert_eq!(image.height, 4);
+    assert_eq!(image.pixel(7, 3), [255, 0, 255, 255]);
+
+    let filename = std::env::temp_dir().join("graphics_device_read_back_test.png");
+    let filename = filename.to_str().unwrap();
+
+    save_render_target_to_file(&graphics_layer, &graphics_layer.backbuffer_rtv, filename).unwrap();
+
+    let png = std::fs::read(filename).unwrap();
+    assert_eq!(&png[0..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+
+    std::fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn destroyed_resources_invalidate_handles() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let texture_load_result = dds_parser::parse_dds_header(WHITE_4X4_RGBA).unwrap();
+
+    let texture = create_texture(
+        &graphics_layer.device,
+        texture_load_result.desc,
+        texture_load_result.subresources_data,
+        "White 4x4",
+    )
+    .unwrap();
+
+    let texture_handle = graphics_layer.resources.add_texture(texture);
+
+    // handles are copyable and can be shared
+    let shared_handle = texture_handle;
+    assert!(graphics_layer.resources.texture(shared_handle).is_ok());
+
+    assert!(graphics_layer.resources.destroy_texture(texture_handle).is_ok());
+
+    assert!(matches!(
+        graphics_layer.resources.texture(shared_handle),
+        Err(GraphicsError::InvalidHandle)
+    ));
+    assert!(matches!(
+        graphics_layer.resources.destroy_texture(shared_handle),
+        Err(GraphicsError::InvalidHandle)
+    ));
+
+    // a new texture reuses the slot, the old handle still doesn't resolve
+    let texture_load_result = dds_parser::parse_dds_header(WHITE_4X4_RGBA).unwrap();
+
+    let texture = create_texture(
+        &graphics_layer.device,
+        texture_load_result.desc,
+        texture_load_result.subresources_data,
+        "White 4x4",
+    )
+    .unwrap();
+
+    let new_texture_handle = graphics_layer.resources.add_texture(texture);
+
+    assert!(graphics_layer.resources.texture(new_texture_handle).is_ok());
+    assert!(matches!(
+        graphics_layer.resources.texture(texture_handle),
+        Err(GraphicsError::InvalidHandle)
+    ));
+}
+
+#[test]
+fn cached_resources_are_shared_until_the_last_release() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let device = &graphics_layer.device;
+    let resources = &mut graphics_layer.resources;
+
+    let texture_path = "../dds_parser/tests/data/paintnet/white_4x4_rgba8.dds";
+
+    let texture0 = resources.load_texture(device, texture_path).unwrap();
+    let texture1 = resources.load_texture(device, texture_path).unwrap();
+    assert_eq!(texture0, texture1);
+
+    let pso_desc = || PipelineStateObjectDesc {
+        shader_name: "target_data/shaders/game_space_quad",
+        blend_mode: BlendMode::Opaque,
+        sampler: SamplerDesc::default(),
+    };
+
+    let pso0 = resources.load_pso(device, pso_desc()).unwrap();
+    let pso1 = resources.load_pso(device, pso_desc()).unwrap();
+    assert_eq!(pso0, pso1);
+
+    // a different desc creates a new pso
+    let blended_pso = resources
+        .load_pso(
+            device,
+            PipelineStateObjectDesc {
+                shader_name: "target_data/shaders/game_space_quad",
+                blend_mode: BlendMode::Alpha,
+                sampler: SamplerDesc::default(),
+            },
+        )
+        .unwrap();
+    assert_ne!(pso0, blended_pso);
+
+    // the sampler is part of the desc too
+    let point_sampled_pso = resources
+        .load_pso(
+            device,
+            PipelineStateObjectDesc {
+                sampler: SamplerDesc {
+                    filter: SamplerFilter::Point,
+                    ..SamplerDesc::default()
+                },
+                ..pso_desc()
+            },
+        )
+        .unwrap();
+    assert_ne!(pso0, point_sampled_pso);
+
+    let texture_stats = resources.texture_cache_stats();
+    assert_eq!((texture_stats.hits, texture_stats.misses), (1, 1));
+
+    let pso_stats = resources.pso_cache_stats();
+    assert_eq!((pso_stats.hits, pso_stats.misses), (1, 3));
+
+    // shared resources can't be destroyed directly
+    assert!(resources.destroy_texture(texture0).is_err());
+
+    resources.release_texture(texture0).unwrap();
+    assert!(resources.texture(texture1).is_ok());
+
+    resources.release_texture(texture1).unwrap();
+    assert!(matches!(
+        resources.texture(texture1),
+        Err(GraphicsError::InvalidHandle)
+    ));
+    assert_eq!(resources.texture_cache_stats().evictions, 1);
+}
+
+#[test]
+fn reloading_a_broken_texture_keeps_the_previous_version() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let texture_path = std::env::temp_dir().join(format!("reload_test_{}.dds", std::process::id()));
+    let texture_path = texture_path.to_string_lossy().to_string();
+
+    std::fs::write(&texture_path, &WHITE_4X4_RGBA[..]).unwrap();
+
+    let device = &graphics_layer.device;
+    let resources = &mut graphics_layer.resources;
+
+    let handle = resources.load_texture(device, &texture_path).unwrap();
+
+    let software_texture = |resources: &ResourceRegistry| {
+        resources
+            .texture(handle)
+            .unwrap()
+            .srv
+            .software_texture
+            .clone()
+            .unwrap()
+    };
+
+    let original = software_texture(resources);
+
+    std::fs::write(&texture_path, b"not a dds file").unwrap();
+    let report = resources.reload_changed_files(device, std::slice::from_ref(&texture_path));
+
+    assert!(std::rc::Rc::ptr_eq(&original, &software_texture(resources)));
+    assert!(report.reloaded.is_empty());
+    assert!(matches!(
+        report.errors.as_slice(),
+        [GraphicsError::InvalidDds { .. }]
+    ));
+
+    // textures that are not loaded through the cache are reloaded as well
+    std::fs::write(&texture_path, &WHITE_4X4_RGBA[..]).unwrap();
+    let added_handle = resources.add_texture(load_dds_from_file(&texture_path, device).unwrap());
+
+    // the handle stays valid when the texture is replaced
+    let report = resources.reload_changed_files(device, std::slice::from_ref(&texture_path));
+
+    assert!(!std::rc::Rc::ptr_eq(&original, &software_texture(resources)));
+    assert_eq!(
+        report.reloaded,
+        vec![texture_path.clone(), texture_path.clone()]
+    );
+    assert!(report.errors.is_empty());
+
+    resources.destroy_texture(added_handle).unwrap();
+
+    std::fs::remove_file(&texture_path).unwrap();
+}
+
+#[test]
+fn missing_files_and_unknown_shaders_return_errors() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let device = &graphics_layer.device;
+    let resources = &mut graphics_layer.resources;
+
+    let texture = resources.load_texture(device, "target_data/textures/missing.dds");
+
+    match texture {
+        Err(GraphicsError::FileRead { path, error }) => {
+            assert_eq!(path, "target_data/textures/missing.dds");
+            assert_eq!(error, std::io::ErrorKind::NotFound);
+        }
+        _ => panic!("expected a FileRead error"),
+    }
+
+    let pso = resources.load_pso(
+        device,
+        PipelineStateObjectDesc {
+            shader_name: "target_data/shaders/missing",
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc::default(),
+        },
+    );
+
+    assert!(matches!(pso, Err(GraphicsError::UnsupportedShader { .. })));
+
+    // a missing compiled shader tells the user how to fix it
+    let error = GraphicsError::FileRead {
+        path: String::from("target_data/shaders/missing.vsb"),
+        error: std::io::ErrorKind::NotFound,
+    };
+
+    assert!(error.to_string().ends_with("make sure the shaders have been compiled"));
+}
+
+#[test]
+fn backbuffer_size_needs_to_be_positive() {
+    assert!(matches!(
+        create_device_graphics_layer_software(0, 960),
+        Err(GraphicsError::InvalidArgument { .. })
+    ));
+}
+
+#[test]
+fn software_backbuffer_can_be_resized() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    resize_swapchain(&mut graphics_layer, 1080, 1920).unwrap();
+
+    assert_eq!(graphics_layer.backbuffer_rtv.width, 1080);
+    assert_eq!(graphics_layer.backbuffer_rtv.height, 1920);
+
+    let backbuffer = graphics_layer
+        .backbuffer_rtv
+        .software_target
+        .as_ref()
+        .unwrap()
+        .borrow();
+
+    assert_eq!((backbuffer.width, backbuffer.height), (1080, 1920));
+    drop(backbuffer);
+
+    assert!(matches!(
+        resize_swapchain(&mut graphics_layer, 0, 0),
+        Err(GraphicsError::InvalidArgument { .. })
+    ));
+}
+
+#[test]
+fn recorded_draws_remember_the_clip_rect() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_recording(540, 960).unwrap();
+
+    let command_list = &mut graphics_layer.graphics_command_list;
+
+    let panel = ClipRect {
+        x: 50,
+        y: 400,
+        width: 440,
+        height: 200,
+    };
+
+    begin_render_pass(command_list, &graphics_layer.backbuffer_rtv).unwrap();
+    draw_vertices(command_list, 4).unwrap();
+
+    push_clip_rect(command_list, panel).unwrap();
+    draw_vertices(command_list, 4).unwrap();
+
+    // a child that extends past the panel is limited to the panel
+    push_clip_rect(
+        command_list,
+        ClipRect {
+            x: 0,
+            y: 500,
+            width: 540,
+            height: 200,
+        },
+    )
+    .unwrap();
+    draw_vertices(command_list, 4).unwrap();
+
+    pop_clip_rect(command_list).unwrap();
+    draw_vertices(command_list, 4).unwrap();
+
+    pop_clip_rect(command_list).unwrap();
+    draw_vertices(command_list, 4).unwrap();
+
+    let draws = match &graphics_layer.graphics_command_list {
+        GraphicsCommandList::Recording(recorder) => recorder.draws(),
+        _ => panic!("expected a recording command list"),
+    };
+
+    let clip_rects: Vec<Option<ClipRect>> = draws.iter().map(|x| x.clip_rect).collect();
+
+    assert_eq!(
+        clip_rects,
+        vec![
+            None,
+            Some(panel),
+            Some(ClipRect {
+                x: 50,
+                y: 500,
+                width: 440,
+                height: 100,
+            }),
+            Some(panel),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn invalid_commands_return_errors() {
+    for mut graphics_layer in [
+        create_device_graphics_layer_software(540, 960).unwrap(),
+        create_device_graphics_layer_recording(540, 960).unwrap(),
+    ] {
+        let command_list = &mut graphics_layer.graphics_command_list;
+
+        begin_render_pass(command_list, &graphics_layer.backbuffer_rtv).unwrap();
+
+        assert!(matches!(
+            pop_clip_rect(command_list),
+            Err(GraphicsError::InvalidArgument { .. })
+        ));
+
+        assert!(matches!(
+            push_clip_rect(
+                command_list,
+                ClipRect {
+                    x: 0,
+                    y: 0,
+                    width: -1,
+                    height: 10,
+                },
+            ),
+            Err(GraphicsError::InvalidArgument { .. })
+        ));
+    }
+
+    // the software rasterizer can't draw without a pipeline state
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_software(540, 960).unwrap();
+
+    let command_list = &mut graphics_layer.graphics_command_list;
+
+    begin_render_pass(command_list, &graphics_layer.backbuffer_rtv).unwrap();
+
+    assert!(matches!(
+        draw_vertices(command_list, 4),
+        Err(GraphicsError::InvalidArgument { .. })
+    ));
+}
+
+#[test]
+fn created_resources_are_tracked_until_dropped() {
+    let mut graphics_layer: GraphicsDeviceLayer =
+        create_device_graphics_layer_recording(540, 960).unwrap();
+
+    let device = &graphics_layer.device;
+    let tracker = device.tracker.clone();
+
+    let texture = graphics_layer
+        .resources
+        .load_texture(
+            device,
+            "../dds_parser/tests/data/paintnet/white_4x4_rgba8.dds",
+        )
+        .unwrap();
+
+    let render_target = create_render_target(device, 64, 32, "Tracked Target").unwrap();
+
+    let mut gpu_heap = LinearAllocator::new(device, 1024);
+    gpu_heap.begin_frame(0);
+    HeapAlloc::new([0.0f32; 4], &mut gpu_heap).unwrap();
+    gpu_heap.end_frame();
+
+    let usage = |kind| tracker.usage(kind);
+    assert_eq!(usage(TrackedResourceKind::Texture).memory_bytes, 4 * 4 * 4);
+    assert_eq!(
+        usage(TrackedResourceKind::RenderTarget).memory_bytes,
+        64 * 32 * 4
+    );
+    assert_eq!(usage(TrackedResourceKind::RenderTargetView).count, 1);
+    assert_eq!(usage(TrackedResourceKind::ShaderResourceView).count, 2);
+    assert_eq!(usage(TrackedResourceKind::Buffer).memory_bytes, 1024);
+
+    // every resource remembers the code that requested it
+    for resource in tracker.live_resources() {
+        assert_eq!(resource.creation_site.file(), file!());
+    }
+
+    drop(gpu_heap);
+    drop(render_target);
+    graphics_layer.resources.release_texture(texture).unwrap();
+
+    assert_eq!(tracker.total_usage(), ResourceUsage::default());
+    assert!(tracker.leak_report().is_none());
+}
+
+#[test]
+fn command_lists_count_draws_and_binds() {
+    let software_layer = create_device_graphics_layer_software(540, 960).unwrap();
+    let recording_layer = create_device_graphics_layer_recording(540, 960).unwrap();
+
+    for mut graphics_layer in [software_layer, recording_layer] {
+        let pso = create_pso(
+            &graphics_layer.device,
+            PipelineStateObjectDesc {
+                shader_name: "target_data/shaders/game_space_quad",
+                blend_mode: BlendMode::Opaque,
+                sampler: SamplerDesc::default(),
+            },
+        )
+        .unwrap();
+
+        let texture_load_result = dds_parser::parse_dds_header(WHITE_4X4_RGBA).unwrap();
+
+        let texture = create_texture(
+            &graphics_layer.device,
+            texture_load_result.desc,
+            texture_load_result.subresources_data,
+            "White 4x4",
+        )
+        .unwrap();
+
+        let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 1024);
+        gpu_heap.begin_frame(0);
+
+        let command_list = &mut graphics_layer.graphics_command_list;
+
+        begin_render_pass(command_list, &graphics_layer.backbuffer_rtv).unwrap();
+        bind_pso(command_list, &pso).unwrap();
+        bind_texture(command_list, 0, &texture.srv).unwrap();
+
+        for x in 0..3 {
+            let obj_alloc = HeapAlloc::new(
+                GameSpaceQuadData {
+                    color: [1.0, 0.0, 0.0, 1.0],
+                    size_pixels: [10, 10],
+                    position_bottom_left: [x * 20, 0],
+                },
+                &mut gpu_heap,
+            )
+            .unwrap();
+
+            bind_constant(command_list, 0, &obj_alloc).unwrap();
+            draw_vertices(command_list, 4).unwrap();
+        }
+
+        gpu_heap.end_frame();
+
+        assert_eq!(
+            take_command_list_stats(command_list),
+            CommandListStats {
+                render_passes: 1,
+                draw_calls: 3,
+                pso_binds: 1,
+                texture_binds: 1,
+                constant_binds: 3,
+            }
+        );
+
+        // the counters start again from zero
+        assert_eq!(
+            take_command_list_stats(command_list),
+            CommandListStats::default()
+        );
+    }
+}
@@ -0,0 +1,523 @@
+use crate::sprite_batch::{Sprite, SpriteBatch, UvRect};
+use crate::{Float2, Float4};
+use graphics_device::*;
+use std::collections::HashMap;
+
+// a glyph of the font atlas, all values are in pixels
+// x, y, width and height are the texel rect in the atlas, with 0,0 at the top-left of the texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+
+    // offset from the pen position on the top of the line to the top-left of the glyph, y going down
+    pub x_offset: i32,
+    pub y_offset: i32,
+
+    // how far the pen moves after the glyph
+    pub x_advance: i32,
+}
+
+// the parts of a BMFont text file the game needs
+// see http://www.angelcode.com/products/bmfont/doc/file_format.html
+pub struct BitmapFontDesc {
+    pub line_height: i32,
+    pub atlas_width: i32,
+    pub atlas_height: i32,
+
+    // relative to the location of the font file
+    pub texture_file: String,
+
+    pub glyphs: HashMap<char, Glyph>,
+    pub kerning: HashMap<(char, char), i32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FontParseError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+// splits a line like `char id=65 x=1 file="a b.dds"` into the tag and the key value pairs
+fn parse_line(line: &str) -> (&str, Vec<(&str, &str)>) {
+    let line = line.trim();
+
+    let (tag, mut rest) = match line.find(' ') {
+        Some(x) => (&line[..x], line[x..].trim_start()),
+        None => (line, ""),
+    };
+
+    let mut attributes = Vec::new();
+
+    while let Some(equals) = rest.find('=') {
+        let key = &rest[..equals];
+        rest = &rest[(equals + 1)..];
+
+        // quoted values can contain spaces
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            rest = quoted.get((end + 1)..).unwrap_or("");
+            &quoted[..end]
+        } else {
+            let end = rest.find(' ').unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+
+        attributes.push((key, value));
+        rest = rest.trim_start();
+    }
+
+    (tag, attributes)
+}
+
+fn attribute(
+    attributes: &[(&str, &str)],
+    key: &'static str,
+    line: usize,
+) -> Result<i32, FontParseError> {
+    let value = attributes
+        .iter()
+        .find(|x| x.0 == key)
+        .ok_or(FontParseError {
+            line,
+            reason: "missing value",
+        })?;
+
+    value.1.parse::<i32>().map_err(|_| FontParseError {
+        line,
+        reason: "value is not a number",
+    })
+}
+
+fn attribute_char(
+    attributes: &[(&str, &str)],
+    key: &'static str,
+    line: usize,
+) -> Result<char, FontParseError> {
+    std::char::from_u32(attribute(attributes, key, line)? as u32).ok_or(FontParseError {
+        line,
+        reason: "invalid character id",
+    })
+}
+
+// only single page fonts are supported, the whole font needs to fit into one texture
+pub fn parse_bmfont(text: &str) -> Result<BitmapFontDesc, FontParseError> {
+    let mut desc = BitmapFontDesc {
+        line_height: 0,
+        atlas_width: 0,
+        atlas_height: 0,
+        texture_file: String::new(),
+        glyphs: HashMap::new(),
+        kerning: HashMap::new(),
+    };
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let (tag, attributes) = parse_line(line);
+
+        match tag {
+            "common" => {
+                desc.line_height = attribute(&attributes, "lineHeight", line_number)?;
+                desc.atlas_width = attribute(&attributes, "scaleW", line_number)?;
+                desc.atlas_height = attribute(&attributes, "scaleH", line_number)?;
+
+                if attribute(&attributes, "pages", line_number)? != 1 {
+                    return Err(FontParseError {
+                        line: line_number,
+                        reason: "only fonts with a single page are supported",
+                    });
+                }
+            }
+
+            "page" => {
+                desc.texture_file = attributes
+                    .iter()
+                    .find(|x| x.0 == "file")
+                    .map(|x| x.1.to_string())
+                    .ok_or(FontParseError {
+                        line: line_number,
+                        reason: "missing value",
+                    })?;
+            }
+
+            "char" => {
+                desc.glyphs.insert(
+                    attribute_char(&attributes, "id", line_number)?,
+                    Glyph {
+                        x: attribute(&attributes, "x", line_number)?,
+                        y: attribute(&attributes, "y", line_number)?,
+                        width: attribute(&attributes, "width", line_number)?,
+                        height: attribute(&attributes, "height", line_number)?,
+                        x_offset: attribute(&attributes, "xoffset", line_number)?,
+                        y_offset: attribute(&attributes, "yoffset", line_number)?,
+                        x_advance: attribute(&attributes, "xadvance", line_number)?,
+                    },
+                );
+            }
+
+            "kerning" => {
+                desc.kerning.insert(
+                    (
+                        attribute_char(&attributes, "first", line_number)?,
+                        attribute_char(&attributes, "second", line_number)?,
+                    ),
+                    attribute(&attributes, "amount", line_number)?,
+                );
+            }
+
+            // info, chars and kernings only contain information that isn't needed at runtime
+            _ => {}
+        }
+    }
+
+    if desc.texture_file.is_empty() || desc.atlas_width <= 0 || desc.atlas_height <= 0 {
+        return Err(FontParseError {
+            line: 0,
+            reason: "missing common or page line",
+        });
+    }
+
+    Ok(desc)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlignment {
+    Left,
+    Center,
+}
+
+// a glyph placed by layout_text, relative to the anchor of the text
+// x goes right and y goes down from the top of the first line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedGlyph {
+    pub character: char,
+    pub x: i32,
+    pub y: i32,
+    pub glyph: Glyph,
+}
+
+impl BitmapFontDesc {
+    // characters that are not part of the font are drawn as '?'
+    fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&character)
+            .or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn kerning(&self, first: char, second: char) -> i32 {
+        self.kerning.get(&(first, second)).copied().unwrap_or(0)
+    }
+
+    // width of a single line of text in pixels
+    pub fn line_width(&self, line: &str) -> i32 {
+        let mut width = 0;
+        let mut previous = None;
+
+        for character in line.chars() {
+            if let Some(glyph) = self.glyph(character) {
+                if let Some(previous) = previous {
+                    width += self.kerning(previous, character);
+                }
+
+                width += glyph.x_advance;
+                previous = Some(character);
+            }
+        }
+
+        width
+    }
+
+    // splits the text into lines at '\n' and at the last space that keeps a line inside of max_width
+    // words that are wider than max_width are not split and overflow
+    pub fn wrap_lines(&self, text: &str, max_width: Option<i32>) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for paragraph in text.split('\n') {
+            let max_width = match max_width {
+                Some(x) => x,
+                None => {
+                    lines.push(paragraph.to_string());
+                    continue;
+                }
+            };
+
+            let mut line = String::new();
+
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{} {}", line, word)
+                };
+
+                if !line.is_empty() && self.line_width(&candidate) > max_width {
+                    lines.push(std::mem::replace(&mut line, word.to_string()));
+                } else {
+                    line = candidate;
+                }
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    // the anchor is on the top of the first line
+    // for left aligned text it's on the left edge and for centered text in the center of each line
+    pub fn layout_text(
+        &self,
+        text: &str,
+        alignment: TextAlignment,
+        max_width: Option<i32>,
+    ) -> Vec<PlacedGlyph> {
+        let mut placed_glyphs = Vec::new();
+
+        for (line_index, line) in self.wrap_lines(text, max_width).iter().enumerate() {
+            let mut pen_x = match alignment {
+                TextAlignment::Left => 0,
+                TextAlignment::Center => -self.line_width(line) / 2,
+            };
+
+            let pen_y = line_index as i32 * self.line_height;
+
+            let mut previous = None;
+
+            for character in line.chars() {
+                let glyph = match self.glyph(character) {
+                    Some(x) => *x,
+                    None => continue,
+                };
+
+                if let Some(previous) = previous {
+                    pen_x += self.kerning(previous, character);
+                }
+
+                // spaces have no pixels, they only move the pen
+                if glyph.width > 0 && glyph.height > 0 {
+                    placed_glyphs.push(PlacedGlyph {
+                        character,
+                        x: pen_x + glyph.x_offset,
+                        y: pen_y + glyph.y_offset,
+                        glyph,
+                    });
+                }
+
+                pen_x += glyph.x_advance;
+                previous = Some(character);
+            }
+        }
+
+        placed_glyphs
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub layer: i32,
+    pub color: Float4,
+    pub alignment: TextAlignment,
+
+    // lines are wrapped at spaces to stay inside of this width, in pixels before the scale is applied
+    pub max_width: Option<i32>,
+
+    pub scale: f32,
+}
+
+impl TextStyle {
+    // white, left aligned and without wrapping
+    pub fn new(layer: i32) -> TextStyle {
+        TextStyle {
+            layer,
+            color: Float4 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+                a: 1.0,
+            },
+            alignment: TextAlignment::Left,
+            max_width: None,
+            scale: 1.0,
+        }
+    }
+}
+
+pub struct BitmapFont {
+    pub desc: BitmapFontDesc,
+    pub texture: TextureHandle,
+}
+
+impl BitmapFont {
+    // loads a BMFont text file and the texture of its page
+    pub fn load(
+        device: &GraphicsDevice,
+        resources: &mut ResourceRegistry,
+        filename: &str,
+    ) -> Result<BitmapFont, GraphicsError> {
+        let text = std::fs::read_to_string(filename).map_err(|error| GraphicsError::FileRead {
+            path: filename.to_string(),
+            error: error.kind(),
+        })?;
+
+        let desc = parse_bmfont(&text).map_err(|error| GraphicsError::InvalidArgument {
+            resource_name: format!("{} line {}", filename, error.line),
+            reason: error.reason,
+        })?;
+
+        let texture_path = match filename.rfind('/') {
+            Some(x) => format!("{}/{}", &filename[..x], desc.texture_file),
+            None => desc.texture_file.clone(),
+        };
+
+        let texture = resources.load_texture(device, &texture_path)?;
+
+        Ok(BitmapFont { desc, texture })
+    }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        resources.release_texture(self.texture).unwrap();
+    }
+
+    // adds a sprite for each visible glyph, position is the game space anchor of the text, see layout_text
+    pub fn draw_text(
+        &self,
+        sprite_batch: &mut SpriteBatch,
+        text: &str,
+        position: Float2,
+        style: &TextStyle,
+    ) {
+        let atlas_width = self.desc.atlas_width as f32;
+        let atlas_height = self.desc.atlas_height as f32;
+
+        for placed in self
+            .desc
+            .layout_text(text, style.alignment, style.max_width)
+        {
+            let glyph = placed.glyph;
+
+            // the layout goes down from the top of the text, game space goes up
+            let mut sprite = Sprite::new(
+                self.texture,
+                style.layer,
+                Float2 {
+                    x: position.x + placed.x as f32 * style.scale,
+                    y: position.y - (placed.y + glyph.height) as f32 * style.scale,
+                },
+                Float2 {
+                    x: glyph.width as f32 * style.scale,
+                    y: glyph.height as f32 * style.scale,
+                },
+            );
+
+            sprite.color = style.color;
+            sprite.uv_rect = UvRect {
+                left: glyph.x as f32 / atlas_width,
+                top: glyph.y as f32 / atlas_height,
+                right: (glyph.x + glyph.width) as f32 / atlas_width,
+                bottom: (glyph.y + glyph.height) as f32 / atlas_height,
+            };
+
+            sprite_batch.add(sprite);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every glyph is 10 pixels wide and advances by 12, 'A' followed by 'V' moves 3 pixels closer
+    static TEST_FONT: &str = "info face=\"Test Font\" size=16 bold=0
+common lineHeight=20 base=16 scaleW=128 scaleH=64 pages=1 packed=0
+page id=0 file=\"test font.dds\"
+chars count=5
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=16 xadvance=6 page=0 chnl=15
+char id=63 x=1 y=1 width=10 height=16 xoffset=1 yoffset=0 xadvance=12 page=0 chnl=15
+char id=65 x=12 y=1 width=10 height=16 xoffset=1 yoffset=0 xadvance=12 page=0 chnl=15
+char id=86 x=23 y=1 width=10 height=16 xoffset=1 yoffset=0 xadvance=12 page=0 chnl=15
+char id=103 x=34 y=1 width=10 height=20 xoffset=1 yoffset=4 xadvance=12 page=0 chnl=15
+kernings count=1
+kerning first=65 second=86 amount=-3
+";
+
+    #[test]
+    fn parse_bmfont_text_format() {
+        let desc = parse_bmfont(TEST_FONT).unwrap();
+
+        assert_eq!(desc.line_height, 20);
+        assert_eq!((desc.atlas_width, desc.atlas_height), (128, 64));
+        assert_eq!(desc.texture_file, "test font.dds");
+        assert_eq!(desc.glyphs.len(), 5);
+        assert_eq!(
+            desc.glyphs[&'g'],
+            Glyph {
+                x: 34,
+                y: 1,
+                width: 10,
+                height: 20,
+                x_offset: 1,
+                y_offset: 4,
+                x_advance: 12,
+            }
+        );
+        assert_eq!(desc.kerning[&('A', 'V')], -3);
+
+        assert_eq!(
+            parse_bmfont("common lineHeight=20 scaleW=128 scaleH=64 pages=2").err(),
+            Some(FontParseError {
+                line: 1,
+                reason: "only fonts with a single page are supported",
+            })
+        );
+    }
+
+    #[test]
+    fn layout_applies_kerning_and_alignment() {
+        let desc = parse_bmfont(TEST_FONT).unwrap();
+
+        // kerning moves the V closer to the A, unknown characters use the '?' glyph
+        assert_eq!(desc.line_width("AV"), 21);
+        assert_eq!(desc.line_width("A%"), 24);
+
+        let positions = |alignment| -> Vec<(char, i32, i32)> {
+            desc.layout_text("AV g", alignment, None)
+                .iter()
+                .map(|x| (x.character, x.x, x.y))
+                .collect()
+        };
+
+        assert_eq!(
+            positions(TextAlignment::Left),
+            vec![('A', 1, 0), ('V', 10, 0), ('g', 28, 4)]
+        );
+
+        // the line is 39 pixels wide
+        assert_eq!(
+            positions(TextAlignment::Center),
+            vec![('A', -18, 0), ('V', -9, 0), ('g', 9, 4)]
+        );
+    }
+
+    #[test]
+    fn lines_wrap_at_spaces_and_newlines() {
+        let desc = parse_bmfont(TEST_FONT).unwrap();
+
+        assert_eq!(
+            desc.wrap_lines("AA VV AAA\nV", Some(60)),
+            vec!["AA VV", "AAA", "V"]
+        );
+
+        // words wider than the limit are kept on their own line
+        assert_eq!(desc.wrap_lines("AAAAA V", Some(30)), vec!["AAAAA", "V"]);
+
+        let glyphs = desc.layout_text("AA VV AAA", TextAlignment::Left, Some(60));
+        assert_eq!(
+            (glyphs[4].character, glyphs[4].x, glyphs[4].y),
+            ('A', 1, 20)
+        );
+    }
+}
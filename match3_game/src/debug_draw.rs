@@ -0,0 +1,396 @@
+// building without the debug_draw feature strips the overlay, DebugDraw is replaced with a stub that
+// has the same functions but doesn't load any resources or record anything
+
+#[cfg(feature = "debug_draw")]
+use crate::bitmap_font::{BitmapFont, TextStyle};
+#[cfg(feature = "debug_draw")]
+use crate::sprite_batch::{Sprite, SpriteBatch};
+use crate::{Float2, Float4};
+use graphics_device::*;
+
+// in pixels
+#[cfg(feature = "debug_draw")]
+const LINE_THICKNESS: f32 = 2.0;
+#[cfg(feature = "debug_draw")]
+const CIRCLE_SEGMENTS: usize = 24;
+#[cfg(feature = "debug_draw")]
+const TEXT_SCALE: f32 = 0.5;
+
+// everything is in game space, rects are given by their bottom-left corner and size
+#[cfg(feature = "debug_draw")]
+#[derive(Clone)]
+pub enum DebugPrimitive {
+    Line {
+        from: Float2,
+        to: Float2,
+        color: Float4,
+    },
+    Rect {
+        position: Float2,
+        size: Float2,
+        color: Float4,
+    },
+    Circle {
+        center: Float2,
+        radius: f32,
+        color: Float4,
+    },
+    Cross {
+        center: Float2,
+        size: f32,
+        color: Float4,
+    },
+    // position is the top-left of the text
+    Text {
+        position: Float2,
+        text: String,
+        color: Float4,
+    },
+}
+
+// the line segments a primitive is drawn with, text has none
+#[cfg(feature = "debug_draw")]
+pub fn primitive_lines(primitive: &DebugPrimitive) -> Vec<(Float2, Float2)> {
+    let point = |x: f32, y: f32| Float2 { x, y };
+
+    match primitive {
+        DebugPrimitive::Line { from, to, .. } => vec![(*from, *to)],
+
+        DebugPrimitive::Rect { position, size, .. } => {
+            let (left, bottom) = (position.x, position.y);
+            let (right, top) = (position.x + size.x, position.y + size.y);
+
+            vec![
+                (point(left, bottom), point(right, bottom)),
+                (point(right, bottom), point(right, top)),
+                (point(right, top), point(left, top)),
+                (point(left, top), point(left, bottom)),
+            ]
+        }
+
+        DebugPrimitive::Circle { center, radius, .. } => {
+            let on_circle = |segment: usize| {
+                let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+                point(
+                    center.x + angle.cos() * radius,
+                    center.y + angle.sin() * radius,
+                )
+            };
+
+            (0..CIRCLE_SEGMENTS)
+                .map(|x| (on_circle(x), on_circle(x + 1)))
+                .collect()
+        }
+
+        DebugPrimitive::Cross { center, size, .. } => {
+            let half = size * 0.5;
+
+            vec![
+                (
+                    point(center.x - half, center.y - half),
+                    point(center.x + half, center.y + half),
+                ),
+                (
+                    point(center.x - half, center.y + half),
+                    point(center.x + half, center.y - half),
+                ),
+            ]
+        }
+
+        DebugPrimitive::Text { .. } => Vec::new(),
+    }
+}
+
+// collects primitives during the update and draws them on top of all game states
+// the primitives are cleared before every update, so only the primitives of the last update are visible
+#[cfg(feature = "debug_draw")]
+pub struct DebugDraw {
+    // toggled at runtime, primitives are ignored while the overlay is hidden
+    pub visible: bool,
+
+    primitives: Vec<DebugPrimitive>,
+
+    pso: PsoHandle,
+    texture_white: TextureHandle,
+    font: BitmapFont,
+}
+
+#[cfg(feature = "debug_draw")]
+impl DebugDraw {
+    pub fn new(
+        device: &GraphicsDevice,
+        resources: &mut ResourceRegistry,
+    ) -> Result<DebugDraw, GraphicsError> {
+        let pso = resources.load_pso(
+            device,
+            PipelineStateObjectDesc {
+                shader_name: "target_data/shaders/game_space_quad_batch",
+                blend_mode: BlendMode::Alpha,
+                sampler: SamplerDesc::default(),
+            },
+        )?;
+
+        let texture_white =
+            resources.load_texture(device, "target_data/textures/engine/white.dds")?;
+
+        let font = BitmapFont::load(
+            device,
+            resources,
+            "target_data/fonts/DejaVu/dejavu_sans_bold_32.fnt",
+        )?;
+
+        Ok(DebugDraw {
+            visible: false,
+            primitives: Vec::new(),
+            pso,
+            texture_white,
+            font,
+        })
+    }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        resources.release_pso(self.pso).unwrap();
+        resources.release_texture(self.texture_white).unwrap();
+        self.font.release(resources);
+    }
+
+    pub fn clear(&mut self) {
+        self.primitives.clear();
+    }
+
+    fn add(&mut self, primitive: DebugPrimitive) {
+        if self.visible {
+            self.primitives.push(primitive);
+        }
+    }
+
+    pub fn line(&mut self, from: Float2, to: Float2, color: Float4) {
+        self.add(DebugPrimitive::Line { from, to, color });
+    }
+
+    pub fn rect(&mut self, position: Float2, size: Float2, color: Float4) {
+        self.add(DebugPrimitive::Rect {
+            position,
+            size,
+            color,
+        });
+    }
+
+    pub fn circle(&mut self, center: Float2, radius: f32, color: Float4) {
+        self.add(DebugPrimitive::Circle {
+            center,
+            radius,
+            color,
+        });
+    }
+
+    pub fn cross(&mut self, center: Float2, size: f32, color: Float4) {
+        self.add(DebugPrimitive::Cross {
+            center,
+            size,
+            color,
+        });
+    }
+
+    pub fn text(&mut self, position: Float2, text: &str, color: Float4) {
+        // skips the copy of the text while nothing is recorded
+        if self.visible {
+            self.add(DebugPrimitive::Text {
+                position,
+                text: text.to_string(),
+                color,
+            });
+        }
+    }
+
+    // draws into the canvas on top of everything that has been drawn so far
+    pub fn draw(
+        &self,
+        command_list: &mut GraphicsCommandList,
+        canvas_rtv: &RenderTargetView,
+        resources: &ResourceRegistry,
+        gpu_heap: &mut LinearAllocator,
+    ) -> Result<(), GraphicsError> {
+        if !self.visible || self.primitives.is_empty() {
+            return Ok(());
+        }
+
+        begin_render_pass(command_list, canvas_rtv)?;
+
+        let mut sprite_batch = SpriteBatch::new();
+
+        for primitive in self.primitives.iter() {
+            match primitive {
+                DebugPrimitive::Text {
+                    position,
+                    text,
+                    color,
+                } => {
+                    let mut style = TextStyle::new(1);
+                    style.color = *color;
+                    style.scale = TEXT_SCALE;
+
+                    self.font
+                        .draw_text(&mut sprite_batch, text, *position, &style);
+                }
+
+                DebugPrimitive::Line { color, .. }
+                | DebugPrimitive::Rect { color, .. }
+                | DebugPrimitive::Circle { color, .. }
+                | DebugPrimitive::Cross { color, .. } => {
+                    // a thin quad from the start to the end of the line, rotated around the start
+                    for (from, to) in primitive_lines(primitive) {
+                        let (dx, dy) = (to.x - from.x, to.y - from.y);
+
+                        let mut sprite = Sprite::new(
+                            self.texture_white,
+                            0,
+                            from,
+                            Float2 {
+                                x: dx.hypot(dy),
+                                y: LINE_THICKNESS,
+                            },
+                        );
+
+                        sprite.color = *color;
+                        sprite.pivot = Float2 { x: 0.0, y: 0.5 };
+                        sprite.rotation = dy.atan2(dx);
+
+                        sprite_batch.add(sprite);
+                    }
+                }
+            }
+        }
+
+        sprite_batch.submit(command_list, resources, self.pso, gpu_heap)
+    }
+}
+
+// F3 doesn't toggle the stub, visible stays false unless it's set by the code
+#[cfg(not(feature = "debug_draw"))]
+pub struct DebugDraw {
+    pub visible: bool,
+}
+
+#[cfg(not(feature = "debug_draw"))]
+impl DebugDraw {
+    pub fn new(
+        _device: &GraphicsDevice,
+        _resources: &mut ResourceRegistry,
+    ) -> Result<DebugDraw, GraphicsError> {
+        Ok(DebugDraw { visible: false })
+    }
+
+    pub fn release(&self, _resources: &mut ResourceRegistry) {}
+
+    pub fn clear(&mut self) {}
+
+    pub fn line(&mut self, _from: Float2, _to: Float2, _color: Float4) {}
+
+    pub fn rect(&mut self, _position: Float2, _size: Float2, _color: Float4) {}
+
+    pub fn circle(&mut self, _center: Float2, _radius: f32, _color: Float4) {}
+
+    pub fn cross(&mut self, _center: Float2, _size: f32, _color: Float4) {}
+
+    pub fn text(&mut self, _position: Float2, _text: &str, _color: Float4) {}
+
+    pub fn draw(
+        &self,
+        _command_list: &mut GraphicsCommandList,
+        _canvas_rtv: &RenderTargetView,
+        _resources: &ResourceRegistry,
+        _gpu_heap: &mut LinearAllocator,
+    ) -> Result<(), GraphicsError> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "debug_draw"))]
+mod tests {
+    use super::*;
+
+    const RED: Float4 = Float4 {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+        a: 1.0,
+    };
+
+    #[test]
+    fn primitives_are_drawn_with_lines() {
+        let rect = DebugPrimitive::Rect {
+            position: Float2 { x: 10.0, y: 20.0 },
+            size: Float2 { x: 30.0, y: 40.0 },
+            color: RED,
+        };
+
+        let lines = primitive_lines(&rect);
+        assert_eq!(lines.len(), 4);
+
+        // the outline is closed
+        for (index, line) in lines.iter().enumerate() {
+            let next = lines[(index + 1) % lines.len()];
+            assert_eq!((line.1.x, line.1.y), (next.0.x, next.0.y));
+        }
+
+        assert_eq!((lines[1].1.x, lines[1].1.y), (40.0, 60.0));
+
+        let circle = DebugPrimitive::Circle {
+            center: Float2 { x: 100.0, y: 100.0 },
+            radius: 8.0,
+            color: RED,
+        };
+
+        let lines = primitive_lines(&circle);
+        assert_eq!(lines.len(), CIRCLE_SEGMENTS);
+
+        for (from, _to) in lines.iter() {
+            let distance = (from.x - 100.0).hypot(from.y - 100.0);
+            assert!((distance - 8.0).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn primitives_are_only_recorded_while_visible() {
+        let mut graphics_layer = create_device_graphics_layer_recording(540, 960).unwrap();
+        let mut debug_draw =
+            DebugDraw::new(&graphics_layer.device, &mut graphics_layer.resources).unwrap();
+
+        debug_draw.cross(Float2 { x: 1.0, y: 2.0 }, 10.0, RED);
+        assert!(debug_draw.primitives.is_empty());
+
+        debug_draw.visible = true;
+        debug_draw.cross(Float2 { x: 1.0, y: 2.0 }, 10.0, RED);
+        debug_draw.text(Float2 { x: 1.0, y: 2.0 }, "1, 2", RED);
+
+        let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 64 * 1024);
+        gpu_heap.begin_frame(0);
+
+        debug_draw
+            .draw(
+                &mut graphics_layer.graphics_command_list,
+                &graphics_layer.backbuffer_rtv,
+                &graphics_layer.resources,
+                &mut gpu_heap,
+            )
+            .unwrap();
+
+        gpu_heap.end_frame();
+
+        let draws = match &graphics_layer.graphics_command_list {
+            GraphicsCommandList::Recording(recorder) => recorder.draws(),
+            _ => panic!("expected a recording command list"),
+        };
+
+        // the lines of the cross and then the glyphs of the label on top
+        assert_eq!(draws.len(), 2);
+        assert_eq!(draws[0].instance_count, 2);
+        assert_eq!(draws[1].instance_count, 3);
+
+        debug_draw.clear();
+        assert!(debug_draw.primitives.is_empty());
+
+        debug_draw.release(&mut graphics_layer.resources);
+    }
+}
@@ -1,15 +1,18 @@
 mod gameplay;
 mod pause;
 
+use crate::debug_draw::DebugDraw;
 use crate::gamestates::gameplay::draw_gameplay_state;
 use crate::gamestates::gameplay::{update_gameplay_state, GameplayState};
-use crate::gamestates::pause::draw_pause_state;
+use crate::gamestates::pause::{draw_pause_state, pause_backdrop_effects};
 use crate::gamestates::pause::{update_pause_state, PauseState};
+use crate::profiler::profile_scope;
+use crate::virtual_canvas::VirtualCanvas;
 use graphics_device::GraphicsCommandList;
 use graphics_device::GraphicsDevice;
-use graphics_device::LinearAllocatorState;
-use graphics_device::MappedGpuData;
-use graphics_device::RenderTargetView;
+use graphics_device::GraphicsError;
+use graphics_device::LinearAllocator;
+use graphics_device::ResourceRegistry;
 use os_window::WindowMessages;
 
 pub struct UpdateBehaviourDesc {
@@ -27,9 +30,10 @@ pub enum GameStateType {
     Gameplay,
 }
 
-pub enum GameStateData<'a> {
-    Gameplay(GameplayState<'a>),
-    Pause(PauseState<'a>),
+// the states keep their scenes and animations in both frame datas and are boxed to keep the stack entries small
+pub enum GameStateData {
+    Gameplay(Box<GameplayState>),
+    Pause(Box<PauseState>),
 }
 
 pub enum GameStateTransitionState {
@@ -38,26 +42,37 @@ pub enum GameStateTransitionState {
     ReturnToPreviousState,
 }
 
-pub fn execute_possible_state_transition<'a>(
+pub fn execute_possible_state_transition(
     state_transition: GameStateTransitionState,
-    game_state_stack: &mut Vec<GameStateData<'a>>,
-    graphics_device: &'a GraphicsDevice,
+    game_state_stack: &mut Vec<GameStateData>,
+    graphics_device: &GraphicsDevice,
+    resources: &mut ResourceRegistry,
 ) {
     // we are starting a new frame, do we need to transition to a new state?
     match state_transition {
         GameStateTransitionState::TransitionToNewState(x) => match x {
             GameStateType::Gameplay => {
-                game_state_stack.push(GameStateData::Gameplay(GameplayState::new(graphics_device)));
+                game_state_stack.push(GameStateData::Gameplay(Box::new(GameplayState::new(
+                    graphics_device,
+                    resources,
+                ))));
             }
 
             GameStateType::Pause => {
-                game_state_stack.push(GameStateData::Pause(PauseState::new(graphics_device)));
+                game_state_stack.push(GameStateData::Pause(Box::new(PauseState::new(
+                    graphics_device,
+                    resources,
+                ))));
             }
         },
 
         GameStateTransitionState::ReturnToPreviousState => {
-            // remove the top most state from the stack
-            game_state_stack.pop();
+            // remove the top most state from the stack and release the resources it created
+            match game_state_stack.pop() {
+                Some(GameStateData::Gameplay(x)) => x.static_data.release(resources),
+                Some(GameStateData::Pause(x)) => x.static_data.release(resources),
+                None => {}
+            }
         }
 
         GameStateTransitionState::Unchanged => {}
@@ -67,9 +82,12 @@ pub fn execute_possible_state_transition<'a>(
 pub fn update_gamestate_stack(
     dt: f32,
     update_frame_number: u64,
-    game_state_stack: &mut Vec<GameStateData>,
+    game_state_stack: &mut [GameStateData],
     messages: &mut Vec<WindowMessages>,
+    debug_draw: &mut DebugDraw,
 ) -> GameStateTransitionState {
+    let _zone = profile_scope("update_gamestate_stack");
+
     let mut game_state_transtion = GameStateTransitionState::Unchanged;
 
     for state in game_state_stack.iter_mut().rev() {
@@ -81,7 +99,16 @@ pub fn update_gamestate_stack(
                     (&game_state.frame_data1, &mut game_state.frame_data0)
                 };
 
-                update_gameplay_state(prev_frame_params, frame_params, &messages, dt)
+                let _zone = profile_scope("update_gameplay_state");
+
+                update_gameplay_state(
+                    &game_state.static_data,
+                    prev_frame_params,
+                    frame_params,
+                    &messages,
+                    debug_draw,
+                    dt,
+                )
             }
 
             GameStateData::Pause(game_state) => {
@@ -91,7 +118,15 @@ pub fn update_gamestate_stack(
                     (&game_state.frame_data1, &mut game_state.frame_data0)
                 };
 
-                update_pause_state(prev_frame_params, frame_params, &messages, dt)
+                let _zone = profile_scope("update_pause_state");
+
+                update_pause_state(
+                    &game_state.static_data,
+                    prev_frame_params,
+                    frame_params,
+                    &messages,
+                    dt,
+                )
             }
         };
 
@@ -119,10 +154,15 @@ pub fn draw_gamestate_stack(
     game_state_stack: &[GameStateData],
     frame_number: u64,
     command_list: &mut GraphicsCommandList,
-    backbuffer_rtv: &RenderTargetView,
-    gpu_heap_data: &MappedGpuData,
-    gpu_heap_state: &mut LinearAllocatorState,
-) {
+    canvas: &VirtualCanvas,
+    resources: &ResourceRegistry,
+    gpu_heap: &mut LinearAllocator,
+    debug_draw: &DebugDraw,
+) -> Result<(), GraphicsError> {
+    let _zone = profile_scope("draw_gamestate_stack");
+
+    let canvas_rtv = &canvas.render_target.rtv;
+
     for state in game_state_stack.iter() {
         match state {
             GameStateData::Gameplay(game_state) => {
@@ -132,14 +172,16 @@ pub fn draw_gamestate_stack(
                     &game_state.frame_data0
                 };
 
+                let _zone = profile_scope("draw_gameplay_state");
+
                 draw_gameplay_state(
                     &game_state.static_data,
                     frame_params,
                     command_list,
-                    backbuffer_rtv,
-                    gpu_heap_data,
-                    gpu_heap_state,
-                );
+                    canvas_rtv,
+                    resources,
+                    gpu_heap,
+                )?;
             }
 
             GameStateData::Pause(x) => {
@@ -149,15 +191,34 @@ pub fn draw_gamestate_stack(
                     &x.frame_data0
                 };
 
+                // the effects are applied to all states below the pause state
+                {
+                    let _zone = profile_scope("post_process");
+
+                    canvas.apply_post_process(
+                        &pause_backdrop_effects(frame_params),
+                        command_list,
+                        resources,
+                        gpu_heap,
+                    )?;
+                }
+
+                let _zone = profile_scope("draw_pause_state");
+
                 draw_pause_state(
                     &x.static_data,
                     frame_params,
                     command_list,
-                    backbuffer_rtv,
-                    gpu_heap_data,
-                    gpu_heap_state,
-                )
+                    canvas_rtv,
+                    resources,
+                    gpu_heap,
+                )?;
             }
         };
     }
+
+    // on top of all game states
+    let _zone = profile_scope("debug_draw");
+
+    debug_draw.draw(command_list, canvas_rtv, resources, gpu_heap)
 }
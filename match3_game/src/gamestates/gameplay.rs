@@ -1,61 +1,185 @@
 use super::{GameStateTransitionState, GameStateType, UpdateBehaviourDesc};
-use crate::GameSpaceQuadData;
-use crate::Int2;
-use crate::{Float4, HeapAlloc};
+use crate::debug_draw::DebugDraw;
+use crate::nine_slice::{BorderInsets, NineSlice, PanelRect};
+use crate::particles::{ParticleEffects, ParticleSystem};
+use crate::random::*;
+use crate::scene::{Scene, SceneNodeId};
+use crate::sprite_batch::{Sprite, SpriteBatch};
+use crate::Float2;
+use crate::Float4;
 
 use graphics_device::*;
 use os_window::WindowMessages;
+use tweening::{Easing, Tween, TweenPlayer};
 
-pub struct GameplayStateStaticData<'a> {
-    game_space_quad_opaque_pso: PipelineStateObject<'a>,
-    bg_texture: Texture<'a>,
-    border_top_texture: Texture<'a>,
-    border_bottom_texture: Texture<'a>,
-    texture_item_background: Texture<'a>,
-}
+const LAYER_BACKGROUND: i32 = 0;
+const LAYER_BORDER: i32 = 1;
+const LAYER_ITEMS: i32 = 2;
+const LAYER_PARTICLES: i32 = 3;
 
-impl GameplayStateStaticData<'_> {
-    pub fn new<'a>(device: &'a GraphicsDevice) -> GameplayStateStaticData<'a> {
-        let game_space_quad_opaque_pso: PipelineStateObject = create_pso(
-            device,
-            PipelineStateObjectDesc {
-                shader_name: "target_data/shaders/game_space_quad",
-                premultiplied_alpha: true,
-            },
-        );
+// cells are 90 pixels large with a 1 pixel gap between them
+const CELL_SIZE: f32 = 90.0;
 
-        let texture_bg = load_dds_from_file(
-            "target_data/textures/KawaiiCookieAssetPack/gameplay_background_tall.dds",
-            device,
-        )
-        .unwrap();
+// the particles are simulated with their own generator so that effects don't change the gameplay random numbers
+const PARTICLE_SEED: u64 = 0x6d61_7463_6833;
 
-        let texture_border_top = load_dds_from_file(
-            "target_data/textures/KawaiiCookieAssetPack/gameplay_top_border.dds",
-            device,
-        )
-        .unwrap();
+// the channels of the cell selection tween
+const CHANNEL_CELL_SCALE: usize = 0;
+const CHANNEL_CELL_TINT: usize = 1;
 
-        let texture_border_bottom = load_dds_from_file(
-            "target_data/textures/KawaiiCookieAssetPack/gameplay_bottom_border.dds",
-            device,
+const CELL_TINT_SELECTED: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+pub struct GameplayStateStaticData {
+    game_space_quad_batch_pso: PsoHandle,
+    bg_texture: TextureHandle,
+    border_top: NineSlice,
+    border_bottom: NineSlice,
+    texture_item_background: TextureHandle,
+    particle_effects: ParticleEffects,
+    cookie_clear_effect: usize,
+
+    // a selected cell pops up from a smaller size and blends to the selected tint
+    cell_select: Tween,
+}
+
+impl GameplayStateStaticData {
+    pub fn new(
+        device: &GraphicsDevice,
+        resources: &mut ResourceRegistry,
+    ) -> GameplayStateStaticData {
+        let game_space_quad_batch_pso = resources
+            .load_pso(
+                device,
+                PipelineStateObjectDesc {
+                    shader_name: "target_data/shaders/game_space_quad_batch",
+                    blend_mode: BlendMode::Alpha,
+                    sampler: SamplerDesc::default(),
+                },
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let texture_bg = resources
+            .load_texture(
+                device,
+                "target_data/textures/KawaiiCookieAssetPack/gameplay_background_tall.dds",
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let texture_border_top = resources
+            .load_texture(
+                device,
+                "target_data/textures/KawaiiCookieAssetPack/gameplay_top_border.dds",
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let texture_border_bottom = resources
+            .load_texture(
+                device,
+                "target_data/textures/KawaiiCookieAssetPack/gameplay_bottom_border.dds",
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let texture_item_background = resources
+            .load_texture(
+                device,
+                "target_data/textures/KawaiiCookieAssetPack/gameplay_item_background.dds",
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        // only the scalloped edges facing the board keep their size, the rest of the border stretches vertically
+        let border_top = NineSlice::new(
+            resources,
+            texture_border_top,
+            BorderInsets {
+                left: 0.0,
+                right: 0.0,
+                top: 0.0,
+                bottom: 50.0,
+            },
         )
-        .unwrap();
+        .unwrap_or_else(|error| panic!("{}", error));
+
+        let border_bottom = NineSlice::new(
+            resources,
+            texture_border_bottom,
+            BorderInsets {
+                left: 0.0,
+                right: 0.0,
+                top: 45.0,
+                bottom: 0.0,
+            },
+        )
+        .unwrap_or_else(|error| panic!("{}", error));
 
-        let texture_item_background = load_dds_from_file(
-            "target_data/textures/KawaiiCookieAssetPack/gameplay_item_background.dds",
+        let particle_effects = ParticleEffects::load(
             device,
+            resources,
+            "target_data/particles/gameplay.particles",
         )
-        .unwrap();
+        .unwrap_or_else(|error| panic!("{}", error));
+
+        let cookie_clear_effect = particle_effects
+            .find("cookie_clear")
+            .expect("gameplay.particles is missing the cookie_clear effect");
+
+        let cell_select = Tween::Parallel(vec![
+            Tween::vector(
+                CHANNEL_CELL_SCALE,
+                [0.6, 0.6],
+                [1.0, 1.0],
+                0.35,
+                Easing::BackOut,
+            ),
+            Tween::color(
+                CHANNEL_CELL_TINT,
+                [1.0, 1.0, 1.0, 1.0],
+                CELL_TINT_SELECTED,
+                0.25,
+                Easing::QuadOut,
+            ),
+        ]);
 
         GameplayStateStaticData {
-            game_space_quad_opaque_pso,
+            game_space_quad_batch_pso,
             bg_texture: texture_bg,
-            border_top_texture: texture_border_top,
-            border_bottom_texture: texture_border_bottom,
+            border_top,
+            border_bottom,
             texture_item_background,
+            particle_effects,
+            cookie_clear_effect,
+            cell_select,
         }
     }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        resources
+            .release_pso(self.game_space_quad_batch_pso)
+            .unwrap();
+        resources.release_texture(self.bg_texture).unwrap();
+        resources.release_texture(self.border_top.texture).unwrap();
+        resources
+            .release_texture(self.border_bottom.texture)
+            .unwrap();
+        resources
+            .release_texture(self.texture_item_background)
+            .unwrap();
+        self.particle_effects.release(resources);
+    }
+}
+
+// the nodes of the scene that change while playing
+#[derive(Clone)]
+struct GameplaySceneNodes {
+    // row by row, in the same order as the grid
+    cells: Vec<SceneNodeId>,
+    particles: SceneNodeId,
+}
+
+#[derive(Clone, Copy)]
+struct CellAnimation {
+    row: usize,
+    column: usize,
+    player: TweenPlayer,
 }
 
 pub struct GameplayStateFrameData {
@@ -63,49 +187,289 @@ pub struct GameplayStateFrameData {
     grid: [[bool; 5]; 6],
 
     rnd_state: Xoroshiro128Rng,
+
+    particles: ParticleSystem,
+
+    // cells that have been selected recently, removed once their tween is finished
+    cell_animations: Vec<CellAnimation>,
+
+    // last known position of the cursor in game space
+    mouse_position: Option<Float2>,
+
+    // everything that is drawn, updated from the state above at the end of each update
+    scene: Scene,
+    scene_nodes: GameplaySceneNodes,
 }
 
-pub struct GameplayState<'a> {
-    pub static_data: GameplayStateStaticData<'a>,
+pub struct GameplayState {
+    pub static_data: GameplayStateStaticData,
     pub frame_data0: GameplayStateFrameData,
     pub frame_data1: GameplayStateFrameData,
 }
 
 impl GameplayStateFrameData {
-    pub fn new() -> GameplayStateFrameData {
+    pub fn new(static_data: &GameplayStateStaticData) -> GameplayStateFrameData {
+        let (scene, scene_nodes) = build_scene(static_data);
+
         GameplayStateFrameData {
             grid: { [[false; 5]; 6] },
             rnd_state: Xoroshiro128Rng {
                 state: [23_480_923_840_238, 459],
             },
+            particles: ParticleSystem::new(PARTICLE_SEED),
+            cell_animations: Vec::new(),
+            mouse_position: None,
+            scene,
+            scene_nodes,
         }
     }
 }
 
-impl GameplayState<'_> {
-    pub fn new<'a>(device: &'a GraphicsDevice) -> GameplayState<'a> {
+impl GameplayState {
+    pub fn new(device: &GraphicsDevice, resources: &mut ResourceRegistry) -> GameplayState {
+        let static_data = GameplayStateStaticData::new(device, resources);
+        let frame_data0 = GameplayStateFrameData::new(&static_data);
+        let frame_data1 = GameplayStateFrameData::new(&static_data);
+
         GameplayState {
-            static_data: GameplayStateStaticData::new(device),
-            frame_data0: GameplayStateFrameData::new(),
-            frame_data1: GameplayStateFrameData::new(),
+            static_data,
+            frame_data0,
+            frame_data1,
         }
     }
 }
 
-struct Xoroshiro128Rng {
-    state: [u64; 2],
+// the tweens store colors as rgba arrays
+fn color_from_array(color: [f32; 4]) -> Float4 {
+    Float4 {
+        x: color[0],
+        y: color[1],
+        z: color[2],
+        a: color[3],
+    }
+}
+
+// game space position of the bottom-left corner of a cell of the grid, rows go down from the top of the board
+fn cell_position(row: usize, column: usize) -> Float2 {
+    Float2 {
+        x: 45.0 + (column * 91) as f32,
+        y: 960.0 - 330.0 + 45.0 - (row * 91) as f32,
+    }
+}
+
+// the background, the borders, the board with one node per cell and a node for the particles
+// the layers of the nodes decide the draw order, the sprites inside of the nodes are all on layer 0
+fn build_scene(static_data: &GameplayStateStaticData) -> (Scene, GameplaySceneNodes) {
+    let mut scene = Scene::new();
+
+    let background = scene.add_node(None);
+    scene.node_mut(background).layer = LAYER_BACKGROUND;
+    scene.node_mut(background).sprites.push(Sprite::new(
+        static_data.bg_texture,
+        0,
+        Float2 { x: 0.0, y: 0.0 },
+        Float2 { x: 540.0, y: 960.0 },
+    ));
+
+    let white = Float4 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+        a: 1.0,
+    };
+
+    let border = scene.add_node(None);
+    scene.node_mut(border).layer = LAYER_BORDER;
+
+    let mut border_sprites = static_data.border_top.sprites(
+        0,
+        white,
+        PanelRect {
+            x: 0.0,
+            y: 960.0 - 184.0,
+            width: 540.0,
+            height: 184.0,
+        },
+    );
+
+    border_sprites.extend(static_data.border_bottom.sprites(
+        0,
+        white,
+        PanelRect {
+            x: 0.0,
+            y: 0.0,
+            width: 540.0,
+            height: 184.0,
+        },
+    ));
+
+    scene.node_mut(border).sprites = border_sprites;
+
+    // the cells are placed relative to the bottom-left corner of the board and scale around their centers
+    let board_origin = cell_position(5, 0);
+
+    let board = scene.add_node(None);
+    scene.node_mut(board).layer = LAYER_ITEMS;
+    scene.node_mut(board).transform.position = board_origin;
+
+    let mut cells = Vec::with_capacity(6 * 5);
+
+    for row in 0..6 {
+        for column in 0..5 {
+            let position = cell_position(row, column);
+
+            let cell = scene.add_node(Some(board));
+            cells.push(cell);
+
+            scene.node_mut(cell).transform.position = Float2 {
+                x: position.x - board_origin.x + CELL_SIZE * 0.5,
+                y: position.y - board_origin.y + CELL_SIZE * 0.5,
+            };
+            scene.node_mut(cell).sprites.push(Sprite::new(
+                static_data.texture_item_background,
+                0,
+                Float2 {
+                    x: -CELL_SIZE * 0.5,
+                    y: -CELL_SIZE * 0.5,
+                },
+                Float2 {
+                    x: CELL_SIZE,
+                    y: CELL_SIZE,
+                },
+            ));
+        }
+    }
+
+    // the particles are simulated in game space
+    let particles = scene.add_node(None);
+    scene.node_mut(particles).layer = LAYER_PARTICLES;
+
+    (scene, GameplaySceneNodes { cells, particles })
 }
 
-fn rnd_next_u64(rnd: &mut Xoroshiro128Rng) -> u64 {
-    let s0 = rnd.state[0];
-    let mut s1 = rnd.state[1];
-    let result = s0.wrapping_add(s1);
+// selected cells are tinted green, the particle sprites are replaced every update
+fn update_scene(static_data: &GameplayStateStaticData, frame_data: &mut GameplayStateFrameData) {
+    let nodes = &frame_data.scene_nodes;
 
-    s1 ^= s0;
-    rnd.state[0] = s0.rotate_left(24) ^ s1 ^ (s1 << 16);
-    rnd.state[1] = s1.rotate_left(37);
+    for (cell, selected) in nodes.cells.iter().zip(frame_data.grid.iter().flatten()) {
+        let node = frame_data.scene.node_mut(*cell);
 
-    result
+        node.tint = color_from_array(if *selected {
+            CELL_TINT_SELECTED
+        } else {
+            [1.0, 1.0, 1.0, 1.0]
+        });
+        node.transform.scale = Float2 { x: 1.0, y: 1.0 };
+    }
+
+    // cells that are being selected right now follow their tween instead
+    for animation in frame_data.cell_animations.iter() {
+        let values = animation.player.values(&static_data.cell_select);
+        let node = frame_data
+            .scene
+            .node_mut(nodes.cells[animation.row * 5 + animation.column]);
+
+        if let Some(scale) = values.vector(CHANNEL_CELL_SCALE) {
+            node.transform.scale = Float2 {
+                x: scale[0],
+                y: scale[1],
+            };
+        }
+
+        if let Some(tint) = values.color(CHANNEL_CELL_TINT) {
+            node.tint = color_from_array(tint);
+        }
+    }
+
+    frame_data.scene.node_mut(nodes.particles).sprites = frame_data
+        .particles
+        .sprites(&static_data.particle_effects, 0);
+}
+
+// the cell the game space position is inside of, positions in the gaps between cells don't hit anything
+fn cell_at(position: Float2) -> Option<(usize, usize)> {
+    for row in 0..6 {
+        for column in 0..5 {
+            let cell = cell_position(row, column);
+
+            if position.x >= cell.x
+                && position.x < cell.x + CELL_SIZE
+                && position.y >= cell.y
+                && position.y < cell.y + CELL_SIZE
+            {
+                return Some((row, column));
+            }
+        }
+    }
+
+    None
+}
+
+// the hit boxes of the cells, the cell under the cursor and the cursor position
+fn debug_draw_board(frame_data: &GameplayStateFrameData, debug_draw: &mut DebugDraw) {
+    if !debug_draw.visible {
+        return;
+    }
+
+    let green = Float4 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+        a: 1.0,
+    };
+
+    let yellow = Float4 {
+        x: 1.0,
+        y: 1.0,
+        z: 0.0,
+        a: 1.0,
+    };
+
+    let red = Float4 {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+        a: 1.0,
+    };
+
+    for row in 0..6 {
+        for column in 0..5 {
+            debug_draw.rect(
+                cell_position(row, column),
+                Float2 {
+                    x: CELL_SIZE,
+                    y: CELL_SIZE,
+                },
+                green,
+            );
+        }
+    }
+
+    let mouse = match frame_data.mouse_position {
+        Some(x) => x,
+        None => return,
+    };
+
+    if let Some((row, column)) = cell_at(mouse) {
+        let cell = cell_position(row, column);
+        let center = Float2 {
+            x: cell.x + CELL_SIZE * 0.5,
+            y: cell.y + CELL_SIZE * 0.5,
+        };
+
+        debug_draw.circle(center, CELL_SIZE * 0.5, yellow);
+        debug_draw.line(mouse, center, yellow);
+    }
+
+    debug_draw.cross(mouse, 16.0, red);
+    debug_draw.text(
+        Float2 {
+            x: mouse.x + 10.0,
+            y: mouse.y - 10.0,
+        },
+        &format!("{}, {}", mouse.x, mouse.y),
+        red,
+    );
 }
 
 fn count_selected_fields(grid: &[[bool; 5]; 6]) -> i32 {
@@ -123,19 +487,46 @@ fn count_selected_fields(grid: &[[bool; 5]; 6]) -> i32 {
 }
 
 pub fn update_gameplay_state(
+    static_data: &GameplayStateStaticData,
     prev_frame_data: &GameplayStateFrameData,
     frame_data: &mut GameplayStateFrameData,
     messages: &[WindowMessages],
-    _dt: f32,
+    debug_draw: &mut DebugDraw,
+    dt: f32,
 ) -> UpdateBehaviourDesc {
     // copy the state of the previous state as starting point
     frame_data.grid = prev_frame_data.grid;
     frame_data.rnd_state.state = prev_frame_data.rnd_state.state;
+    frame_data.particles.clone_from(&prev_frame_data.particles);
+    frame_data
+        .cell_animations
+        .clone_from(&prev_frame_data.cell_animations);
+    frame_data.mouse_position = prev_frame_data.mouse_position;
+    frame_data.scene.clone_from(&prev_frame_data.scene);
+    frame_data
+        .scene_nodes
+        .clone_from(&prev_frame_data.scene_nodes);
+
+    // particles and animations started this frame are drawn at their start
+    frame_data
+        .particles
+        .update(&static_data.particle_effects.effects, dt);
+
+    for animation in frame_data.cell_animations.iter_mut() {
+        animation.player.update(&static_data.cell_select, dt);
+    }
+
+    frame_data
+        .cell_animations
+        .retain(|x| !x.player.is_finished(&static_data.cell_select));
 
     for x in messages {
         match x {
             WindowMessages::MousePositionChanged(pos) => {
-                println!("cursor position changed: x {0}, y {1}", pos.x, pos.y);
+                frame_data.mouse_position = Some(Float2 {
+                    x: pos.x as f32,
+                    y: pos.y as f32,
+                });
             }
 
             WindowMessages::MouseLeftButtonDown => {
@@ -144,6 +535,27 @@ pub fn update_gameplay_state(
                 let rnd_col = (rnd_next_u64(&mut frame_data.rnd_state) % 5) as usize;
 
                 frame_data.grid[rnd_row][rnd_col] = true;
+
+                // selecting a cell again restarts its animation
+                frame_data
+                    .cell_animations
+                    .retain(|x| x.row != rnd_row || x.column != rnd_col);
+                frame_data.cell_animations.push(CellAnimation {
+                    row: rnd_row,
+                    column: rnd_col,
+                    player: TweenPlayer::new(),
+                });
+
+                let cell = cell_position(rnd_row, rnd_col);
+
+                frame_data.particles.start_effect(
+                    &static_data.particle_effects.effects,
+                    static_data.cookie_clear_effect,
+                    Float2 {
+                        x: cell.x + CELL_SIZE * 0.5,
+                        y: cell.y + CELL_SIZE * 0.5,
+                    },
+                );
             }
 
             WindowMessages::MouseLeftButtonUp => {
@@ -158,15 +570,24 @@ pub fn update_gameplay_state(
                 println!("mouse:focus lost");
             }
 
+            WindowMessages::KeyDown(_x) => {}
+
             WindowMessages::WindowClosed => {
                 panic!();
             } // this should never happen, handled by higher level code
             WindowMessages::WindowCreated(_x) => {
                 panic!();
             } // this should never happen
+            WindowMessages::WindowResized(_x) => {
+                panic!();
+            } // this should never happen, handled by higher level code
         }
     }
 
+    update_scene(static_data, frame_data);
+
+    debug_draw_board(frame_data, debug_draw);
+
     // count the number of selected fields
     // open the pause after 5
     // and close the game after 10
@@ -197,125 +618,268 @@ pub fn draw_gameplay_state(
     static_data: &GameplayStateStaticData,
     frame_params: &GameplayStateFrameData,
     command_list: &mut GraphicsCommandList,
-    backbuffer_rtv: &RenderTargetView,
-    gpu_heap_data: &super::super::MappedGpuData,
-    gpu_heap_state: &mut LinearAllocatorState,
-) {
+    canvas_rtv: &RenderTargetView,
+    resources: &ResourceRegistry,
+    gpu_heap: &mut LinearAllocator,
+) -> Result<(), GraphicsError> {
     let color: [f32; 4] = [0.0, 0.2, 0.4, 1.0];
 
-    begin_render_pass_and_clear(command_list, color, backbuffer_rtv);
+    begin_render_pass_and_clear(command_list, color, canvas_rtv)?;
 
-    bind_pso(command_list, &static_data.game_space_quad_opaque_pso);
+    // the scene sorts the background, the board and the particles by their layers
+    let mut sprite_batch = SpriteBatch::new();
+    frame_params.scene.draw(&mut sprite_batch);
 
-    // draw the background
-    {
-        bind_texture(command_list, 0, &static_data.bg_texture.srv);
+    sprite_batch.submit(
+        command_list,
+        resources,
+        static_data.game_space_quad_batch_pso,
+        gpu_heap,
+    )
+}
 
-        let obj_alloc = HeapAlloc::new(
-            GameSpaceQuadData {
-                color: Float4 {
-                    x: 1.0,
-                    y: 1.0,
-                    z: 1.0,
-                    a: 1.0,
-                },
-                size_pixels: Int2 { x: 540, y: 960 },
-                position_bottom_left: Int2 { x: 0, y: 0 },
-            },
-            gpu_heap_data,
-            gpu_heap_state,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_device::command_recorder::RecordedDraw;
+    use graphics_device::software_rasterizer::QuadConstants;
+
+    // updates a frame starting from the grid with the messages and records the draws of the result
+    fn record_gameplay_frame(
+        grid: [[bool; 5]; 6],
+        messages: &[WindowMessages],
+        show_debug_draw: bool,
+    ) -> Vec<RecordedDraw> {
+        let mut graphics_layer: GraphicsDeviceLayer =
+            create_device_graphics_layer_recording(540, 960).unwrap();
+
+        let static_data =
+            GameplayStateStaticData::new(&graphics_layer.device, &mut graphics_layer.resources);
+
+        let mut debug_draw =
+            DebugDraw::new(&graphics_layer.device, &mut graphics_layer.resources).unwrap();
+        debug_draw.visible = show_debug_draw;
+
+        let mut prev_frame_data = GameplayStateFrameData::new(&static_data);
+        prev_frame_data.grid = grid;
+
+        let mut frame_data = GameplayStateFrameData::new(&static_data);
+
+        update_gameplay_state(
+            &static_data,
+            &prev_frame_data,
+            &mut frame_data,
+            messages,
+            &mut debug_draw,
+            1.0 / 60.0,
         );
 
-        bind_constant(command_list, 0, &obj_alloc);
+        let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 64 * 1024);
+        gpu_heap.begin_frame(0);
+
+        draw_gameplay_state(
+            &static_data,
+            &frame_data,
+            &mut graphics_layer.graphics_command_list,
+            &graphics_layer.backbuffer_rtv,
+            &graphics_layer.resources,
+            &mut gpu_heap,
+        )
+        .unwrap();
+
+        debug_draw
+            .draw(
+                &mut graphics_layer.graphics_command_list,
+                &graphics_layer.backbuffer_rtv,
+                &graphics_layer.resources,
+                &mut gpu_heap,
+            )
+            .unwrap();
+
+        gpu_heap.end_frame();
 
-        draw_vertices(command_list, 4);
+        match &graphics_layer.graphics_command_list {
+            GraphicsCommandList::Recording(recorder) => recorder.draws(),
+            _ => panic!("expected a recording command list"),
+        }
+    }
+
+    #[test]
+    fn gameplay_draws_tinted_item_backgrounds() {
+        let mut grid = [[false; 5]; 6];
+        grid[0][0] = true;
+        grid[2][3] = true;
+        grid[5][4] = true;
+
+        let draws = record_gameplay_frame(grid, &[], false);
+
+        // all 30 cells share a texture and are drawn with a single instanced draw
+        let item_draws: Vec<&RecordedDraw> = draws
+            .iter()
+            .filter(|x| {
+                x.texture(0)
+                    == Some(
+                        "target_data/textures/KawaiiCookieAssetPack/gameplay_item_background.dds",
+                    )
+            })
+            .collect();
+
+        assert_eq!(item_draws.len(), 1);
+        assert_eq!(item_draws[0].instance_count, 30);
+
+        let items = item_draws[0].quad_instances(0);
+        assert_eq!(items.len(), 30);
+
+        let green = [0.0, 1.0, 0.0, 1.0];
+
+        let selected_count = items.iter().filter(|x| x.color() == green).count();
+
+        assert_eq!(selected_count, 3);
+
+        // the first cell is drawn in the top left corner of the board
+        match &items[0] {
+            QuadConstants::GameSpaceSprite {
+                color,
+                uv_rect,
+                position,
+                size,
+                pivot,
+                rotation,
+            } => {
+                assert_eq!(*color, green);
+                assert_eq!(*uv_rect, [0.0, 0.0, 1.0, 1.0]);
+                assert_eq!(*position, [45.0, 960.0 - 330.0 + 45.0]);
+                assert_eq!(*size, [90.0, 90.0]);
+                assert_eq!(*pivot, [0.0, 0.0]);
+                assert_eq!(*rotation, [1.0, 0.0]);
+            }
+            _ => panic!("item backgrounds should be drawn in game space"),
+        }
     }
 
-    {
-        bind_texture(command_list, 0, &static_data.border_top_texture.srv);
+    #[test]
+    fn gameplay_background_is_drawn_before_the_board() {
+        let draws = record_gameplay_frame([[false; 5]; 6], &[], false);
 
-        let obj_alloc = HeapAlloc::new(
-            GameSpaceQuadData {
-                color: Float4 {
-                    x: 1.0,
-                    y: 1.0,
-                    z: 1.0,
-                    a: 1.0,
-                },
-                size_pixels: Int2 { x: 540, y: 184 },
-                position_bottom_left: Int2 { x: 0, y: 960 - 184 },
-            },
-            gpu_heap_data,
-            gpu_heap_state,
+        // background, both borders and the item backgrounds
+        assert_eq!(draws.len(), 4);
+
+        assert_eq!(
+            draws[0].texture(0),
+            Some("target_data/textures/KawaiiCookieAssetPack/gameplay_background_tall.dds")
         );
 
-        bind_constant(command_list, 0, &obj_alloc);
+        assert_eq!(
+            draws[3].texture(0),
+            Some("target_data/textures/KawaiiCookieAssetPack/gameplay_item_background.dds")
+        );
+    }
+
+    #[test]
+    fn selecting_a_cell_starts_the_cookie_clear_effect() {
+        let draws = record_gameplay_frame(
+            [[false; 5]; 6],
+            &[WindowMessages::MouseLeftButtonDown],
+            false,
+        );
+
+        // the particles are drawn last, on top of the board
+        let particles = draws.last().unwrap();
+
+        assert_eq!(draws.len(), 5);
+        assert_eq!(
+            particles.texture(0),
+            Some("target_data/particles/particles.dds")
+        );
 
-        draw_vertices(command_list, 4);
+        // the crumbs and sparkles that are spawned right away
+        assert_eq!(particles.instance_count, 12 + 4);
     }
 
-    {
-        bind_texture(command_list, 0, &static_data.border_bottom_texture.srv);
+    #[test]
+    fn selected_cells_pop_into_place() {
+        let draws = record_gameplay_frame(
+            [[false; 5]; 6],
+            &[WindowMessages::MouseLeftButtonDown],
+            false,
+        );
 
-        let obj_alloc = HeapAlloc::new(
-            GameSpaceQuadData {
-                color: Float4 {
-                    x: 1.0,
-                    y: 1.0,
-                    z: 1.0,
-                    a: 1.0,
-                },
-                size_pixels: Int2 { x: 540, y: 184 },
-                position_bottom_left: Int2 { x: 0, y: 0 },
-            },
-            gpu_heap_data,
-            gpu_heap_state,
+        let items = draws[3].quad_instances(0);
+        assert_eq!(items.len(), 30);
+
+        let popping: Vec<&QuadConstants> = items
+            .iter()
+            .filter(|x| match x {
+                QuadConstants::GameSpaceSprite { size, .. } => size[0] < CELL_SIZE,
+                _ => false,
+            })
+            .collect();
+
+        assert_eq!(popping.len(), 1);
+
+        // the animation starts untinted at 60% of the size, scaled around the center of the cell
+        match popping[0] {
+            QuadConstants::GameSpaceSprite {
+                color,
+                position,
+                size,
+                ..
+            } => {
+                assert_eq!(*color, [1.0, 1.0, 1.0, 1.0]);
+                assert!((size[0] - CELL_SIZE * 0.6).abs() < 0.001);
+
+                let offset_in_cell = (position[0] - 45.0) % 91.0;
+                assert!((offset_in_cell - CELL_SIZE * 0.2).abs() < 0.001);
+            }
+            _ => panic!("item backgrounds should be drawn in game space"),
+        }
+    }
+
+    #[test]
+    fn cells_are_hit_inside_of_their_bounds() {
+        let cell = cell_position(2, 3);
+
+        assert_eq!(
+            cell_at(Float2 {
+                x: cell.x + 45.0,
+                y: cell.y + 45.0
+            }),
+            Some((2, 3))
         );
 
-        bind_constant(command_list, 0, &obj_alloc);
+        assert_eq!(cell_at(cell), Some((2, 3)));
 
-        draw_vertices(command_list, 4);
+        // the gap between two cells and the border outside of the board
+        assert_eq!(
+            cell_at(Float2 {
+                x: cell.x + 90.5,
+                y: cell.y
+            }),
+            None
+        );
+        assert_eq!(cell_at(Float2 { x: 10.0, y: 10.0 }), None);
     }
 
-    bind_pso(command_list, &static_data.game_space_quad_opaque_pso);
-    bind_texture(command_list, 0, &static_data.texture_item_background.srv);
-
-    for (y, row) in frame_params.grid.iter().enumerate() {
-        for (x, column) in row.iter().enumerate() {
-            let x_offset_in_pixels = (x * 91) as i32;
-            let y_offset_in_pixels = (y * 91) as i32;
-
-            // allocate the constants for this draw call
-            let obj_alloc = HeapAlloc::new(
-                GameSpaceQuadData {
-                    color: if !column {
-                        Float4 {
-                            x: 1.0,
-                            y: 1.0,
-                            z: 1.0,
-                            a: 1.0,
-                        }
-                    } else {
-                        Float4 {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                            a: 1.0,
-                        }
-                    },
-                    size_pixels: Int2 { x: 90, y: 90 },
-                    position_bottom_left: Int2 {
-                        x: 45 + x_offset_in_pixels,
-                        y: 960 - 330 + 45 - y_offset_in_pixels,
-                    },
+    #[cfg(feature = "debug_draw")]
+    #[test]
+    fn debug_draw_shows_hit_boxes_and_the_cursor() {
+        let cell = cell_position(0, 0);
+
+        let draws = record_gameplay_frame(
+            [[false; 5]; 6],
+            &[WindowMessages::MousePositionChanged(
+                os_window::MousePositionChangedData {
+                    x: cell.x as i32 + 10,
+                    y: cell.y as i32 + 10,
                 },
-                gpu_heap_data,
-                gpu_heap_state,
-            );
+            )],
+            true,
+        );
 
-            bind_constant(command_list, 0, &obj_alloc);
+        // the lines of the overlay and the label are drawn after the board
+        assert_eq!(draws.len(), 6);
 
-            draw_vertices(command_list, 4);
-        }
+        // 4 lines for each cell, a circle and a line for the cell under the cursor and the cross
+        assert_eq!(draws[4].instance_count, 30 * 4 + 24 + 1 + 2);
     }
 }
@@ -1,57 +1,172 @@
+use crate::bitmap_font::{BitmapFont, TextAlignment, TextStyle};
+use crate::nine_slice::{BorderInsets, NineSlice, PanelRect};
+use crate::post_process::PostProcessEffect;
+use crate::sprite_batch::{SpriteBatch, UvRect};
 use crate::{
-    clamp, Float2, Float4, GameStateTransitionState, HeapAlloc, ScreenSpaceQuadData,
-    UpdateBehaviourDesc,
+    Float2, Float4, GameStateTransitionState, HeapAlloc, ScreenSpaceQuadData, UpdateBehaviourDesc,
 };
 use graphics_device::*;
 use os_window::WindowMessages;
+use tweening::{Easing, Tween, TweenPlayer};
 
-pub struct PauseStateStaticData<'a> {
-    screen_space_quad_blended_pso: PipelineStateObject<'a>,
-    texture_white: Texture<'a>,
+// the channels of the pause tweens
+const CHANNEL_FADE: usize = 0;
+const CHANNEL_PANEL_OFFSET: usize = 1;
+const CHANNEL_HINT_ALPHA: usize = 2;
+
+const EVENT_INTRO_FINISHED: u32 = 0;
+
+// the panel slides up from below into its place, in game space
+const PANEL_START_OFFSET: [f32; 2] = [0.0, -80.0];
+
+pub struct PauseStateStaticData {
+    screen_space_quad_blended_pso: PsoHandle,
+    game_space_quad_batch_pso: PsoHandle,
+    texture_white: TextureHandle,
+    panel: NineSlice,
+    font: BitmapFont,
+
+    // the backdrop fades in while the panel slides into place
+    intro: Tween,
+
+    // started once the intro is finished, the hint below the title pulses until the pause is closed
+    hint_pulse: Tween,
 }
 
-impl PauseStateStaticData<'_> {
-    pub fn new<'a>(device: &'a GraphicsDevice) -> PauseStateStaticData<'a> {
-        let screen_space_quad_blended_pso: PipelineStateObject = create_pso(
-            device,
-            PipelineStateObjectDesc {
-                shader_name: "target_data/shaders/screen_space_quad",
-                premultiplied_alpha: true,
+impl PauseStateStaticData {
+    pub fn new(device: &GraphicsDevice, resources: &mut ResourceRegistry) -> PauseStateStaticData {
+        let screen_space_quad_blended_pso = resources
+            .load_pso(
+                device,
+                PipelineStateObjectDesc {
+                    shader_name: "target_data/shaders/screen_space_quad",
+                    blend_mode: BlendMode::Alpha,
+                    sampler: SamplerDesc::default(),
+                },
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let game_space_quad_batch_pso = resources
+            .load_pso(
+                device,
+                PipelineStateObjectDesc {
+                    shader_name: "target_data/shaders/game_space_quad_batch",
+                    blend_mode: BlendMode::Alpha,
+                    sampler: SamplerDesc::default(),
+                },
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let texture_white = resources
+            .load_texture(device, "target_data/textures/engine/white.dds")
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let texture_panel = resources
+            .load_texture(
+                device,
+                "target_data/textures/KawaiiCookieAssetPack/gameplay_item_background.dds",
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        // the frame of the item background is about 10 texels wide
+        let panel = NineSlice::new(
+            resources,
+            texture_panel,
+            BorderInsets {
+                left: 12.0,
+                right: 12.0,
+                top: 12.0,
+                bottom: 12.0,
             },
-        );
+        )
+        .unwrap_or_else(|error| panic!("{}", error));
+
+        let font = BitmapFont::load(
+            device,
+            resources,
+            "target_data/fonts/DejaVu/dejavu_sans_bold_32.fnt",
+        )
+        .unwrap_or_else(|error| panic!("{}", error));
 
-        let texture_white =
-            load_dds_from_file("target_data/textures/engine/white.dds", device).unwrap();
+        let intro = Tween::Sequence(vec![
+            Tween::Parallel(vec![
+                Tween::scalar(CHANNEL_FADE, 0.0, 1.0, 1.0, Easing::QuadOut),
+                Tween::Sequence(vec![
+                    Tween::Delay(0.1),
+                    Tween::vector(
+                        CHANNEL_PANEL_OFFSET,
+                        PANEL_START_OFFSET,
+                        [0.0, 0.0],
+                        0.5,
+                        Easing::BackOut,
+                    ),
+                ]),
+            ]),
+            Tween::Event(EVENT_INTRO_FINISHED),
+        ]);
+
+        let hint_pulse =
+            Tween::scalar(CHANNEL_HINT_ALPHA, 1.0, 0.4, 0.8, Easing::QuadInOut).repeat(None, true);
 
         PauseStateStaticData {
             screen_space_quad_blended_pso,
+            game_space_quad_batch_pso,
             texture_white,
+            panel,
+            font,
+            intro,
+            hint_pulse,
         }
     }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        resources
+            .release_pso(self.screen_space_quad_blended_pso)
+            .unwrap();
+        resources
+            .release_pso(self.game_space_quad_batch_pso)
+            .unwrap();
+        resources.release_texture(self.texture_white).unwrap();
+        resources.release_texture(self.panel.texture).unwrap();
+        self.font.release(resources);
+    }
 }
 
 pub struct PauseStateFrameData {
+    intro: TweenPlayer,
+    hint_pulse: Option<TweenPlayer>,
+
+    // the values of the tweens after the update
     fade_in_status: f32,
+    panel_offset: Float2,
+    hint_alpha: f32,
 }
 
 impl PauseStateFrameData {
     pub fn new() -> PauseStateFrameData {
         PauseStateFrameData {
+            intro: TweenPlayer::new(),
+            hint_pulse: None,
             fade_in_status: 0.0,
+            panel_offset: Float2 {
+                x: PANEL_START_OFFSET[0],
+                y: PANEL_START_OFFSET[1],
+            },
+            hint_alpha: 1.0,
         }
     }
 }
 
-pub struct PauseState<'a> {
-    pub static_data: PauseStateStaticData<'a>,
+pub struct PauseState {
+    pub static_data: PauseStateStaticData,
     pub frame_data0: PauseStateFrameData,
     pub frame_data1: PauseStateFrameData,
 }
 
-impl PauseState<'_> {
-    pub fn new<'a>(device: &'a GraphicsDevice) -> PauseState<'a> {
+impl PauseState {
+    pub fn new(device: &GraphicsDevice, resources: &mut ResourceRegistry) -> PauseState {
         PauseState {
-            static_data: PauseStateStaticData::new(device),
+            static_data: PauseStateStaticData::new(device, resources),
             frame_data0: PauseStateFrameData::new(),
             frame_data1: PauseStateFrameData::new(),
         }
@@ -59,13 +174,47 @@ impl PauseState<'_> {
 }
 
 pub fn update_pause_state(
+    static_data: &PauseStateStaticData,
     prev_frame_params: &PauseStateFrameData,
     frame_params: &mut PauseStateFrameData,
     messages: &[WindowMessages],
     dt: f32,
 ) -> UpdateBehaviourDesc {
-    // fade in the screen state
-    frame_params.fade_in_status = clamp(prev_frame_params.fade_in_status + dt, 0.0, 1.0);
+    frame_params.intro = prev_frame_params.intro;
+    frame_params.hint_pulse = prev_frame_params.hint_pulse;
+
+    if let Some(hint_pulse) = &mut frame_params.hint_pulse {
+        hint_pulse.update(&static_data.hint_pulse, dt);
+    }
+
+    for event in frame_params.intro.update(&static_data.intro, dt) {
+        if event == EVENT_INTRO_FINISHED {
+            frame_params.hint_pulse = Some(TweenPlayer::new());
+        }
+    }
+
+    // channels keep their initial values until their tween has started
+    let intro = frame_params.intro.values(&static_data.intro);
+
+    if let Some(fade) = intro.scalar(CHANNEL_FADE) {
+        frame_params.fade_in_status = fade;
+    }
+
+    if let Some(offset) = intro.vector(CHANNEL_PANEL_OFFSET) {
+        frame_params.panel_offset = Float2 {
+            x: offset[0],
+            y: offset[1],
+        };
+    }
+
+    if let Some(hint_pulse) = &frame_params.hint_pulse {
+        if let Some(alpha) = hint_pulse
+            .values(&static_data.hint_pulse)
+            .scalar(CHANNEL_HINT_ALPHA)
+        {
+            frame_params.hint_alpha = alpha;
+        }
+    }
 
     for x in messages.iter() {
         if let WindowMessages::MouseLeftButtonDown = x {
@@ -82,22 +231,51 @@ pub fn update_pause_state(
     }
 }
 
+// the game behind the pause menu is blurred and fades to a cool gray
+pub fn pause_backdrop_effects(frame_params: &PauseStateFrameData) -> Vec<PostProcessEffect> {
+    let fade = frame_params.fade_in_status;
+
+    vec![
+        PostProcessEffect::Blur { sigma: 4.0 * fade },
+        PostProcessEffect::Desaturate { amount: 0.7 * fade },
+        PostProcessEffect::ColorGrading {
+            tint: Float4 {
+                x: 1.0 - 0.15 * fade,
+                y: 1.0 - 0.1 * fade,
+                z: 1.0,
+                a: 1.0,
+            },
+            contrast: 1.0 - 0.2 * fade,
+            brightness: 0.0,
+        },
+        PostProcessEffect::Vignette {
+            intensity: 0.6 * fade,
+            radius: 0.5,
+            softness: 0.9,
+        },
+    ]
+}
+
 pub fn draw_pause_state(
     static_state_data: &PauseStateStaticData,
     frame_params: &PauseStateFrameData,
     command_list: &mut GraphicsCommandList,
-    backbuffer_rtv: &RenderTargetView,
-    gpu_heap_data: &MappedGpuData,
-    gpu_heap_state: &mut LinearAllocatorState,
-) {
-    begin_render_pass(command_list, backbuffer_rtv);
+    canvas_rtv: &RenderTargetView,
+    resources: &ResourceRegistry,
+    gpu_heap: &mut LinearAllocator,
+) -> Result<(), GraphicsError> {
+    begin_render_pass(command_list, canvas_rtv)?;
 
     bind_pso(
         command_list,
-        &static_state_data.screen_space_quad_blended_pso,
-    );
+        resources.pso(static_state_data.screen_space_quad_blended_pso)?,
+    )?;
 
-    bind_texture(command_list, 0, &static_state_data.texture_white.srv);
+    bind_texture(
+        command_list,
+        0,
+        &resources.texture(static_state_data.texture_white)?.srv,
+    )?;
 
     let obj_alloc = HeapAlloc::new(
         ScreenSpaceQuadData {
@@ -105,16 +283,94 @@ pub fn draw_pause_state(
                 x: 0.0,
                 y: 0.0,
                 z: 0.0,
-                a: frame_params.fade_in_status * 0.8,
+                a: frame_params.fade_in_status * 0.4,
             },
             scale: Float2 { x: 1.0, y: 1.0 },
             position: Float2 { x: 0.0, y: 0.0 },
+            uv_rect: UvRect::FULL.to_float4(),
+        },
+        gpu_heap,
+    )?;
+
+    bind_constant(command_list, 0, &obj_alloc)?;
+
+    draw_vertices(command_list, 4)?;
+
+    let offset = frame_params.panel_offset;
+
+    // panel behind the text, in canvas pixels with y going down
+    static_state_data.panel.draw_screen_space(
+        command_list,
+        canvas_rtv,
+        resources,
+        gpu_heap,
+        Float4 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+            a: frame_params.fade_in_status,
+        },
+        PanelRect {
+            x: 50.0 + offset.x,
+            y: 370.0 - offset.y,
+            width: 440.0,
+            height: 200.0,
         },
-        gpu_heap_data,
-        gpu_heap_state,
+    )?;
+
+    let mut sprite_batch = SpriteBatch::new();
+
+    let mut style = TextStyle::new(0);
+    style.alignment = TextAlignment::Center;
+    style.color = Float4 {
+        x: 0.3,
+        y: 0.18,
+        z: 0.1,
+        a: frame_params.fade_in_status,
+    };
+
+    static_state_data.font.draw_text(
+        &mut sprite_batch,
+        "Paused",
+        Float2 {
+            x: 270.0 + offset.x,
+            y: 560.0 + offset.y,
+        },
+        &style,
+    );
+
+    style.max_width = Some(400);
+    style.color.a *= 0.8 * frame_params.hint_alpha;
+
+    static_state_data.font.draw_text(
+        &mut sprite_batch,
+        "Click anywhere to return to the game",
+        Float2 {
+            x: 270.0 + offset.x,
+            y: 500.0 + offset.y,
+        },
+        &style,
+    );
+
+    // keeps the text inside of the frame of the panel, the clip rect is in game space with y going up
+    push_clip_rect(
+        command_list,
+        ClipRect {
+            x: 62 + offset.x.round() as i32,
+            y: 402 + offset.y.round() as i32,
+            width: 416,
+            height: 176,
+        },
+    )?;
+
+    let result = sprite_batch.submit(
+        command_list,
+        resources,
+        static_state_data.game_space_quad_batch_pso,
+        gpu_heap,
     );
 
-    bind_constant(command_list, 0, &obj_alloc);
+    pop_clip_rect(command_list)?;
 
-    draw_vertices(command_list, 4);
+    result
 }
@@ -1,16 +1,33 @@
 // these make sure we compile the modules
+mod bitmap_font;
+mod debug_draw;
 mod gamestates;
+mod nine_slice;
+mod particles;
+mod post_process;
+mod profiler;
+mod random;
+mod scene;
+mod sprite_batch;
+mod stats_overlay;
+mod virtual_canvas;
 
 // and the modules we use
+use debug_draw::DebugDraw;
 use gamestates::*;
+use graphics_device::file_watcher::FileWatcher;
 use graphics_device::*;
 use os_window::*;
+use profiler::{draw_frame_time_graph, profile_scope, ProfileCapture, Profiler};
+use stats_overlay::{FrameStats, StatsOverlay};
+use virtual_canvas::*;
 
 pub fn as_fractional_secs(dur: &std::time::Duration) -> f32 {
     (dur.as_secs() as f64 + f64::from(dur.subsec_nanos()) / 1_000_000_000.0) as f32
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 struct Float4 {
     x: f32,
     y: f32,
@@ -19,43 +36,83 @@ struct Float4 {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 struct Float2 {
     x: f32,
     y: f32,
 }
 
-#[repr(C)]
-struct Int2 {
-    x: i32,
-    y: i32,
+// the layout needs to match the cbuffer in screen_space_quad.hlsl
+constant_buffer_struct! {
+    struct ScreenSpaceQuadData {
+        color: Float4,
+        scale: Float2,
+        position: Float2,
+        uv_rect: Float4,
+    }
 }
 
-#[repr(C)]
-struct ScreenSpaceQuadData {
-    color: Float4,
-    scale: Float2,
-    position: Float2,
+// the layout needs to match the struct in game_space_quad_batch.hlsl, created from a Sprite
+constant_buffer_struct! {
+    #[derive(Clone, Copy)]
+    struct GameSpaceSpriteData {
+        color: Float4,
+        uv_rect: Float4,
+        position: Float2,
+        size: Float2,
+        pivot: Float2,
+        rotation: Float2,
+    }
 }
 
-#[repr(C)]
-struct GameSpaceQuadData {
-    color: Float4,
-    size_pixels: Int2,
-    position_bottom_left: Int2,
+// the layouts need to match the cbuffers in post_process_blur.hlsl and post_process_color.hlsl
+constant_buffer_struct! {
+    #[derive(Clone, Copy)]
+    struct PostProcessBlurData {
+        texel_step: Float2,
+        sigma: f32,
+    }
 }
 
-struct CpuRenderFrameData {
-    frame_constant_buffer: GpuBuffer,
+constant_buffer_struct! {
+    #[derive(Clone, Copy)]
+    struct PostProcessColorData {
+        tint: Float4,
+        desaturation: f32,
+        contrast: f32,
+        brightness: f32,
+        vignette_intensity: f32,
+        vignette_radius: f32,
+        vignette_softness: f32,
+    }
 }
 
 struct CommandLineArgs {
     enable_debug_device: bool,
+
+    // reload shaders and textures when the files in target_data change
+    enable_hot_reload: bool,
+
+    // update frame numbers after which the backbuffer will be saved to disk
+    screenshot_frames: Vec<u64>,
+
+    // how the game canvas is scaled to the size of the window
+    canvas_scaling: CanvasScaling,
+
+    // show the stats overlay from the start, it can also be toggled with F2
+    show_stats: bool,
 }
 
 fn parse_cmdline() -> CommandLineArgs {
     let mut enable_debug_device = false;
+    let mut enable_hot_reload = cfg!(debug_assertions);
+    let mut screenshot_frames: Vec<u64> = Vec::new();
+    let mut canvas_scaling = CanvasScaling::Aspect;
+    let mut show_stats = false;
+
+    let mut args = std::env::args();
 
-    for argument in std::env::args() {
+    while let Some(argument) = args.next() {
         // make sure we always compare agsinst the lowercase version so that casing doesn't matter
         let mut arg = argument;
         arg.make_ascii_lowercase();
@@ -63,71 +120,161 @@ fn parse_cmdline() -> CommandLineArgs {
         if arg == "-debugdevice" {
             enable_debug_device = true;
         }
+
+        if arg == "-hotreload" {
+            enable_hot_reload = true;
+        }
+
+        if arg == "-stats" {
+            show_stats = true;
+        }
+
+        // only scale the canvas by whole numbers, keeps the pixels sharp but can leave larger bars
+        if arg == "-integerscaling" {
+            canvas_scaling = CanvasScaling::Integer;
+        }
+
+        // -screenshot <frame>, can be passed multiple times
+        if arg == "-screenshot" {
+            match args.next().map(|x| x.parse::<u64>()) {
+                Some(Ok(frame)) => screenshot_frames.push(frame),
+                _ => println!("-screenshot expects a frame number"),
+            }
+        }
     }
 
     CommandLineArgs {
         enable_debug_device,
+        enable_hot_reload,
+        screenshot_frames,
+        canvas_scaling,
+        show_stats,
     }
 }
 
-// data for each displayed frame
-// frame = "A piece of data that is processed and ultimately displayed on screen"
-struct FrameParams {
-    cpu_render: CpuRenderFrameData,
+fn save_screenshot(graphics_layer: &GraphicsDeviceLayer, frame_number: u64) {
+    let filename = format!("screenshots/frame_{:06}.png", frame_number);
+
+    let result = std::fs::create_dir_all("screenshots")
+        .map_err(|error| GraphicsError::FileWrite {
+            path: String::from("screenshots"),
+            error: error.kind(),
+        })
+        .and_then(|_| {
+            save_render_target_to_file(graphics_layer, &graphics_layer.backbuffer_rtv, &filename)
+        });
+
+    match result {
+        Ok(()) => println!("saved screenshot {}", filename),
+        Err(error) => println!("failed to save screenshot {}: {}", filename, error),
+    }
 }
 
-fn clamp<T: std::cmp::PartialOrd>(x: T, min: T, max: T) -> T {
-    if x > max {
-        max
-    } else if x < min {
-        min
-    } else {
-        x
+fn save_profile_capture(capture: &ProfileCapture, frame_number: u64) {
+    let filename = format!("profiles/frame_{:06}.json", frame_number);
+
+    let result = std::fs::create_dir_all("profiles")
+        .and_then(|_| std::fs::write(&filename, capture.to_chrome_trace()));
+
+    match result {
+        Ok(()) => println!("saved profile capture {}", filename),
+        Err(error) => println!("failed to save profile capture {}: {}", filename, error),
     }
 }
 
+// number of frames recorded when a profile capture is started with F5
+const PROFILE_CAPTURE_FRAMES: u32 = 120;
+
+// constant buffers can only bind 64 KB, a page is the largest possible allocation
+const GPU_HEAP_PAGE_SIZE: usize = 64 * 1024;
+
+// the CPU waits for the GPU when it gets further ahead than this
+// present doesn't block while DXGI queues frames and headless devices don't present at all
+const GPU_FRAMES_IN_FLIGHT: u64 = 3;
+
 fn main() {
     let args: CommandLineArgs = parse_cmdline();
 
     let mut should_game_close = false;
 
     // afterwards open a window we can render into
-    let main_window: Window = create_window(540, 960).unwrap();
+    // as large as the monitor allows, the game is scaled to fit the window
+    let (work_area_width, work_area_height) = work_area_size();
+    let (window_width, window_height) =
+        initial_window_size(work_area_width, work_area_height, args.canvas_scaling);
 
+    let main_window: Window = create_window(window_width, window_height).unwrap();
+
+    #[cfg(windows)]
     let mut graphics_layer: GraphicsDeviceLayer =
         create_device_graphics_layer(main_window.hwnd, args.enable_debug_device).unwrap();
 
-    let mut engine_frame_params0 = FrameParams {
-        cpu_render: CpuRenderFrameData {
-            frame_constant_buffer: create_constant_buffer(
-                &graphics_layer,
-                1024 * 64,
-                "Frame 0 Constants",
-            ),
-        },
-    };
+    // without D3D11 the game renders on the CPU, the frames can only be seen with -screenshot
+    #[cfg(not(windows))]
+    let mut graphics_layer: GraphicsDeviceLayer = {
+        if args.enable_debug_device {
+            println!("-debugdevice requires D3D11 and is ignored");
+        }
 
-    let mut engine_frame_params1 = FrameParams {
-        cpu_render: CpuRenderFrameData {
-            frame_constant_buffer: create_constant_buffer(
-                &graphics_layer,
-                1024 * 64,
-                "Frame 1 Constants",
-            ),
-        },
+        create_device_graphics_layer_software(window_width, window_height).unwrap()
     };
 
+    // all game states render into the canvas, it's scaled into the backbuffer at the end of the frame
+    let canvas = VirtualCanvas::new(
+        &graphics_layer.device,
+        &mut graphics_layer.resources,
+        args.canvas_scaling,
+    )
+    .unwrap();
+
+    // toggled with F3, a stub that loads nothing when the game is built without the debug_draw feature
+    let mut debug_draw = DebugDraw::new(&graphics_layer.device, &mut graphics_layer.resources)
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    let mut stats_overlay = StatsOverlay::new(
+        &graphics_layer.device,
+        &mut graphics_layer.resources,
+        args.show_stats,
+    )
+    .unwrap_or_else(|error| panic!("{}", error));
+
+    // the stats overlay shows the numbers of the previous frame
+    let mut frame_stats = FrameStats::default();
+
+    // the window can be resized many times a frame, only the last size is applied
+    let mut pending_window_size: Option<(i32, i32)> = None;
+
+    // constant data for all draws, grows when a frame needs more than a single page
+    let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, GPU_HEAP_PAGE_SIZE);
+    let mut render_frame_number: u64 = 0;
+
+    // the pages of a frame can only be written again once the GPU has finished the frame
+    let mut frame_fences = FrameFences::new(&graphics_layer.device);
+
     let dt: f32 = 1.0 / 60.0;
     let mut accumulator: f32 = dt;
 
     let mut current_time = std::time::Instant::now();
     let mut update_frame_number: u64 = 0;
 
+    let mut take_screenshot = false;
+
+    // the frame time graph is part of the debug draw overlay
+    let mut profiler = Profiler::new();
+
+    let mut file_watcher = if args.enable_hot_reload {
+        Some(FileWatcher::new("target_data", &["vsb", "psb", "dds"]))
+    } else {
+        None
+    };
+
     let mut game_state_stack: Vec<GameStateData> = Vec::new();
     let mut next_game_state: GameStateTransitionState =
         GameStateTransitionState::TransitionToNewState(GameStateType::Gameplay);
 
     while !should_game_close {
+        let frame_zone = profile_scope("frame");
+
         let new_time = std::time::Instant::now();
 
         // calculate how much time has passed
@@ -146,34 +293,53 @@ fn main() {
         if accumulator < dt {
             let sleep_duration = dt - accumulator;
 
+            let _zone = profile_scope("sleep");
             std::thread::sleep(std::time::Duration::from_secs_f32(sleep_duration));
         }
 
         accumulator = dt;
 
+        let mut update_ticks: u32 = 0;
+
         execute_possible_state_transition(
             next_game_state,
             &mut game_state_stack,
             &graphics_layer.device,
+            &mut graphics_layer.resources,
         );
         next_game_state = GameStateTransitionState::Unchanged;
 
+        // no resources are bound at this point, so it's safe to replace them
+        // checking the files twice a second is enough
+        if let Some(watcher) = &mut file_watcher {
+            if update_frame_number.is_multiple_of(30) {
+                let changed_files = watcher.poll();
+
+                if !changed_files.is_empty() {
+                    let reload_report = graphics_layer
+                        .resources
+                        .reload_changed_files(&graphics_layer.device, &changed_files);
+
+                    print!("{}", reload_report);
+                }
+            }
+        }
+
         if game_state_stack.is_empty() {
             should_game_close = true;
         }
 
-        let (_prev_engine_frame_params, engine_frame_params) = if update_frame_number % 2 == 0 {
-            (&engine_frame_params1, &mut engine_frame_params0)
-        } else {
-            (&engine_frame_params0, &mut engine_frame_params1)
-        };
-
         while accumulator >= dt {
             // update the game for a fixed number of steps
             accumulator -= dt;
 
+            // only the primitives of the last update are drawn
+            debug_draw.clear();
+
             let mut messages: Vec<WindowMessages> = Vec::new();
 
+            let message_zone = profile_scope("process_window_messages");
+
             while let Some(x) = process_window_messages(&main_window) {
                 match x {
                     WindowMessages::WindowClosed => {
@@ -182,44 +348,285 @@ fn main() {
                     WindowMessages::WindowCreated(_x) => {
                         panic!();
                     } // this should never happen
+                    WindowMessages::WindowResized(size) => {
+                        pending_window_size = Some((size.width, size.height));
+                    }
+                    // game states only see positions in game space
+                    WindowMessages::MousePositionChanged(pos) => {
+                        let canvas_rect = canvas.rect(&graphics_layer.backbuffer_rtv);
+
+                        if let Some((x, y)) = window_to_game_space(&canvas_rect, pos.x, pos.y) {
+                            messages.push(WindowMessages::MousePositionChanged(
+                                MousePositionChangedData { x, y },
+                            ));
+                        }
+                    }
+                    WindowMessages::KeyDown(KeyDownData { virtual_key })
+                        if virtual_key == VK_F12 =>
+                    {
+                        take_screenshot = true;
+                    }
+                    WindowMessages::KeyDown(KeyDownData { virtual_key })
+                        if virtual_key == VK_F2 =>
+                    {
+                        stats_overlay.visible = !stats_overlay.visible;
+                    }
+                    #[cfg(feature = "debug_draw")]
+                    WindowMessages::KeyDown(KeyDownData { virtual_key })
+                        if virtual_key == VK_F3 =>
+                    {
+                        debug_draw.visible = !debug_draw.visible;
+                    }
+                    WindowMessages::KeyDown(KeyDownData { virtual_key })
+                        if virtual_key == VK_F5 =>
+                    {
+                        profiler.start_capture(PROFILE_CAPTURE_FRAMES);
+                    }
+                    WindowMessages::KeyDown(KeyDownData { virtual_key })
+                        if virtual_key == VK_F4 =>
+                    {
+                        print!("{}", graphics_layer.device.tracker.report());
+                    }
                     _ => messages.push(x),
                 }
             }
 
+            drop(message_zone);
+
             next_game_state = update_gamestate_stack(
                 dt,
                 update_frame_number,
                 &mut game_state_stack,
                 &mut messages,
+                &mut debug_draw,
             );
 
             update_frame_number += 1;
+            update_ticks += 1;
+        }
+
+        if debug_draw.visible {
+            draw_frame_time_graph(&profiler.frame_times, &mut debug_draw);
+        }
+
+        if let Some((width, height)) = pending_window_size.take() {
+            resize_swapchain(&mut graphics_layer, width, height).unwrap();
+        }
+
+        if let Some(oldest_frame_number) = render_frame_number.checked_sub(GPU_FRAMES_IN_FLIGHT) {
+            let _zone = profile_scope("wait_for_gpu");
+            frame_fences.wait_for_frame(oldest_frame_number).unwrap();
+        }
+
+        // the pages of finished frames are no longer used by the GPU and can be written again
+        if let Some(completed_frame_number) = frame_fences.poll_completed_frame().unwrap() {
+            gpu_heap.retire_frame(completed_frame_number);
         }
 
         // draw the game
-        let mut gpu_heap = LinearAllocator {
-            gpu_data: map_gpu_buffer(
-                &engine_frame_params.cpu_render.frame_constant_buffer,
-                &graphics_layer,
-            ),
-            state: LinearAllocatorState { used_bytes: 0 },
-        };
+        gpu_heap.begin_frame(render_frame_number);
 
         draw_gamestate_stack(
             &game_state_stack,
             update_frame_number,
             &mut graphics_layer.graphics_command_list,
-            &graphics_layer.backbuffer_rtv,
-            &gpu_heap.gpu_data,
-            &mut gpu_heap.state,
+            &canvas,
+            &graphics_layer.resources,
+            &mut gpu_heap,
+            &debug_draw,
+        )
+        .unwrap();
+
+        stats_overlay
+            .draw(
+                &mut graphics_layer.graphics_command_list,
+                &canvas.render_target.rtv,
+                &graphics_layer.resources,
+                &mut gpu_heap,
+                &frame_stats,
+                &profiler.frame_times,
+            )
+            .unwrap();
+
+        canvas
+            .present(
+                &mut graphics_layer.graphics_command_list,
+                &graphics_layer.backbuffer_rtv,
+                &graphics_layer.resources,
+                &mut gpu_heap,
+            )
+            .unwrap();
+
+        // unmap the gpu heap pages
+        // from this point onwards we are unable to allocate further memory
+        gpu_heap.end_frame();
+
+        {
+            let _zone = profile_scope("execute_command_list");
+            execute_command_list(&graphics_layer, &graphics_layer.graphics_command_list).unwrap();
+        }
+
+        frame_fences.signal_frame(render_frame_number).unwrap();
+
+        frame_stats = FrameStats {
+            command_list: take_command_list_stats(&mut graphics_layer.graphics_command_list),
+            gpu_heap: gpu_heap.stats,
+            game_state_count: game_state_stack.len(),
+            update_ticks,
+        };
+
+        // needs to happen before present, afterwards the content of the backbuffer is undefined
+        if take_screenshot || args.screenshot_frames.contains(&update_frame_number) {
+            save_screenshot(&graphics_layer, update_frame_number);
+            take_screenshot = false;
+        }
+
+        {
+            let _zone = profile_scope("present_swapchain");
+            present_swapchain(&graphics_layer).unwrap();
+        }
+
+        render_frame_number += 1;
+
+        drop(frame_zone);
+
+        if let Some(capture) = profiler.end_frame() {
+            save_profile_capture(&capture, render_frame_number);
+        }
+    }
+
+    // the states that are still on the stack when the window is closed
+    while !game_state_stack.is_empty() {
+        execute_possible_state_transition(
+            GameStateTransitionState::ReturnToPreviousState,
+            &mut game_state_stack,
+            &graphics_layer.device,
+            &mut graphics_layer.resources,
         );
+    }
 
-        // unmap the gpu buffer
-        // from this point onwards we are unable to allocate further memory
-        unmap_gpu_buffer(gpu_heap.gpu_data, &graphics_layer);
+    stats_overlay.release(&mut graphics_layer.resources);
+    debug_draw.release(&mut graphics_layer.resources);
+    canvas.release(&mut graphics_layer.resources);
+}
 
-        execute_command_list(&graphics_layer, &graphics_layer.graphics_command_list);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        present_swapchain(&graphics_layer);
+    use graphics_device::cbuffer_layout::*;
+
+    fn find_layout(source: &str, name: &str) -> ConstantBufferLayout {
+        parse_constant_buffer_layouts(source)
+            .unwrap()
+            .into_iter()
+            .find(|x| x.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn constant_buffer_structs_match_the_shaders() {
+        let screen_space_quad = include_str!("../src_data/shaders/screen_space_quad.hlsl");
+        let game_space_quad_batch = include_str!("../src_data/shaders/game_space_quad_batch.hlsl");
+        let post_process_blur = include_str!("../src_data/shaders/post_process_blur.hlsl");
+        let post_process_color = include_str!("../src_data/shaders/post_process_color.hlsl");
+
+        assert_eq!(
+            validate_constant_buffer_data::<ScreenSpaceQuadData>(&find_layout(
+                screen_space_quad,
+                "ScreenSpaceQuadData"
+            )),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_constant_buffer_data::<GameSpaceSpriteData>(&find_layout(
+                game_space_quad_batch,
+                "GameSpaceSpriteData"
+            )),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_constant_buffer_data::<PostProcessBlurData>(&find_layout(
+                post_process_blur,
+                "PostProcessBlurData"
+            )),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_constant_buffer_data::<PostProcessColorData>(&find_layout(
+                post_process_color,
+                "PostProcessColorData"
+            )),
+            Ok(())
+        );
+
+        // the sprite batch uploads a tightly packed array, the HLSL array stride needs to be the same
+        let batch = find_layout(game_space_quad_batch, "GameSpaceQuadBatchData");
+
+        assert_eq!(
+            batch.fields[0].size,
+            sprite_batch::MAX_SPRITES_PER_DRAW * std::mem::size_of::<GameSpaceSpriteData>()
+        );
+    }
+
+    #[test]
+    fn render_gameplay_frame_with_software_rasterizer() {
+        let mut graphics_layer: GraphicsDeviceLayer =
+            create_device_graphics_layer_software(540, 960).unwrap();
+
+        let mut game_state_stack: Vec<GameStateData> = Vec::new();
+
+        execute_possible_state_transition(
+            GameStateTransitionState::TransitionToNewState(GameStateType::Gameplay),
+            &mut game_state_stack,
+            &graphics_layer.device,
+            &mut graphics_layer.resources,
+        );
+
+        let canvas = VirtualCanvas::new(
+            &graphics_layer.device,
+            &mut graphics_layer.resources,
+            CanvasScaling::Aspect,
+        )
+        .unwrap();
+
+        let debug_draw =
+            DebugDraw::new(&graphics_layer.device, &mut graphics_layer.resources).unwrap();
+
+        let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, GPU_HEAP_PAGE_SIZE);
+        gpu_heap.begin_frame(0);
+
+        draw_gamestate_stack(
+            &game_state_stack,
+            0,
+            &mut graphics_layer.graphics_command_list,
+            &canvas,
+            &graphics_layer.resources,
+            &mut gpu_heap,
+            &debug_draw,
+        )
+        .unwrap();
+
+        gpu_heap.end_frame();
+
+        let backbuffer = canvas
+            .render_target
+            .rtv
+            .software_target
+            .as_ref()
+            .unwrap()
+            .borrow();
+
+        // the background covers the whole screen, the clear color should never be visible
+        let clear_color: [u8; 4] = [0, 51, 102, 255];
+
+        for y in 0..backbuffer.height {
+            for x in 0..backbuffer.width {
+                assert_ne!(backbuffer.pixel(x, y), clear_color);
+            }
+        }
     }
 }
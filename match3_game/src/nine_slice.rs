@@ -0,0 +1,295 @@
+use crate::sprite_batch::{Sprite, UvRect};
+use crate::{Float2, Float4, ScreenSpaceQuadData};
+use graphics_device::*;
+
+// distances in texels from the edges of the texture to the stretchable center
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BorderInsets {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+// a rectangle in pixels, for game space x,y is the bottom-left corner and for screen space the top-left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PanelRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+// one of the up to nine quads a panel is made of
+// x and y are the offset of the bottom-left corner from the bottom-left of the panel, y going up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NineSlicePiece {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub uv_rect: UvRect,
+}
+
+// splits a panel of the given size into corners that keep their size,
+// edges that stretch along the edge and a center that stretches in both directions
+// when the panel is smaller than the borders the borders are shrunk so they just meet
+pub fn nine_slice_pieces(
+    texture_width: f32,
+    texture_height: f32,
+    insets: &BorderInsets,
+    width: f32,
+    height: f32,
+) -> Vec<NineSlicePiece> {
+    let shrink = |size: f32, inset_a: f32, inset_b: f32| {
+        if inset_a + inset_b > size {
+            let scale = size / (inset_a + inset_b);
+            (inset_a * scale, inset_b * scale)
+        } else {
+            (inset_a, inset_b)
+        }
+    };
+
+    let (left, right) = shrink(width, insets.left, insets.right);
+    let (bottom, top) = shrink(height, insets.bottom, insets.top);
+
+    let xs = [0.0, left, width - right, width];
+    let ys = [0.0, bottom, height - top, height];
+
+    let us = [
+        0.0,
+        insets.left / texture_width,
+        1.0 - insets.right / texture_width,
+        1.0,
+    ];
+
+    // texture coordinates go down from the top of the texture, the rows go up from the bottom of the panel
+    let vs = [
+        1.0,
+        1.0 - insets.bottom / texture_height,
+        insets.top / texture_height,
+        0.0,
+    ];
+
+    let mut pieces = Vec::with_capacity(9);
+
+    for row in 0..3 {
+        for column in 0..3 {
+            let piece = NineSlicePiece {
+                x: xs[column],
+                y: ys[row],
+                width: xs[column + 1] - xs[column],
+                height: ys[row + 1] - ys[row],
+                uv_rect: UvRect {
+                    left: us[column],
+                    top: vs[row + 1],
+                    right: us[column + 1],
+                    bottom: vs[row],
+                },
+            };
+
+            // insets of 0 remove the border on that side
+            if piece.width > 0.0 && piece.height > 0.0 {
+                pieces.push(piece);
+            }
+        }
+    }
+
+    pieces
+}
+
+// a texture with borders that are not stretched when the texture is drawn at a different size
+#[derive(Clone, Copy)]
+pub struct NineSlice {
+    pub texture: TextureHandle,
+    pub insets: BorderInsets,
+    texture_width: f32,
+    texture_height: f32,
+}
+
+impl NineSlice {
+    pub fn new(
+        resources: &ResourceRegistry,
+        texture: TextureHandle,
+        insets: BorderInsets,
+    ) -> Result<NineSlice, GraphicsError> {
+        let texture_data = resources.texture(texture)?;
+
+        Ok(NineSlice {
+            texture,
+            insets,
+            texture_width: texture_data.width as f32,
+            texture_height: texture_data.height as f32,
+        })
+    }
+
+    pub fn pieces(&self, width: f32, height: f32) -> Vec<NineSlicePiece> {
+        nine_slice_pieces(
+            self.texture_width,
+            self.texture_height,
+            &self.insets,
+            width,
+            height,
+        )
+    }
+
+    // the sprites of the panel, rect is in game space
+    pub fn sprites(&self, layer: i32, color: Float4, rect: PanelRect) -> Vec<Sprite> {
+        let mut sprites = Vec::new();
+
+        for piece in self.pieces(rect.width, rect.height) {
+            let mut sprite = Sprite::new(
+                self.texture,
+                layer,
+                Float2 {
+                    x: rect.x + piece.x,
+                    y: rect.y + piece.y,
+                },
+                Float2 {
+                    x: piece.width,
+                    y: piece.height,
+                },
+            );
+
+            sprite.color = color;
+            sprite.uv_rect = piece.uv_rect;
+
+            sprites.push(sprite);
+        }
+
+        sprites
+    }
+
+    // draws the panel into the target of the current render pass, rect is in pixels of the target with y going down
+    // expects a pso that uses the screen_space_quad shader to be bound
+    pub fn draw_screen_space(
+        &self,
+        command_list: &mut GraphicsCommandList,
+        target: &RenderTargetView,
+        resources: &ResourceRegistry,
+        gpu_heap: &mut LinearAllocator,
+        color: Float4,
+        rect: PanelRect,
+    ) -> Result<(), GraphicsError> {
+        bind_texture(command_list, 0, &resources.texture(self.texture)?.srv)?;
+
+        let target_width = target.width as f32;
+        let target_height = target.height as f32;
+
+        for piece in self.pieces(rect.width, rect.height) {
+            // the pieces go up from the bottom of the panel
+            let left = rect.x + piece.x;
+            let top = rect.y + rect.height - piece.y - piece.height;
+
+            // the screen space quad covers the whole target before scale and position are applied
+            let obj_alloc = HeapAlloc::new(
+                ScreenSpaceQuadData {
+                    color,
+                    scale: Float2 {
+                        x: piece.width / target_width,
+                        y: piece.height / target_height,
+                    },
+                    position: Float2 {
+                        x: (left * 2.0 + piece.width) / target_width - 1.0,
+                        y: 1.0 - (top * 2.0 + piece.height) / target_height,
+                    },
+                    uv_rect: piece.uv_rect.to_float4(),
+                },
+                gpu_heap,
+            )?;
+
+            bind_constant(command_list, 0, &obj_alloc)?;
+
+            draw_vertices(command_list, 4)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSETS: BorderInsets = BorderInsets {
+        left: 8.0,
+        right: 16.0,
+        top: 4.0,
+        bottom: 8.0,
+    };
+
+    #[test]
+    fn corners_keep_their_size() {
+        let pieces = nine_slice_pieces(64.0, 32.0, &INSETS, 300.0, 200.0);
+
+        assert_eq!(pieces.len(), 9);
+
+        // bottom-left corner
+        assert_eq!(
+            pieces[0],
+            NineSlicePiece {
+                x: 0.0,
+                y: 0.0,
+                width: 8.0,
+                height: 8.0,
+                uv_rect: UvRect {
+                    left: 0.0,
+                    top: 0.75,
+                    right: 0.125,
+                    bottom: 1.0,
+                },
+            }
+        );
+
+        // the center stretches to fill everything between the borders
+        assert_eq!(
+            (pieces[4].x, pieces[4].y, pieces[4].width, pieces[4].height),
+            (8.0, 8.0, 276.0, 188.0)
+        );
+        assert_eq!(
+            pieces[4].uv_rect,
+            UvRect {
+                left: 0.125,
+                top: 0.125,
+                right: 0.75,
+                bottom: 0.75,
+            }
+        );
+
+        // top-right corner
+        assert_eq!(
+            (pieces[8].x, pieces[8].y, pieces[8].width, pieces[8].height),
+            (284.0, 196.0, 16.0, 4.0)
+        );
+    }
+
+    #[test]
+    fn borders_shrink_when_the_panel_is_too_small() {
+        // 12 pixels wide, the left and right borders are scaled to 4 and 8 pixels and the center disappears
+        let pieces = nine_slice_pieces(64.0, 32.0, &INSETS, 12.0, 200.0);
+
+        assert_eq!(pieces.len(), 6);
+        assert_eq!(
+            (pieces[0].width, pieces[1].x, pieces[1].width),
+            (4.0, 4.0, 8.0)
+        );
+
+        // the texture coordinates still show the whole border
+        assert_eq!(pieces[1].uv_rect.left, 0.75);
+    }
+
+    #[test]
+    fn zero_insets_only_stretch_along_one_axis() {
+        let insets = BorderInsets {
+            left: 0.0,
+            right: 0.0,
+            top: 0.0,
+            bottom: 40.0,
+        };
+
+        let pieces = nine_slice_pieces(540.0, 187.0, &insets, 540.0, 184.0);
+
+        assert_eq!(pieces.len(), 2);
+        assert_eq!((pieces[0].height, pieces[1].height), (40.0, 144.0));
+    }
+}
@@ -0,0 +1,918 @@
+use crate::random::*;
+use crate::sprite_batch::{Sprite, UvRect};
+use crate::{Float2, Float4};
+use graphics_device::*;
+
+// particles alive at the same time in a particle system, new particles are dropped once the limit is reached
+pub const MAX_PARTICLES: usize = 4096;
+
+// values are picked uniformly between min and max
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValueRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ValueRange {
+    fn constant(value: f32) -> ValueRange {
+        ValueRange {
+            min: value,
+            max: value,
+        }
+    }
+}
+
+pub struct EmitterDesc {
+    // relative to the location of the effect file
+    pub texture_file: String,
+
+    // the texture is split into columns x rows frames, numbered left to right and top to bottom
+    pub atlas_columns: u32,
+    pub atlas_rows: u32,
+
+    // the frames first_frame .. first_frame + frame_count are looped at frame_rate frames per second
+    // with a frame rate of 0 they are played once over the lifetime of the particle
+    pub first_frame: u32,
+    pub frame_count: u32,
+    pub frame_rate: f32,
+
+    // particles spawned when the effect is started
+    pub burst_count: u32,
+
+    // particles spawned per second for the first duration seconds after the effect was started
+    pub spawn_rate: f32,
+    pub duration: f32,
+
+    // in seconds
+    pub lifetime: ValueRange,
+
+    // particles start at a random position inside of this radius around the effect position
+    pub spawn_radius: f32,
+
+    // pixels per second in a direction in degrees, counter-clockwise from the x axis
+    pub speed: ValueRange,
+    pub angle: ValueRange,
+
+    // rotation speed in degrees per second
+    pub spin: ValueRange,
+
+    // acceleration in pixels per second squared
+    pub gravity: Float2,
+
+    // keys of (time, value), time goes from 0 at spawn to 1 at the end of the lifetime
+    // values between keys are interpolated linearly
+    pub size_over_life: Vec<(f32, f32)>,
+    pub color_over_life: Vec<(f32, Float4)>,
+}
+
+// a list of emitters that are started together
+pub struct EffectDesc {
+    pub name: String,
+    pub emitters: Vec<EmitterDesc>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParticleParseError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl EmitterDesc {
+    // a single white particle that lives for a second and doesn't move
+    fn new() -> EmitterDesc {
+        EmitterDesc {
+            texture_file: String::new(),
+            atlas_columns: 1,
+            atlas_rows: 1,
+            first_frame: 0,
+            frame_count: 1,
+            frame_rate: 0.0,
+            burst_count: 0,
+            spawn_rate: 0.0,
+            duration: 0.0,
+            lifetime: ValueRange::constant(1.0),
+            spawn_radius: 0.0,
+            speed: ValueRange::constant(0.0),
+            angle: ValueRange {
+                min: 0.0,
+                max: 360.0,
+            },
+            spin: ValueRange::constant(0.0),
+            gravity: Float2 { x: 0.0, y: 0.0 },
+            size_over_life: vec![(0.0, 16.0)],
+            color_over_life: vec![(
+                0.0,
+                Float4 {
+                    x: 1.0,
+                    y: 1.0,
+                    z: 1.0,
+                    a: 1.0,
+                },
+            )],
+        }
+    }
+}
+
+fn parse_numbers(values: &[&str], line: usize) -> Result<Vec<f32>, ParticleParseError> {
+    values
+        .iter()
+        .map(|x| match x.parse::<f32>() {
+            // nan and inf parse fine but would poison every particle that uses them
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(ParticleParseError {
+                line,
+                reason: "value is not a number",
+            }),
+        })
+        .collect()
+}
+
+fn parse_exact<'a>(
+    values: &'a [&str],
+    count: usize,
+    line: usize,
+) -> Result<&'a [&'a str], ParticleParseError> {
+    if values.len() != count {
+        return Err(ParticleParseError {
+            line,
+            reason: "wrong number of values",
+        });
+    }
+
+    Ok(values)
+}
+
+fn parse_f32(values: &[&str], line: usize) -> Result<f32, ParticleParseError> {
+    Ok(parse_numbers(parse_exact(values, 1, line)?, line)?[0])
+}
+
+// rates, durations and distances
+fn parse_non_negative_f32(values: &[&str], line: usize) -> Result<f32, ParticleParseError> {
+    let number = parse_f32(values, line)?;
+
+    if number < 0.0 {
+        return Err(ParticleParseError {
+            line,
+            reason: "value can't be negative",
+        });
+    }
+
+    Ok(number)
+}
+
+fn parse_u32(values: &[&str], line: usize) -> Result<u32, ParticleParseError> {
+    parse_exact(values, 1, line)?[0]
+        .parse::<u32>()
+        .map_err(|_| ParticleParseError {
+            line,
+            reason: "value is not a positive integer",
+        })
+}
+
+// either a single value or min and max
+fn parse_range(values: &[&str], line: usize) -> Result<ValueRange, ParticleParseError> {
+    let numbers = parse_numbers(values, line)?;
+
+    match numbers.len() {
+        1 => Ok(ValueRange::constant(numbers[0])),
+        2 if numbers[0] <= numbers[1] => Ok(ValueRange {
+            min: numbers[0],
+            max: numbers[1],
+        }),
+        2 => Err(ParticleParseError {
+            line,
+            reason: "min is larger than max",
+        }),
+        _ => Err(ParticleParseError {
+            line,
+            reason: "wrong number of values",
+        }),
+    }
+}
+
+// a list of keys with key_size numbers each, the first number of a key is the time
+fn parse_curve(
+    values: &[&str],
+    key_size: usize,
+    line: usize,
+) -> Result<Vec<Vec<f32>>, ParticleParseError> {
+    let numbers = parse_numbers(values, line)?;
+
+    if numbers.is_empty() || numbers.len() % key_size != 0 {
+        return Err(ParticleParseError {
+            line,
+            reason: "wrong number of values",
+        });
+    }
+
+    let keys: Vec<Vec<f32>> = numbers.chunks(key_size).map(|x| x.to_vec()).collect();
+
+    let mut previous_time = 0.0;
+
+    for key in keys.iter() {
+        if key[0] < previous_time || key[0] > 1.0 {
+            return Err(ParticleParseError {
+                line,
+                reason: "key times need to be sorted and between 0 and 1",
+            });
+        }
+
+        previous_time = key[0];
+    }
+
+    Ok(keys)
+}
+
+fn parse_emitter_property(
+    emitter: &mut EmitterDesc,
+    key: &str,
+    values: &[&str],
+    line: usize,
+) -> Result<(), ParticleParseError> {
+    match key {
+        "texture" => {
+            emitter.texture_file = parse_exact(values, 1, line)?[0].to_string();
+        }
+
+        "atlas" => {
+            let values = parse_exact(values, 2, line)?;
+            emitter.atlas_columns = parse_u32(&values[0..1], line)?;
+            emitter.atlas_rows = parse_u32(&values[1..2], line)?;
+        }
+
+        "frames" => {
+            let values = parse_exact(values, 3, line)?;
+            emitter.first_frame = parse_u32(&values[0..1], line)?;
+            emitter.frame_count = parse_u32(&values[1..2], line)?;
+            emitter.frame_rate = parse_non_negative_f32(&values[2..3], line)?;
+        }
+
+        "burst" => emitter.burst_count = parse_u32(values, line)?,
+        "spawn_rate" => emitter.spawn_rate = parse_non_negative_f32(values, line)?,
+        "duration" => emitter.duration = parse_non_negative_f32(values, line)?,
+        "lifetime" => emitter.lifetime = parse_range(values, line)?,
+        "spawn_radius" => emitter.spawn_radius = parse_non_negative_f32(values, line)?,
+        "speed" => emitter.speed = parse_range(values, line)?,
+        "angle" => emitter.angle = parse_range(values, line)?,
+        "spin" => emitter.spin = parse_range(values, line)?,
+
+        "gravity" => {
+            let numbers = parse_numbers(parse_exact(values, 2, line)?, line)?;
+            emitter.gravity = Float2 {
+                x: numbers[0],
+                y: numbers[1],
+            };
+        }
+
+        "size" => {
+            emitter.size_over_life = parse_curve(values, 2, line)?
+                .iter()
+                .map(|key| (key[0], key[1]))
+                .collect();
+        }
+
+        "color" => {
+            emitter.color_over_life = parse_curve(values, 5, line)?
+                .iter()
+                .map(|key| {
+                    (
+                        key[0],
+                        Float4 {
+                            x: key[1],
+                            y: key[2],
+                            z: key[3],
+                            a: key[4],
+                        },
+                    )
+                })
+                .collect();
+        }
+
+        _ => {
+            return Err(ParticleParseError {
+                line,
+                reason: "unknown property",
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_emitter(emitter: &EmitterDesc, line: usize) -> Result<(), ParticleParseError> {
+    // frame numbers come straight from the file, so they are checked for overflow
+    let last_frame = emitter.first_frame.checked_add(emitter.frame_count);
+    let atlas_frames = emitter.atlas_columns.checked_mul(emitter.atlas_rows);
+
+    let reason = if emitter.texture_file.is_empty() {
+        "emitter without a texture"
+    } else if emitter.atlas_columns == 0 || emitter.atlas_rows == 0 || emitter.frame_count == 0 {
+        "the atlas needs at least one frame"
+    } else if atlas_frames.is_none() {
+        "the atlas has too many frames"
+    } else if last_frame.is_none() || last_frame > atlas_frames {
+        "frames outside of the atlas"
+    } else if emitter.lifetime.min <= 0.0 {
+        "lifetime needs to be larger than 0"
+    } else {
+        return Ok(());
+    };
+
+    Err(ParticleParseError { line, reason })
+}
+
+// the file is a list of lines with a key followed by values separated by whitespace, # starts a comment
+// `effect <name>` starts a new effect and `emitter` adds an emitter to it
+// all other lines set a property of the last emitter, see EmitterDesc and parse_emitter_property
+pub fn parse_particle_effects(text: &str) -> Result<Vec<EffectDesc>, ParticleParseError> {
+    let mut effects: Vec<EffectDesc> = Vec::new();
+
+    // errors that are only found once an effect or emitter is complete are reported on the line that started it
+    let mut effect_lines: Vec<usize> = Vec::new();
+    let mut emitter_lines: Vec<usize> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+
+        let content = match line.find('#') {
+            Some(x) => &line[..x],
+            None => line,
+        };
+
+        let words: Vec<&str> = content.split_whitespace().collect();
+
+        let (key, values) = match words.split_first() {
+            Some(x) => x,
+            None => continue,
+        };
+
+        match *key {
+            "effect" => {
+                let name = parse_exact(values, 1, line_number)?[0];
+
+                if effects.iter().any(|x| x.name == name) {
+                    return Err(ParticleParseError {
+                        line: line_number,
+                        reason: "an effect with the same name already exists",
+                    });
+                }
+
+                effects.push(EffectDesc {
+                    name: name.to_string(),
+                    emitters: Vec::new(),
+                });
+                effect_lines.push(line_number);
+            }
+
+            "emitter" => {
+                parse_exact(values, 0, line_number)?;
+
+                let effect = effects.last_mut().ok_or(ParticleParseError {
+                    line: line_number,
+                    reason: "emitter outside of an effect",
+                })?;
+
+                effect.emitters.push(EmitterDesc::new());
+                emitter_lines.push(line_number);
+            }
+
+            _ => {
+                let emitter = effects
+                    .last_mut()
+                    .and_then(|x| x.emitters.last_mut())
+                    .ok_or(ParticleParseError {
+                        line: line_number,
+                        reason: "property outside of an emitter",
+                    })?;
+
+                parse_emitter_property(emitter, key, values, line_number)?;
+            }
+        }
+    }
+
+    for (effect, line) in effects.iter().zip(effect_lines.iter()) {
+        if effect.emitters.is_empty() {
+            return Err(ParticleParseError {
+                line: *line,
+                reason: "effect without emitters",
+            });
+        }
+    }
+
+    let emitters = effects.iter().flat_map(|x| x.emitters.iter());
+
+    for (emitter, line) in emitters.zip(emitter_lines.iter()) {
+        validate_emitter(emitter, *line)?;
+    }
+
+    Ok(effects)
+}
+
+fn lerp(from: f32, to: f32, fraction: f32) -> f32 {
+    from + (to - from) * fraction
+}
+
+// before the first and after the last key the value of that key is used
+fn sample_curve<T: Copy>(keys: &[(f32, T)], t: f32, interpolate: impl Fn(T, T, f32) -> T) -> T {
+    let next = match keys.iter().position(|x| x.0 > t) {
+        Some(0) => return keys[0].1,
+        Some(x) => x,
+        None => return keys[keys.len() - 1].1,
+    };
+
+    let (start, end) = (keys[next - 1], keys[next]);
+
+    interpolate(start.1, end.1, (t - start.0) / (end.0 - start.0))
+}
+
+pub fn sample_size_curve(keys: &[(f32, f32)], t: f32) -> f32 {
+    sample_curve(keys, t, lerp)
+}
+
+pub fn sample_color_curve(keys: &[(f32, Float4)], t: f32) -> Float4 {
+    sample_curve(keys, t, |from, to, fraction| Float4 {
+        x: lerp(from.x, to.x, fraction),
+        y: lerp(from.y, to.y, fraction),
+        z: lerp(from.z, to.z, fraction),
+        a: lerp(from.a, to.a, fraction),
+    })
+}
+
+// the atlas frame a particle shows at the given age
+pub fn particle_frame(emitter: &EmitterDesc, age: f32, lifetime: f32) -> u32 {
+    let frame = if emitter.frame_rate > 0.0 {
+        (age * emitter.frame_rate) as u32 % emitter.frame_count
+    } else {
+        ((age / lifetime * emitter.frame_count as f32) as u32).min(emitter.frame_count - 1)
+    };
+
+    emitter.first_frame + frame
+}
+
+fn frame_uv_rect(emitter: &EmitterDesc, frame: u32) -> UvRect {
+    let column = (frame % emitter.atlas_columns) as f32;
+    let row = (frame / emitter.atlas_columns) as f32;
+
+    let width = 1.0 / emitter.atlas_columns as f32;
+    let height = 1.0 / emitter.atlas_rows as f32;
+
+    UvRect {
+        left: column * width,
+        top: row * height,
+        right: (column + 1.0) * width,
+        bottom: (row + 1.0) * height,
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Particle {
+    effect: usize,
+    emitter: usize,
+
+    position: Float2,
+    velocity: Float2,
+
+    // in radians and radians per second
+    rotation: f32,
+    spin: f32,
+
+    age: f32,
+    lifetime: f32,
+}
+
+#[derive(Clone, Copy)]
+struct ActiveEmitter {
+    effect: usize,
+    emitter: usize,
+    position: Float2,
+    age: f32,
+
+    // particles spawned by the spawn rate so far, the burst is not included
+    spawned_count: u32,
+}
+
+// simulates the particles of all started effects
+// all random values come from the generator of the system, so the same seed, effects and time steps
+// always give the same particles
+#[derive(Clone)]
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    emitters: Vec<ActiveEmitter>,
+    rnd_state: Xoroshiro128Rng,
+}
+
+impl ParticleSystem {
+    pub fn new(seed: u64) -> ParticleSystem {
+        ParticleSystem {
+            particles: Vec::new(),
+            emitters: Vec::new(),
+            rnd_state: rnd_from_seed(seed),
+        }
+    }
+
+    fn spawn_particle(&mut self, desc: &EmitterDesc, effect: usize, emitter: usize, at: Float2) {
+        if self.particles.len() >= MAX_PARTICLES {
+            return;
+        }
+
+        let rnd = &mut self.rnd_state;
+
+        // uniformly distributed inside of the circle
+        let offset_angle = rnd_range_f32(rnd, 0.0, std::f32::consts::PI * 2.0);
+        let offset_distance = desc.spawn_radius * rnd_next_f32(rnd).sqrt();
+
+        let angle = rnd_range_f32(rnd, desc.angle.min, desc.angle.max).to_radians();
+        let speed = rnd_range_f32(rnd, desc.speed.min, desc.speed.max);
+
+        let particle = Particle {
+            effect,
+            emitter,
+            position: Float2 {
+                x: at.x + offset_angle.cos() * offset_distance,
+                y: at.y + offset_angle.sin() * offset_distance,
+            },
+            velocity: Float2 {
+                x: angle.cos() * speed,
+                y: angle.sin() * speed,
+            },
+            rotation: rnd_range_f32(rnd, 0.0, std::f32::consts::PI * 2.0),
+            spin: rnd_range_f32(rnd, desc.spin.min, desc.spin.max).to_radians(),
+            age: 0.0,
+            lifetime: rnd_range_f32(rnd, desc.lifetime.min, desc.lifetime.max),
+        };
+
+        self.particles.push(particle);
+    }
+
+    // starts all emitters of the effect at the game space position, bursts are spawned right away
+    pub fn start_effect(&mut self, effects: &[EffectDesc], effect: usize, position: Float2) {
+        for (emitter_index, desc) in effects[effect].emitters.iter().enumerate() {
+            for _ in 0..desc.burst_count {
+                self.spawn_particle(desc, effect, emitter_index, position);
+            }
+
+            if desc.spawn_rate > 0.0 && desc.duration > 0.0 {
+                self.emitters.push(ActiveEmitter {
+                    effect,
+                    emitter: emitter_index,
+                    position,
+                    age: 0.0,
+                    spawned_count: 0,
+                });
+            }
+        }
+    }
+
+    // advances the simulation by dt seconds
+    // existing particles are moved first, particles spawned during the step start at the emitter position
+    pub fn update(&mut self, effects: &[EffectDesc], dt: f32) {
+        for particle in self.particles.iter_mut() {
+            let desc = &effects[particle.effect].emitters[particle.emitter];
+
+            particle.age += dt;
+
+            particle.velocity.x += desc.gravity.x * dt;
+            particle.velocity.y += desc.gravity.y * dt;
+
+            particle.position.x += particle.velocity.x * dt;
+            particle.position.y += particle.velocity.y * dt;
+
+            particle.rotation += particle.spin * dt;
+        }
+
+        self.particles.retain(|x| x.age < x.lifetime);
+
+        let mut emitters = std::mem::take(&mut self.emitters);
+
+        for emitter in emitters.iter_mut() {
+            let desc = &effects[emitter.effect].emitters[emitter.emitter];
+
+            emitter.age += dt;
+
+            // derived from the total time instead of accumulating fractions so rounding errors can't lose particles
+            let target_count = (desc.spawn_rate * f32::min(emitter.age, desc.duration)) as u32;
+
+            while emitter.spawned_count < target_count {
+                emitter.spawned_count += 1;
+
+                self.spawn_particle(desc, emitter.effect, emitter.emitter, emitter.position);
+            }
+        }
+
+        emitters.retain(|x| x.age < effects[x.effect].emitters[x.emitter].duration);
+
+        self.emitters = emitters;
+    }
+
+    pub fn sprites(&self, effects: &ParticleEffects, layer: i32) -> Vec<Sprite> {
+        let mut sprites = Vec::with_capacity(self.particles.len());
+
+        for particle in self.particles.iter() {
+            let desc = &effects.effects[particle.effect].emitters[particle.emitter];
+
+            let t = particle.age / particle.lifetime;
+            let size = sample_size_curve(&desc.size_over_life, t);
+
+            let mut sprite = Sprite::new(
+                effects.textures[particle.effect][particle.emitter],
+                layer,
+                particle.position,
+                Float2 { x: size, y: size },
+            );
+
+            sprite.pivot = Float2 { x: 0.5, y: 0.5 };
+            sprite.rotation = particle.rotation;
+            sprite.color = sample_color_curve(&desc.color_over_life, t);
+            sprite.uv_rect =
+                frame_uv_rect(desc, particle_frame(desc, particle.age, particle.lifetime));
+
+            sprites.push(sprite);
+        }
+
+        sprites
+    }
+}
+
+// the effects of a file together with the textures of the emitters
+pub struct ParticleEffects {
+    pub effects: Vec<EffectDesc>,
+
+    // for each effect the texture of each emitter
+    textures: Vec<Vec<TextureHandle>>,
+}
+
+impl ParticleEffects {
+    pub fn load(
+        device: &GraphicsDevice,
+        resources: &mut ResourceRegistry,
+        filename: &str,
+    ) -> Result<ParticleEffects, GraphicsError> {
+        let text = std::fs::read_to_string(filename).map_err(|error| GraphicsError::FileRead {
+            path: filename.to_string(),
+            error: error.kind(),
+        })?;
+
+        let effects =
+            parse_particle_effects(&text).map_err(|error| GraphicsError::InvalidArgument {
+                resource_name: format!("{} line {}", filename, error.line),
+                reason: error.reason,
+            })?;
+
+        let directory = match filename.rfind('/') {
+            Some(x) => &filename[..(x + 1)],
+            None => "",
+        };
+
+        let mut textures = Vec::new();
+
+        for effect in effects.iter() {
+            let mut effect_textures = Vec::new();
+
+            for emitter in effect.emitters.iter() {
+                let path = format!("{}{}", directory, emitter.texture_file);
+                effect_textures.push(resources.load_texture(device, &path)?);
+            }
+
+            textures.push(effect_textures);
+        }
+
+        Ok(ParticleEffects { effects, textures })
+    }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        for texture in self.textures.iter().flatten() {
+            resources.release_texture(*texture).unwrap();
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.effects.iter().position(|x| x.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EFFECTS: &str = "
+        # a burst of sparkles and a short stream of crumbs
+        effect test
+        emitter
+        texture particles.dds
+        atlas 4 2
+        frames 0 4 8
+        burst 10
+        lifetime 0.5 1
+        speed 100 200
+        angle 45 135
+        gravity 0 -100
+
+        emitter
+        texture particles.dds
+        atlas 4 2
+        frames 4 4 0
+        spawn_rate 30
+        duration 0.5
+        lifetime 2
+        size 0 10  0.5 20  1 0
+        color 0 1 1 1 1  1 1 1 1 0
+    ";
+
+    #[test]
+    fn parse_effects() {
+        let effects = parse_particle_effects(EFFECTS).unwrap();
+
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].name, "test");
+        assert_eq!(effects[0].emitters.len(), 2);
+
+        let sparkles = &effects[0].emitters[0];
+        assert_eq!(sparkles.texture_file, "particles.dds");
+        assert_eq!((sparkles.atlas_columns, sparkles.atlas_rows), (4, 2));
+        assert_eq!(sparkles.burst_count, 10);
+        assert_eq!(sparkles.lifetime, ValueRange { min: 0.5, max: 1.0 });
+        assert_eq!(sparkles.gravity.y, -100.0);
+
+        let crumbs = &effects[0].emitters[1];
+        assert_eq!(crumbs.lifetime, ValueRange::constant(2.0));
+        assert_eq!(
+            crumbs.size_over_life,
+            vec![(0.0, 10.0), (0.5, 20.0), (1.0, 0.0)]
+        );
+        assert_eq!(crumbs.color_over_life.len(), 2);
+    }
+
+    #[test]
+    fn parse_errors_report_the_line() {
+        let error = |text: &str| parse_particle_effects(text).err().unwrap();
+
+        assert_eq!(
+            error("effect a\nemitter\ntexture a.dds\nburst -1"),
+            ParticleParseError {
+                line: 4,
+                reason: "value is not a positive integer"
+            }
+        );
+
+        assert_eq!(error("burst 1").reason, "property outside of an emitter");
+
+        assert_eq!(
+            error("effect a\nemitter\ntexture a.dds\nsize 0.5 1 0.2 2"),
+            ParticleParseError {
+                line: 4,
+                reason: "key times need to be sorted and between 0 and 1"
+            }
+        );
+
+        // the texture is only known to be missing at the end, the emitter line is reported
+        assert_eq!(
+            error("effect a\n\nemitter\nburst 1"),
+            ParticleParseError {
+                line: 3,
+                reason: "emitter without a texture"
+            }
+        );
+
+        assert_eq!(
+            error("effect a\nemitter\ntexture a.dds\nspawn_rate NaN"),
+            ParticleParseError {
+                line: 4,
+                reason: "value is not a number"
+            }
+        );
+
+        assert_eq!(
+            error("effect a\nemitter\ntexture a.dds\nduration -1"),
+            ParticleParseError {
+                line: 4,
+                reason: "value can't be negative"
+            }
+        );
+
+        // frame numbers that would overflow are reported instead of wrapping around
+        assert_eq!(
+            error("effect a\nemitter\ntexture a.dds\natlas 65536 65536"),
+            ParticleParseError {
+                line: 2,
+                reason: "the atlas has too many frames"
+            }
+        );
+
+        assert_eq!(
+            error("effect a\nemitter\ntexture a.dds\nframes 4294967295 1 1"),
+            ParticleParseError {
+                line: 2,
+                reason: "frames outside of the atlas"
+            }
+        );
+    }
+
+    #[test]
+    fn curves_interpolate_between_keys() {
+        let keys = vec![(0.0, 10.0), (0.5, 20.0), (1.0, 0.0)];
+
+        assert_eq!(sample_size_curve(&keys, 0.0), 10.0);
+        assert_eq!(sample_size_curve(&keys, 0.25), 15.0);
+        assert_eq!(sample_size_curve(&keys, 0.75), 10.0);
+        assert_eq!(sample_size_curve(&keys, 1.0), 0.0);
+
+        // values outside of the keys are clamped to the first and last key
+        assert_eq!(sample_size_curve(&keys[1..], 0.0), 20.0);
+        assert_eq!(sample_size_curve(&keys, 2.0), 0.0);
+    }
+
+    #[test]
+    fn frames_loop_or_play_over_the_lifetime() {
+        let effects = parse_particle_effects(EFFECTS).unwrap();
+        let looping = &effects[0].emitters[0];
+        let over_lifetime = &effects[0].emitters[1];
+
+        // 8 frames per second over frames 0 to 3
+        assert_eq!(particle_frame(looping, 0.0, 1.0), 0);
+        assert_eq!(particle_frame(looping, 0.25, 1.0), 2);
+        assert_eq!(particle_frame(looping, 0.5, 1.0), 0);
+
+        // frames 4 to 7 once over two seconds
+        assert_eq!(particle_frame(over_lifetime, 0.0, 2.0), 4);
+        assert_eq!(particle_frame(over_lifetime, 1.0, 2.0), 6);
+        assert_eq!(particle_frame(over_lifetime, 2.0, 2.0), 7);
+
+        // the second row of the atlas
+        assert_eq!(
+            frame_uv_rect(over_lifetime, 5),
+            UvRect {
+                left: 0.25,
+                top: 0.5,
+                right: 0.5,
+                bottom: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn bursts_spawn_at_once_and_rates_over_the_duration() {
+        let effects = parse_particle_effects(EFFECTS).unwrap();
+        let mut system = ParticleSystem::new(1);
+
+        system.start_effect(&effects, 0, Float2 { x: 100.0, y: 100.0 });
+        assert_eq!(system.particles.len(), 10);
+
+        // the burst particles live for at most a second, 30 per second are spawned for half a second
+        for _ in 0..70 {
+            system.update(&effects, 1.0 / 60.0);
+        }
+
+        assert_eq!(system.particles.len(), 15);
+        assert!(system.emitters.is_empty());
+
+        // all of the stream particles are gone after their lifetime of 2 seconds
+        for _ in 0..90 {
+            system.update(&effects, 1.0 / 60.0);
+        }
+
+        assert_eq!(system.particles.len(), 0);
+    }
+
+    #[test]
+    fn gravity_accelerates_particles() {
+        let effects = parse_particle_effects(
+            "effect fall\nemitter\ntexture a.dds\nburst 1\nlifetime 10\ngravity 0 -10",
+        )
+        .unwrap();
+
+        let mut system = ParticleSystem::new(1);
+        system.start_effect(&effects, 0, Float2 { x: 0.0, y: 0.0 });
+
+        system.update(&effects, 0.5);
+        system.update(&effects, 0.5);
+
+        let particle = system.particles[0];
+        assert_eq!(particle.velocity.y, -10.0);
+        assert_eq!(particle.position.y, -7.5);
+        assert_eq!(particle.position.x, 0.0);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_particles() {
+        let effects = parse_particle_effects(EFFECTS).unwrap();
+
+        let simulate = |seed: u64| {
+            let mut system = ParticleSystem::new(seed);
+
+            system.start_effect(&effects, 0, Float2 { x: 270.0, y: 480.0 });
+
+            for _ in 0..20 {
+                system.update(&effects, 1.0 / 60.0);
+            }
+
+            system
+                .particles
+                .iter()
+                .map(|x| (x.position.x, x.position.y, x.rotation, x.lifetime))
+                .collect::<Vec<(f32, f32, f32, f32)>>()
+        };
+
+        assert_eq!(simulate(5), simulate(5));
+        assert_ne!(simulate(5), simulate(6));
+    }
+}
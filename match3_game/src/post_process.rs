@@ -0,0 +1,309 @@
+use crate::{Float2, Float4, HeapAlloc, PostProcessBlurData, PostProcessColorData};
+use graphics_device::*;
+
+// full-screen effects that are applied to everything that has been rendered into a target so far
+#[derive(Clone, Copy)]
+pub enum PostProcessEffect {
+    // separable gaussian blur, sigma is in pixels of the target
+    Blur {
+        sigma: f32,
+    },
+
+    // 0 keeps the colors, 1 is grayscale
+    Desaturate {
+        amount: f32,
+    },
+
+    // darkens the edges, radius and softness are relative to the distance from the center to the edges
+    Vignette {
+        intensity: f32,
+        radius: f32,
+        softness: f32,
+    },
+
+    ColorGrading {
+        tint: Float4,
+        contrast: f32,
+        brightness: f32,
+    },
+}
+
+// a single full-screen draw
+pub enum PostProcessPass {
+    Blur(PostProcessBlurData),
+    Color(PostProcessColorData),
+}
+
+// leaves the image unchanged
+fn neutral_color_data() -> PostProcessColorData {
+    PostProcessColorData {
+        tint: Float4 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+            a: 1.0,
+        },
+        desaturation: 0.0,
+        contrast: 1.0,
+        brightness: 0.0,
+        vignette_intensity: 0.0,
+        vignette_radius: 1.0,
+        vignette_softness: 0.5,
+    }
+}
+
+// every effect is a pass of its own so that the effects are applied in the order they are given
+// effects that don't change the image are skipped
+// the last pass writes back into the source, when there is only a single pass a copy is added for this
+pub fn post_process_passes(
+    effects: &[PostProcessEffect],
+    width: i32,
+    height: i32,
+) -> Vec<PostProcessPass> {
+    let mut passes = Vec::new();
+
+    for effect in effects.iter() {
+        match *effect {
+            PostProcessEffect::Blur { sigma } => {
+                if sigma > 0.0 {
+                    let blur = |x: f32, y: f32| {
+                        PostProcessPass::Blur(PostProcessBlurData {
+                            texel_step: Float2 { x, y },
+                            sigma,
+                        })
+                    };
+
+                    passes.push(blur(1.0 / width as f32, 0.0));
+                    passes.push(blur(0.0, 1.0 / height as f32));
+                }
+            }
+
+            PostProcessEffect::Desaturate { amount } => {
+                if amount > 0.0 {
+                    passes.push(PostProcessPass::Color(PostProcessColorData {
+                        desaturation: amount,
+                        ..neutral_color_data()
+                    }));
+                }
+            }
+
+            PostProcessEffect::Vignette {
+                intensity,
+                radius,
+                softness,
+            } => {
+                if intensity > 0.0 {
+                    passes.push(PostProcessPass::Color(PostProcessColorData {
+                        vignette_intensity: intensity,
+                        vignette_radius: radius,
+                        vignette_softness: softness,
+                        ..neutral_color_data()
+                    }));
+                }
+            }
+
+            PostProcessEffect::ColorGrading {
+                tint,
+                contrast,
+                brightness,
+            } => {
+                passes.push(PostProcessPass::Color(PostProcessColorData {
+                    tint,
+                    contrast,
+                    brightness,
+                    ..neutral_color_data()
+                }));
+            }
+        }
+    }
+
+    if passes.len() == 1 {
+        passes.push(PostProcessPass::Color(neutral_color_data()));
+    }
+
+    passes
+}
+
+// the intermediate targets the passes ping-pong between
+pub struct PostProcessChain<'a> {
+    targets: [RenderTarget<'a>; 2],
+
+    blur_pso: PsoHandle,
+    color_pso: PsoHandle,
+}
+
+impl<'a> PostProcessChain<'a> {
+    // width and height need to match the targets the effects are applied to
+    pub fn new(
+        device: &GraphicsDevice,
+        resources: &mut ResourceRegistry,
+        width: i32,
+        height: i32,
+    ) -> Result<PostProcessChain<'a>, GraphicsError> {
+        let targets = [
+            create_render_target(device, width, height, "Post Process 0")?,
+            create_render_target(device, width, height, "Post Process 1")?,
+        ];
+
+        // every sample is the center of a texel, clamping repeats the edges for the blur
+        let pso_desc = |shader_name| PipelineStateObjectDesc {
+            shader_name,
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc {
+                filter: SamplerFilter::Point,
+                ..SamplerDesc::default()
+            },
+        };
+
+        let blur_pso =
+            resources.load_pso(device, pso_desc("target_data/shaders/post_process_blur"))?;
+        let color_pso =
+            resources.load_pso(device, pso_desc("target_data/shaders/post_process_color"))?;
+
+        Ok(PostProcessChain {
+            targets,
+            blur_pso,
+            color_pso,
+        })
+    }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        resources.release_pso(self.blur_pso).unwrap();
+        resources.release_pso(self.color_pso).unwrap();
+    }
+
+    // applies the effects to the content of the target, the result is written back into the target
+    pub fn apply(
+        &self,
+        effects: &[PostProcessEffect],
+        command_list: &mut GraphicsCommandList,
+        target: &RenderTarget,
+        resources: &ResourceRegistry,
+        gpu_heap: &mut LinearAllocator,
+    ) -> Result<(), GraphicsError> {
+        let passes = post_process_passes(effects, target.rtv.width, target.rtv.height);
+
+        for (index, pass) in passes.iter().enumerate() {
+            let source_srv = if index == 0 {
+                &target.srv
+            } else {
+                &self.targets[(index - 1) % 2].srv
+            };
+
+            let destination_rtv = if index == passes.len() - 1 {
+                &target.rtv
+            } else {
+                &self.targets[index % 2].rtv
+            };
+
+            begin_render_pass(command_list, destination_rtv)?;
+
+            let obj_alloc = match pass {
+                PostProcessPass::Blur(data) => {
+                    bind_pso(command_list, resources.pso(self.blur_pso)?)?;
+                    HeapAlloc::new(*data, gpu_heap)?
+                }
+
+                PostProcessPass::Color(data) => {
+                    bind_pso(command_list, resources.pso(self.color_pso)?)?;
+                    HeapAlloc::new(*data, gpu_heap)?
+                }
+            };
+
+            bind_texture(command_list, 0, source_srv)?;
+            bind_constant(command_list, 0, &obj_alloc)?;
+
+            draw_vertices(command_list, 4)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_are_split_into_passes() {
+        let passes = post_process_passes(
+            &[
+                PostProcessEffect::Blur { sigma: 2.0 },
+                PostProcessEffect::Desaturate { amount: 0.5 },
+                PostProcessEffect::Vignette {
+                    intensity: 0.0,
+                    radius: 0.5,
+                    softness: 0.5,
+                },
+            ],
+            540,
+            960,
+        );
+
+        // the blur is separated into a horizontal and a vertical pass, the vignette without intensity is skipped
+        assert_eq!(passes.len(), 3);
+
+        match &passes[1] {
+            PostProcessPass::Blur(data) => {
+                assert_eq!((data.texel_step.x, data.texel_step.y), (0.0, 1.0 / 960.0));
+                assert_eq!(data.sigma, 2.0);
+            }
+            _ => panic!("expected the vertical blur pass"),
+        }
+
+        match &passes[2] {
+            PostProcessPass::Color(data) => assert_eq!(data.desaturation, 0.5),
+            _ => panic!("expected the desaturation pass"),
+        }
+
+        // a single pass needs a copy back into the target
+        let passes =
+            post_process_passes(&[PostProcessEffect::Desaturate { amount: 1.0 }], 540, 960);
+        assert_eq!(passes.len(), 2);
+
+        assert!(
+            post_process_passes(&[PostProcessEffect::Blur { sigma: 0.0 }], 540, 960).is_empty()
+        );
+    }
+
+    #[test]
+    fn effects_are_written_back_into_the_target() {
+        let mut graphics_layer = create_device_graphics_layer_software(540, 960).unwrap();
+
+        let chain = PostProcessChain::new(
+            &graphics_layer.device,
+            &mut graphics_layer.resources,
+            540,
+            960,
+        )
+        .unwrap();
+
+        let target = create_render_target(&graphics_layer.device, 540, 960, "Scene").unwrap();
+
+        let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 64 * 1024);
+        gpu_heap.begin_frame(0);
+
+        let command_list = &mut graphics_layer.graphics_command_list;
+
+        begin_render_pass_and_clear(command_list, [1.0, 0.0, 0.0, 1.0], &target.rtv).unwrap();
+
+        chain
+            .apply(
+                &[PostProcessEffect::Desaturate { amount: 1.0 }],
+                command_list,
+                &target,
+                &graphics_layer.resources,
+                &mut gpu_heap,
+            )
+            .unwrap();
+
+        gpu_heap.end_frame();
+
+        // red has a luminance of 0.2126
+        let pixels = target.rtv.software_target.as_ref().unwrap().borrow();
+        assert_eq!(pixels.pixel(0, 0), [54, 54, 54, 255]);
+        assert_eq!(pixels.pixel(539, 959), [54, 54, 54, 255]);
+
+        chain.release(&mut graphics_layer.resources);
+    }
+}
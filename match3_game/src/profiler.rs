@@ -0,0 +1,473 @@
+use crate::debug_draw::DebugDraw;
+use crate::{Float2, Float4};
+use std::cell::Cell;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+// a finished zone, zones of the same thread with a higher depth are nested inside of the zones with a lower depth
+#[derive(Clone, Debug)]
+pub struct ProfileZone {
+    pub name: &'static str,
+    pub thread_id: u64,
+    pub depth: u32,
+
+    // in microseconds since the first zone of the process has started
+    pub start: u64,
+    pub duration: u64,
+}
+
+// every thread records into a buffer of its own, the buffers are only shared while they are collected
+struct ThreadProfileBuffer {
+    thread_id: u64,
+    thread_name: String,
+    zones: Vec<ProfileZone>,
+}
+
+struct ThreadProfileState {
+    buffer: Arc<Mutex<ThreadProfileBuffer>>,
+    depth: Cell<u32>,
+}
+
+static THREAD_BUFFERS: Mutex<Vec<Arc<Mutex<ThreadProfileBuffer>>>> = Mutex::new(Vec::new());
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+// ids are never reused, buffers of threads that have ended are removed once they have been collected
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static THREAD_STATE: ThreadProfileState = register_thread();
+}
+
+fn register_thread() -> ThreadProfileState {
+    let buffer = Arc::new(Mutex::new(ThreadProfileBuffer {
+        thread_id: NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed),
+        thread_name: std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string(),
+        zones: Vec::new(),
+    }));
+
+    THREAD_BUFFERS.lock().unwrap().push(buffer.clone());
+
+    ThreadProfileState {
+        buffer,
+        depth: Cell::new(0),
+    }
+}
+
+fn microseconds_since_epoch() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_micros() as u64
+}
+
+// records the time until it is dropped as a zone of the current thread
+pub struct ProfileScope {
+    name: &'static str,
+    depth: u32,
+    start: u64,
+}
+
+pub fn profile_scope(name: &'static str) -> ProfileScope {
+    let depth = THREAD_STATE.with(|state| {
+        let depth = state.depth.get();
+        state.depth.set(depth + 1);
+        depth
+    });
+
+    ProfileScope {
+        name,
+        depth,
+        start: microseconds_since_epoch(),
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        let end = microseconds_since_epoch();
+
+        THREAD_STATE.with(|state| {
+            state.depth.set(self.depth);
+
+            let mut buffer = state.buffer.lock().unwrap();
+            let thread_id = buffer.thread_id;
+
+            buffer.zones.push(ProfileZone {
+                name: self.name,
+                thread_id,
+                depth: self.depth,
+                start: self.start,
+                duration: end - self.start,
+            });
+        });
+    }
+}
+
+// the zones of all threads for a number of frames
+#[derive(Default)]
+pub struct ProfileCapture {
+    pub thread_names: BTreeMap<u64, String>,
+    pub zones: Vec<ProfileZone>,
+}
+
+impl ProfileCapture {
+    // moves the zones all threads have finished since the last collection into the capture
+    pub fn collect(&mut self) {
+        let mut buffers = THREAD_BUFFERS.lock().unwrap();
+
+        for buffer in buffers.iter() {
+            let mut buffer = buffer.lock().unwrap();
+
+            if buffer.zones.is_empty() {
+                continue;
+            }
+
+            self.thread_names
+                .insert(buffer.thread_id, buffer.thread_name.clone());
+            self.zones.append(&mut buffer.zones);
+        }
+
+        // only this list references the buffers of threads that have ended
+        buffers.retain(|x| Arc::strong_count(x) > 1);
+    }
+
+    // the trace_event format, can be opened with chrome://tracing or ui.perfetto.dev
+    pub fn to_chrome_trace(&self) -> String {
+        let mut events: Vec<String> = self
+            .thread_names
+            .iter()
+            .map(|(thread_id, name)| {
+                format!(
+                    "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"name\":{}}}}}",
+                    thread_id,
+                    json_string(name)
+                )
+            })
+            .collect();
+
+        let mut zones: Vec<&ProfileZone> = self.zones.iter().collect();
+        zones.sort_by_key(|x| (x.start, x.depth));
+
+        events.extend(zones.iter().map(|x| {
+            format!(
+                "{{\"name\":{},\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":0,\"tid\":{}}}",
+                json_string(x.name),
+                x.start,
+                x.duration,
+                x.thread_id
+            )
+        }));
+
+        format!("{{\"traceEvents\":[\n{}\n]}}\n", events.join(",\n"))
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut result = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
+// the duration of the most recent frames in milliseconds, oldest first
+pub struct FrameTimeHistory {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl FrameTimeHistory {
+    pub fn new(capacity: usize) -> FrameTimeHistory {
+        FrameTimeHistory {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, milliseconds: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(milliseconds);
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &f32> {
+        self.samples.iter()
+    }
+
+    pub fn max(&self) -> f32 {
+        self.samples.iter().cloned().fold(0.0, f32::max)
+    }
+
+    // nearest-rank percentile, 0 while the history is empty
+    pub fn percentile(&self, percent: f32) -> f32 {
+        let mut sorted: Vec<f32> = self.samples.iter().cloned().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let rank = (percent / 100.0 * sorted.len() as f32).ceil() as usize;
+
+        match rank.checked_sub(1) {
+            Some(index) => sorted[index.min(sorted.len() - 1)],
+            None => sorted.first().cloned().unwrap_or(0.0),
+        }
+    }
+}
+
+// number of frames shown in the frame time graph
+const FRAME_TIME_HISTORY_FRAMES: usize = 120;
+
+pub struct Profiler {
+    pub frame_times: FrameTimeHistory,
+    last_frame_end: Instant,
+
+    // the capture and the number of frames that still need to be recorded
+    capture: Option<(ProfileCapture, u32)>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            frame_times: FrameTimeHistory::new(FRAME_TIME_HISTORY_FRAMES),
+            last_frame_end: Instant::now(),
+            capture: None,
+        }
+    }
+
+    // records all zones of the next frames, does nothing while a capture is running
+    pub fn start_capture(&mut self, frame_count: u32) {
+        if self.capture.is_none() && frame_count > 0 {
+            self.capture = Some((ProfileCapture::default(), frame_count));
+        }
+    }
+
+    // needs to be called once at the end of every frame, after all zones of the frame have been finished
+    // zones are discarded while no capture is running, returns the capture once all its frames have been recorded
+    pub fn end_frame(&mut self) -> Option<ProfileCapture> {
+        let now = Instant::now();
+        self.frame_times
+            .push(now.duration_since(self.last_frame_end).as_secs_f32() * 1000.0);
+        self.last_frame_end = now;
+
+        match self.capture.take() {
+            Some((mut capture, remaining_frames)) => {
+                capture.collect();
+
+                if remaining_frames > 1 {
+                    self.capture = Some((capture, remaining_frames - 1));
+                    None
+                } else {
+                    Some(capture)
+                }
+            }
+            None => {
+                ProfileCapture::default().collect();
+                None
+            }
+        }
+    }
+}
+
+// the frame time graph is drawn in the bottom-left corner of the canvas, in game space
+const GRAPH_POSITION: Float2 = Float2 { x: 10.0, y: 10.0 };
+const GRAPH_HEIGHT: f32 = 100.0;
+const GRAPH_SAMPLE_WIDTH: f32 = 2.0;
+
+// frames at 60 fps fit into the lower half of the graph
+const TARGET_FRAME_TIME: f32 = 1000.0 / 60.0;
+
+fn frame_time_color(milliseconds: f32) -> Float4 {
+    let (x, y) = if milliseconds <= TARGET_FRAME_TIME * 1.05 {
+        (0.2, 1.0)
+    } else if milliseconds <= TARGET_FRAME_TIME * 2.0 {
+        (1.0, 0.8)
+    } else {
+        (1.0, 0.2)
+    };
+
+    Float4 {
+        x,
+        y,
+        z: 0.2,
+        a: 1.0,
+    }
+}
+
+// a bar for every frame in the history with a line at the target frame time
+pub fn draw_frame_time_graph(frame_times: &FrameTimeHistory, debug_draw: &mut DebugDraw) {
+    let scale = GRAPH_HEIGHT / (TARGET_FRAME_TIME * 2.0);
+    let width = frame_times.capacity as f32 * GRAPH_SAMPLE_WIDTH;
+
+    for (index, milliseconds) in frame_times.samples().enumerate() {
+        let x = GRAPH_POSITION.x + (index as f32 + 0.5) * GRAPH_SAMPLE_WIDTH;
+        let height = (milliseconds * scale).min(GRAPH_HEIGHT);
+
+        debug_draw.line(
+            Float2 {
+                x,
+                y: GRAPH_POSITION.y,
+            },
+            Float2 {
+                x,
+                y: GRAPH_POSITION.y + height,
+            },
+            frame_time_color(*milliseconds),
+        );
+    }
+
+    let white = Float4 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+        a: 1.0,
+    };
+
+    debug_draw.rect(
+        GRAPH_POSITION,
+        Float2 {
+            x: width,
+            y: GRAPH_HEIGHT,
+        },
+        white,
+    );
+
+    let target_y = GRAPH_POSITION.y + TARGET_FRAME_TIME * scale;
+    debug_draw.line(
+        Float2 {
+            x: GRAPH_POSITION.x,
+            y: target_y,
+        },
+        Float2 {
+            x: GRAPH_POSITION.x + width,
+            y: target_y,
+        },
+        white,
+    );
+
+    let latest = frame_times.samples().last().cloned().unwrap_or(0.0);
+    debug_draw.text(
+        Float2 {
+            x: GRAPH_POSITION.x,
+            y: GRAPH_POSITION.y + GRAPH_HEIGHT + 24.0,
+        },
+        &format!("{:.1} ms (max {:.1} ms)", latest, frame_times.max()),
+        white,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_zones_are_collected_from_all_threads() {
+        {
+            let _outer = profile_scope("test outer");
+            let _inner = profile_scope("test inner");
+        }
+
+        std::thread::Builder::new()
+            .name(String::from("test worker"))
+            .spawn(|| {
+                let _zone = profile_scope("test worker zone");
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        let mut capture = ProfileCapture::default();
+        capture.collect();
+
+        // other tests can record zones at the same time
+        let zone = |name| capture.zones.iter().find(|x| x.name == name).unwrap();
+
+        let outer = zone("test outer");
+        let inner = zone("test inner");
+        let worker = zone("test worker zone");
+
+        assert_eq!((outer.depth, inner.depth), (0, 1));
+        assert_eq!(outer.thread_id, inner.thread_id);
+        assert!(inner.start >= outer.start);
+        assert!(inner.start + inner.duration <= outer.start + outer.duration);
+
+        assert_ne!(worker.thread_id, outer.thread_id);
+        assert_eq!(worker.depth, 0);
+        assert_eq!(capture.thread_names[&worker.thread_id], "test worker");
+
+        // collected zones are removed from the thread buffers
+        let mut capture = ProfileCapture::default();
+        capture.collect();
+        assert!(capture.zones.iter().all(|x| !x.name.starts_with("test ")));
+    }
+
+    #[test]
+    fn captures_are_exported_as_chrome_traces() {
+        let mut capture = ProfileCapture::default();
+        capture
+            .thread_names
+            .insert(0, String::from("main \"thread\""));
+        capture.zones.push(ProfileZone {
+            name: "draw",
+            thread_id: 0,
+            depth: 1,
+            start: 150,
+            duration: 20,
+        });
+        capture.zones.push(ProfileZone {
+            name: "frame",
+            thread_id: 0,
+            depth: 0,
+            start: 100,
+            duration: 100,
+        });
+
+        let trace = capture.to_chrome_trace();
+        let lines: Vec<&str> = trace.lines().collect();
+
+        assert_eq!(lines[0], "{\"traceEvents\":[");
+        assert_eq!(
+            lines[1],
+            "{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":0,\"args\":{\"name\":\"main \\\"thread\\\"\"}},"
+        );
+
+        // sorted by start time
+        assert_eq!(
+            lines[2],
+            "{\"name\":\"frame\",\"ph\":\"X\",\"ts\":100,\"dur\":100,\"pid\":0,\"tid\":0},"
+        );
+        assert_eq!(
+            lines[3],
+            "{\"name\":\"draw\",\"ph\":\"X\",\"ts\":150,\"dur\":20,\"pid\":0,\"tid\":0}"
+        );
+        assert_eq!(lines[4], "]}");
+    }
+
+    #[test]
+    fn frame_time_history_keeps_the_latest_frames() {
+        let mut history = FrameTimeHistory::new(3);
+
+        for milliseconds in [16.0, 40.0, 17.0, 15.0].iter() {
+            history.push(*milliseconds);
+        }
+
+        assert_eq!(
+            history.samples().cloned().collect::<Vec<f32>>(),
+            vec![40.0, 17.0, 15.0]
+        );
+        assert_eq!(history.max(), 40.0);
+
+        assert_eq!(history.percentile(50.0), 17.0);
+        assert_eq!(history.percentile(99.0), 40.0);
+        assert_eq!(history.percentile(0.0), 15.0);
+        assert_eq!(FrameTimeHistory::new(3).percentile(50.0), 0.0);
+    }
+}
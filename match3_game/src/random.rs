@@ -0,0 +1,78 @@
+// a small and fast generator, the same seed always produces the same sequence
+// see http://xoroshiro.di.unimi.it/
+#[derive(Clone, Copy)]
+pub struct Xoroshiro128Rng {
+    pub state: [u64; 2],
+}
+
+// expands a single seed into the state with splitmix64, this makes sure the state is never all zeros
+pub fn rnd_from_seed(seed: u64) -> Xoroshiro128Rng {
+    let mut x = seed;
+
+    let mut splitmix64 = || {
+        x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+
+    Xoroshiro128Rng {
+        state: [splitmix64(), splitmix64()],
+    }
+}
+
+pub fn rnd_next_u64(rnd: &mut Xoroshiro128Rng) -> u64 {
+    let s0 = rnd.state[0];
+    let mut s1 = rnd.state[1];
+    let result = s0.wrapping_add(s1);
+
+    s1 ^= s0;
+    rnd.state[0] = s0.rotate_left(24) ^ s1 ^ (s1 << 16);
+    rnd.state[1] = s1.rotate_left(37);
+
+    result
+}
+
+// uniformly distributed in [0, 1), uses the upper 24 bits since those are the most random ones
+pub fn rnd_next_f32(rnd: &mut Xoroshiro128Rng) -> f32 {
+    (rnd_next_u64(rnd) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// uniformly distributed between min and max
+pub fn rnd_range_f32(rnd: &mut Xoroshiro128Rng, min: f32, max: f32) -> f32 {
+    min + (max - min) * rnd_next_f32(rnd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let mut a = rnd_from_seed(42);
+        let mut b = rnd_from_seed(42);
+        let mut c = rnd_from_seed(43);
+
+        let sequence_a: Vec<u64> = (0..8).map(|_| rnd_next_u64(&mut a)).collect();
+        let sequence_b: Vec<u64> = (0..8).map(|_| rnd_next_u64(&mut b)).collect();
+        let sequence_c: Vec<u64> = (0..8).map(|_| rnd_next_u64(&mut c)).collect();
+
+        assert_eq!(sequence_a, sequence_b);
+        assert_ne!(sequence_a, sequence_c);
+
+        // a seed of zero still produces a usable state
+        assert_ne!(rnd_from_seed(0).state, [0, 0]);
+    }
+
+    #[test]
+    fn floats_stay_inside_of_the_range() {
+        let mut rnd = rnd_from_seed(7);
+
+        for _ in 0..1000 {
+            let x = rnd_range_f32(&mut rnd, -2.0, 3.0);
+            assert!((-2.0..=3.0).contains(&x));
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use crate::sprite_batch::{Sprite, SpriteBatch};
+use crate::{Float2, Float4};
+
+// position, rotation and scale of a node relative to its parent
+// applied in the order scale, rotation, translation
+#[derive(Clone, Copy)]
+pub struct Transform2D {
+    pub position: Float2,
+
+    // counter-clockwise in radians
+    pub rotation: f32,
+
+    pub scale: Float2,
+}
+
+impl Transform2D {
+    pub const IDENTITY: Transform2D = Transform2D {
+        position: Float2 { x: 0.0, y: 0.0 },
+        rotation: 0.0,
+        scale: Float2 { x: 1.0, y: 1.0 },
+    };
+
+    pub fn apply(&self, point: Float2) -> Float2 {
+        let x = point.x * self.scale.x;
+        let y = point.y * self.scale.y;
+
+        let (sin, cos) = self.rotation.sin_cos();
+
+        Float2 {
+            x: self.position.x + x * cos - y * sin,
+            y: self.position.y + x * sin + y * cos,
+        }
+    }
+
+    // the transform of a child in the space of the parent of self
+    // a non-uniform scale combined with a rotation would need a shear, the scales are multiplied instead
+    pub fn combine(&self, child: &Transform2D) -> Transform2D {
+        Transform2D {
+            position: self.apply(child.position),
+            rotation: self.rotation + child.rotation,
+            scale: Float2 {
+                x: self.scale.x * child.scale.x,
+                y: self.scale.y * child.scale.y,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SceneNodeId(usize);
+
+#[derive(Clone)]
+pub struct SceneNode {
+    pub transform: Transform2D,
+
+    // hides the node together with all of its children
+    pub visible: bool,
+
+    // multiplied with the tint of the parents and the color of the sprites
+    pub tint: Float4,
+
+    // multiplied with the opacity of the parents, fades the whole subtree
+    pub opacity: f32,
+
+    // added to the layer of the parent, the layers of the sprites are relative to the layer of the node
+    pub layer: i32,
+
+    // in the space of the node
+    pub sprites: Vec<Sprite>,
+
+    children: Vec<SceneNodeId>,
+}
+
+// a tree of nodes that is kept alive between frames and flattened into sprites every time it's drawn
+// inside of a layer parents are drawn before their children and siblings in the order they have been added
+#[derive(Clone)]
+pub struct Scene {
+    nodes: Vec<SceneNode>,
+    roots: Vec<SceneNodeId>,
+}
+
+// the state a node inherits from its parents
+struct InheritedState {
+    transform: Transform2D,
+    tint: Float4,
+    opacity: f32,
+    layer: i32,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    // adds an empty, visible node without any transformation
+    pub fn add_node(&mut self, parent: Option<SceneNodeId>) -> SceneNodeId {
+        let id = SceneNodeId(self.nodes.len());
+
+        self.nodes.push(SceneNode {
+            transform: Transform2D::IDENTITY,
+            visible: true,
+            tint: Float4 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+                a: 1.0,
+            },
+            opacity: 1.0,
+            layer: 0,
+            sprites: Vec::new(),
+            children: Vec::new(),
+        });
+
+        match parent {
+            Some(parent) => self.nodes[parent.0].children.push(id),
+            None => self.roots.push(id),
+        }
+
+        id
+    }
+
+    pub fn node_mut(&mut self, id: SceneNodeId) -> &mut SceneNode {
+        &mut self.nodes[id.0]
+    }
+
+    // the sprites of all visible nodes in game space, sorted by layer
+    pub fn flatten(&self) -> Vec<Sprite> {
+        let root_state = InheritedState {
+            transform: Transform2D::IDENTITY,
+            tint: Float4 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+                a: 1.0,
+            },
+            opacity: 1.0,
+            layer: 0,
+        };
+
+        let mut sprites = Vec::new();
+
+        for root in self.roots.iter() {
+            self.flatten_node(*root, &root_state, &mut sprites);
+        }
+
+        // stable, keeps the tree order inside of a layer
+        sprites.sort_by_key(|x| x.layer);
+
+        sprites
+    }
+
+    fn flatten_node(&self, id: SceneNodeId, parent: &InheritedState, sprites: &mut Vec<Sprite>) {
+        let node = &self.nodes[id.0];
+
+        if !node.visible {
+            return;
+        }
+
+        let state = InheritedState {
+            transform: parent.transform.combine(&node.transform),
+            tint: Float4 {
+                x: parent.tint.x * node.tint.x,
+                y: parent.tint.y * node.tint.y,
+                z: parent.tint.z * node.tint.z,
+                a: parent.tint.a * node.tint.a,
+            },
+            opacity: parent.opacity * node.opacity,
+            layer: parent.layer + node.layer,
+        };
+
+        for local in node.sprites.iter() {
+            let mut sprite = *local;
+
+            sprite.layer = state.layer + local.layer;
+            sprite.position = state.transform.apply(local.position);
+            sprite.rotation = state.transform.rotation + local.rotation;
+            sprite.scale = Float2 {
+                x: state.transform.scale.x * local.scale.x,
+                y: state.transform.scale.y * local.scale.y,
+            };
+            sprite.color = Float4 {
+                x: state.tint.x * local.color.x,
+                y: state.tint.y * local.color.y,
+                z: state.tint.z * local.color.z,
+                a: state.tint.a * local.color.a * state.opacity,
+            };
+
+            sprites.push(sprite);
+        }
+
+        for child in node.children.iter() {
+            self.flatten_node(*child, &state, sprites);
+        }
+    }
+
+    // the sprite batch groups the sprites by texture inside of each layer
+    pub fn draw(&self, sprite_batch: &mut SpriteBatch) {
+        for sprite in self.flatten() {
+            sprite_batch.add(sprite);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_device::resource_handles::ResourcePool;
+    use graphics_device::TextureHandle;
+
+    fn sprite(layer: i32, position: Float2) -> Sprite {
+        let mut pool = ResourcePool::new();
+
+        Sprite::new(
+            TextureHandle(pool.insert(())),
+            layer,
+            position,
+            Float2 { x: 10.0, y: 10.0 },
+        )
+    }
+
+    #[test]
+    fn children_inherit_the_transform_of_their_parents() {
+        let mut scene = Scene::new();
+
+        let parent = scene.add_node(None);
+        let child = scene.add_node(Some(parent));
+
+        scene.node_mut(parent).transform = Transform2D {
+            position: Float2 { x: 100.0, y: 50.0 },
+            rotation: std::f32::consts::FRAC_PI_2,
+            scale: Float2 { x: 2.0, y: 2.0 },
+        };
+
+        scene.node_mut(child).transform.position = Float2 { x: 10.0, y: 0.0 };
+        scene
+            .node_mut(child)
+            .sprites
+            .push(sprite(0, Float2 { x: 5.0, y: 0.0 }));
+
+        let sprites = scene.flatten();
+        assert_eq!(sprites.len(), 1);
+
+        // 15 pixels along the x axis of the parent, scaled by 2 and rotated onto the y axis
+        assert!((sprites[0].position.x - 100.0).abs() < 0.001);
+        assert!((sprites[0].position.y - 80.0).abs() < 0.001);
+        assert_eq!(sprites[0].scale.x, 2.0);
+        assert!((sprites[0].rotation - std::f32::consts::FRAC_PI_2).abs() < 0.001);
+    }
+
+    #[test]
+    fn sprites_are_sorted_by_their_accumulated_layer() {
+        let mut scene = Scene::new();
+
+        let popup = scene.add_node(None);
+        let board = scene.add_node(None);
+        let tile = scene.add_node(Some(board));
+
+        scene.node_mut(popup).layer = 10;
+        scene.node_mut(board).layer = 2;
+        scene.node_mut(tile).layer = 1;
+
+        scene
+            .node_mut(popup)
+            .sprites
+            .push(sprite(0, Float2 { x: 1.0, y: 0.0 }));
+        scene
+            .node_mut(board)
+            .sprites
+            .push(sprite(1, Float2 { x: 2.0, y: 0.0 }));
+        scene
+            .node_mut(tile)
+            .sprites
+            .push(sprite(0, Float2 { x: 3.0, y: 0.0 }));
+
+        let sprites = scene.flatten();
+
+        let layers: Vec<i32> = sprites.iter().map(|x| x.layer).collect();
+        assert_eq!(layers, vec![3, 3, 10]);
+
+        // the parent is drawn before its child on the same layer
+        let order: Vec<f32> = sprites.iter().map(|x| x.position.x).collect();
+        assert_eq!(order, vec![2.0, 3.0, 1.0]);
+    }
+
+    #[test]
+    fn tint_and_opacity_fade_a_whole_subtree() {
+        let mut scene = Scene::new();
+
+        let root = scene.add_node(None);
+        let child = scene.add_node(Some(root));
+        let hidden = scene.add_node(Some(root));
+
+        scene.node_mut(root).opacity = 0.5;
+        scene.node_mut(root).tint = Float4 {
+            x: 1.0,
+            y: 0.5,
+            z: 1.0,
+            a: 1.0,
+        };
+        scene.node_mut(child).opacity = 0.5;
+
+        let mut red = sprite(0, Float2 { x: 0.0, y: 0.0 });
+        red.color = Float4 {
+            x: 1.0,
+            y: 0.0,
+            z: 1.0,
+            a: 1.0,
+        };
+        scene.node_mut(child).sprites.push(red);
+
+        scene.node_mut(hidden).visible = false;
+        scene
+            .node_mut(hidden)
+            .sprites
+            .push(sprite(0, Float2 { x: 0.0, y: 0.0 }));
+
+        let sprites = scene.flatten();
+        assert_eq!(sprites.len(), 1);
+
+        let color = sprites[0].color;
+        assert_eq!([color.x, color.y, color.z, color.a], [1.0, 0.0, 1.0, 0.25]);
+
+        // hiding the root hides everything below it
+        scene.node_mut(root).visible = false;
+        assert!(scene.flatten().is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{Float2, Float4, GameSpaceSpriteData};
+use graphics_device::*;
+
+// must match the size of the quads array in game_space_quad_batch.hlsl
+pub const MAX_SPRITES_PER_DRAW: usize = 1024;
+
+// the part of the texture a sprite shows, in texture coordinates with 0,0 at the top-left of the texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl UvRect {
+    pub const FULL: UvRect = UvRect {
+        left: 0.0,
+        top: 0.0,
+        right: 1.0,
+        bottom: 1.0,
+    };
+
+    // the layout the shaders expect, left, top, right, bottom
+    pub fn to_float4(self) -> Float4 {
+        Float4 {
+            x: self.left,
+            y: self.top,
+            z: self.right,
+            a: self.bottom,
+        }
+    }
+}
+
+// a textured quad in game space
+#[derive(Clone, Copy)]
+pub struct Sprite {
+    pub texture: TextureHandle,
+
+    // sprites on higher layers are drawn on top of sprites on lower layers
+    pub layer: i32,
+
+    pub color: Float4,
+    pub uv_rect: UvRect,
+
+    // game space position of the pivot in pixels
+    pub position: Float2,
+
+    // size in pixels before the scale is applied
+    pub size: Float2,
+
+    // a negative scale mirrors the sprite around the pivot
+    pub scale: Float2,
+
+    // relative to the size, 0,0 is the bottom-left and 1,1 the top-right corner
+    pub pivot: Float2,
+
+    // counter-clockwise around the pivot in radians
+    pub rotation: f32,
+
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Sprite {
+    // shows the whole texture without any transformation, the pivot is the bottom-left corner
+    pub fn new(texture: TextureHandle, layer: i32, position: Float2, size: Float2) -> Sprite {
+        Sprite {
+            texture,
+            layer,
+            color: Float4 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+                a: 1.0,
+            },
+            uv_rect: UvRect::FULL,
+            position,
+            size,
+            scale: Float2 { x: 1.0, y: 1.0 },
+            pivot: Float2 { x: 0.0, y: 0.0 },
+            rotation: 0.0,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+
+    // the shader only supports positive sizes, a negative size would flip the winding order and the quad gets culled
+    // mirroring around the pivot is the same as flipping the texture and mirroring the pivot
+    pub fn quad_data(&self) -> GameSpaceSpriteData {
+        let mut uv_rect = self.uv_rect;
+        let mut pivot = self.pivot;
+
+        let size_x = self.size.x * self.scale.x;
+        let size_y = self.size.y * self.scale.y;
+
+        if self.flip_x != (size_x < 0.0) {
+            std::mem::swap(&mut uv_rect.left, &mut uv_rect.right);
+        }
+
+        if self.flip_y != (size_y < 0.0) {
+            std::mem::swap(&mut uv_rect.top, &mut uv_rect.bottom);
+        }
+
+        if size_x < 0.0 {
+            pivot.x = 1.0 - pivot.x;
+        }
+
+        if size_y < 0.0 {
+            pivot.y = 1.0 - pivot.y;
+        }
+
+        GameSpaceSpriteData {
+            color: self.color,
+            uv_rect: uv_rect.to_float4(),
+            position: self.position,
+            size: Float2 {
+                x: size_x.abs(),
+                y: size_y.abs(),
+            },
+            pivot,
+            rotation: Float2 {
+                x: self.rotation.cos(),
+                y: self.rotation.sin(),
+            },
+        }
+    }
+}
+
+struct SpriteBatchEntry {
+    texture: TextureHandle,
+    layer: i32,
+    quad: GameSpaceSpriteData,
+}
+
+// collects game space quads and draws all quads that share a texture with a single instanced draw
+// sprites are drawn ordered by layer, inside of a layer the draw order is not defined
+pub struct SpriteBatch {
+    sprites: Vec<SpriteBatchEntry>,
+}
+
+impl SpriteBatch {
+    pub fn new() -> SpriteBatch {
+        SpriteBatch {
+            sprites: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, sprite: Sprite) {
+        self.sprites.push(SpriteBatchEntry {
+            texture: sprite.texture,
+            layer: sprite.layer,
+            quad: sprite.quad_data(),
+        });
+    }
+
+    // the pso needs to use the game_space_quad_batch shader
+    // all sprites are removed from the batch after they have been submitted
+    pub fn submit(
+        &mut self,
+        command_list: &mut GraphicsCommandList,
+        resources: &ResourceRegistry,
+        pso: PsoHandle,
+        gpu_heap: &mut LinearAllocator,
+    ) -> Result<(), GraphicsError> {
+        if self.sprites.is_empty() {
+            return Ok(());
+        }
+
+        // stable sort, sprites with the same layer and texture keep the order they have been added in
+        self.sprites.sort_by_key(|x| (x.layer, x.texture));
+
+        bind_pso(command_list, resources.pso(pso)?)?;
+
+        let mut quads: Vec<GameSpaceSpriteData> = Vec::with_capacity(MAX_SPRITES_PER_DRAW);
+
+        for sprites in self.sprites.chunk_by(|a, b| a.texture == b.texture) {
+            let texture = resources.texture(sprites[0].texture)?;
+
+            bind_texture(command_list, 0, &texture.srv)?;
+
+            for chunk in sprites.chunks(MAX_SPRITES_PER_DRAW) {
+                quads.clear();
+                quads.extend(chunk.iter().map(|x| x.quad));
+
+                let obj_alloc = HeapAlloc::new_array(&quads, gpu_heap)?;
+
+                bind_constant(command_list, 0, &obj_alloc)?;
+
+                draw_vertices_instanced(command_list, 4, quads.len() as u32)?;
+            }
+        }
+
+        self.sprites.clear();
+
+        Ok(())
+    }
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        SpriteBatch::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_device::resource_handles::ResourcePool;
+
+    fn test_texture() -> TextureHandle {
+        let mut pool = ResourcePool::new();
+        TextureHandle(pool.insert(()))
+    }
+
+    #[test]
+    fn negative_scale_flips_the_texture_and_mirrors_the_pivot() {
+        let sprite = Sprite {
+            scale: Float2 { x: -2.0, y: 1.0 },
+            pivot: Float2 { x: 0.25, y: 0.5 },
+            flip_y: true,
+            ..Sprite::new(
+                test_texture(),
+                0,
+                Float2 { x: 100.0, y: 200.0 },
+                Float2 { x: 30.0, y: 40.0 },
+            )
+        };
+
+        let quad = sprite.quad_data();
+
+        assert_eq!((quad.size.x, quad.size.y), (60.0, 40.0));
+        assert_eq!((quad.pivot.x, quad.pivot.y), (0.75, 0.5));
+        assert_eq!(
+            (
+                quad.uv_rect.x,
+                quad.uv_rect.y,
+                quad.uv_rect.z,
+                quad.uv_rect.a
+            ),
+            (1.0, 1.0, 0.0, 0.0)
+        );
+
+        // flipping a mirrored sprite again shows the texture the right way around
+        let quad = Sprite {
+            flip_x: true,
+            ..sprite
+        }
+        .quad_data();
+
+        assert_eq!(quad.uv_rect.x, 0.0);
+        assert_eq!(quad.uv_rect.z, 1.0);
+    }
+}
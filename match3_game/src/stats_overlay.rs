@@ -0,0 +1,220 @@
+use crate::bitmap_font::{BitmapFont, TextStyle};
+use crate::profiler::FrameTimeHistory;
+use crate::sprite_batch::{Sprite, SpriteBatch};
+use crate::{Float2, Float4};
+use graphics_device::*;
+
+// in game space, the overlay is anchored at the top-left corner of the canvas
+const OVERLAY_POSITION: Float2 = Float2 { x: 10.0, y: 950.0 };
+const OVERLAY_PADDING: f32 = 6.0;
+const TEXT_SCALE: f32 = 0.5;
+
+// gathered at the end of every frame, the overlay shows the numbers of the previous frame
+#[derive(Clone, Copy, Default)]
+pub struct FrameStats {
+    pub command_list: CommandListStats,
+    pub gpu_heap: LinearAllocatorStats,
+    pub game_state_count: usize,
+    pub update_ticks: u32,
+}
+
+pub fn stats_lines(stats: &FrameStats, frame_times: &FrameTimeHistory) -> Vec<String> {
+    let commands = &stats.command_list;
+    let gpu_heap = &stats.gpu_heap;
+
+    let gpu_heap_percent = if gpu_heap.capacity_bytes > 0 {
+        gpu_heap.frame_used_bytes as f32 / gpu_heap.capacity_bytes as f32 * 100.0
+    } else {
+        0.0
+    };
+
+    vec![
+        format!(
+            "draws: {}  passes: {}",
+            commands.draw_calls, commands.render_passes
+        ),
+        format!(
+            "binds: {} pso  {} texture  {} constant",
+            commands.pso_binds, commands.texture_binds, commands.constant_binds
+        ),
+        format!(
+            "gpu heap: {:.1} / {} KB ({:.0}%)",
+            gpu_heap.frame_used_bytes as f32 / 1024.0,
+            gpu_heap.capacity_bytes / 1024,
+            gpu_heap_percent
+        ),
+        format!(
+            "states: {}  ticks: {}",
+            stats.game_state_count, stats.update_ticks
+        ),
+        format!(
+            "frame ms: p50 {:.1}  p95 {:.1}  p99 {:.1}",
+            frame_times.percentile(50.0),
+            frame_times.percentile(95.0),
+            frame_times.percentile(99.0)
+        ),
+    ]
+}
+
+// text on a dark panel on top of all game states, independent of the debug draw overlay
+pub struct StatsOverlay {
+    // toggled at runtime
+    pub visible: bool,
+
+    pso: PsoHandle,
+    texture_white: TextureHandle,
+    font: BitmapFont,
+}
+
+impl StatsOverlay {
+    pub fn new(
+        device: &GraphicsDevice,
+        resources: &mut ResourceRegistry,
+        visible: bool,
+    ) -> Result<StatsOverlay, GraphicsError> {
+        let pso = resources.load_pso(
+            device,
+            PipelineStateObjectDesc {
+                shader_name: "target_data/shaders/game_space_quad_batch",
+                blend_mode: BlendMode::Alpha,
+                sampler: SamplerDesc::default(),
+            },
+        )?;
+
+        let texture_white =
+            resources.load_texture(device, "target_data/textures/engine/white.dds")?;
+
+        let font = BitmapFont::load(
+            device,
+            resources,
+            "target_data/fonts/DejaVu/dejavu_sans_bold_32.fnt",
+        )?;
+
+        Ok(StatsOverlay {
+            visible,
+            pso,
+            texture_white,
+            font,
+        })
+    }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        resources.release_pso(self.pso).unwrap();
+        resources.release_texture(self.texture_white).unwrap();
+        self.font.release(resources);
+    }
+
+    // draws into the canvas on top of everything that has been drawn so far
+    pub fn draw(
+        &self,
+        command_list: &mut GraphicsCommandList,
+        canvas_rtv: &RenderTargetView,
+        resources: &ResourceRegistry,
+        gpu_heap: &mut LinearAllocator,
+        stats: &FrameStats,
+        frame_times: &FrameTimeHistory,
+    ) -> Result<(), GraphicsError> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let lines = stats_lines(stats, frame_times);
+
+        let line_height = self.font.desc.line_height as f32 * TEXT_SCALE;
+        let text_width = lines
+            .iter()
+            .map(|x| self.font.desc.line_width(x))
+            .max()
+            .unwrap_or(0) as f32
+            * TEXT_SCALE;
+
+        let panel_size = Float2 {
+            x: text_width + OVERLAY_PADDING * 2.0,
+            y: line_height * lines.len() as f32 + OVERLAY_PADDING * 2.0,
+        };
+
+        let mut sprite_batch = SpriteBatch::new();
+
+        let mut panel = Sprite::new(
+            self.texture_white,
+            0,
+            Float2 {
+                x: OVERLAY_POSITION.x,
+                y: OVERLAY_POSITION.y - panel_size.y,
+            },
+            panel_size,
+        );
+
+        panel.color = Float4 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            a: 0.7,
+        };
+
+        sprite_batch.add(panel);
+
+        let mut style = TextStyle::new(1);
+        style.scale = TEXT_SCALE;
+
+        for (index, line) in lines.iter().enumerate() {
+            self.font.draw_text(
+                &mut sprite_batch,
+                line,
+                Float2 {
+                    x: OVERLAY_POSITION.x + OVERLAY_PADDING,
+                    y: OVERLAY_POSITION.y - OVERLAY_PADDING - index as f32 * line_height,
+                },
+                &style,
+            );
+        }
+
+        begin_render_pass(command_list, canvas_rtv)?;
+
+        sprite_batch.submit(command_list, resources, self.pso, gpu_heap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_are_formatted_into_lines() {
+        let stats = FrameStats {
+            command_list: CommandListStats {
+                render_passes: 3,
+                draw_calls: 12,
+                pso_binds: 4,
+                texture_binds: 7,
+                constant_binds: 12,
+            },
+            gpu_heap: LinearAllocatorStats {
+                frame_used_bytes: 32 * 1024,
+                frame_pages: 1,
+                high_water_mark_bytes: 32 * 1024,
+                high_water_mark_pages: 1,
+                page_count: 2,
+                capacity_bytes: 128 * 1024,
+            },
+            game_state_count: 2,
+            update_ticks: 1,
+        };
+
+        let mut frame_times = FrameTimeHistory::new(4);
+        for milliseconds in [16.0, 17.0, 16.5, 30.0].iter() {
+            frame_times.push(*milliseconds);
+        }
+
+        assert_eq!(
+            stats_lines(&stats, &frame_times),
+            vec![
+                "draws: 12  passes: 3",
+                "binds: 4 pso  7 texture  12 constant",
+                "gpu heap: 32.0 / 128 KB (25%)",
+                "states: 2  ticks: 1",
+                "frame ms: p50 16.5  p95 30.0  p99 30.0",
+            ]
+        );
+    }
+}
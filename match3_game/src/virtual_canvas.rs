@@ -0,0 +1,328 @@
+use crate::post_process::{PostProcessChain, PostProcessEffect};
+use crate::sprite_batch::UvRect;
+use crate::{Float2, Float4, ScreenSpaceQuadData};
+use graphics_device::*;
+
+// size of game space in pixels, all game states render into a canvas of this size
+// the canvas is scaled to fit into the backbuffer, the area around it is filled with black bars
+pub const CANVAS_WIDTH: i32 = 540;
+pub const CANVAS_HEIGHT: i32 = 960;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CanvasScaling {
+    // largest whole number scale that fits, every game pixel covers the same number of backbuffer pixels
+    // backbuffers smaller than the canvas fall back to Aspect
+    Integer,
+
+    // largest scale that fits while keeping the aspect ratio
+    Aspect,
+}
+
+// area of the backbuffer the canvas is drawn to, in pixels with the origin at the top-left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CanvasRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+pub fn canvas_rect(
+    backbuffer_width: i32,
+    backbuffer_height: i32,
+    scaling: CanvasScaling,
+) -> CanvasRect {
+    let scale_x = backbuffer_width as f32 / CANVAS_WIDTH as f32;
+    let scale_y = backbuffer_height as f32 / CANVAS_HEIGHT as f32;
+
+    let mut scale = scale_x.min(scale_y);
+
+    if scaling == CanvasScaling::Integer && scale >= 1.0 {
+        scale = scale.floor();
+    }
+
+    let width = ((CANVAS_WIDTH as f32 * scale).round() as i32).max(1);
+    let height = ((CANVAS_HEIGHT as f32 * scale).round() as i32).max(1);
+
+    // center the canvas, the bars on both sides have the same size
+    CanvasRect {
+        x: (backbuffer_width - width) / 2,
+        y: (backbuffer_height - height) / 2,
+        width,
+        height,
+    }
+}
+
+// converts a position in the client area of the window into game space pixels, y going up
+// returns None for positions on the bars around the canvas
+pub fn window_to_game_space(rect: &CanvasRect, x: i32, y: i32) -> Option<(i32, i32)> {
+    if x < rect.x || y < rect.y || x >= rect.x + rect.width || y >= rect.y + rect.height {
+        return None;
+    }
+
+    // map the center of the window pixel, this matches the game pixel the rasterizer would draw there
+    let canvas_x = ((x - rect.x) as f32 + 0.5) * CANVAS_WIDTH as f32 / rect.width as f32;
+    let canvas_y = ((y - rect.y) as f32 + 0.5) * CANVAS_HEIGHT as f32 / rect.height as f32;
+
+    Some((canvas_x as i32, CANVAS_HEIGHT - 1 - canvas_y as i32))
+}
+
+// client size of the largest window that fits onto the monitor
+// leaves some space for the window frame and title bar
+pub fn initial_window_size(
+    work_area_width: i32,
+    work_area_height: i32,
+    scaling: CanvasScaling,
+) -> (i32, i32) {
+    let rect = canvas_rect(work_area_width * 9 / 10, work_area_height * 9 / 10, scaling);
+
+    (rect.width, rect.height)
+}
+
+pub struct VirtualCanvas<'a> {
+    pub render_target: RenderTarget<'a>,
+    pub scaling: CanvasScaling,
+
+    // point sampling is used when the canvas is scaled by a whole number, keeps the pixels sharp
+    point_sampled_pso: PsoHandle,
+    linear_sampled_pso: PsoHandle,
+
+    // game states can request effects that are applied to the content of the canvas
+    post_process: PostProcessChain<'a>,
+}
+
+impl<'a> VirtualCanvas<'a> {
+    pub fn new(
+        device: &GraphicsDevice,
+        resources: &mut ResourceRegistry,
+        scaling: CanvasScaling,
+    ) -> Result<VirtualCanvas<'a>, GraphicsError> {
+        let render_target = create_render_target(device, CANVAS_WIDTH, CANVAS_HEIGHT, "Canvas")?;
+
+        let pso_desc = |filter| PipelineStateObjectDesc {
+            shader_name: "target_data/shaders/screen_space_quad",
+            blend_mode: BlendMode::Opaque,
+            sampler: SamplerDesc {
+                filter,
+                ..SamplerDesc::default()
+            },
+        };
+
+        let point_sampled_pso = resources.load_pso(device, pso_desc(SamplerFilter::Point))?;
+        let linear_sampled_pso = resources.load_pso(device, pso_desc(SamplerFilter::Linear))?;
+
+        let post_process = PostProcessChain::new(device, resources, CANVAS_WIDTH, CANVAS_HEIGHT)?;
+
+        Ok(VirtualCanvas {
+            render_target,
+            scaling,
+            point_sampled_pso,
+            linear_sampled_pso,
+            post_process,
+        })
+    }
+
+    pub fn release(&self, resources: &mut ResourceRegistry) {
+        resources.release_pso(self.point_sampled_pso).unwrap();
+        resources.release_pso(self.linear_sampled_pso).unwrap();
+        self.post_process.release(resources);
+    }
+
+    // applies the effects to everything that has been drawn into the canvas so far
+    pub fn apply_post_process(
+        &self,
+        effects: &[PostProcessEffect],
+        command_list: &mut GraphicsCommandList,
+        resources: &ResourceRegistry,
+        gpu_heap: &mut LinearAllocator,
+    ) -> Result<(), GraphicsError> {
+        self.post_process.apply(
+            effects,
+            command_list,
+            &self.render_target,
+            resources,
+            gpu_heap,
+        )
+    }
+
+    pub fn rect(&self, backbuffer_rtv: &RenderTargetView) -> CanvasRect {
+        canvas_rect(backbuffer_rtv.width, backbuffer_rtv.height, self.scaling)
+    }
+
+    // draws the canvas into the backbuffer, the area around the canvas is cleared to black
+    pub fn present(
+        &self,
+        command_list: &mut GraphicsCommandList,
+        backbuffer_rtv: &RenderTargetView,
+        resources: &ResourceRegistry,
+        gpu_heap: &mut LinearAllocator,
+    ) -> Result<(), GraphicsError> {
+        let rect = self.rect(backbuffer_rtv);
+
+        begin_render_pass_and_clear(command_list, [0.0, 0.0, 0.0, 1.0], backbuffer_rtv)?;
+
+        let pso = if rect.width % CANVAS_WIDTH == 0 {
+            self.point_sampled_pso
+        } else {
+            self.linear_sampled_pso
+        };
+
+        bind_pso(command_list, resources.pso(pso)?)?;
+        bind_texture(command_list, 0, &self.render_target.srv)?;
+
+        let backbuffer_width = backbuffer_rtv.width as f32;
+        let backbuffer_height = backbuffer_rtv.height as f32;
+
+        // the screen space quad covers the whole backbuffer before scale and position are applied
+        let obj_alloc = HeapAlloc::new(
+            ScreenSpaceQuadData {
+                color: Float4 {
+                    x: 1.0,
+                    y: 1.0,
+                    z: 1.0,
+                    a: 1.0,
+                },
+                scale: Float2 {
+                    x: rect.width as f32 / backbuffer_width,
+                    y: rect.height as f32 / backbuffer_height,
+                },
+                position: Float2 {
+                    x: (rect.x * 2 + rect.width) as f32 / backbuffer_width - 1.0,
+                    y: 1.0 - (rect.y * 2 + rect.height) as f32 / backbuffer_height,
+                },
+                uv_rect: UvRect::FULL.to_float4(),
+            },
+            gpu_heap,
+        )?;
+
+        bind_constant(command_list, 0, &obj_alloc)?;
+
+        draw_vertices(command_list, 4)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas_is_centered_with_letterbox_bars() {
+        // 2560x1440, the canvas is limited by the height
+        assert_eq!(
+            canvas_rect(2560, 1440, CanvasScaling::Aspect),
+            CanvasRect {
+                x: 875,
+                y: 0,
+                width: 810,
+                height: 1440,
+            }
+        );
+
+        assert_eq!(
+            canvas_rect(2560, 1440, CanvasScaling::Integer),
+            CanvasRect {
+                x: 1010,
+                y: 240,
+                width: 540,
+                height: 960,
+            }
+        );
+
+        // 3840x2160 fits a 2x canvas
+        assert_eq!(
+            canvas_rect(3840, 2160, CanvasScaling::Integer),
+            CanvasRect {
+                x: 1380,
+                y: 120,
+                width: 1080,
+                height: 1920,
+            }
+        );
+
+        // windows smaller than the canvas are scaled down even with integer scaling
+        assert_eq!(
+            canvas_rect(270, 600, CanvasScaling::Integer),
+            CanvasRect {
+                x: 0,
+                y: 60,
+                width: 270,
+                height: 480,
+            }
+        );
+    }
+
+    #[test]
+    fn window_positions_map_to_game_space() {
+        let rect = canvas_rect(3840, 2160, CanvasScaling::Integer);
+
+        // the top-left window pixel of the canvas is the top-left game pixel
+        assert_eq!(window_to_game_space(&rect, 1380, 120), Some((0, 959)));
+        assert_eq!(window_to_game_space(&rect, 1381, 121), Some((0, 959)));
+        assert_eq!(window_to_game_space(&rect, 1382, 122), Some((1, 958)));
+
+        assert_eq!(
+            window_to_game_space(&rect, 1380 + 1079, 120 + 1919),
+            Some((539, 0))
+        );
+
+        // positions on the bars are not part of game space
+        assert_eq!(window_to_game_space(&rect, 1379, 500), None);
+        assert_eq!(window_to_game_space(&rect, 2000, 2040), None);
+    }
+
+    #[test]
+    fn present_scales_the_canvas_into_the_backbuffer() {
+        let mut graphics_layer: GraphicsDeviceLayer =
+            create_device_graphics_layer_software(1280, 1200).unwrap();
+
+        let canvas = VirtualCanvas::new(
+            &graphics_layer.device,
+            &mut graphics_layer.resources,
+            CanvasScaling::Aspect,
+        )
+        .unwrap();
+
+        let mut gpu_heap = LinearAllocator::new(&graphics_layer.device, 1024);
+        gpu_heap.begin_frame(0);
+
+        let command_list = &mut graphics_layer.graphics_command_list;
+
+        begin_render_pass_and_clear(
+            command_list,
+            [1.0, 0.0, 0.0, 1.0],
+            &canvas.render_target.rtv,
+        )
+        .unwrap();
+
+        canvas
+            .present(
+                command_list,
+                &graphics_layer.backbuffer_rtv,
+                &graphics_layer.resources,
+                &mut gpu_heap,
+            )
+            .unwrap();
+
+        gpu_heap.end_frame();
+
+        // scaled by 1.25 to fill the height of the backbuffer
+        let rect = canvas.rect(&graphics_layer.backbuffer_rtv);
+        assert_eq!((rect.x, rect.width), (302, 675));
+
+        let backbuffer = graphics_layer
+            .backbuffer_rtv
+            .software_target
+            .as_ref()
+            .unwrap()
+            .borrow();
+
+        assert_eq!(backbuffer.pixel(301, 600), [0, 0, 0, 255]);
+        assert_eq!(backbuffer.pixel(302, 0), [255, 0, 0, 255]);
+        assert_eq!(backbuffer.pixel(976, 1199), [255, 0, 0, 255]);
+        assert_eq!(backbuffer.pixel(977, 600), [0, 0, 0, 255]);
+
+        canvas.release(&mut graphics_layer.resources);
+    }
+}
@@ -14,6 +14,7 @@ struct VertexToPixelShader
 Texture2D Texture;
 SamplerState Sampler;
 
+// game space is the 540x960 canvas the game renders into, see virtual_canvas.rs
 float4 TransformWorldToScreen(int2 world_space_pos)
 {
 	float2 screen_space_pos = float2(
@@ -0,0 +1,71 @@
+struct GameSpaceSpriteData
+{
+	float4 color;
+	float4 uv_rect; // left, top, right, bottom
+	float2 position; // position of the pivot in pixels
+	float2 size; // in pixels, scale is already applied
+	float2 pivot; // relative to the size, 0,0 is bottom-left and 1,1 is top-right
+	float2 rotation; // cos and sin of the counter-clockwise rotation around the pivot
+};
+
+// one entry for each instance
+// a constant buffer binding can be at most 4096 constants (64kb) large, each entry uses 4 constants
+cbuffer GameSpaceQuadBatchData : register(b0)
+{
+	GameSpaceSpriteData quads[1024];
+};
+
+struct VertexToPixelShader
+{
+	float4 position_clip : SV_POSITION;
+	float2 uv : TEXCOORD0;
+	nointerpolation float4 color : COLOR0;
+};
+
+Texture2D Texture;
+SamplerState Sampler;
+
+// game space is the 540x960 canvas the game renders into, see virtual_canvas.rs
+float4 TransformWorldToScreen(float2 world_space_pos)
+{
+	float2 screen_space_pos = float2(
+		(world_space_pos.x / 540.0f) * 2 - 1,
+		(world_space_pos.y / 960.0f) * 2 - 1);
+
+	return float4(screen_space_pos, 0, 1);
+}
+
+VertexToPixelShader VS_main(uint vertex_id: SV_VertexID, uint instance_id: SV_InstanceID)
+{
+	VertexToPixelShader output;
+
+	GameSpaceSpriteData quad = quads[instance_id];
+
+	// corner of the sprite, 0,0 is bottom-left
+	float2 corner = float2(0, 0);
+
+	switch (vertex_id) {
+	case 0: corner = float2(0, 1); output.uv = quad.uv_rect.xy; break; // top-left
+	case 1: corner = float2(1, 1); output.uv = quad.uv_rect.zy; break; // top-right
+	case 2: corner = float2(0, 0); output.uv = quad.uv_rect.xw; break; // bottom-left
+	case 3: corner = float2(1, 0); output.uv = quad.uv_rect.zw; break; // bottom-right
+	}
+
+	// rotate the corner around the pivot
+	float2 offset = (corner - quad.pivot) * quad.size;
+
+	float2 rotated_offset = float2(
+		offset.x * quad.rotation.x - offset.y * quad.rotation.y,
+		offset.x * quad.rotation.y + offset.y * quad.rotation.x);
+
+	output.position_clip = TransformWorldToScreen(quad.position + rotated_offset);
+
+	output.color = quad.color;
+
+	return output;
+}
+
+float4 PS_main(VertexToPixelShader input) : SV_TARGET
+{
+	return Texture.Sample(Sampler, input.uv) * input.color;
+}
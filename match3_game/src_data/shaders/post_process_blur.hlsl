@@ -0,0 +1,56 @@
+cbuffer PostProcessBlurData : register(b0)
+{
+	float2 texel_step; // (1 / width, 0) for the horizontal pass and (0, 1 / height) for the vertical pass
+	float sigma;       // in texels
+};
+
+struct VertexToPixelShader
+{
+	float4 position_clip : SV_POSITION;
+	float2 uv : TEXCOORD0;
+};
+
+// needs to be used with a point sampler and clamp addressing, every sample is the center of a texel
+Texture2D Texture;
+SamplerState Sampler;
+
+// a quad that covers the whole render target
+VertexToPixelShader VS_main(uint vertex_id: SV_VertexID)
+{
+	VertexToPixelShader output;
+
+	switch (vertex_id) {
+	case 0: output.position_clip = float4(-1,  1, 0, 1); output.uv = float2(0, 0); break; // top-left
+	case 1: output.position_clip = float4( 1,  1, 0, 1); output.uv = float2(1, 0); break; // top-right
+	case 2: output.position_clip = float4(-1, -1, 0, 1); output.uv = float2(0, 1); break; // bottom-left
+	case 3: output.position_clip = float4( 1, -1, 0, 1); output.uv = float2(1, 1); break; // bottom-right
+	}
+
+	return output;
+}
+
+// one direction of a separable gaussian blur, the CPU reference is blur_pass in software_post_process.rs
+float4 PS_main(VertexToPixelShader input) : SV_TARGET
+{
+	if (sigma <= 0)
+	{
+		return Texture.Sample(Sampler, input.uv);
+	}
+
+	// samples beyond 3 sigma contribute less than 0.3% and are skipped
+	int radius = (int)ceil(sigma * 3);
+
+	float4 result = 0;
+	float total_weight = 0;
+
+	[loop]
+	for (int i = -radius; i <= radius; ++i)
+	{
+		float weight = exp(-(i * i) / (2 * sigma * sigma));
+
+		result += Texture.Sample(Sampler, input.uv + texel_step * i) * weight;
+		total_weight += weight;
+	}
+
+	return result / total_weight;
+}
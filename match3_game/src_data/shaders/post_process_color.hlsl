@@ -0,0 +1,53 @@
+cbuffer PostProcessColorData : register(b0)
+{
+	float4 tint;                // multiplied with the result
+	float desaturation;         // 0 keeps the colors, 1 is grayscale
+	float contrast;             // scales the distance of each channel from 0.5
+	float brightness;           // added to each channel
+	float vignette_intensity;   // how much the edges are darkened
+	float vignette_radius;      // distance from the center where the vignette starts, 1 is the middle of the edges
+	float vignette_softness;    // distance it takes the vignette to reach full intensity
+};
+
+struct VertexToPixelShader
+{
+	float4 position_clip : SV_POSITION;
+	float2 uv : TEXCOORD0;
+};
+
+Texture2D Texture;
+SamplerState Sampler;
+
+// a quad that covers the whole render target
+VertexToPixelShader VS_main(uint vertex_id: SV_VertexID)
+{
+	VertexToPixelShader output;
+
+	switch (vertex_id) {
+	case 0: output.position_clip = float4(-1,  1, 0, 1); output.uv = float2(0, 0); break; // top-left
+	case 1: output.position_clip = float4( 1,  1, 0, 1); output.uv = float2(1, 0); break; // top-right
+	case 2: output.position_clip = float4(-1, -1, 0, 1); output.uv = float2(0, 1); break; // bottom-left
+	case 3: output.position_clip = float4( 1, -1, 0, 1); output.uv = float2(1, 1); break; // bottom-right
+	}
+
+	return output;
+}
+
+// desaturation, color grading and vignette in a single pass, the CPU reference is color_grade in software_post_process.rs
+float4 PS_main(VertexToPixelShader input) : SV_TARGET
+{
+	float4 color = Texture.Sample(Sampler, input.uv);
+
+	// rec. 709 luma weights
+	float luminance = dot(color.rgb, float3(0.2126, 0.7152, 0.0722));
+
+	float3 result = lerp(color.rgb, luminance.xxx, desaturation);
+	result = (result - 0.5) * contrast + 0.5 + brightness;
+
+	float distance_to_center = length((input.uv - 0.5) * 2);
+	float vignette = smoothstep(vignette_radius, vignette_radius + vignette_softness, distance_to_center);
+
+	result *= tint.rgb * (1 - vignette * vignette_intensity);
+
+	return saturate(float4(result, color.a * tint.a));
+}
@@ -3,6 +3,7 @@ cbuffer ScreenSpaceQuadData : register(b0)
 	float4 color;
 	float2 scale;
 	float2 position;
+	float4 uv_rect; // left, top, right, bottom
 };
 
 struct VertexToPixelShader
@@ -26,10 +27,10 @@ VertexToPixelShader VS_main(uint vertex_id: SV_VertexID)
     }
 
 	switch (vertex_id) {
-	case 0: output.uv = float2(0, 0); break; // top-left
-	case 1: output.uv = float2(1, 0); break; // top-right
-	case 2: output.uv = float2(0, 1); break; // bottom-left
-	case 3: output.uv = float2(1, 1); break; // bottom-right
+	case 0: output.uv = uv_rect.xy; break; // top-left
+	case 1: output.uv = uv_rect.zy; break; // top-right
+	case 2: output.uv = uv_rect.xw; break; // bottom-left
+	case 3: output.uv = uv_rect.zw; break; // bottom-right
 	}
 
 	output.position_clip.xy *= scale;
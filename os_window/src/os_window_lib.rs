@@ -1,14 +1,24 @@
+// windows are only implemented with winapi
+// on other platforms the window is never shown and doesn't receive any messages
+#[cfg(windows)]
 use std::ffi::OsStr;
+#[cfg(windows)]
 use std::os::windows::ffi::OsStrExt;
+#[cfg(windows)]
 use winapi::shared::minwindef::{HINSTANCE, LPARAM, LRESULT, UINT, WPARAM};
+#[cfg(windows)]
 use winapi::shared::ntdef::LPCWSTR;
+#[cfg(windows)]
 use winapi::shared::windef::{HBRUSH, HICON, HMENU, HWND};
+#[cfg(windows)]
 use winapi::um::winuser::*;
 
 pub struct WindowCreatedData {
+    #[cfg(windows)]
     pub hwnd: HWND,
 }
 
+#[cfg(windows)]
 unsafe impl std::marker::Send for WindowCreatedData {}
 
 pub struct MousePositionChangedData {
@@ -16,6 +26,23 @@ pub struct MousePositionChangedData {
     pub y: i32,
 }
 
+pub struct KeyDownData {
+    pub virtual_key: i32, // VK_* virtual key code
+}
+
+// the virtual key codes of the function keys, same values as in winuser.h
+pub const VK_F2: i32 = 0x71;
+pub const VK_F3: i32 = 0x72;
+pub const VK_F4: i32 = 0x73;
+pub const VK_F5: i32 = 0x74;
+pub const VK_F12: i32 = 0x7B;
+
+// size of the client area in pixels
+pub struct WindowResizedData {
+    pub width: i32,
+    pub height: i32,
+}
+
 pub enum WindowMessages {
     // mouse related messages
     MousePositionChanged(MousePositionChangedData),
@@ -26,21 +53,30 @@ pub enum WindowMessages {
     MouseFocusLost,
     MouseFocusGained,
 
+    // keyboard related messages
+    // only send when a key is pressed, holding the key down doesn't repeat the message
+    KeyDown(KeyDownData),
+
     // window related messages
     WindowCreated(WindowCreatedData),
     WindowClosed,
+    // not send while the window is minimized
+    WindowResized(WindowResizedData),
 }
 
 pub struct Window {
     message_receiver: std::sync::mpsc::Receiver<WindowMessages>,
+    #[cfg(windows)]
     pub hwnd: HWND,
 }
 
+#[cfg(windows)]
 pub struct WindowThreadState {
     pub message_sender: std::sync::mpsc::Sender<WindowMessages>,
     pub is_tracking: bool,
 }
 
+#[cfg(windows)]
 unsafe extern "system" fn window_proc(
     h_wnd: HWND,
     msg: UINT,
@@ -114,6 +150,36 @@ unsafe extern "system" fn window_proc(
             .unwrap();
     }
 
+    if msg == WM_KEYDOWN {
+        let window_state_ptr = GetWindowLongPtrW(h_wnd, GWLP_USERDATA) as *mut WindowThreadState;
+        let window_state: &mut WindowThreadState = window_state_ptr.as_mut().unwrap();
+
+        // bit 30 is set if the key was already down before this message
+        let is_repeat = (l_param >> 30) & 1 == 1;
+
+        if !is_repeat {
+            window_state
+                .message_sender
+                .send(WindowMessages::KeyDown(KeyDownData {
+                    virtual_key: w_param as i32,
+                }))
+                .unwrap();
+        }
+    }
+
+    if msg == WM_SIZE && w_param != SIZE_MINIMIZED {
+        let window_state_ptr = GetWindowLongPtrW(h_wnd, GWLP_USERDATA) as *mut WindowThreadState;
+        let window_state: &mut WindowThreadState = window_state_ptr.as_mut().unwrap();
+
+        window_state
+            .message_sender
+            .send(WindowMessages::WindowResized(WindowResizedData {
+                width: i32::from(winapi::shared::minwindef::LOWORD(l_param as u32)),
+                height: i32::from(winapi::shared::minwindef::HIWORD(l_param as u32)),
+            }))
+            .unwrap();
+    }
+
     if msg == WM_CREATE {
         // retrieve the message struct that contains the creation parameters
         let create_struct = l_param as *mut winapi::um::winuser::CREATESTRUCTW;
@@ -149,6 +215,7 @@ unsafe extern "system" fn window_proc(
     DefWindowProcW(h_wnd, msg, w_param, l_param)
 }
 
+#[cfg(windows)]
 pub fn create_window(size_x: i32, size_y: i32) -> Result<Window, ()> {
     let (channel_sender, channel_receiver) = std::sync::mpsc::channel();
 
@@ -189,18 +256,14 @@ pub fn create_window(size_x: i32, size_y: i32) -> Result<Window, ()> {
             };
 
             // calculate the required size of the window
-            AdjustWindowRectEx(
-                &mut window_rect,
-                WS_OVERLAPPEDWINDOW | WS_MINIMIZEBOX | WS_SYSMENU,
-                0,
-                0,
-            );
+            AdjustWindowRectEx(&mut window_rect, WS_OVERLAPPEDWINDOW, 0, 0);
 
+            // the window can be resized, the game is scaled to fit into the client area
             let h_wnd_window = CreateWindowExW(
                 0,
                 window_class_name.as_ptr(),
                 0 as LPCWSTR,
-                WS_OVERLAPPED | WS_MINIMIZEBOX | WS_SYSMENU,
+                WS_OVERLAPPEDWINDOW,
                 0,
                 0,
                 window_rect.right - window_rect.left,
@@ -243,6 +306,44 @@ pub fn create_window(size_x: i32, size_y: i32) -> Result<Window, ()> {
     Err(())
 }
 
+// the sender is dropped right away, process_window_messages never returns a message
+#[cfg(not(windows))]
+pub fn create_window(_size_x: i32, _size_y: i32) -> Result<Window, ()> {
+    let (_, channel_receiver) = std::sync::mpsc::channel();
+
+    Ok(Window {
+        message_receiver: channel_receiver,
+    })
+}
+
+// size of the primary monitor without the taskbar
+#[cfg(windows)]
+pub fn work_area_size() -> (i32, i32) {
+    let mut rect = winapi::shared::windef::RECT {
+        bottom: 0,
+        left: 0,
+        right: 0,
+        top: 0,
+    };
+
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETWORKAREA,
+            0,
+            &mut rect as *mut winapi::shared::windef::RECT as *mut winapi::ctypes::c_void,
+            0,
+        );
+    }
+
+    (rect.right - rect.left, rect.bottom - rect.top)
+}
+
+// there is no monitor to query, assumes a 1080p monitor
+#[cfg(not(windows))]
+pub fn work_area_size() -> (i32, i32) {
+    (1920, 1080)
+}
+
 pub fn process_window_messages(window: &Window) -> Option<WindowMessages> {
     if let Ok(x) = window.message_receiver.try_recv() {
         return Some(x);
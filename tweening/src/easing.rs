@@ -0,0 +1,200 @@
+// the curves of https://easings.net
+// every curve starts at 0 for t = 0 and ends at 1 for t = 1, back and elastic overshoot in between
+
+use std::f32::consts::PI;
+
+// how far the back curves overshoot, about 10%
+const BACK_OVERSHOOT: f32 = 1.70158;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+pub const EASINGS: [Easing; 16] = [
+    Easing::Linear,
+    Easing::QuadIn,
+    Easing::QuadOut,
+    Easing::QuadInOut,
+    Easing::CubicIn,
+    Easing::CubicOut,
+    Easing::CubicInOut,
+    Easing::BackIn,
+    Easing::BackOut,
+    Easing::BackInOut,
+    Easing::ElasticIn,
+    Easing::ElasticOut,
+    Easing::ElasticInOut,
+    Easing::BounceIn,
+    Easing::BounceOut,
+    Easing::BounceInOut,
+];
+
+fn bounce_out(t: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+
+    if t < 1.0 / D1 {
+        N1 * t * t
+    } else if t < 2.0 / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984_375
+    }
+}
+
+impl Easing {
+    // t is clamped to 0..=1
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+
+            Easing::BackIn => (BACK_OVERSHOOT + 1.0) * t * t * t - BACK_OVERSHOOT * t * t,
+            Easing::BackOut => {
+                1.0 + (BACK_OVERSHOOT + 1.0) * (t - 1.0).powi(3)
+                    + BACK_OVERSHOOT * (t - 1.0).powi(2)
+            }
+            Easing::BackInOut => {
+                let c = BACK_OVERSHOOT * 1.525;
+
+                if t < 0.5 {
+                    (2.0 * t).powi(2) * ((c + 1.0) * 2.0 * t - c) / 2.0
+                } else {
+                    ((2.0 * t - 2.0).powi(2) * ((c + 1.0) * (2.0 * t - 2.0) + c) + 2.0) / 2.0
+                }
+            }
+
+            // the exponential decay never reaches 0 exactly, the ends are returned as they are
+            Easing::ElasticIn => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    -(2.0f32).powf(10.0 * t - 10.0) * ((10.0 * t - 10.75) * (2.0 * PI / 3.0)).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    (2.0f32).powf(-10.0 * t) * ((10.0 * t - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+                }
+            }
+            Easing::ElasticInOut => {
+                let c = 2.0 * PI / 4.5;
+
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    -((2.0f32).powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * c).sin()) / 2.0
+                } else {
+                    (2.0f32).powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * c).sin() / 2.0 + 1.0
+                }
+            }
+
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+                } else {
+                    (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_curves_start_at_0_and_end_at_1() {
+        for easing in EASINGS.iter() {
+            assert!(easing.apply(0.0).abs() < 0.0001, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 0.0001, "{:?}", easing);
+
+            // outside of the range the ends are held
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(2.0), easing.apply(1.0), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn curves_have_their_expected_shape() {
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+        assert_eq!(Easing::QuadOut.apply(0.5), 0.75);
+        assert_eq!(Easing::CubicIn.apply(0.5), 0.125);
+        assert_eq!(Easing::CubicInOut.apply(0.5), 0.5);
+
+        // the in-out curves are symmetric around the center
+        for easing in [
+            Easing::QuadInOut,
+            Easing::CubicInOut,
+            Easing::BackInOut,
+            Easing::BounceInOut,
+        ]
+        .iter()
+        {
+            let a = easing.apply(0.2);
+            let b = easing.apply(0.8);
+            assert!((a + b - 1.0).abs() < 0.0001, "{:?}", easing);
+        }
+
+        // back pulls back before it starts and overshoots before it settles
+        assert!(Easing::BackIn.apply(0.2) < 0.0);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+
+        // elastic oscillates around the target
+        assert!(Easing::ElasticOut.apply(0.1) > 1.0);
+
+        // bounce never overshoots
+        for i in 0..=100 {
+            let value = Easing::BounceOut.apply(i as f32 / 100.0);
+            assert!((0.0..=1.0001).contains(&value));
+        }
+    }
+}
@@ -0,0 +1,559 @@
+// tweens describe how values change over time, a TweenPlayer only stores how far a tween has been played
+// the tweens are advanced by the fixed update dt, so the same updates always produce the same values and events
+// the tween itself is never modified while playing and can be shared by all players that play it
+
+mod easing;
+
+pub use easing::{Easing, EASINGS};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TweenValue {
+    Scalar(f32),
+
+    // positions and scales
+    Vector([f32; 2]),
+
+    // rgba
+    Color([f32; 4]),
+}
+
+impl TweenValue {
+    // t = 0 is from and t = 1 is to, values outside of the range extrapolate
+    pub fn lerp(from: TweenValue, to: TweenValue, t: f32) -> TweenValue {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        match (from, to) {
+            (TweenValue::Scalar(a), TweenValue::Scalar(b)) => TweenValue::Scalar(lerp(a, b)),
+
+            (TweenValue::Vector(a), TweenValue::Vector(b)) => {
+                TweenValue::Vector([lerp(a[0], b[0]), lerp(a[1], b[1])])
+            }
+
+            (TweenValue::Color(a), TweenValue::Color(b)) => TweenValue::Color([
+                lerp(a[0], b[0]),
+                lerp(a[1], b[1]),
+                lerp(a[2], b[2]),
+                lerp(a[3], b[3]),
+            ]),
+
+            _ => panic!("can't tween between {:?} and {:?}", from, to),
+        }
+    }
+}
+
+// the values of all channels a tween has written to, channels are indices chosen by the game
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TweenValues {
+    values: Vec<Option<TweenValue>>,
+}
+
+impl TweenValues {
+    pub fn new() -> TweenValues {
+        TweenValues::default()
+    }
+
+    pub fn set(&mut self, channel: usize, value: TweenValue) {
+        if self.values.len() <= channel {
+            self.values.resize(channel + 1, None);
+        }
+
+        self.values[channel] = Some(value);
+    }
+
+    // None if no tween has written to the channel yet
+    pub fn get(&self, channel: usize) -> Option<TweenValue> {
+        self.values.get(channel).copied().flatten()
+    }
+
+    // the typed getters panic if the channel holds a different kind of value
+    pub fn scalar(&self, channel: usize) -> Option<f32> {
+        self.get(channel).map(|x| match x {
+            TweenValue::Scalar(value) => value,
+            _ => panic!("channel {} doesn't hold a scalar but {:?}", channel, x),
+        })
+    }
+
+    pub fn vector(&self, channel: usize) -> Option<[f32; 2]> {
+        self.get(channel).map(|x| match x {
+            TweenValue::Vector(value) => value,
+            _ => panic!("channel {} doesn't hold a vector but {:?}", channel, x),
+        })
+    }
+
+    pub fn color(&self, channel: usize) -> Option<[f32; 4]> {
+        self.get(channel).map(|x| match x {
+            TweenValue::Color(value) => value,
+            _ => panic!("channel {} doesn't hold a color but {:?}", channel, x),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tween {
+    // blends the channel from one value to another, the channel keeps the last value once the tween is done
+    Value {
+        channel: usize,
+        from: TweenValue,
+        to: TweenValue,
+        duration: f32,
+        easing: Easing,
+    },
+
+    // takes time without changing any channel
+    Delay(f32),
+
+    // reported by TweenPlayer::update when the player passes it
+    Event(u32),
+
+    // the tweens one after the other, tweens that have started overwrite the channels of the tweens before them
+    Sequence(Vec<Tween>),
+
+    // the tweens at the same time, done when the longest one is done
+    Parallel(Vec<Tween>),
+
+    // plays the tween count times or forever if count is None
+    // with yoyo every second iteration is played backwards, events inside of it are reported on the way back too
+    // but only once where the direction changes
+    Repeat {
+        tween: Box<Tween>,
+        count: Option<u32>,
+        yoyo: bool,
+    },
+}
+
+impl Tween {
+    pub fn scalar(channel: usize, from: f32, to: f32, duration: f32, easing: Easing) -> Tween {
+        Tween::Value {
+            channel,
+            from: TweenValue::Scalar(from),
+            to: TweenValue::Scalar(to),
+            duration,
+            easing,
+        }
+    }
+
+    pub fn vector(
+        channel: usize,
+        from: [f32; 2],
+        to: [f32; 2],
+        duration: f32,
+        easing: Easing,
+    ) -> Tween {
+        Tween::Value {
+            channel,
+            from: TweenValue::Vector(from),
+            to: TweenValue::Vector(to),
+            duration,
+            easing,
+        }
+    }
+
+    pub fn color(
+        channel: usize,
+        from: [f32; 4],
+        to: [f32; 4],
+        duration: f32,
+        easing: Easing,
+    ) -> Tween {
+        Tween::Value {
+            channel,
+            from: TweenValue::Color(from),
+            to: TweenValue::Color(to),
+            duration,
+            easing,
+        }
+    }
+
+    pub fn repeat(self, count: Option<u32>, yoyo: bool) -> Tween {
+        Tween::Repeat {
+            tween: Box::new(self),
+            count,
+            yoyo,
+        }
+    }
+
+    // infinite for tweens that repeat forever
+    pub fn duration(&self) -> f32 {
+        match self {
+            Tween::Value { duration, .. } => *duration,
+            Tween::Delay(duration) => *duration,
+            Tween::Event(_) => 0.0,
+            Tween::Sequence(tweens) => tweens.iter().map(|x| x.duration()).sum(),
+            Tween::Parallel(tweens) => tweens.iter().map(|x| x.duration()).fold(0.0, f32::max),
+            Tween::Repeat { tween, count, .. } => match count {
+                Some(count) => tween.duration() * *count as f32,
+                None => f32::INFINITY,
+            },
+        }
+    }
+
+    // writes the values of all channels at the time into values
+    pub fn sample(&self, time: f32, values: &mut TweenValues) {
+        match self {
+            Tween::Value {
+                channel,
+                from,
+                to,
+                duration,
+                easing,
+            } => {
+                // a tween without a duration jumps to the target right away
+                let t = if *duration > 0.0 {
+                    time / duration
+                } else {
+                    1.0
+                };
+
+                values.set(*channel, TweenValue::lerp(*from, *to, easing.apply(t)));
+            }
+
+            Tween::Delay(_) | Tween::Event(_) => {}
+
+            Tween::Sequence(tweens) => {
+                let mut start = 0.0;
+
+                for tween in tweens {
+                    if time < start {
+                        break;
+                    }
+
+                    tween.sample(time - start, values);
+
+                    start += tween.duration();
+                }
+            }
+
+            Tween::Parallel(tweens) => {
+                for tween in tweens {
+                    tween.sample(time, values);
+                }
+            }
+
+            Tween::Repeat { tween, count, yoyo } => {
+                let duration = tween.duration();
+
+                if duration <= 0.0 {
+                    tween.sample(time, values);
+                    return;
+                }
+
+                let last_iteration = count.map_or(u64::MAX, |x| u64::from(x.max(1)) - 1);
+                let iteration = ((time / duration).max(0.0) as u64).min(last_iteration);
+
+                let mut local_time = (time - iteration as f32 * duration).clamp(0.0, duration);
+
+                if *yoyo && iteration % 2 == 1 {
+                    local_time = duration - local_time;
+                }
+
+                tween.sample(local_time, values);
+            }
+        }
+    }
+
+    // adds the events with a player time inside of (window_start, window_end] to events
+    // start and end are the window in the local time of the tween, offset and direction map the local time back to the player time
+    fn collect_events(
+        &self,
+        start: f32,
+        end: f32,
+        offset: f32,
+        direction: f32,
+        window: (f32, f32),
+        events: &mut Vec<(f32, u32)>,
+    ) {
+        match self {
+            Tween::Value { .. } | Tween::Delay(_) => {}
+
+            Tween::Event(event) => {
+                if window.0 < offset && offset <= window.1 {
+                    events.push((offset, *event));
+                }
+            }
+
+            Tween::Sequence(tweens) => {
+                let mut tween_start = 0.0;
+
+                for tween in tweens {
+                    let tween_end = tween_start + tween.duration();
+
+                    if tween_start > end {
+                        break;
+                    }
+
+                    if tween_end >= start {
+                        tween.collect_events(
+                            start - tween_start,
+                            end - tween_start,
+                            offset + direction * tween_start,
+                            direction,
+                            window,
+                            events,
+                        );
+                    }
+
+                    tween_start = tween_end;
+                }
+            }
+
+            Tween::Parallel(tweens) => {
+                for tween in tweens {
+                    tween.collect_events(start, end, offset, direction, window, events);
+                }
+            }
+
+            Tween::Repeat { tween, count, yoyo } => {
+                let duration = tween.duration();
+
+                if duration <= 0.0 {
+                    tween.collect_events(start, end, offset, direction, window, events);
+                    return;
+                }
+
+                // the window test above decides about events on the boundaries, one more iteration on each side doesn't hurt
+                let last_iteration = count.map_or(u64::MAX, |x| u64::from(x.max(1)) - 1);
+                let first = ((start / duration).floor().max(0.0) as u64).saturating_sub(1);
+                let last = ((end / duration).floor().max(0.0) as u64)
+                    .saturating_add(1)
+                    .min(last_iteration);
+
+                for iteration in first..=last {
+                    let iteration_start = iteration as f32 * duration;
+
+                    // a yoyo turns around where the previous iteration ended, the events there have already been reported
+                    let mut window = window;
+
+                    if *yoyo && iteration > 0 {
+                        let turnaround = offset + direction * iteration_start;
+
+                        if direction > 0.0 {
+                            window.0 = window.0.max(turnaround);
+                        } else {
+                            window.1 = window.1.min(turnaround.next_down());
+                        }
+                    }
+
+                    if *yoyo && iteration % 2 == 1 {
+                        // the local time runs from the end of the tween back to its start
+                        let iteration_end = iteration_start + duration;
+
+                        tween.collect_events(
+                            iteration_end - end,
+                            iteration_end - start,
+                            offset + direction * iteration_end,
+                            -direction,
+                            window,
+                            events,
+                        );
+                    } else {
+                        tween.collect_events(
+                            start - iteration_start,
+                            end - iteration_start,
+                            offset + direction * iteration_start,
+                            direction,
+                            window,
+                            events,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the state of a tween that is being played, the tween itself is passed to every call
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TweenPlayer {
+    // seconds since the player has been started
+    pub time: f32,
+
+    // events at time 0 are reported by the first update
+    started: bool,
+}
+
+impl TweenPlayer {
+    pub fn new() -> TweenPlayer {
+        TweenPlayer::default()
+    }
+
+    // advances the player and returns the events that have been passed, ordered by time
+    // the time stops at the end of the tween so that a finished tween doesn't report anything again
+    pub fn update(&mut self, tween: &Tween, dt: f32) -> Vec<u32> {
+        let start = if self.started {
+            self.time
+        } else {
+            f32::NEG_INFINITY
+        };
+
+        let end = (self.time + dt).min(tween.duration());
+
+        let mut events = Vec::new();
+        tween.collect_events(start, end, 0.0, 1.0, (start, end), &mut events);
+
+        // stable, events at the same time are reported in the order they appear in the tween
+        events.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        self.time = end;
+        self.started = true;
+
+        events.into_iter().map(|x| x.1).collect()
+    }
+
+    // the values of all channels the tween has written to at the current time
+    pub fn values(&self, tween: &Tween) -> TweenValues {
+        let mut values = TweenValues::new();
+        tween.sample(self.time, &mut values);
+        values
+    }
+
+    pub fn is_finished(&self, tween: &Tween) -> bool {
+        self.started && self.time >= tween.duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALPHA: usize = 0;
+    const POSITION: usize = 1;
+    const COLOR: usize = 2;
+
+    const FADED_IN: u32 = 1;
+    const DONE: u32 = 2;
+
+    // records the events of every update
+    fn play(tween: &Tween, player: &mut TweenPlayer, dt: f32, updates: usize) -> Vec<Vec<u32>> {
+        (0..updates).map(|_| player.update(tween, dt)).collect()
+    }
+
+    #[test]
+    fn sequences_play_their_tweens_one_after_the_other() {
+        let tween = Tween::Sequence(vec![
+            Tween::Delay(0.25),
+            Tween::scalar(ALPHA, 0.0, 1.0, 0.5, Easing::Linear),
+            Tween::Event(FADED_IN),
+            Tween::scalar(ALPHA, 1.0, 0.5, 0.25, Easing::Linear),
+            Tween::Event(DONE),
+        ]);
+
+        assert_eq!(tween.duration(), 1.0);
+
+        let mut player = TweenPlayer::new();
+
+        // nothing has been written during the delay
+        assert_eq!(player.values(&tween).scalar(ALPHA), None);
+
+        let events = play(&tween, &mut player, 0.25, 2);
+        assert_eq!(events, vec![vec![], vec![]]);
+        assert_eq!(player.values(&tween).scalar(ALPHA), Some(0.5));
+
+        // the event is reported in the update that reaches it
+        assert_eq!(player.update(&tween, 0.25), vec![FADED_IN]);
+        assert_eq!(player.values(&tween).scalar(ALPHA), Some(1.0));
+        assert!(!player.is_finished(&tween));
+
+        // overshooting the end stops at the end and reports the last event once
+        assert_eq!(player.update(&tween, 0.5), vec![DONE]);
+        assert_eq!(player.values(&tween).scalar(ALPHA), Some(0.5));
+        assert!(player.is_finished(&tween));
+        assert_eq!(player.update(&tween, 0.25), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn parallel_tweens_animate_different_kinds_of_values() {
+        let tween = Tween::Parallel(vec![
+            Tween::vector(POSITION, [0.0, 100.0], [200.0, 0.0], 1.0, Easing::QuadIn),
+            Tween::color(
+                COLOR,
+                [1.0, 1.0, 1.0, 1.0],
+                [0.0, 1.0, 0.0, 0.5],
+                0.5,
+                Easing::Linear,
+            ),
+        ]);
+
+        assert_eq!(tween.duration(), 1.0);
+
+        let mut player = TweenPlayer::new();
+        player.update(&tween, 0.5);
+
+        let values = player.values(&tween);
+        assert_eq!(values.vector(POSITION), Some([50.0, 75.0]));
+        assert_eq!(values.color(COLOR), Some([0.0, 1.0, 0.0, 0.5]));
+        assert_eq!(values.get(ALPHA), None);
+    }
+
+    #[test]
+    fn loops_and_yoyos_repeat_their_events() {
+        let tween = Tween::Sequence(vec![
+            Tween::Event(FADED_IN),
+            Tween::scalar(ALPHA, 0.0, 1.0, 0.5, Easing::Linear),
+            Tween::Event(DONE),
+        ]);
+
+        // a loop jumps back to the start after every iteration
+        let looped = tween.clone().repeat(Some(2), false);
+        assert_eq!(looped.duration(), 1.0);
+
+        let mut player = TweenPlayer::new();
+        let events = play(&looped, &mut player, 0.25, 5);
+        assert_eq!(
+            events,
+            vec![
+                vec![FADED_IN],
+                vec![DONE, FADED_IN],
+                vec![],
+                vec![DONE],
+                vec![]
+            ]
+        );
+        assert_eq!(player.values(&looped).scalar(ALPHA), Some(1.0));
+
+        // a yoyo plays every second iteration backwards and never finishes without a count
+        let yoyo = tween.repeat(None, true);
+        assert_eq!(yoyo.duration(), f32::INFINITY);
+
+        let mut player = TweenPlayer::new();
+        let mut alpha = Vec::new();
+        let mut events = Vec::new();
+
+        for _ in 0..8 {
+            events.extend(player.update(&yoyo, 0.125));
+            alpha.push(player.values(&yoyo).scalar(ALPHA).unwrap());
+        }
+
+        assert_eq!(alpha, vec![0.25, 0.5, 0.75, 1.0, 0.75, 0.5, 0.25, 0.0]);
+        assert_eq!(events, vec![FADED_IN, DONE, FADED_IN]);
+        assert!(!player.is_finished(&yoyo));
+    }
+
+    #[test]
+    fn players_are_deterministic() {
+        let tween = Tween::Sequence(vec![
+            Tween::scalar(ALPHA, 0.0, 1.0, 0.3, Easing::ElasticOut),
+            Tween::Event(DONE),
+            Tween::scalar(ALPHA, 1.0, 0.0, 0.2, Easing::BounceOut),
+        ])
+        .repeat(Some(3), true);
+
+        let run = || {
+            let mut player = TweenPlayer::new();
+            let mut results = Vec::new();
+
+            for _ in 0..120 {
+                let events = player.update(&tween, 1.0 / 60.0);
+                results.push((player.values(&tween).scalar(ALPHA), events));
+            }
+
+            results
+        };
+
+        let results = run();
+        assert_eq!(results, run());
+
+        // once in each of the 3 iterations, forwards, backwards and forwards again
+        let done_count = results.iter().filter(|x| x.1.contains(&DONE)).count();
+        assert_eq!(done_count, 3);
+    }
+}